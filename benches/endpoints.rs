@@ -0,0 +1,164 @@
+//! Carga sobre los endpoints más usados de un servidor en marcha.
+//!
+//!     BENCH_URL=http://localhost:3000 cargo bench --bench endpoints
+//!
+//! Variables: `BENCH_REQUESTS` (por escenario, 500), `BENCH_CONCURRENCY` (16),
+//! `BENCH_UPLOADS=1` para incluir subidas (escriben ficheros en el servidor).
+
+use std::{
+    env,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::sync::Semaphore;
+
+/// PNG de 1x1 píxel.
+const PNG_1X1: &[u8] = &[
+    0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44,
+    0x52, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x08, 0x06, 0x00, 0x00, 0x00, 0x1f,
+    0x15, 0xc4, 0x89, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x44, 0x41, 0x54, 0x78, 0x9c, 0x63, 0x00,
+    0x01, 0x00, 0x00, 0x05, 0x00, 0x01, 0x0d, 0x0a, 0x2d, 0xb4, 0x00, 0x00, 0x00, 0x00, 0x49,
+    0x45, 0x4e, 0x44, 0xae, 0x42, 0x60, 0x82,
+];
+
+#[derive(Clone)]
+enum Scenario {
+    Get(&'static str),
+    Upload,
+}
+
+impl Scenario {
+    fn name(&self) -> &'static str {
+        match self {
+            Scenario::Get(path) => path,
+            Scenario::Upload => "POST /upload-image",
+        }
+    }
+
+    async fn run(&self, client: &reqwest::Client, base: &str) -> bool {
+        let res = match self {
+            Scenario::Get(path) => client.get(format!("{base}{path}")).send().await,
+            Scenario::Upload => {
+                let boundary = "bench-boundary";
+                let mut body = format!(
+                    "--{boundary}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"b.png\"\r\nContent-Type: image/png\r\n\r\n"
+                )
+                .into_bytes();
+                body.extend_from_slice(PNG_1X1);
+                body.extend_from_slice(format!("\r\n--{boundary}--\r\n").as_bytes());
+
+                client
+                    .post(format!("{base}/upload-image"))
+                    .header(
+                        "content-type",
+                        format!("multipart/form-data; boundary={boundary}"),
+                    )
+                    .body(body)
+                    .send()
+                    .await
+            }
+        };
+
+        match res {
+            Ok(res) => res.status().is_success() && res.bytes().await.is_ok(),
+            Err(_) => false,
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let Ok(base) = env::var("BENCH_URL") else {
+        eprintln!("BENCH_URL no definida; se omiten los benchmarks");
+        return;
+    };
+    let requests: usize = env_or("BENCH_REQUESTS", 500);
+    let concurrency: usize = env_or("BENCH_CONCURRENCY", 16);
+
+    let mut scenarios = vec![
+        Scenario::Get("/mensajes"),
+        Scenario::Get("/images"),
+        Scenario::Get("/version"),
+    ];
+    if env::var("BENCH_UPLOADS").is_ok_and(|v| v == "1") {
+        scenarios.push(Scenario::Upload);
+    }
+
+    let client = reqwest::Client::new();
+    println!(
+        "{:<22} {:>8} {:>8} {:>8} {:>8} {:>8} {:>6}",
+        "escenario", "req/s", "p50 ms", "p95 ms", "p99 ms", "max ms", "fallos"
+    );
+
+    for scenario in scenarios {
+        let (latencies, failures, elapsed) =
+            run_scenario(&scenario, &client, &base, requests, concurrency).await;
+        report(scenario.name(), latencies, failures, elapsed);
+    }
+}
+
+async fn run_scenario(
+    scenario: &Scenario,
+    client: &reqwest::Client,
+    base: &str,
+    requests: usize,
+    concurrency: usize,
+) -> (Vec<Duration>, usize, Duration) {
+    // Calentamiento: conexiones abiertas y cachés del servidor templadas.
+    for _ in 0..concurrency {
+        scenario.run(client, base).await;
+    }
+
+    let semaphore = Arc::new(Semaphore::new(concurrency));
+    let mut tasks = tokio::task::JoinSet::new();
+    let start = Instant::now();
+
+    for _ in 0..requests {
+        let permit = semaphore.clone().acquire_owned().await.unwrap();
+        let (scenario, client, base) = (scenario.clone(), client.clone(), base.to_string());
+        tasks.spawn(async move {
+            let t = Instant::now();
+            let ok = scenario.run(&client, &base).await;
+            drop(permit);
+            (t.elapsed(), ok)
+        });
+    }
+
+    let mut latencies = Vec::with_capacity(requests);
+    let mut failures = 0;
+    while let Some(result) = tasks.join_next().await {
+        let (latency, ok) = result.unwrap();
+        latencies.push(latency);
+        if !ok {
+            failures += 1;
+        }
+    }
+
+    (latencies, failures, start.elapsed())
+}
+
+fn report(name: &str, mut latencies: Vec<Duration>, failures: usize, elapsed: Duration) {
+    latencies.sort();
+    let pct = |p: f64| {
+        let i = ((latencies.len() as f64 * p).ceil() as usize).saturating_sub(1);
+        latencies[i].as_secs_f64() * 1000.0
+    };
+
+    println!(
+        "{:<22} {:>8.0} {:>8.2} {:>8.2} {:>8.2} {:>8.2} {:>6}",
+        name,
+        latencies.len() as f64 / elapsed.as_secs_f64(),
+        pct(0.50),
+        pct(0.95),
+        pct(0.99),
+        pct(1.0),
+        failures,
+    );
+}
+
+fn env_or(key: &str, default: usize) -> usize {
+    env::var(key)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default)
+}
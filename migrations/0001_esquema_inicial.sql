@@ -0,0 +1,11 @@
+-- Tablas existentes antes de introducir migraciones.
+CREATE TABLE IF NOT EXISTS mensajes (
+    id SERIAL PRIMARY KEY,
+    nombre TEXT NOT NULL,
+    mensaje TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS images (
+    id SERIAL PRIMARY KEY,
+    filename TEXT NOT NULL
+);
@@ -0,0 +1,4 @@
+-- Datos necesarios para que la política de permisos sepa quién y cuándo escribió cada mensaje.
+ALTER TABLE mensajes
+    ADD COLUMN IF NOT EXISTS created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    ADD COLUMN IF NOT EXISTS author_ip TEXT;
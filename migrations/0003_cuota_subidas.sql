@@ -0,0 +1,8 @@
+-- Subidas y bytes por identidad y día (UTC) para la cuota diaria.
+CREATE TABLE IF NOT EXISTS upload_quota (
+    identity TEXT NOT NULL,
+    day DATE NOT NULL,
+    uploads INTEGER NOT NULL DEFAULT 0,
+    bytes BIGINT NOT NULL DEFAULT 0,
+    PRIMARY KEY (identity, day)
+);
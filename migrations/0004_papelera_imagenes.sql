@@ -0,0 +1,4 @@
+-- Imágenes en la papelera: se ocultan del listado y se purgan pasado el plazo de retención.
+ALTER TABLE images ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS images_deleted_at_idx ON images (deleted_at) WHERE deleted_at IS NOT NULL;
@@ -0,0 +1,5 @@
+-- Tamaño y fecha de subida para ordenar y filtrar la galería. Las imágenes
+-- anteriores quedan sin tamaño y toman la fecha de esta migración.
+ALTER TABLE images
+    ADD COLUMN IF NOT EXISTS size_bytes BIGINT,
+    ADD COLUMN IF NOT EXISTS created_at TIMESTAMPTZ NOT NULL DEFAULT now();
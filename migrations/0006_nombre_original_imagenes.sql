@@ -0,0 +1,2 @@
+-- Nombre con el que se subió la imagen, ya saneado (sin rutas ni dobles extensiones).
+ALTER TABLE images ADD COLUMN IF NOT EXISTS original_name TEXT;
@@ -0,0 +1,2 @@
+-- Puntuación de las heurísticas de contenido (enlaces, mayúsculas, líneas repetidas).
+ALTER TABLE mensajes ADD COLUMN IF NOT EXISTS spam_score INTEGER NOT NULL DEFAULT 0;
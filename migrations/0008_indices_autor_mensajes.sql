@@ -0,0 +1,3 @@
+-- Para contar los mensajes recientes de un autor (tope diario por nombre o IP).
+CREATE INDEX IF NOT EXISTS mensajes_author_ip_created_idx ON mensajes (author_ip, created_at);
+CREATE INDEX IF NOT EXISTS mensajes_nombre_created_idx ON mensajes (lower(nombre), created_at);
@@ -0,0 +1,3 @@
+-- Listados y filtros por fecha de mensajes e imágenes.
+CREATE INDEX IF NOT EXISTS mensajes_created_at_idx ON mensajes (created_at);
+CREATE INDEX IF NOT EXISTS images_created_at_idx ON images (created_at);
@@ -0,0 +1,11 @@
+-- Acciones de administración que cambian algo, para consultarlas desde el panel.
+CREATE TABLE IF NOT EXISTS admin_audit (
+    id BIGSERIAL PRIMARY KEY,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    method TEXT NOT NULL,
+    uri TEXT NOT NULL,
+    ip TEXT,
+    status SMALLINT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS admin_audit_created_at_idx ON admin_audit (created_at);
@@ -0,0 +1,12 @@
+-- Email opcional del autor y su verificación por enlace de un solo uso.
+ALTER TABLE mensajes
+    ADD COLUMN IF NOT EXISTS author_email TEXT,
+    ADD COLUMN IF NOT EXISTS email_verified_at TIMESTAMPTZ;
+
+CREATE TABLE IF NOT EXISTS email_verifications (
+    token TEXT PRIMARY KEY,
+    mensaje_id INTEGER NOT NULL REFERENCES mensajes (id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    expires_at TIMESTAMPTZ NOT NULL,
+    used_at TIMESTAMPTZ
+);
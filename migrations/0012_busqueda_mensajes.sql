@@ -0,0 +1,5 @@
+-- Vector de texto completo de cada mensaje, para buscar mensajes parecidos.
+ALTER TABLE mensajes ADD COLUMN IF NOT EXISTS search tsvector
+    GENERATED ALWAYS AS (to_tsvector('spanish', mensaje)) STORED;
+
+CREATE INDEX IF NOT EXISTS mensajes_search_idx ON mensajes USING gin (search);
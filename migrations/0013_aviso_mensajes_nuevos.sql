@@ -0,0 +1,14 @@
+-- Aviso por NOTIFY de cada inserción en mensajes, para que las instancias que
+-- sirven el listado vacío sin consultar dejen de hacerlo en cuanto hay uno.
+CREATE OR REPLACE FUNCTION notify_mensajes_insert() RETURNS trigger
+LANGUAGE plpgsql AS $$
+BEGIN
+    PERFORM pg_notify('mensajes_insert', '');
+    RETURN NULL;
+END
+$$;
+
+DROP TRIGGER IF EXISTS mensajes_insert_notify ON mensajes;
+CREATE TRIGGER mensajes_insert_notify
+    AFTER INSERT ON mensajes
+    FOR EACH STATEMENT EXECUTE FUNCTION notify_mensajes_insert();
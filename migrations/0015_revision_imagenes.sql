@@ -0,0 +1,7 @@
+-- Revisión de imágenes: las nuevas quedan pendientes (sin approved_at) hasta
+-- que administración las aprueba. Las que ya estaban publicadas se dan por aprobadas.
+ALTER TABLE images ADD COLUMN IF NOT EXISTS approved_at TIMESTAMPTZ;
+
+UPDATE images SET approved_at = created_at WHERE approved_at IS NULL;
+
+CREATE INDEX IF NOT EXISTS images_pending_idx ON images (created_at) WHERE approved_at IS NULL;
@@ -0,0 +1,12 @@
+-- Cuentas externas (Google, GitHub) que pueden entrar en administración.
+-- Se identifican por email verificado del proveedor, sin distinguir mayúsculas.
+CREATE TABLE IF NOT EXISTS admin_accounts (
+    id SERIAL PRIMARY KEY,
+    provider TEXT NOT NULL CHECK (provider IN ('google', 'github')),
+    email TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    last_login_at TIMESTAMPTZ
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS admin_accounts_provider_email_idx
+    ON admin_accounts (provider, lower(email));
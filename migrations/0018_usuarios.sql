@@ -0,0 +1,16 @@
+-- Usuarios del panel con rol. Las sesiones abiertas con el token (o con una
+-- cuenta externa) no tienen usuario y valen como administración completa.
+CREATE TABLE IF NOT EXISTS users (
+    id SERIAL PRIMARY KEY,
+    username TEXT NOT NULL,
+    -- Cadena PHC de Argon2id (`$argon2id$...`).
+    password_hash TEXT NOT NULL,
+    role TEXT NOT NULL CHECK (role IN ('admin', 'moderator', 'viewer')),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    disabled_at TIMESTAMPTZ
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS users_username_idx ON users (lower(username));
+
+ALTER TABLE admin_sessions
+    ADD COLUMN IF NOT EXISTS user_id INTEGER REFERENCES users (id) ON DELETE CASCADE;
@@ -0,0 +1,8 @@
+-- Ajustes del sitio, guardados por `/setup` en el primer arranque. Una sola
+-- fila: que exista marca la configuración inicial como completada.
+CREATE TABLE IF NOT EXISTS site_settings (
+    id BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+    title TEXT NOT NULL,
+    language TEXT NOT NULL,
+    completed_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
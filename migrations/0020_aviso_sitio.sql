@@ -0,0 +1,7 @@
+-- Aviso del sitio (`/admin/announcement`). Una sola fila; sin fila no hay aviso.
+CREATE TABLE IF NOT EXISTS announcement (
+    id BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+    message TEXT NOT NULL,
+    expires_at TIMESTAMPTZ,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
@@ -0,0 +1,17 @@
+-- Cuentas de autores: usuarios con rol `author`, sin acceso al panel. Sus
+-- mensajes quedan ligados a la cuenta y solo ellos (o el equipo) los editan.
+ALTER TABLE users DROP CONSTRAINT IF EXISTS users_role_check;
+ALTER TABLE users ADD CONSTRAINT users_role_check
+    CHECK (role IN ('admin', 'moderator', 'viewer', 'author'));
+
+ALTER TABLE mensajes
+    ADD COLUMN IF NOT EXISTS user_id INTEGER REFERENCES users (id) ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS mensajes_user_id_idx ON mensajes (user_id);
+
+CREATE TABLE IF NOT EXISTS author_sessions (
+    token TEXT PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    expires_at TIMESTAMPTZ NOT NULL
+);
@@ -0,0 +1,8 @@
+-- Intentos fallidos de acceso a administración, por IP (`ip:...`) y por
+-- usuario (`user:...`). Pasado el umbral, `locked_until` bloquea la clave.
+CREATE TABLE IF NOT EXISTS login_failures (
+    key TEXT PRIMARY KEY,
+    failures INTEGER NOT NULL,
+    last_failure_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    locked_until TIMESTAMPTZ
+);
@@ -0,0 +1,7 @@
+-- Direcciones y rangos que no pueden enviar mensajes ni subir imágenes.
+CREATE TABLE IF NOT EXISTS banned_ips (
+    id SERIAL PRIMARY KEY,
+    network CIDR NOT NULL UNIQUE,
+    reason TEXT NOT NULL DEFAULT '',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
@@ -0,0 +1,12 @@
+-- Envíos descartados por parecer de un bot (de momento, el campo trampa).
+CREATE TABLE IF NOT EXISTS spam_log (
+    id BIGSERIAL PRIMARY KEY,
+    reason TEXT NOT NULL,
+    ip TEXT,
+    user_agent TEXT,
+    nombre TEXT NOT NULL DEFAULT '',
+    mensaje TEXT NOT NULL DEFAULT '',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS spam_log_created_at_idx ON spam_log (created_at);
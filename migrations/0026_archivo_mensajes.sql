@@ -0,0 +1,18 @@
+-- Mensajes antiguos fuera de la tabla caliente, para que el listado, los
+-- recuentos y la búsqueda solo recorran lo reciente. Mismas columnas que
+-- `mensajes` salvo el vector de búsqueda; el id se conserva y los enlaces
+-- permanentes siguen funcionando.
+CREATE TABLE IF NOT EXISTS mensajes_archive (
+    id INTEGER PRIMARY KEY,
+    nombre TEXT NOT NULL,
+    mensaje TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+    author_ip TEXT,
+    spam_score INTEGER NOT NULL DEFAULT 0,
+    author_email TEXT,
+    email_verified_at TIMESTAMPTZ,
+    user_id INTEGER REFERENCES users (id) ON DELETE SET NULL,
+    archived_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS mensajes_archive_created_at_idx ON mensajes_archive (created_at);
@@ -0,0 +1,15 @@
+-- Mensajes nuevos con demasiada puntuación de spam: esperan aquí a que
+-- moderación los publique o los descarte, fuera de los listados. El id sale
+-- de la misma secuencia que `mensajes`, así que al publicarlo se conserva.
+CREATE TABLE IF NOT EXISTS mensajes_quarantine (
+    id INTEGER PRIMARY KEY DEFAULT nextval('mensajes_id_seq'),
+    nombre TEXT NOT NULL,
+    mensaje TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    author_ip TEXT,
+    spam_score INTEGER NOT NULL,
+    author_email TEXT,
+    user_id INTEGER REFERENCES users (id) ON DELETE SET NULL
+);
+
+CREATE INDEX IF NOT EXISTS mensajes_quarantine_author_ip_created_at_idx ON mensajes_quarantine (author_ip, created_at);
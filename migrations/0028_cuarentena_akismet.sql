@@ -0,0 +1,5 @@
+-- Lo que hace falta para devolver a Akismet un falso positivo: el navegador
+-- del autor y si fue Akismet quien lo retuvo.
+ALTER TABLE mensajes_quarantine
+    ADD COLUMN IF NOT EXISTS user_agent TEXT,
+    ADD COLUMN IF NOT EXISTS akismet_spam BOOLEAN NOT NULL DEFAULT false;
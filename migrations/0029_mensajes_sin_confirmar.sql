@@ -0,0 +1,17 @@
+-- Con EMAIL_CONFIRMATION_REQUIRED, los mensajes esperan aquí a que el autor
+-- abra el enlace `/confirmar/<token>`. Como en la cuarentena, el id sale de la
+-- secuencia de `mensajes` y se conserva al publicarlo.
+CREATE TABLE IF NOT EXISTS mensajes_unconfirmed (
+    id INTEGER PRIMARY KEY DEFAULT nextval('mensajes_id_seq'),
+    token TEXT NOT NULL UNIQUE,
+    expires_at TIMESTAMPTZ NOT NULL,
+    nombre TEXT NOT NULL,
+    mensaje TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    author_ip TEXT,
+    spam_score INTEGER NOT NULL,
+    author_email TEXT NOT NULL,
+    user_id INTEGER REFERENCES users (id) ON DELETE SET NULL
+);
+
+CREATE INDEX IF NOT EXISTS mensajes_unconfirmed_expires_at_idx ON mensajes_unconfirmed (expires_at);
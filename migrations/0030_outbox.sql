@@ -0,0 +1,16 @@
+-- Avisos de cada evento (webhook y correo), guardados en la misma transacción
+-- que el cambio que los provoca y enviados después por `outbox::dispatch`.
+-- Cada canal se marca al enviarse, para que un reintento no lo repita.
+CREATE TABLE IF NOT EXISTS outbox (
+    id BIGSERIAL PRIMARY KEY,
+    event JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    last_error TEXT,
+    webhook_sent_at TIMESTAMPTZ,
+    email_sent_at TIMESTAMPTZ,
+    done_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS outbox_pending_idx ON outbox (next_attempt_at) WHERE done_at IS NULL;
@@ -0,0 +1,3 @@
+-- Hash del token con el que el autor sin cuenta edita o borra su mensaje
+-- (ver `edit_token`). Sin él, solo administración.
+ALTER TABLE mensajes ADD COLUMN IF NOT EXISTS edit_token_hash TEXT;
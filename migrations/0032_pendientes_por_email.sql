@@ -0,0 +1,3 @@
+-- Para contar los mensajes que esperan confirmación de cada email (ver
+-- `email_verification::MAX_PENDING_PER_EMAIL`).
+CREATE INDEX IF NOT EXISTS mensajes_unconfirmed_author_email_idx ON mensajes_unconfirmed (author_email);
@@ -0,0 +1,4 @@
+-- Los correos a los autores (verificación y confirmación) también salen por
+-- la outbox: cada fila lleva un evento o un correo ya redactado.
+ALTER TABLE outbox ALTER COLUMN event DROP NOT NULL;
+ALTER TABLE outbox ADD COLUMN IF NOT EXISTS email JSONB;
@@ -0,0 +1,7 @@
+-- Las sesiones guardan el sha256 del token de la cookie, no el token: quien
+-- lea la base no puede usarlas. Las abiertas siguen valiendo.
+UPDATE admin_sessions SET token = encode(sha256(convert_to(token, 'UTF8')), 'hex');
+ALTER TABLE admin_sessions RENAME COLUMN token TO token_hash;
+
+UPDATE author_sessions SET token = encode(sha256(convert_to(token, 'UTF8')), 'hex');
+ALTER TABLE author_sessions RENAME COLUMN token TO token_hash;
@@ -0,0 +1,174 @@
+use axum::{
+    body::HttpBody,
+    extract::{Request, State},
+    http::{header, HeaderMap},
+    middleware::Next,
+    response::Response,
+};
+use std::{sync::Arc, time::Instant};
+use tokio::{io::AsyncWriteExt, sync::mpsc};
+
+use crate::client_ip::client_ip;
+use crate::config::AccessLogConfig;
+
+#[derive(Clone, Copy)]
+pub enum Format {
+    Common,
+    Combined,
+    Json,
+}
+
+impl std::str::FromStr for Format {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "common" => Ok(Format::Common),
+            "combined" => Ok(Format::Combined),
+            "json" => Ok(Format::Json),
+            _ => Err(()),
+        }
+    }
+}
+
+pub struct AccessLog {
+    format: Format,
+    tx: mpsc::UnboundedSender<String>,
+}
+
+impl AccessLog {
+    /// Arranca el escritor en segundo plano; `None` si el access log está desactivado.
+    pub async fn start(config: &AccessLogConfig) -> Option<Arc<Self>> {
+        let target = config.target.clone()?;
+        let (tx, mut rx) = mpsc::unbounded_channel::<String>();
+
+        let mut out: Box<dyn tokio::io::AsyncWrite + Send + Unpin> = if target == "stdout" {
+            Box::new(tokio::io::stdout())
+        } else {
+            Box::new(
+                tokio::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&target)
+                    .await
+                    .expect("no se pudo abrir ACCESS_LOG"),
+            )
+        };
+
+        tokio::spawn(async move {
+            while let Some(line) = rx.recv().await {
+                if out.write_all(line.as_bytes()).await.is_err() {
+                    break;
+                }
+                let _ = out.flush().await;
+            }
+        });
+
+        Some(Arc::new(AccessLog {
+            format: config.format,
+            tx,
+        }))
+    }
+
+    fn write(&self, entry: Entry) {
+        let line = match self.format {
+            Format::Common => entry.common(),
+            Format::Combined => entry.combined(),
+            Format::Json => entry.json(),
+        };
+        let _ = self.tx.send(line + "\n");
+    }
+}
+
+struct Entry {
+    ip: String,
+    user: String,
+    time: chrono::DateTime<chrono::Local>,
+    request_line: String,
+    status: u16,
+    bytes: Option<u64>,
+    referer: String,
+    user_agent: String,
+    latency_ms: f64,
+}
+
+impl Entry {
+    fn common(&self) -> String {
+        format!(
+            "{} - {} [{}] \"{}\" {} {}",
+            self.ip,
+            self.user,
+            self.time.format("%d/%b/%Y:%H:%M:%S %z"),
+            self.request_line,
+            self.status,
+            self.bytes.map_or("-".to_string(), |b| b.to_string()),
+        )
+    }
+
+    fn combined(&self) -> String {
+        format!(
+            "{} \"{}\" \"{}\" {:.3}",
+            self.common(),
+            self.referer,
+            self.user_agent,
+            self.latency_ms / 1000.0,
+        )
+    }
+
+    fn json(&self) -> String {
+        serde_json::json!({
+            "time": self.time.to_rfc3339(),
+            "ip": self.ip,
+            "user": self.user,
+            "request": self.request_line,
+            "status": self.status,
+            "bytes": self.bytes,
+            "referer": self.referer,
+            "user_agent": self.user_agent,
+            "latency_ms": self.latency_ms,
+        })
+        .to_string()
+    }
+}
+
+/* ---------- MIDDLEWARE ---------- */
+
+pub async fn log_request(State(log): State<Arc<AccessLog>>, req: Request, next: Next) -> Response {
+    let start = Instant::now();
+    let time = chrono::Local::now();
+    let ip = client_ip(&req).map_or("-".to_string(), |ip| ip.to_string());
+    let request_line = format!("{} {} {:?}", req.method(), req.uri(), req.version());
+    let referer = header_or_dash(req.headers(), header::REFERER);
+    let user_agent = header_or_dash(req.headers(), header::USER_AGENT);
+
+    let res = next.run(req).await;
+
+    let bytes = res
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse().ok())
+        .or_else(|| res.body().size_hint().exact());
+
+    log.write(Entry {
+        ip,
+        user: "-".to_string(),
+        time,
+        request_line,
+        status: res.status().as_u16(),
+        bytes,
+        referer,
+        user_agent,
+        latency_ms: start.elapsed().as_secs_f64() * 1000.0,
+    });
+
+    res
+}
+
+fn header_or_dash(headers: &HeaderMap, name: header::HeaderName) -> String {
+    headers
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("-")
+        .replace('"', "\\\"")
+}
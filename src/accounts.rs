@@ -0,0 +1,164 @@
+//! Cuentas de autores: `POST /cuenta/registro` crea el usuario (rol `author`,
+//! ver `users`) y `POST /cuenta/login` abre la sesión, en una cookie
+//! `HttpOnly` aparte de la de administración. Con ella los mensajes de
+//! `/enviar` quedan a nombre de la cuenta y solo su autor puede editarlos
+//! (ver `policy`). Las sesiones viven en `author_sessions`, con el hash del
+//! token, y caducan a las `ACCOUNT_SESSION_TTL_HOURS`.
+
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{Html, IntoResponse, Response},
+    routing::post,
+    Form, Router,
+};
+use serde::Deserialize;
+use sqlx::PgPool;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::admin;
+use crate::csrf;
+use crate::db::DbError;
+use crate::edit_token;
+use crate::flash::{self, Flash};
+use crate::queries;
+use crate::state::{AppState, SharedState};
+use crate::users::{self, AUTHOR_ROLE};
+
+const COOKIE: &str = "author_session";
+
+/// Con el mismo freno que los accesos al panel y con CSRF: son formularios.
+pub fn routes(state: &SharedState) -> Router<SharedState> {
+    let router = Router::new()
+        .route("/registro", post(register))
+        .route("/login", post(login))
+        .route("/logout", post(logout))
+        .layer(axum::middleware::from_fn(csrf::verify));
+    admin::throttle(router, state)
+}
+
+/// Cuenta de la sesión vigente de la cookie, si sigue activa.
+pub async fn principal(pool: &PgPool, headers: &HeaderMap) -> Option<i32> {
+    let token = session_token(headers)?;
+
+    match queries::accounts::session_user(pool, &edit_token::hash(token)).await {
+        Ok(user) => user,
+        Err(e) => {
+            tracing::warn!(error = ?e, "no se pudo comprobar la sesión de autor");
+            None
+        }
+    }
+}
+
+fn session_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .find_map(|pair| pair.trim().strip_prefix(COOKIE)?.strip_prefix('='))
+        .filter(|token| !token.is_empty())
+}
+
+fn cookie(token: &str, max_age: Duration) -> HeaderValue {
+    format!(
+        "{COOKIE}={token}; Path=/; Max-Age={}; HttpOnly; Secure; SameSite=Strict",
+        max_age.as_secs()
+    )
+    .parse()
+    .unwrap()
+}
+
+/* ---------- HANDLERS ---------- */
+
+#[derive(Deserialize)]
+struct Credentials {
+    username: String,
+    password: String,
+}
+
+async fn register(State(app): State<SharedState>, headers: HeaderMap, Form(form): Form<Credentials>) -> Response {
+    let username = form.username.trim();
+    if let Some(reason) = users::invalid(username, &form.password) {
+        return failure(&headers, StatusCode::BAD_REQUEST, reason);
+    }
+    let Some(hash) = users::hash(form.password).await else {
+        return (StatusCode::INTERNAL_SERVER_ERROR, Html("❌ No se pudo crear la cuenta")).into_response();
+    };
+
+    let id = match queries::users::insert(&app.db, username, &hash, AUTHOR_ROLE).await {
+        Ok(Some(id)) => id,
+        Ok(None) => return failure(&headers, StatusCode::CONFLICT, "❌ Ese usuario ya existe"),
+        Err(e) => return e.into_response(),
+    };
+    tracing::info!(target: "audit", id, username, "cuenta de autor creada");
+
+    let mut res = sign_in(&app, id, &headers, "✅ Cuenta creada").await;
+    if res.status() == StatusCode::OK {
+        *res.status_mut() = StatusCode::CREATED;
+    }
+    res
+}
+
+async fn login(State(app): State<SharedState>, headers: HeaderMap, Form(form): Form<Credentials>) -> Response {
+    match users::check_author(&app.db, &form.username, &form.password).await {
+        Ok(Some(id)) => sign_in(&app, id, &headers, "✅ Sesión iniciada").await,
+        Ok(None) => {
+            tracing::warn!(target: "audit", username = form.username, "inicio de sesión de autor fallido");
+            failure(&headers, StatusCode::UNAUTHORIZED, "❌ Usuario o contraseña incorrectos")
+        }
+        Err(e) => e.into_response(),
+    }
+}
+
+async fn logout(State(app): State<SharedState>, headers: HeaderMap) -> Response {
+    if let Some(token) = session_token(&headers)
+        && let Err(e) = queries::accounts::delete_session(&app.db, &edit_token::hash(token)).await
+    {
+        return e.into_response();
+    }
+
+    let mut res = if flash::wants_html(&headers) {
+        flash::redirect("/", Flash::success("✅ Sesión cerrada"))
+    } else {
+        Html("✅ Sesión cerrada").into_response()
+    };
+    res.headers_mut().append(header::SET_COOKIE, cookie("", Duration::ZERO));
+    res
+}
+
+/// Abre la sesión y la entrega en `Set-Cookie`: con redirección a la portada
+/// desde un formulario o, desde `fetch`, con el texto.
+async fn sign_in(app: &AppState, user: i32, headers: &HeaderMap, message: &'static str) -> Response {
+    let ttl = app.config.accounts.session_ttl;
+    let token = match create(&app.db, user, ttl).await {
+        Ok(token) => token,
+        Err(e) => return e.into_response(),
+    };
+
+    let mut res = if flash::wants_html(headers) {
+        flash::redirect("/", Flash::success(message))
+    } else {
+        Html(message).into_response()
+    };
+    res.headers_mut().append(header::SET_COOKIE, cookie(&token, ttl));
+    res
+}
+
+fn failure(headers: &HeaderMap, status: StatusCode, message: &'static str) -> Response {
+    if flash::wants_html(headers) {
+        flash::redirect(&flash::back(headers, "/"), Flash::error(message))
+    } else {
+        (status, Html(message)).into_response()
+    }
+}
+
+async fn create(pool: &PgPool, user: i32, ttl: Duration) -> Result<String, DbError> {
+    // De paso se limpian las caducadas, como en `admin_session`.
+    queries::accounts::purge_sessions(pool).await?;
+
+    let token = Uuid::new_v4().simple().to_string();
+    queries::accounts::insert_session(pool, &edit_token::hash(&token), user, ttl).await?;
+    Ok(token)
+}
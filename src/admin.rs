@@ -0,0 +1,143 @@
+use axum::{
+    extract::{OriginalUri, Request, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    Router,
+};
+use sqlx::PgPool;
+use tower_http::set_header::SetResponseHeaderLayer;
+
+use crate::admin_session;
+use crate::audit_log;
+use crate::client_ip::client_ip;
+use crate::config::AdminConfig;
+use crate::jwt;
+use crate::policy::{Forbidden, Permission, Principal};
+use crate::queries;
+use crate::rate_limit::{self, RateLimiter};
+use crate::state::SharedState;
+
+/// Envuelve el router de administración con su propia pila de middleware:
+/// sin caché, rate limit más estricto, autenticación y auditoría. Qué puede
+/// hacer cada rol se decide dentro, por grupo de rutas, con `require`.
+pub fn protect(router: Router<SharedState>, state: &SharedState) -> Router<SharedState> {
+    let router = router
+        .layer(middleware::from_fn_with_state(state.clone(), audit))
+        .layer(middleware::from_fn_with_state(state.clone(), require_staff));
+    throttle(router, state)
+}
+
+/// Limita las rutas a los roles con `permission`; va dentro de `protect`.
+pub fn require(router: Router<SharedState>, permission: Permission) -> Router<SharedState> {
+    router.layer(middleware::from_fn_with_state(permission, require_permission))
+}
+
+/// Solo rate limit y sin caché: para las rutas de acceso, que no pueden pedir
+/// autenticación pero sí frenar la fuerza bruta igual que el resto.
+pub fn throttle(router: Router<SharedState>, state: &SharedState) -> Router<SharedState> {
+    let config = &state.config.admin;
+    let limiter = RateLimiter::new(config.rate_burst, config.rate_per_minute);
+
+    router
+        .layer(middleware::from_fn_with_state(limiter, rate_limit::limit))
+        .layer(SetResponseHeaderLayer::overriding(
+            header::CACHE_CONTROL,
+            HeaderValue::from_static("no-store"),
+        ))
+}
+
+/// `true` si la petición trae `Authorization: Bearer` con el `ADMIN_TOKEN` o
+/// con un JWT vigente de `POST /api/login`.
+pub fn has_admin_token(config: &AdminConfig, headers: &HeaderMap) -> bool {
+    let provided = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+
+    provided.is_some_and(|given| {
+        is_admin_token(config, given)
+            || config
+                .jwt_secret
+                .as_deref()
+                .is_some_and(|secret| jwt::verify(secret, given, chrono::Utc::now().timestamp()))
+    })
+}
+
+/// Sin token configurado ninguno vale.
+pub fn is_admin_token(config: &AdminConfig, given: &str) -> bool {
+    config.token.as_deref().is_some_and(|expected| constant_time_eq(expected, given))
+}
+
+/// Miembro del equipo por token bearer o, desde el navegador, por cookie de
+/// sesión: `Principal::Admin` o `Principal::User` con su rol.
+pub async fn staff(config: &AdminConfig, pool: &PgPool, headers: &HeaderMap) -> Option<Principal> {
+    if has_admin_token(config, headers) {
+        return Some(Principal::Admin);
+    }
+    admin_session::principal(pool, headers).await
+}
+
+/* ---------- MIDDLEWARE ---------- */
+
+/// Deja el `Principal` en las extensiones para `require_permission`.
+async fn require_staff(State(app): State<SharedState>, mut req: Request, next: Next) -> Response {
+    match staff(&app.config.admin, &app.db, req.headers()).await {
+        Some(principal) => {
+            req.extensions_mut().insert(principal);
+            next.run(req).await
+        }
+        None => (StatusCode::UNAUTHORIZED, "❌ No autorizado").into_response(),
+    }
+}
+
+async fn require_permission(State(permission): State<Permission>, req: Request, next: Next) -> Response {
+    let role = req.extensions().get::<Principal>().and_then(Principal::role);
+    if role.is_some_and(|role| role.allows(permission)) {
+        next.run(req).await
+    } else {
+        Forbidden.into_response()
+    }
+}
+
+/// Todo queda en el log (`target: "audit"`); lo que cambia algo se guarda
+/// además en `admin_audit` para verlo en `/admin/audit`.
+async fn audit(State(app): State<SharedState>, req: Request, next: Next) -> Response {
+    let method = req.method().clone();
+    let uri = req
+        .extensions()
+        .get::<OriginalUri>()
+        .map_or_else(|| req.uri().clone(), |OriginalUri(uri)| uri.clone());
+    let ip = client_ip(&req);
+
+    let res = next.run(req).await;
+
+    tracing::info!(
+        target: "audit",
+        %method,
+        %uri,
+        ip = ?ip,
+        status = res.status().as_u16(),
+        "acción de administración"
+    );
+
+    if !method.is_safe() {
+        let entry = audit_log::Entry {
+            method: method.as_str(),
+            uri: &uri.to_string(),
+            ip,
+            status: res.status().as_u16(),
+        };
+        if let Err(e) = queries::audit_log::insert_request(&app.db, &entry).await {
+            tracing::warn!(error = ?e, "no se pudo guardar la auditoría");
+        }
+    }
+    res
+}
+
+/* ---------- UTIL ---------- */
+
+pub fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes().zip(b.bytes()).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
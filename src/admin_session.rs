@@ -0,0 +1,227 @@
+//! Sesión de administración para el navegador: `POST /admin/login` cambia el
+//! token (o usuario y contraseña, ver `users`) por una cookie `HttpOnly`,
+//! `Secure` y `SameSite=Strict`, que a partir de ahí vale lo mismo que
+//! `Authorization: Bearer` para el panel y para las rutas que modifican
+//! mensajes e imágenes; con usuario, según su rol. Las sesiones viven en
+//! `admin_sessions` (con el hash del token, no el token) y caducan a las
+//! `ADMIN_SESSION_TTL_HOURS`. Tras varios fallos seguidos el acceso se
+//! bloquea un tiempo (ver `login_lockout`).
+
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{Html, IntoResponse, Redirect, Response},
+    routing::{get, post},
+    Form, Router,
+};
+use serde::Deserialize;
+use sqlx::PgPool;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::admin;
+use crate::client_ip::ClientIp;
+use crate::db::DbError;
+use crate::edit_token;
+use crate::flash::{self, Flash};
+use crate::html;
+use crate::login_lockout::{self, Locked};
+use crate::oauth;
+use crate::policy::{Principal, Role};
+use crate::queries;
+use crate::state::{AppState, SharedState};
+use crate::users;
+use crate::STATIC_DIR;
+
+const COOKIE: &str = "admin_session";
+
+/// `/admin/login` y `/admin/logout`, fuera de la autenticación.
+pub fn routes(state: &SharedState) -> Router<SharedState> {
+    let router = Router::new()
+        .route("/login", get(login_page).post(login))
+        .route("/logout", post(logout));
+    admin::throttle(router, state)
+}
+
+/// Dueño de la sesión vigente de la cookie: `Principal::Admin` si se abrió con
+/// el token, `Principal::User` si con un usuario que sigue activo.
+pub async fn principal(pool: &PgPool, headers: &HeaderMap) -> Option<Principal> {
+    let token = session_token(headers)?;
+
+    match queries::admin_session::session(pool, &edit_token::hash(token)).await {
+        Ok(None) => None,
+        Ok(Some(None)) => Some(Principal::Admin),
+        Ok(Some(Some((id, role)))) => Some(Principal::User { id, role: Role::parse(&role)? }),
+        Err(e) => {
+            tracing::warn!(error = ?e, "no se pudo comprobar la sesión de administración");
+            None
+        }
+    }
+}
+
+fn session_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .find_map(|pair| pair.trim().strip_prefix(COOKIE)?.strip_prefix('='))
+        .filter(|token| !token.is_empty())
+}
+
+fn cookie(token: &str, max_age: Duration) -> HeaderValue {
+    format!(
+        "{COOKIE}={token}; Path=/; Max-Age={}; HttpOnly; Secure; SameSite=Strict",
+        max_age.as_secs()
+    )
+    .parse()
+    .unwrap()
+}
+
+/* ---------- HANDLERS ---------- */
+
+async fn login_page(State(app): State<SharedState>) -> Html<String> {
+    Html(html::login_page(&app.setup.site(), &oauth::providers(&app.config.oauth)))
+}
+
+/// Con `token`, o con `username` y `password`.
+#[derive(Deserialize)]
+struct LoginForm {
+    token: Option<String>,
+    username: Option<String>,
+    password: Option<String>,
+}
+
+/// Desde el formulario, redirección al panel (o de vuelta con el error); desde
+/// `fetch`, el texto. En ambos casos la sesión va en `Set-Cookie`.
+async fn login(
+    State(app): State<SharedState>,
+    ClientIp(ip): ClientIp,
+    headers: HeaderMap,
+    Form(form): Form<LoginForm>,
+) -> Response {
+    let wants_html = flash::wants_html(&headers);
+
+    let username = form.username.as_deref().filter(|_| form.token.is_none());
+    let keys = login_lockout::keys(ip, username);
+    match login_lockout::locked(&app.db, &keys).await {
+        Ok(Some(locked)) => return locked_out(locked, wants_html),
+        Ok(None) => {}
+        Err(e) => return e.into_response(),
+    }
+
+    let (signed_in, failure) = match (&form.token, &form.username, &form.password) {
+        (Some(token), _, _) => {
+            let ok = admin::is_admin_token(&app.config.admin, token);
+            (ok.then_some(None), "❌ Token incorrecto")
+        }
+        (None, Some(username), Some(password)) => match users::check_password(&app.db, username, password).await {
+            Ok(user) => (user.map(Some), "❌ Usuario o contraseña incorrectos"),
+            Err(e) => return e.into_response(),
+        },
+        _ => (None, "❌ Faltan las credenciales"),
+    };
+
+    let Some(user) = signed_in else {
+        tracing::warn!(target: "audit", username = form.username, "inicio de sesión de administración fallido");
+        match login_lockout::record_failure(&app.db, &app.config.admin, &keys).await {
+            Ok(Some(locked)) => return locked_out(locked, wants_html),
+            Ok(None) => {}
+            Err(e) => return e.into_response(),
+        }
+        return if wants_html {
+            flash::redirect("/admin/login", Flash::error(failure))
+        } else {
+            (StatusCode::UNAUTHORIZED, Html(failure)).into_response()
+        };
+    };
+
+    tracing::info!(target: "audit", user, "sesión de administración iniciada");
+    if let Err(e) = login_lockout::clear(&app.db, &keys).await {
+        tracing::warn!(error = ?e, "no se pudieron borrar los fallos de acceso");
+    }
+    sign_in(&app, user, wants_html).await
+}
+
+fn locked_out(locked: Locked, wants_html: bool) -> Response {
+    if wants_html {
+        flash::redirect("/admin/login", Flash::error(locked.message()))
+    } else {
+        locked.into_response()
+    }
+}
+
+/// Abre una sesión (del usuario `user`, o de administración completa sin él)
+/// y la entrega en `Set-Cookie`: con redirección al panel o, desde `fetch`,
+/// con el texto.
+pub async fn sign_in(app: &AppState, user: Option<i32>, wants_html: bool) -> Response {
+    let ttl = app.config.admin.session_ttl;
+    let token = match create(&app.db, user, ttl).await {
+        Ok(token) => token,
+        Err(e) => return e.into_response(),
+    };
+
+    let mut res = if wants_html {
+        flash::redirect("/admin.html", Flash::success("✅ Sesión iniciada"))
+    } else {
+        Html("✅ Sesión iniciada").into_response()
+    };
+    res.headers_mut().append(header::SET_COOKIE, cookie(&token, ttl));
+    res
+}
+
+async fn create(pool: &PgPool, user: Option<i32>, ttl: Duration) -> Result<String, DbError> {
+    // De paso se limpian las caducadas; no hace falta una tarea aparte.
+    queries::admin_session::purge_sessions(pool).await?;
+
+    let token = Uuid::new_v4().simple().to_string();
+    queries::admin_session::insert_session(pool, &edit_token::hash(&token), user, ttl).await?;
+    Ok(token)
+}
+
+async fn logout(State(app): State<SharedState>, headers: HeaderMap) -> Response {
+    if let Some(token) = session_token(&headers)
+        && let Err(e) = queries::admin_session::delete_session(&app.db, &edit_token::hash(token)).await
+    {
+        return e.into_response();
+    }
+
+    let mut res = if flash::wants_html(&headers) {
+        flash::redirect("/admin/login", Flash::success("✅ Sesión cerrada"))
+    } else {
+        Html("✅ Sesión cerrada").into_response()
+    };
+    res.headers_mut().append(header::SET_COOKIE, cookie("", Duration::ZERO));
+    res
+}
+
+/// `GET /admin.html`: el panel solo se sirve con sesión (o token); sin ella, al login.
+pub async fn admin_html(principal: Principal) -> Response {
+    if principal.role().is_none() {
+        return Redirect::to("/admin/login").into_response();
+    }
+
+    match tokio::fs::read_to_string(std::path::Path::new(STATIC_DIR).join("admin.html")).await {
+        Ok(page) => ([(header::CACHE_CONTROL, "no-store")], Html(page)).into_response(),
+        Err(e) => {
+            tracing::error!(error = %e, "no se pudo leer admin.html");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_session_among_other_cookies() {
+        let mut headers = HeaderMap::new();
+        headers.append(header::COOKIE, "flash=success|ok; admin_session=abc".parse().unwrap());
+        assert_eq!(session_token(&headers), Some("abc"));
+
+        let mut headers = HeaderMap::new();
+        headers.append(header::COOKIE, "admin_session_old=x; admin_session=".parse().unwrap());
+        assert_eq!(session_token(&headers), None);
+    }
+}
@@ -0,0 +1,101 @@
+//! Akismet como segunda opinión sobre los mensajes nuevos: con
+//! `AKISMET_API_KEY`, `/enviar` pregunta por cada mensaje (`comment-check`) y
+//! los que Akismet da por spam van a la cuarentena, sume lo que sume la
+//! puntuación propia. Si moderación publica uno de esos, se le devuelve a
+//! Akismet como falso positivo (`submit-ham`) para que aprenda. Si Akismet no
+//! contesta, el mensaje sigue su camino normal: el captcha ya lo filtró.
+
+use reqwest::Client;
+use std::{net::IpAddr, time::Duration};
+
+use crate::config::AkismetConfig;
+
+/// Lo que Akismet necesita saber de un mensaje.
+pub struct Comment<'a> {
+    pub ip: Option<IpAddr>,
+    pub user_agent: Option<&'a str>,
+    pub nombre: &'a str,
+    pub email: Option<&'a str>,
+    pub mensaje: &'a str,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Verdict {
+    Ham,
+    Spam,
+    /// Sin respuesta válida (caído, clave mala, sin IP): no se retiene por esto.
+    Unavailable,
+}
+
+pub struct Akismet {
+    config: AkismetConfig,
+    client: Client,
+}
+
+impl Akismet {
+    pub fn new(config: &AkismetConfig) -> Self {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(5))
+            .build()
+            .expect("no se pudo crear el cliente HTTP de Akismet");
+        Akismet { config: config.clone(), client }
+    }
+
+    pub async fn check(&self, comment: &Comment<'_>) -> Verdict {
+        match self.call("comment-check", comment).await.as_deref() {
+            Some("true") => Verdict::Spam,
+            Some("false") => Verdict::Ham,
+            other => {
+                tracing::warn!(reply = ?other, "Akismet no pudo clasificar el mensaje");
+                Verdict::Unavailable
+            }
+        }
+    }
+
+    /// Falso positivo. Solo se registra si falla: la moderación ya está hecha.
+    pub async fn submit_ham(&self, comment: &Comment<'_>) {
+        if self.call("submit-ham", comment).await.is_none() {
+            tracing::warn!("no se pudo avisar a Akismet del falso positivo");
+        }
+    }
+
+    /// El cuerpo de la respuesta; `None` si no hay IP (la API la exige) o falla la petición.
+    async fn call(&self, method: &str, comment: &Comment<'_>) -> Option<String> {
+        let ip = comment.ip?.to_string();
+        let form = [
+            ("api_key", self.config.key.as_str()),
+            ("blog", &self.config.site),
+            ("blog_lang", "es"),
+            ("comment_type", "contact-form"),
+            ("user_ip", &ip),
+            ("user_agent", comment.user_agent.unwrap_or_default()),
+            ("comment_author", comment.nombre),
+            ("comment_author_email", comment.email.unwrap_or_default()),
+            ("comment_content", comment.mensaje),
+        ];
+        let url = format!("{}/1.1/{method}", self.config.url);
+        let res = self.client.post(&url).form(&form).send().await.ok()?;
+        res.error_for_status().ok()?.text().await.ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn comment(ip: Option<IpAddr>) -> Comment<'static> {
+        Comment { ip, user_agent: None, nombre: "Ana", email: None, mensaje: "Hola" }
+    }
+
+    #[tokio::test]
+    async fn no_answer_is_unavailable() {
+        let config = AkismetConfig {
+            key: "clave".to_string(),
+            site: "https://motos.example".to_string(),
+            url: "http://127.0.0.1:9".to_string(),
+        };
+        let akismet = Akismet::new(&config);
+        assert_eq!(akismet.check(&comment(Some("10.0.0.1".parse().unwrap()))).await, Verdict::Unavailable);
+        assert_eq!(akismet.check(&comment(None)).await, Verdict::Unavailable);
+    }
+}
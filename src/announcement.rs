@@ -0,0 +1,124 @@
+//! Aviso para todo el sitio, para que el dueño publique avisos sin editar los
+//! HTML. Se gestiona en `GET/PUT /admin/announcement`, con caducidad opcional;
+//! el vigente se sirve en `GET /announcement` (lo pinta `js/announcement.js` en
+//! las páginas estáticas) y va incluido en las que genera el servidor.
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+    Form, Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::audit_log::{self, Change};
+use crate::db::DbError;
+use crate::events::{self, Event};
+use crate::outbox;
+use crate::policy::Principal;
+use crate::queries;
+use crate::state::SharedState;
+use crate::unit_of_work::UnitOfWork;
+
+const MAX_CHARS: usize = 500;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Announcement {
+    pub message: String,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// `GET /announcement`: 204 si no hay aviso vigente.
+pub async fn public_announcement(State(app): State<SharedState>) -> Result<Response, DbError> {
+    Ok(match app.settings.announcement(&app.db).await? {
+        Some(announcement) => Json(announcement).into_response(),
+        None => StatusCode::NO_CONTENT.into_response(),
+    })
+}
+
+/* ---------- /admin/announcement ---------- */
+
+#[derive(Serialize)]
+pub struct Stored {
+    #[serde(flatten)]
+    pub announcement: Announcement,
+    pub updated_at: DateTime<Utc>,
+    pub active: bool,
+}
+
+/// El guardado aunque haya caducado, para poder revisarlo.
+pub async fn get_announcement(State(app): State<SharedState>) -> Result<Response, DbError> {
+    Ok(match queries::announcement::get(&app.db).await? {
+        Some(stored) => Json(stored).into_response(),
+        None => StatusCode::NO_CONTENT.into_response(),
+    })
+}
+
+/// `expires_at` en RFC 3339 y opcional; con `message` vacío se quita el aviso.
+#[derive(Deserialize)]
+pub struct AnnouncementForm {
+    message: String,
+    #[serde(default)]
+    expires_at: String,
+}
+
+pub async fn put_announcement(
+    State(app): State<SharedState>,
+    principal: Principal,
+    Form(form): Form<AnnouncementForm>,
+) -> Response {
+    // El anterior, caducado o no, para la auditoría.
+    let old = match queries::announcement::stored(&app.db).await {
+        Ok(old) => old.map(|old| serde_json::to_value(old).unwrap_or_default()),
+        Err(e) => return e.into_response(),
+    };
+
+    let message = form.message.trim();
+    if message.is_empty() {
+        let delete = async {
+            let mut uow = UnitOfWork::begin(&app.db).await?;
+            queries::announcement::delete(uow.conn()).await?;
+            outbox::enqueue(uow.conn(), &Event::SettingsChanged).await?;
+            uow.commit().await
+        };
+        return match delete.await {
+            Ok(()) => {
+                events::publish(&app, Event::SettingsChanged);
+                let change = Change { old, ..Change::new("announcement.delete", "announcement") };
+                audit_log::record_change(&app.db, &principal, change).await;
+                Html("✅ Aviso retirado").into_response()
+            }
+            Err(e) => e.into_response(),
+        };
+    }
+    if message.chars().count() > MAX_CHARS {
+        return (StatusCode::BAD_REQUEST, Html("❌ El aviso no puede pasar de 500 caracteres")).into_response();
+    }
+    let expires_at = match form.expires_at.trim() {
+        "" => None,
+        text => match DateTime::parse_from_rfc3339(text) {
+            Ok(at) => Some(at.with_timezone(&Utc)),
+            Err(_) => return (StatusCode::BAD_REQUEST, Html("❌ Fecha de caducidad inválida")).into_response(),
+        },
+    };
+
+    let upsert = async {
+        let mut uow = UnitOfWork::begin(&app.db).await?;
+        queries::announcement::put(uow.conn(), message, expires_at).await?;
+        outbox::enqueue(uow.conn(), &Event::SettingsChanged).await?;
+        uow.commit().await
+    };
+
+    match upsert.await {
+        Ok(()) => {
+            events::publish(&app, Event::SettingsChanged);
+            let new = Announcement { message: message.to_string(), expires_at };
+            let change = Change { old, ..Change::new("announcement.update", "announcement") }
+                .new_value(serde_json::to_value(new).unwrap_or_default());
+            audit_log::record_change(&app.db, &principal, change).await;
+            Html("✅ Aviso publicado").into_response()
+        }
+        Err(e) => e.into_response(),
+    }
+}
@@ -0,0 +1,138 @@
+//! Claves de API para acceso programático a `/mensajes` y `/images` con la
+//! cabecera `X-Api-Key`. Administración las crea y revoca en
+//! `/api/admin/api-keys`; la clave solo se muestra al crearla y en la base de
+//! datos queda su SHA-256. Cada una tiene un alcance: `read` solo lee (sin el
+//! límite suave de lectura de los visitantes) y `write` además puede editar y
+//! borrar como administración.
+
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::{Html, IntoResponse, Response},
+    Form, Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::audit_log::{self, Change};
+use crate::db::DbError;
+use crate::policy::Principal;
+use crate::queries;
+use crate::state::SharedState;
+
+pub const HEADER: &str = "x-api-key";
+
+/// Prefijo fijo de las claves, para reconocerlas si se filtran.
+const KEY_PREFIX: &str = "hak_";
+
+/// Caracteres de la clave que se guardan en claro para identificarla.
+const SHOWN_CHARS: usize = 12;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    Read,
+    Write,
+}
+
+impl Scope {
+    fn as_str(self) -> &'static str {
+        match self {
+            Scope::Read => "read",
+            Scope::Write => "write",
+        }
+    }
+}
+
+fn hash(key: &str) -> String {
+    format!("{:x}", Sha256::digest(key.as_bytes()))
+}
+
+/// Clave de `X-Api-Key` vigente: su id y alcance. `Ok(None)` si no hay cabecera;
+/// `Err` si la hay pero no vale (o no se pudo comprobar).
+pub async fn authenticate(pool: &PgPool, headers: &HeaderMap) -> Result<Option<(i32, Scope)>, InvalidApiKey> {
+    let Some(key) = headers.get(HEADER) else { return Ok(None) };
+    let key = key.to_str().map_err(|_| InvalidApiKey)?;
+
+    match queries::api_keys::touch(pool, &hash(key)).await {
+        Ok(Some((id, scope))) => Ok(Some((id, if scope == "write" { Scope::Write } else { Scope::Read }))),
+        Ok(None) => Err(InvalidApiKey),
+        Err(e) => {
+            tracing::warn!(error = ?e, "no se pudo comprobar la clave de API");
+            Err(InvalidApiKey)
+        }
+    }
+}
+
+pub struct InvalidApiKey;
+
+impl IntoResponse for InvalidApiKey {
+    fn into_response(self) -> Response {
+        (StatusCode::UNAUTHORIZED, Html("❌ Clave de API no válida")).into_response()
+    }
+}
+
+/* ---------- /api/admin/api-keys ---------- */
+
+#[derive(Deserialize)]
+pub struct NewKey {
+    name: String,
+    scope: Scope,
+}
+
+#[derive(Serialize)]
+struct Created {
+    id: i32,
+    name: String,
+    scope: Scope,
+    /// Única vez que se ve entera.
+    key: String,
+}
+
+pub async fn create(State(app): State<SharedState>, principal: Principal, Form(new): Form<NewKey>) -> Response {
+    let name = new.name.trim();
+    if name.is_empty() || name.chars().count() > 100 {
+        return (StatusCode::BAD_REQUEST, Html("❌ Nombre inválido")).into_response();
+    }
+
+    let key = format!("{KEY_PREFIX}{}", Uuid::new_v4().simple());
+    match queries::api_keys::insert(&app.db, name, &key[..SHOWN_CHARS], &hash(&key), new.scope.as_str()).await {
+        Ok(id) => {
+            let change = Change::new("api_key.create", format!("api_key:{id}"))
+                .new_value(serde_json::json!({ "name": name, "scope": new.scope.as_str() }));
+            audit_log::record_change(&app.db, &principal, change).await;
+            (StatusCode::CREATED, Json(Created { id, name: name.to_string(), scope: new.scope, key })).into_response()
+        }
+        Err(e) => e.into_response(),
+    }
+}
+
+#[derive(Serialize)]
+pub struct KeyInfo {
+    pub id: i32,
+    pub name: String,
+    pub prefix: String,
+    pub scope: String,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+pub async fn list(State(app): State<SharedState>) -> Result<Json<Vec<KeyInfo>>, DbError> {
+    Ok(Json(queries::api_keys::list(&app.db).await?))
+}
+
+/// La clave deja de valer al momento; el registro se conserva para el historial.
+pub async fn revoke(State(app): State<SharedState>, principal: Principal, Path(id): Path<i32>) -> Response {
+    match queries::api_keys::revoke(&app.db, id).await {
+        Ok(false) => (StatusCode::NOT_FOUND, Html("❌ Clave no encontrada")).into_response(),
+        Ok(true) => {
+            audit_log::record_change(&app.db, &principal, Change::new("api_key.revoke", format!("api_key:{id}"))).await;
+            Html("✅ Clave revocada").into_response()
+        }
+        Err(e) => e.into_response(),
+    }
+}
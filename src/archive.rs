@@ -0,0 +1,78 @@
+//! Archivo de mensajes: los que pasan de `MESSAGES_ARCHIVE_AFTER_DAYS` se
+//! mueven de `mensajes` a `mensajes_archive`, para que la tabla caliente (y
+//! con ella la paginación, los recuentos y la búsqueda) no crezca con los
+//! años. El detalle y el enlace permanente siguen encontrándolos por id, y
+//! moderación los edita, los borra y los exporta igual que los publicados.
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+};
+use sqlx::PgPool;
+use std::time::Duration;
+
+use crate::audit_log::{self, Change};
+use crate::db::DbError;
+use crate::dry_run::{self, DryRunQuery, Preview};
+use crate::policy::Principal;
+use crate::queries;
+use crate::state::SharedState;
+
+/// Cada cuánto se buscan mensajes que archivar.
+const ARCHIVE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Mensajes por sentencia: la primera vuelta tras activarlo puede mover años
+/// de mensajes, y así ninguna transacción bloquea la tabla mucho rato.
+const BATCH: i64 = 1000;
+
+pub async fn archive_loop(pool: PgPool, after: Duration) {
+    let mut interval = tokio::time::interval(ARCHIVE_INTERVAL);
+    loop {
+        interval.tick().await;
+        match archive(&pool, after).await {
+            Ok(0) => {}
+            Ok(n) => tracing::info!(archived = n, "mensajes archivados"),
+            Err(err) => tracing::warn!(error = ?err, "no se pudieron archivar los mensajes"),
+        }
+    }
+}
+
+/// Mueve por lotes todo lo anterior a `after` (ver `queries::archive::archive_batch`).
+async fn archive(pool: &PgPool, after: Duration) -> Result<u64, DbError> {
+    let mut total = 0;
+    loop {
+        let moved = queries::archive::archive_batch(pool, after, BATCH).await?;
+        total += moved;
+        if moved < BATCH as u64 {
+            return Ok(total);
+        }
+    }
+}
+
+/// `POST /api/admin/mensajes/archive`: lo mismo que el bucle, sin esperar a la próxima vuelta.
+pub async fn archive_now(
+    State(app): State<SharedState>,
+    principal: Principal,
+    Query(dry): Query<DryRunQuery>,
+) -> Response {
+    let Some(after) = app.config.content.archive_after else {
+        return (StatusCode::CONFLICT, Html("❌ El archivo de mensajes está desactivado")).into_response();
+    };
+
+    if dry.dry_run {
+        return match queries::archive::archivable(&app.jobs_db, after, dry_run::SAMPLE as i64).await {
+            Ok((count, ids)) => Preview::new(count, ids).into_response(),
+            Err(e) => e.into_response(),
+        };
+    }
+
+    match archive(&app.jobs_db, after).await {
+        Ok(n) => {
+            let change = Change::new("mensaje.archive", "mensajes").new_value(serde_json::json!({ "archived": n }));
+            audit_log::record_change(&app.db, &principal, change).await;
+            Html(format!("✅ {n} mensajes archivados")).into_response()
+        }
+        Err(e) => e.into_response(),
+    }
+}
@@ -0,0 +1,190 @@
+//! Auditoría de administración. Cada petición que cambia algo queda en
+//! `admin_audit` (método, ruta, estado), con su vista en `GET /admin/audit`,
+//! paginada y filtrable por método, estado y ruta. Además, los handlers que
+//! borran o modifican apuntan el cambio en `audit_log` con quién lo hizo, sobre
+//! qué y los valores de antes y después; se consulta en `GET /api/admin/audit`.
+
+use axum::{
+    extract::{Query, State},
+    http::HeaderMap,
+    response::Html,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::PgPool;
+use std::net::IpAddr;
+
+use crate::db::DbError;
+use crate::html;
+use crate::pagination::{self, PageQuery, Paginated};
+use crate::policy::Principal;
+use crate::queries;
+use crate::server_timing;
+use crate::state::SharedState;
+use crate::timezone::Zone;
+
+pub struct Entry<'a> {
+    pub method: &'a str,
+    pub uri: &'a str,
+    pub ip: Option<IpAddr>,
+    pub status: u16,
+}
+
+#[derive(Deserialize)]
+pub struct AuditQuery {
+    /// `POST`, `PUT`, `DELETE`…; vacío para todos.
+    #[serde(default)]
+    method: String,
+    /// Clase de respuesta: `2xx`, `4xx`…; vacío para todas.
+    #[serde(default)]
+    status: String,
+    /// Texto contenido en la ruta.
+    #[serde(default)]
+    q: String,
+}
+
+/// Igual que la tabla de mensajes: fragmento con `HX-Request`, página completa sin él.
+pub async fn audit_page(
+    State(app): State<SharedState>,
+    Query(query): Query<AuditQuery>,
+    page: PageQuery,
+    zone: Zone,
+    headers: HeaderMap,
+) -> Result<Html<String>, DbError> {
+    let method = query.method.trim().to_ascii_uppercase();
+    let class = status_class(&query.status);
+    let q = query.q.trim();
+    let filter = queries::audit_log::RequestFilter { method: &method, class, q };
+    let total = queries::audit_log::count_requests(&app.db, &filter).await?;
+
+    let per_page = page.per_page();
+    let pages = pagination::total_pages(total, per_page);
+    let page = page.page().min(pages);
+
+    let rows = queries::audit_log::requests(&app.db, &filter, per_page, (page - 1) * per_page).await?;
+    let times: Vec<DateTime<Utc>> = rows.iter().map(|r| r.created_at).collect();
+    let times = zone.localize(&app.db, &times).await?;
+
+    let rows: Vec<html::AuditRow> = rows
+        .into_iter()
+        .zip(times)
+        .map(|(r, created_at)| html::AuditRow { created_at, method: r.method, uri: r.uri, ip: r.ip, status: r.status })
+        .collect();
+
+    let table = html::AuditTable {
+        rows: &rows,
+        page,
+        pages,
+        method: &method,
+        status: if class > 0 { &query.status } else { "" },
+        q,
+        tz: zone.query_value(),
+    };
+
+    Ok(Html(server_timing::render(|| {
+        let fragment = html::audit_table(&table);
+        if headers.contains_key("hx-request") {
+            fragment
+        } else {
+            html::admin_page(&app.setup.site(), "Auditoría", &fragment)
+        }
+    })))
+}
+
+/* ---------- audit_log ---------- */
+
+/// Un cambio: `action` como `mensaje.delete`, `target` como `mensaje:42`.
+pub struct Change {
+    pub action: &'static str,
+    pub target: String,
+    pub old: Option<Value>,
+    pub new: Option<Value>,
+}
+
+impl Change {
+    pub fn new(action: &'static str, target: impl std::fmt::Display) -> Self {
+        Change { action, target: target.to_string(), old: None, new: None }
+    }
+
+    pub fn old(mut self, value: Value) -> Self {
+        self.old = Some(value);
+        self
+    }
+
+    pub fn new_value(mut self, value: Value) -> Self {
+        self.new = Some(value);
+        self
+    }
+}
+
+/// `admin`, `user:3`, `api_key:7`, `author:5`, `ip:1.2.3.4`…
+fn actor(principal: &Principal) -> String {
+    match principal {
+        Principal::Admin => "admin".to_string(),
+        Principal::User { id, .. } => format!("user:{id}"),
+        Principal::ApiKey { id, .. } => format!("api_key:{id}"),
+        Principal::Author { id } => format!("author:{id}"),
+        Principal::Visitor { ip, .. } => format!("ip:{ip}"),
+        Principal::Anonymous => "anonymous".to_string(),
+    }
+}
+
+/// El cambio ya está hecho: si no se puede apuntar, solo se avisa en el log.
+pub async fn record_change(pool: &PgPool, principal: &Principal, change: Change) {
+    record_change_as(pool, &actor(principal), change).await;
+}
+
+/// Para lo que no hace nadie desde una petición, como los comandos de mantenimiento.
+pub async fn record_change_as(pool: &PgPool, actor: &str, change: Change) {
+    tracing::info!(target: "audit", actor, action = change.action, target = change.target, "cambio");
+
+    if let Err(e) = queries::audit_log::insert_change(pool, actor, &change).await {
+        tracing::warn!(error = ?e, "no se pudo guardar el cambio en la auditoría");
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ChangesQuery {
+    /// Acción exacta (`mensaje.delete`) o familia (`mensaje`); vacío para todas.
+    #[serde(default)]
+    action: String,
+    /// `user:3`, `admin`…; vacío para todos.
+    #[serde(default)]
+    actor: String,
+}
+
+#[derive(Serialize)]
+pub struct ChangeEntry {
+    pub id: i64,
+    pub created_at: DateTime<Utc>,
+    pub actor: String,
+    pub action: String,
+    pub target: String,
+    pub old_value: Option<Value>,
+    pub new_value: Option<Value>,
+}
+
+/// `GET /api/admin/audit`: los más recientes primero.
+pub async fn list_changes(
+    State(app): State<SharedState>,
+    Query(query): Query<ChangesQuery>,
+    page: PageQuery,
+) -> Result<Json<Paginated<ChangeEntry>>, DbError> {
+    let action = query.action.trim();
+    let actor = query.actor.trim();
+    let total = queries::audit_log::count_changes(&app.db, action, actor).await?;
+    let entries = queries::audit_log::changes(&app.db, action, actor, page.per_page(), page.offset()).await?;
+    Ok(Json(Paginated::new(entries, total, &page)))
+}
+
+/// `4xx` → 4; cualquier otra cosa, sin filtro.
+fn status_class(status: &str) -> i16 {
+    status
+        .trim()
+        .strip_suffix("xx")
+        .and_then(|c| c.parse().ok())
+        .filter(|c| (1..=5).contains(c))
+        .unwrap_or(0)
+}
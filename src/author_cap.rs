@@ -0,0 +1,71 @@
+//! Tope diario de mensajes por autor: cuenta lo publicado en las últimas 24 h
+//! con el mismo nombre o desde la misma IP. A diferencia del limitador de
+//! ritmo, que solo corta ráfagas, esto frena a quien escribe poco a poco todo el día.
+
+use axum::{
+    http::{header, StatusCode},
+    response::{Html, IntoResponse, Response},
+};
+use chrono::{DateTime, Duration, Utc};
+use sqlx::PgPool;
+use std::net::IpAddr;
+
+use crate::db::DbError;
+use crate::queries;
+
+const WINDOW_HOURS: i64 = 24;
+
+/// Rechazo por haber llegado al tope.
+pub struct CapReached {
+    /// Cuándo sale de la ventana el mensaje más antiguo y queda hueco.
+    pub frees_at: DateTime<Utc>,
+}
+
+impl CapReached {
+    pub const CODE: &'static str = "daily_cap";
+
+    pub fn message(&self) -> String {
+        format!(
+            "❌ Has alcanzado el máximo de mensajes por día; podrás escribir de nuevo el {}",
+            self.frees_at.format("%Y-%m-%d a las %H:%M UTC")
+        )
+    }
+}
+
+impl IntoResponse for CapReached {
+    fn into_response(self) -> Response {
+        let wait = (self.frees_at - Utc::now()).num_seconds().max(1);
+        (
+            StatusCode::TOO_MANY_REQUESTS,
+            [
+                (header::RETRY_AFTER, wait.to_string()),
+                (header::HeaderName::from_static("x-error-code"), Self::CODE.to_string()),
+            ],
+            Html(self.message()),
+        )
+            .into_response()
+    }
+}
+
+/// `Err(CapReached)` si el autor ya publicó `cap` mensajes en la ventana.
+/// Con `cap == 0` no hay tope.
+pub async fn check(
+    pool: &PgPool,
+    cap: i64,
+    ip: Option<IpAddr>,
+    nombre: &str,
+) -> Result<Result<(), CapReached>, DbError> {
+    if cap == 0 {
+        return Ok(Ok(()));
+    }
+
+    let (count, oldest) = queries::author_cap::recent(pool, WINDOW_HOURS as i32, ip, nombre).await?;
+
+    if count < cap {
+        return Ok(Ok(()));
+    }
+    let oldest = oldest.unwrap_or_else(Utc::now);
+    Ok(Err(CapReached {
+        frees_at: oldest + Duration::hours(WINDOW_HOURS),
+    }))
+}
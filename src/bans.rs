@@ -0,0 +1,114 @@
+//! IPs bloqueadas: direcciones sueltas o rangos CIDR en `banned_ips` que
+//! moderación gestiona en `/api/admin/bans`. `reject` va delante de `/enviar`
+//! y de las subidas de imágenes; leer sigue permitido.
+
+use axum::{
+    extract::{Path, Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::{Html, IntoResponse, Response},
+    Form, Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use crate::audit_log::{self, Change};
+use crate::client_ip::{self, Network};
+use crate::db::DbError;
+use crate::policy::Principal;
+use crate::queries;
+use crate::state::SharedState;
+
+/// Sin IP o sin poder consultar la tabla no se deja pasar: solo protege
+/// escrituras, y sin la base de datos tampoco se podrían guardar.
+pub async fn reject(State(app): State<SharedState>, req: Request, next: Next) -> Response {
+    let unavailable = || (StatusCode::SERVICE_UNAVAILABLE, Html("❌ No se puede enviar ahora mismo, inténtalo más tarde"));
+    let Some(ip) = client_ip::client_ip(&req) else {
+        // Solo pasa por un socket Unix si el proxy no manda `X-Forwarded-For`.
+        tracing::warn!("petición sin IP de cliente: no se puede comprobar si está bloqueada");
+        return unavailable().into_response();
+    };
+    match queries::bans::is_banned(&app.db, ip).await {
+        Ok(true) => {
+            tracing::info!(%ip, "petición rechazada: IP bloqueada");
+            (StatusCode::FORBIDDEN, Html("❌ Tu dirección IP está bloqueada")).into_response()
+        }
+        Ok(false) => next.run(req).await,
+        Err(e) => {
+            tracing::warn!(error = ?e, "no se pudo comprobar la lista de IPs bloqueadas");
+            unavailable().into_response()
+        }
+    }
+}
+
+/// `1.2.3.4`, `2001:db8::1` o con prefijo (`10.0.0.0/8`).
+fn valid_network(network: &str) -> bool {
+    network.parse::<Network>().is_ok()
+}
+
+/* ---------- /api/admin/bans ---------- */
+
+#[derive(Deserialize)]
+pub struct NewBan {
+    network: String,
+    #[serde(default)]
+    reason: String,
+}
+
+#[derive(Serialize)]
+pub struct Ban {
+    pub id: i32,
+    pub network: String,
+    pub reason: String,
+    pub created_at: DateTime<Utc>,
+}
+
+pub async fn create(State(app): State<SharedState>, principal: Principal, Form(new): Form<NewBan>) -> Response {
+    let network = new.network.trim();
+    let reason = new.reason.trim();
+    if !valid_network(network) {
+        return (StatusCode::BAD_REQUEST, Html("❌ Dirección o rango inválido")).into_response();
+    }
+
+    match queries::bans::insert(&app.db, network, reason).await {
+        Ok(Some(ban)) => {
+            let change = Change::new("ban.create", format!("ban:{}", ban.id))
+                .new_value(serde_json::json!({ "network": ban.network, "reason": ban.reason }));
+            audit_log::record_change(&app.db, &principal, change).await;
+            (StatusCode::CREATED, Json(ban)).into_response()
+        }
+        Ok(None) => (StatusCode::CONFLICT, Html("❌ Ese rango ya está bloqueado")).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+pub async fn list(State(app): State<SharedState>) -> Result<Json<Vec<Ban>>, DbError> {
+    Ok(Json(queries::bans::list(&app.db).await?))
+}
+
+pub async fn remove(State(app): State<SharedState>, principal: Principal, Path(id): Path<i32>) -> Response {
+    match queries::bans::delete(&app.db, id).await {
+        Ok(None) => (StatusCode::NOT_FOUND, Html("❌ Bloqueo no encontrado")).into_response(),
+        Ok(Some((network, reason))) => {
+            let change = Change::new("ban.delete", format!("ban:{id}"))
+                .old(serde_json::json!({ "network": network, "reason": reason }));
+            audit_log::record_change(&app.db, &principal, change).await;
+            Html("✅ Bloqueo eliminado").into_response()
+        }
+        Err(e) => e.into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validates_addresses_and_ranges() {
+        assert!(valid_network("10.0.0.1"));
+        assert!(valid_network("10.0.0.0/8"));
+        assert!(valid_network("2001:db8::/32"));
+        assert!(!valid_network("10.0.0.0/33"));
+        assert!(!valid_network("10.0.0.0/"));
+        assert!(!valid_network("example.com"));
+    }
+}
@@ -0,0 +1,46 @@
+//! Límite de cuerpo por ruta. Si el `Content-Length` declarado ya lo supera se
+//! contesta 413 de inmediato, sin leer ni un byte; sin `Content-Length` (chunked)
+//! lo aplica `DefaultBodyLimit` mientras se lee.
+
+use axum::{
+    extract::{DefaultBodyLimit, Request, State},
+    http::{header, StatusCode},
+    middleware::{self, Next},
+    response::{Html, IntoResponse, Response},
+    routing::MethodRouter,
+};
+
+/// Formularios de mensajes: texto corto codificado como URL.
+pub const FORM: usize = 16 * 1024;
+
+/// Holgura para las cabeceras y separadores del multipart.
+pub const MULTIPART_OVERHEAD: usize = 64 * 1024;
+
+/// Aplica el límite `max` (en bytes) a todos los métodos de la ruta.
+pub fn limit<S>(router: MethodRouter<S>, max: usize) -> MethodRouter<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    router
+        .layer(DefaultBodyLimit::max(max))
+        .layer(middleware::from_fn_with_state(max, reject_oversized))
+}
+
+async fn reject_oversized(State(max): State<usize>, req: Request, next: Next) -> Response {
+    let declared = req
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok());
+
+    if declared.is_some_and(|len| len > max as u64) {
+        // Sin leer el cuerpo no se puede reutilizar la conexión.
+        return (
+            StatusCode::PAYLOAD_TOO_LARGE,
+            [(header::CONNECTION, "close")],
+            Html("❌ La petición es demasiado grande"),
+        )
+            .into_response();
+    }
+    next.run(req).await
+}
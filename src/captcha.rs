@@ -0,0 +1,257 @@
+//! Verificación del captcha de `/enviar` en el servidor, con el proveedor que
+//! diga `CAPTCHA_PROVIDER`: reCAPTCHA (por defecto), Cloudflare Turnstile o
+//! hCaptcha. Los tres usan el mismo protocolo `siteverify`: `POST` con el
+//! secreto (`CAPTCHA_SECRET`), la respuesta del widget y la IP del autor. Con
+//! reCAPTCHA v3 la respuesta trae una puntuación y por debajo de
+//! `CAPTCHA_MIN_SCORE` se rechaza. Sin secreto no arranca, salvo con
+//! `CAPTCHA_UNVERIFIED=true`: entonces solo se comprueba que el campo venga
+//! relleno, que en local es lo práctico.
+//!
+//! El formulario pinta el widget que toca con `js/captcha.js`, que lo pide a
+//! `GET /captcha`: cambiar de proveedor no obliga a tocar los HTML.
+
+use axum::{async_trait, extract::State, Json};
+use serde::{Deserialize, Serialize};
+use std::{net::IpAddr, sync::Arc, time::Duration};
+
+use crate::config::CaptchaConfig;
+use crate::state::SharedState;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Provider {
+    Recaptcha,
+    Turnstile,
+    Hcaptcha,
+}
+
+impl Provider {
+    /// Campo del formulario en el que el widget deja su respuesta.
+    pub fn field(self) -> &'static str {
+        match self {
+            Provider::Recaptcha => "g-recaptcha-response",
+            Provider::Turnstile => "cf-turnstile-response",
+            Provider::Hcaptcha => "h-captcha-response",
+        }
+    }
+
+    pub fn verify_url(self) -> &'static str {
+        match self {
+            Provider::Recaptcha => "https://www.google.com/recaptcha/api/siteverify",
+            Provider::Turnstile => "https://challenges.cloudflare.com/turnstile/v0/siteverify",
+            Provider::Hcaptcha => "https://api.hcaptcha.com/siteverify",
+        }
+    }
+}
+
+impl std::str::FromStr for Provider {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "recaptcha" => Ok(Provider::Recaptcha),
+            "turnstile" => Ok(Provider::Turnstile),
+            "hcaptcha" => Ok(Provider::Hcaptcha),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Verdict {
+    Passed,
+    /// El proveedor no lo da por bueno, o la puntuación no llega al mínimo.
+    Failed,
+    /// No se pudo preguntar al proveedor: se rechaza igual, pero se avisa distinto.
+    Unavailable,
+}
+
+#[async_trait]
+pub trait CaptchaVerifier: Send + Sync {
+    fn provider(&self) -> Provider;
+
+    async fn verify(&self, response: &str, ip: Option<IpAddr>) -> Verdict;
+}
+
+/// El verificador de `CAPTCHA_PROVIDER`.
+pub fn verifier(config: &CaptchaConfig) -> Arc<dyn CaptchaVerifier> {
+    let Some(secret) = config.secret.clone() else {
+        tracing::warn!("captcha sin verificar (CAPTCHA_UNVERIFIED): /enviar acepta cualquier respuesta");
+        return Arc::new(Unverified(config.provider));
+    };
+    let client = SiteVerify::new(config, secret);
+    match config.provider {
+        Provider::Recaptcha => Arc::new(Recaptcha { client, min_score: config.min_score }),
+        Provider::Turnstile => Arc::new(Turnstile(client)),
+        Provider::Hcaptcha => Arc::new(Hcaptcha(client)),
+    }
+}
+
+/* ---------- siteverify ---------- */
+
+#[derive(Deserialize)]
+struct Reply {
+    success: bool,
+    /// reCAPTCHA v3: de 0 (bot) a 1 (persona).
+    score: Option<f64>,
+    #[serde(rename = "error-codes", default)]
+    error_codes: Vec<String>,
+}
+
+struct SiteVerify {
+    url: String,
+    secret: String,
+    client: reqwest::Client,
+}
+
+impl SiteVerify {
+    fn new(config: &CaptchaConfig, secret: String) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(5))
+            .build()
+            .expect("no se pudo crear el cliente HTTP del captcha");
+        let url = config.verify_url.clone().unwrap_or_else(|| config.provider.verify_url().to_string());
+        SiteVerify { url, secret, client }
+    }
+
+    async fn ask(&self, response: &str, ip: Option<IpAddr>) -> Result<Reply, reqwest::Error> {
+        let remoteip = ip.map(|ip| ip.to_string());
+        let mut form = vec![("secret", self.secret.as_str()), ("response", response)];
+        if let Some(ip) = &remoteip {
+            form.push(("remoteip", ip));
+        }
+        let res = self.client.post(&self.url).form(&form).send().await?;
+        res.error_for_status()?.json().await
+    }
+
+    /// Respuesta vacía, proveedor caído o `success: false`; el resto, a `judge`.
+    async fn check(&self, response: &str, ip: Option<IpAddr>, judge: impl FnOnce(&Reply) -> bool) -> Verdict {
+        if response.is_empty() {
+            return Verdict::Failed;
+        }
+        match self.ask(response, ip).await {
+            Ok(reply) if reply.success && judge(&reply) => Verdict::Passed,
+            Ok(reply) => {
+                tracing::info!(score = ?reply.score, errors = ?reply.error_codes, "captcha rechazado");
+                Verdict::Failed
+            }
+            Err(e) => {
+                tracing::warn!(error = %e, "no se pudo verificar el captcha");
+                Verdict::Unavailable
+            }
+        }
+    }
+}
+
+struct Recaptcha {
+    client: SiteVerify,
+    min_score: f64,
+}
+
+#[async_trait]
+impl CaptchaVerifier for Recaptcha {
+    fn provider(&self) -> Provider {
+        Provider::Recaptcha
+    }
+
+    async fn verify(&self, response: &str, ip: Option<IpAddr>) -> Verdict {
+        // v2 no trae puntuación.
+        let min_score = self.min_score;
+        self.client.check(response, ip, |reply| reply.score.is_none_or(|score| score >= min_score)).await
+    }
+}
+
+struct Turnstile(SiteVerify);
+
+#[async_trait]
+impl CaptchaVerifier for Turnstile {
+    fn provider(&self) -> Provider {
+        Provider::Turnstile
+    }
+
+    async fn verify(&self, response: &str, ip: Option<IpAddr>) -> Verdict {
+        self.0.check(response, ip, |_| true).await
+    }
+}
+
+/// La puntuación de hCaptcha Enterprise va al revés (1 es bot) y no se usa.
+struct Hcaptcha(SiteVerify);
+
+#[async_trait]
+impl CaptchaVerifier for Hcaptcha {
+    fn provider(&self) -> Provider {
+        Provider::Hcaptcha
+    }
+
+    async fn verify(&self, response: &str, ip: Option<IpAddr>) -> Verdict {
+        self.0.check(response, ip, |_| true).await
+    }
+}
+
+/// Sin secreto configurado: basta con que el widget haya respondido algo.
+struct Unverified(Provider);
+
+#[async_trait]
+impl CaptchaVerifier for Unverified {
+    fn provider(&self) -> Provider {
+        self.0
+    }
+
+    async fn verify(&self, response: &str, _ip: Option<IpAddr>) -> Verdict {
+        if response.is_empty() { Verdict::Failed } else { Verdict::Passed }
+    }
+}
+
+/* ---------- GET /captcha ---------- */
+
+#[derive(Serialize)]
+pub struct Widget {
+    provider: Provider,
+    site_key: Option<String>,
+    /// Campo en el que `/enviar` espera la respuesta, para clientes sin widget.
+    field: &'static str,
+}
+
+/// Lo que necesita `js/captcha.js` para pintar el widget.
+pub async fn widget(State(app): State<SharedState>) -> Json<Widget> {
+    let config = &app.config.captcha;
+    Json(Widget { provider: config.provider, site_key: config.site_key.clone(), field: config.provider.field() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(provider: Provider) -> CaptchaConfig {
+        CaptchaConfig {
+            provider,
+            secret: Some("secreto".to_string()),
+            site_key: None,
+            min_score: 0.5,
+            verify_url: Some("http://127.0.0.1:9/siteverify".to_string()),
+        }
+    }
+
+    #[test]
+    fn each_provider_reads_its_own_field() {
+        let fields: Vec<_> = [Provider::Recaptcha, Provider::Turnstile, Provider::Hcaptcha]
+            .into_iter()
+            .map(|p| verifier(&config(p)).provider().field())
+            .collect();
+        assert_eq!(fields, ["g-recaptcha-response", "cf-turnstile-response", "h-captcha-response"]);
+    }
+
+    #[tokio::test]
+    async fn unreachable_provider_is_unavailable() {
+        let verifier = verifier(&config(Provider::Turnstile));
+        assert_eq!(verifier.verify("token", None).await, Verdict::Unavailable);
+        assert_eq!(verifier.verify("", None).await, Verdict::Failed);
+    }
+
+    #[tokio::test]
+    async fn without_secret_any_response_passes() {
+        let verifier = verifier(&CaptchaConfig { secret: None, ..config(Provider::Hcaptcha) });
+        assert_eq!(verifier.verify("token", None).await, Verdict::Passed);
+        assert_eq!(verifier.verify("", None).await, Verdict::Failed);
+    }
+}
@@ -0,0 +1,202 @@
+//! IP del cliente. Por defecto es la del socket. `X-Forwarded-For` solo se lee
+//! si quien conecta es un proxy de `TRUSTED_PROXIES` (o llega por un socket
+//! Unix, que solo alcanza el proxy local), y entonces se toma el salto más a la
+//! derecha que no sea de confianza: lo de su izquierda lo escribe el cliente.
+//! `resolve` la calcula una vez por petición, por fuera de todo el middleware.
+
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRequestParts, Request, State},
+    http::{request::Parts, Extensions, HeaderMap},
+    middleware::Next,
+    response::Response,
+};
+use std::{
+    convert::Infallible,
+    net::{IpAddr, SocketAddr},
+    str::FromStr,
+    sync::Arc,
+};
+
+/// Dirección suelta (`10.0.0.1`) o rango CIDR (`10.0.0.0/8`, `fd00::/8`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Network {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl FromStr for Network {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        let (addr, prefix) = match s.trim().split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (s.trim(), None),
+        };
+        let addr: IpAddr = addr.parse().map_err(|_| ())?;
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(p) => p.parse::<u8>().ok().filter(|p| *p <= max).ok_or(())?,
+            None => max,
+        };
+        Ok(Network { addr, prefix })
+    }
+}
+
+impl Network {
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.addr, ip.to_canonical()) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// `TRUSTED_PROXIES`: redes separadas por comas; vacía, no se confía en nadie.
+#[derive(Debug, Clone, Default)]
+pub struct TrustedProxies(Vec<Network>);
+
+impl FromStr for TrustedProxies {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        let networks = s.split(',').filter(|n| !n.trim().is_empty()).map(str::parse).collect::<Result<_, _>>()?;
+        Ok(TrustedProxies(networks))
+    }
+}
+
+impl TrustedProxies {
+    fn trusts(&self, ip: IpAddr) -> bool {
+        self.0.iter().any(|net| net.contains(ip))
+    }
+}
+
+/// La conexión llegó por un socket Unix (ver `server::serve_unix`).
+#[derive(Clone, Copy)]
+pub struct UnixPeer;
+
+fn from_parts(trusted: &TrustedProxies, headers: &HeaderMap, extensions: &Extensions) -> Option<IpAddr> {
+    let peer = extensions
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip().to_canonical());
+    let from_proxy = match peer {
+        Some(ip) => trusted.trusts(ip),
+        None => extensions.get::<UnixPeer>().is_some(),
+    };
+    if !from_proxy {
+        return peer;
+    }
+
+    // Todas las cabeceras, en orden: cada proxy añade el salto que vio a la derecha.
+    let hops = headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(|hop| hop.trim().parse::<IpAddr>().ok().map(|ip| ip.to_canonical()));
+    let mut client = peer;
+    for hop in hops.rev() {
+        // Lo ilegible lo escribió el cliente: se queda el último salto de confianza.
+        let Some(ip) = hop else { break };
+        client = Some(ip);
+        if !trusted.trusts(ip) {
+            break;
+        }
+    }
+    client
+}
+
+/// La IP que resolvió `resolve`; sin ella (fuera de la app), la del socket.
+pub fn client_ip(req: &Request) -> Option<IpAddr> {
+    match req.extensions().get::<ClientIp>() {
+        Some(ClientIp(ip)) => *ip,
+        None => from_parts(&TrustedProxies::default(), req.headers(), req.extensions()),
+    }
+}
+
+/* ---------- MIDDLEWARE ---------- */
+
+/// Por fuera de todo el middleware, para que todos vean la misma IP.
+pub async fn resolve(State(trusted): State<Arc<TrustedProxies>>, mut req: Request, next: Next) -> Response {
+    let ip = from_parts(&trusted, req.headers(), req.extensions());
+    req.extensions_mut().insert(ClientIp(ip));
+    next.run(req).await
+}
+
+/// Extractor con la IP del cliente, para handlers que la guardan o la usan como identidad.
+#[derive(Clone, Copy)]
+pub struct ClientIp(pub Option<IpAddr>);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for ClientIp {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(match parts.extensions.get::<ClientIp>() {
+            Some(resolved) => *resolved,
+            None => ClientIp(from_parts(&TrustedProxies::default(), &parts.headers, &parts.extensions)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolve_with(trusted: &str, peer: Option<&str>, xff: &[&str]) -> Option<IpAddr> {
+        let trusted: TrustedProxies = trusted.parse().unwrap();
+        let mut headers = HeaderMap::new();
+        for value in xff {
+            headers.append("x-forwarded-for", value.parse().unwrap());
+        }
+        let mut extensions = Extensions::new();
+        match peer {
+            Some(peer) => {
+                extensions.insert(ConnectInfo(SocketAddr::new(peer.parse().unwrap(), 4000)));
+            }
+            None => {
+                extensions.insert(UnixPeer);
+            }
+        }
+        from_parts(&trusted, &headers, &extensions)
+    }
+
+    fn ip(s: &str) -> Option<IpAddr> {
+        Some(s.parse().unwrap())
+    }
+
+    #[test]
+    fn forwarded_for_only_counts_from_trusted_proxies() {
+        // Sin proxy de confianza, la cabecera no cuenta.
+        assert_eq!(resolve_with("", Some("203.0.113.9"), &["10.0.0.1"]), ip("203.0.113.9"));
+        assert_eq!(resolve_with("10.0.0.0/8", Some("203.0.113.9"), &["1.2.3.4"]), ip("203.0.113.9"));
+
+        // Desde el proxy: el salto más a la derecha que no es de confianza.
+        assert_eq!(resolve_with("10.0.0.0/8", Some("10.0.0.2"), &["6.6.6.6, 198.51.100.7"]), ip("198.51.100.7"));
+        assert_eq!(resolve_with("10.0.0.0/8", Some("10.0.0.2"), &["6.6.6.6", "198.51.100.7, 10.0.0.3"]), ip("198.51.100.7"));
+        assert_eq!(resolve_with("10.0.0.0/8", Some("::ffff:10.0.0.2"), &["198.51.100.7"]), ip("198.51.100.7"));
+        assert_eq!(resolve_with("10.0.0.0/8", Some("10.0.0.2"), &[]), ip("10.0.0.2"));
+        assert_eq!(resolve_with("10.0.0.0/8", Some("10.0.0.2"), &["basura, 10.0.0.3"]), ip("10.0.0.3"));
+
+        // Por socket Unix el proxy es local.
+        assert_eq!(resolve_with("", None, &["198.51.100.7"]), ip("198.51.100.7"));
+    }
+
+    #[test]
+    fn networks_parse_and_match() {
+        let net: Network = "192.168.0.0/16".parse().unwrap();
+        assert!(net.contains("192.168.4.1".parse().unwrap()));
+        assert!(!net.contains("192.169.0.1".parse().unwrap()));
+        assert!("0.0.0.0/0".parse::<Network>().unwrap().contains("8.8.8.8".parse().unwrap()));
+        assert!("2001:db8::/32".parse::<Network>().unwrap().contains("2001:db8::1".parse().unwrap()));
+        assert!("10.0.0.0/33".parse::<Network>().is_err());
+        assert!("no".parse::<TrustedProxies>().is_err());
+    }
+}
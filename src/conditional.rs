@@ -0,0 +1,123 @@
+//! Validadores HTTP para listados y medios: `ETag` (hash del cuerpo),
+//! `Content-Length` exacto y peticiones condicionales (`If-None-Match`,
+//! `If-Modified-Since`) con 304. Sirve igual para `HEAD`, que axum resuelve con
+//! el handler de `GET` quitando el cuerpo pero conservando las cabeceras.
+//! Solo se leen cuerpos de tamaño conocido y hasta `MAX_BODY`; los demás pasan
+//! tal cual, sin `ETag`.
+
+use std::time::SystemTime;
+
+use axum::{
+    body::{to_bytes, Body, HttpBody},
+    extract::Request,
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+
+/// Respuestas mayores se sirven sin `ETag`.
+const MAX_BODY: u64 = 16 * 1024 * 1024;
+
+pub async fn validate(req: Request, next: Next) -> Response {
+    if req.method() != Method::GET && req.method() != Method::HEAD {
+        return next.run(req).await;
+    }
+    let conditions = req.headers().clone();
+    let res = next.run(req).await;
+    // Sin tamaño conocido (un stream) podría pasar del tope a mitad de lectura.
+    let fits = res.body().size_hint().upper().is_some_and(|len| len <= MAX_BODY);
+    if res.status() != StatusCode::OK || !fits {
+        return res;
+    }
+
+    let (mut parts, body) = res.into_parts();
+    let Ok(bytes) = to_bytes(body, MAX_BODY as usize).await else {
+        tracing::error!("no se pudo leer la respuesta para calcular su ETag");
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+    if !parts.headers.contains_key(header::ETAG) {
+        parts.headers.insert(header::ETAG, etag(&bytes));
+    }
+    parts.headers.insert(header::CONTENT_LENGTH, HeaderValue::from(bytes.len()));
+
+    if not_modified(&conditions, &parts.headers) {
+        parts.status = StatusCode::NOT_MODIFIED;
+        parts.headers.remove(header::CONTENT_LENGTH);
+        return Response::from_parts(parts, Body::empty());
+    }
+    Response::from_parts(parts, Body::from(bytes))
+}
+
+fn etag(bytes: &[u8]) -> HeaderValue {
+    let hash = format!("{:x}", Sha256::digest(bytes));
+    HeaderValue::from_str(&format!("\"{}\"", &hash[..32])).unwrap()
+}
+
+/// `If-None-Match` manda; sin él se mira `If-Modified-Since`.
+fn not_modified(request: &HeaderMap, response: &HeaderMap) -> bool {
+    if let Some(tags) = request.get(header::IF_NONE_MATCH).and_then(|v| v.to_str().ok()) {
+        let Some(etag) = response.get(header::ETAG).and_then(|v| v.to_str().ok()) else {
+            return false;
+        };
+        let etag = etag.trim_start_matches("W/");
+        return tags.split(',').map(str::trim).any(|t| t == "*" || t.trim_start_matches("W/") == etag);
+    }
+
+    let since = request.get(header::IF_MODIFIED_SINCE).and_then(|v| parse_date(v.to_str().ok()?));
+    let modified = response.get(header::LAST_MODIFIED).and_then(|v| parse_date(v.to_str().ok()?));
+    matches!((since, modified), (Some(since), Some(modified)) if modified <= since)
+}
+
+/// Fecha HTTP (`Wed, 21 Oct 2015 07:28:00 GMT`) para `Last-Modified`.
+pub fn http_date(time: SystemTime) -> HeaderValue {
+    let time = DateTime::<Utc>::from(time);
+    HeaderValue::from_str(&time.format("%a, %d %b %Y %H:%M:%S GMT").to_string()).unwrap()
+}
+
+fn parse_date(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc2822(value).ok().map(|d| d.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_etags_and_dates() {
+        let mut response = HeaderMap::new();
+        response.insert(header::ETAG, etag(b"hola"));
+        response.insert(header::LAST_MODIFIED, http_date(SystemTime::UNIX_EPOCH));
+
+        let mut request = HeaderMap::new();
+        request.insert(header::IF_NONE_MATCH, HeaderValue::from_static("\"otro\", W/\"x\""));
+        assert!(!not_modified(&request, &response));
+        request.insert(header::IF_NONE_MATCH, response[header::ETAG].clone());
+        assert!(not_modified(&request, &response));
+
+        let mut request = HeaderMap::new();
+        request.insert(header::IF_MODIFIED_SINCE, HeaderValue::from_static("Thu, 01 Jan 1970 00:00:01 GMT"));
+        assert!(not_modified(&request, &response));
+    }
+
+    #[tokio::test]
+    async fn oversized_and_streamed_bodies_pass_through() {
+        use axum::{routing::get, Router};
+        use futures_util::stream;
+        use tower::ServiceExt;
+
+        let app = Router::new()
+            .route("/big", get(|| async { vec![b'x'; MAX_BODY as usize + 1] }))
+            .route("/stream", get(|| async { Body::from_stream(stream::iter([Ok::<_, std::io::Error>("hola")])) }))
+            .route("/small", get(|| async { "hola" }))
+            .layer(axum::middleware::from_fn(validate));
+
+        for (uri, tagged) in [("/big", false), ("/stream", false), ("/small", true)] {
+            let req = Request::builder().uri(uri).body(Body::empty()).unwrap();
+            let res = app.clone().oneshot(req).await.unwrap();
+            assert_eq!(res.status(), StatusCode::OK, "{uri}");
+            assert_eq!(res.headers().contains_key(header::ETAG), tagged, "{uri}");
+        }
+    }
+}
@@ -0,0 +1,866 @@
+use std::{env, net::SocketAddr, path::PathBuf, time::Duration};
+
+use axum::http::{HeaderName, HeaderValue, Method};
+
+use crate::access_log;
+use crate::captcha;
+use crate::client_ip::TrustedProxies;
+use crate::file_types::{self, FileTypePolicy};
+use crate::json_case;
+use crate::logging::LogFormat;
+use crate::metrics::DEFAULT_BUCKETS;
+use crate::pagination::PageSize;
+use crate::security_headers;
+use crate::server::{self, Listen};
+use crate::trace::Sampler;
+use crate::uploads::DimensionLimits;
+
+#[derive(Clone)]
+pub struct Config {
+    pub metrics: MetricsConfig,
+    pub access_log: AccessLogConfig,
+    pub log: LogConfig,
+    pub db: DbConfig,
+    pub admin: AdminConfig,
+    pub server: ServerConfig,
+    pub uploads: UploadsConfig,
+    pub content: ContentConfig,
+    pub alerts: AlertConfig,
+    pub trace: TraceConfig,
+    pub reads: ReadsConfig,
+    pub writes: WritesConfig,
+    pub mail: MailConfig,
+    pub captcha: CaptchaConfig,
+    /// Con `AKISMET_API_KEY`, cada mensaje nuevo se consulta a Akismet (ver `akismet`).
+    pub akismet: Option<AkismetConfig>,
+    pub oauth: OAuthConfig,
+    pub events: EventsConfig,
+    pub security_headers: SecurityHeadersConfig,
+    pub cors: CorsConfig,
+    pub accounts: AccountsConfig,
+    pub pagination: PaginationConfig,
+}
+
+#[derive(Clone)]
+pub struct MetricsConfig {
+    /// Límites superiores (en segundos) de los buckets del histograma de latencia.
+    pub buckets: Vec<f64>,
+    /// Máximo de rutas distintas con etiqueta propia; el resto se agrupa en `other`.
+    pub max_routes: usize,
+}
+
+#[derive(Clone)]
+pub struct AccessLogConfig {
+    /// `stdout` o ruta de fichero; `None` desactiva el access log.
+    pub target: Option<String>,
+    pub format: access_log::Format,
+}
+
+#[derive(Clone)]
+pub struct LogConfig {
+    /// Directorio de ficheros de log; `None` escribe en stdout.
+    pub dir: Option<String>,
+    /// `minutely`, `hourly`, `daily`, `weekly` o `never`.
+    pub rotation: String,
+    /// Ficheros rotados que se conservan.
+    pub max_files: usize,
+    pub format: LogFormat,
+    /// Si es `Some`, registra cuerpos de petición/respuesta hasta ese tamaño.
+    pub debug_payloads: Option<usize>,
+}
+
+#[derive(Clone)]
+pub struct DbConfig {
+    pub url: String,
+    pub max_connections: u32,
+    /// Conexiones abiertas en el arranque y mantenidas abiertas.
+    pub min_connections: u32,
+    /// Tiempo máximo esperando una conexión libre del pool.
+    pub acquire_timeout: Duration,
+    /// `statement_timeout` aplicado a cada conexión nueva.
+    pub statement_timeout: Duration,
+    /// Pool aparte de los trabajos en segundo plano y exportaciones (ver `db::connect_jobs`).
+    pub jobs_max_connections: u32,
+    /// Más holgado que el de las peticiones: una purga o una exportación puede tardar.
+    pub jobs_statement_timeout: Duration,
+    /// Umbral a partir del cual una consulta se registra como lenta.
+    pub slow_query: Duration,
+    /// Consultas por petición a partir de las cuales se avisa (posible N+1).
+    pub query_budget: u32,
+    /// Si es `true`, cada respuesta lleva su número de consultas en `X-Db-Queries`.
+    pub debug_query_count: bool,
+}
+
+#[derive(Clone)]
+pub struct AdminConfig {
+    /// Token bearer para `/api/admin`; sin él la API de administración queda cerrada.
+    pub token: Option<String>,
+    pub rate_burst: u32,
+    pub rate_per_minute: u32,
+    /// Vida de la sesión que abre `POST /admin/login`.
+    pub session_ttl: Duration,
+    /// Clave HS256 de los JWT de `POST /api/login`; sin ella no se emiten ni aceptan.
+    pub jwt_secret: Option<String>,
+    pub jwt_ttl: Duration,
+    /// Fallos seguidos (por IP o por usuario) que bloquean el acceso.
+    pub lockout_threshold: i32,
+    /// Primer bloqueo; cada fallo más lo duplica, hasta `lockout_max`.
+    pub lockout_base: Duration,
+    pub lockout_max: Duration,
+}
+
+#[derive(Clone)]
+pub struct ServerConfig {
+    /// Listeners públicos (`LISTEN`, separados por comas).
+    pub listen: Vec<Listen>,
+    /// Listeners internos para métricas y administración (`INTERNAL_LISTEN`).
+    /// Si está vacío, esas rutas se sirven junto a las públicas.
+    pub internal_listen: Vec<Listen>,
+    /// Permisos (octal) del socket Unix.
+    pub socket_mode: u32,
+    /// Activa `SO_REUSEPORT` para relevos sin cortes entre instancias.
+    pub reuse_port: bool,
+    /// URL pública del sitio (`https://ejemplo.com`) para enlaces absolutos.
+    pub public_url: Option<String>,
+    /// Claves del JSON de la API (`JSON_CASE`: `snake` o `camel`); cada
+    /// petición puede pedir otra con `X-Json-Case`.
+    pub json_case: json_case::Case,
+    /// HTTPS en los listeners TCP de `LISTEN`, sin proxy delante (ver `tls`).
+    pub tls: Option<TlsConfig>,
+    /// Cuerpo máximo de las rutas sin límite propio (`MAX_BODY_SIZE`, `2M` por
+    /// defecto); las subidas y los formularios tienen el suyo (ver `body_limit`).
+    pub max_body: usize,
+    /// Con `SERVER_TIMING=true` cada respuesta lleva su `Server-Timing` (ver `server_timing`).
+    pub timing: bool,
+    /// Proxies cuyo `X-Forwarded-For` se cree (`TRUSTED_PROXIES`, ver `client_ip`).
+    pub trusted_proxies: TrustedProxies,
+}
+
+#[derive(Clone)]
+pub struct TlsConfig {
+    /// Cadena de certificados en PEM (`TLS_CERT`).
+    pub cert: PathBuf,
+    /// Clave privada en PEM (`TLS_KEY`).
+    pub key: PathBuf,
+    /// Dirección HTTP que solo redirige a HTTPS (`TLS_REDIRECT_LISTEN`, p. ej. `0.0.0.0:80`).
+    pub redirect_listen: Option<SocketAddr>,
+}
+
+#[derive(Clone)]
+pub struct UploadsConfig {
+    /// Con `IMAGES_ENABLED=false` las rutas de imágenes responden 503 y el
+    /// resto de la aplicación sigue como siempre.
+    pub enabled: bool,
+    /// Directorio de las imágenes subidas; se canonicaliza al arrancar.
+    pub dir: PathBuf,
+    /// Subidas diarias por identidad (IP o cuenta).
+    pub quota_uploads: u32,
+    /// Bytes diarios por identidad.
+    pub quota_bytes: u64,
+    /// Tiempo que una imagen borrada puede restaurarse antes de purgarla.
+    pub trash_retention: Duration,
+    /// `POST /upload-image-url`: el servidor descarga la imagen. Desactivado por defecto.
+    pub from_url: bool,
+    /// Tiempo máximo de esa descarga, redirecciones incluidas.
+    pub fetch_timeout: Duration,
+    /// Formatos admitidos, con su tamaño máximo y su procesado (`UPLOAD_TYPES`,
+    /// con los tamaños rebajados a `MAX_IMAGE_SIZE`).
+    pub types: FileTypePolicy,
+    /// Ancho, alto y megapíxeles máximos, comprobados antes de decodificar.
+    pub dimensions: DimensionLimits,
+    /// Con `UPLOADS_PRIVATE=true`, `/uploads` solo sirve URL firmadas (ver `signed_urls`).
+    pub private: Option<SignedUrlConfig>,
+}
+
+#[derive(Clone)]
+pub struct SignedUrlConfig {
+    /// Clave HMAC de las URL (`UPLOAD_URL_SECRET`, mínimo 32 caracteres).
+    pub secret: String,
+    /// Vida de cada URL (`UPLOAD_URL_TTL_SECS`).
+    pub ttl: Duration,
+}
+
+/// Límites para los mensajes nuevos: heurísticas de contenido y tope diario.
+#[derive(Clone)]
+pub struct ContentConfig {
+    pub max_links: usize,
+    /// Menciones tipo `@alguien`.
+    pub max_mentions: usize,
+    /// Porcentaje máximo de letras en mayúscula.
+    pub max_uppercase_pct: u32,
+    /// Veces que puede aparecer una misma línea.
+    pub max_repeated_lines: usize,
+    /// Mensajes por autor (nombre o IP) en 24 h; 0 desactiva el tope.
+    pub daily_per_author: i64,
+    /// Longitud (en caracteres) del `excerpt` de los listados.
+    pub excerpt_chars: usize,
+    /// Edad a partir de la cual los mensajes pasan a `mensajes_archive`
+    /// (`MESSAGES_ARCHIVE_AFTER_DAYS`); `None` (0, por defecto) no archiva.
+    pub archive_after: Option<Duration>,
+    /// Palabras que delatan spam (`SPAM_KEYWORDS`, separadas por comas); en minúsculas.
+    pub spam_keywords: Vec<String>,
+    /// Puntuación a partir de la cual un mensaje nuevo queda en cuarentena en
+    /// vez de publicarse (`SPAM_QUARANTINE_SCORE`, 80); `None` (0) publica todo.
+    pub quarantine_score: Option<i32>,
+    /// Colación ICU para ordenar por nombre, del idioma de `SORT_LANGUAGE`
+    /// (`es` por defecto: `es-x-icu`, con la Á junto a la A y la Ñ tras la N).
+    pub collation: String,
+}
+
+/// Vigilante interno: avisa por webhook cuando algo pasa de su umbral.
+#[derive(Clone)]
+pub struct AlertConfig {
+    /// Sin URL (`ALERT_WEBHOOK_URL`) no se vigila nada.
+    pub webhook: Option<String>,
+    pub interval: Duration,
+    /// Mínimo tiempo entre dos avisos del mismo tipo.
+    pub cooldown: Duration,
+    /// Fracción de respuestas 5xx en un intervalo.
+    pub error_rate: f64,
+    /// Por debajo de estas peticiones en el intervalo no se calcula la tasa.
+    pub min_requests: u64,
+    pub db_latency: Duration,
+    /// Porcentaje de ocupación del disco de subidas.
+    pub disk_usage_pct: f64,
+}
+
+/// Muestreo de trazas por petición.
+#[derive(Clone)]
+pub struct TraceConfig {
+    pub sampler: Sampler,
+    /// Fracción muestreada con `ratio` y en las raíces de `parent`.
+    pub ratio: f64,
+    /// Valor de `X-Debug-Trace` que fuerza la traza; sin él no se puede forzar.
+    pub debug_token: Option<String>,
+}
+
+/// Límite suave de lectura en `GET /mensajes`: quien lo pasa recibe la última
+/// copia en caché en vez de un error.
+#[derive(Clone)]
+pub struct ReadsConfig {
+    pub rate_burst: u32,
+    pub rate_per_minute: u32,
+    /// Antigüedad máxima de la copia que se sirve al pasar el límite.
+    pub cache_ttl: Duration,
+    /// Consultas distintas (página, tamaño, cursor) que se guardan.
+    pub cache_entries: usize,
+    /// Vida de la copia en memoria del aviso del sitio (ver `settings_cache`).
+    pub settings_ttl: Duration,
+}
+
+/// Límite por IP de `/enviar` y las subidas, compartido entre ellas: pasado el
+/// límite se responde 429 con `Retry-After`.
+#[derive(Clone)]
+pub struct WritesConfig {
+    pub rate_burst: u32,
+    pub rate_per_minute: u32,
+}
+
+/// Envío de correos (verificación del email de los autores) por una API HTTP.
+#[derive(Clone)]
+pub struct MailConfig {
+    /// Sin URL (`MAIL_API_URL`) los correos solo se registran en el log.
+    pub api_url: Option<String>,
+    pub api_token: Option<String>,
+    pub from: String,
+    /// Validez de los enlaces de verificación y de confirmación.
+    pub verify_ttl: Duration,
+    /// Con `EMAIL_CONFIRMATION_REQUIRED=true` el email es obligatorio y el
+    /// mensaje no se publica hasta abrir el enlace (ver `email_verification`).
+    /// Con esto o con `MAIL_API_URL` hace falta `PUBLIC_URL`: los enlaces de
+    /// los correos llevan un token y nunca se arman con `Host`.
+    pub confirmation_required: bool,
+}
+
+/// Captcha de `/enviar`, verificado contra su proveedor (ver `captcha`).
+#[derive(Clone)]
+pub struct CaptchaConfig {
+    pub provider: captcha::Provider,
+    /// Sin secreto solo se exige que el campo venga relleno; para eso hace
+    /// falta `CAPTCHA_UNVERIFIED=true`.
+    pub secret: Option<String>,
+    /// Clave pública del widget, para `GET /captcha`.
+    pub site_key: Option<String>,
+    /// Puntuación mínima de reCAPTCHA v3 (de 0 a 1); v2 no trae puntuación.
+    pub min_score: f64,
+    /// Sustituye al `siteverify` del proveedor.
+    pub verify_url: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct AkismetConfig {
+    pub key: String,
+    /// El `blog` de la API: la URL del sitio (`AKISMET_SITE`, o `PUBLIC_URL`).
+    pub site: String,
+    /// Sustituye a `https://rest.akismet.com`.
+    pub url: String,
+}
+
+/// Acceso a administración con Google o GitHub. Un proveedor sin cliente
+/// (`OAUTH_<PROVEEDOR>_CLIENT_ID` y `_SECRET`) queda desactivado.
+#[derive(Clone)]
+pub struct OAuthConfig {
+    pub google: Option<OAuthClient>,
+    pub github: Option<OAuthClient>,
+}
+
+#[derive(Clone)]
+pub struct OAuthClient {
+    pub client_id: String,
+    pub client_secret: String,
+}
+
+/// Reparto de eventos del dominio (ver `events`).
+#[derive(Clone)]
+pub struct EventsConfig {
+    /// Con URL (`EVENTS_REDIS_URL`) los eventos se comparten entre réplicas.
+    pub redis_url: Option<String>,
+    pub redis_channel: String,
+    /// Recibe cada evento en JSON por `POST`.
+    pub webhook: Option<String>,
+    /// Dirección a la que avisar de cada mensaje nuevo.
+    pub notify_email: Option<String>,
+}
+
+/// Cabeceras de seguridad de todas las respuestas (ver `security_headers`).
+#[derive(Clone)]
+pub struct SecurityHeadersConfig {
+    pub headers: Vec<(HeaderName, HeaderValue)>,
+    /// Con `CSP_NONCE=true` las páginas HTML llevan su nonce (ver `csp_nonce`).
+    pub csp_nonce: bool,
+}
+
+/// Cuentas de autores (ver `accounts`).
+#[derive(Clone)]
+pub struct AccountsConfig {
+    /// Vida de la sesión que abre `POST /cuenta/login`.
+    pub session_ttl: Duration,
+}
+
+/// CORS de las rutas públicas. Sin listas (o con `*`) se admite cualquier
+/// origen, método y cabecera, como en desarrollo; en producción se acota con
+/// `CORS_ORIGINS`, `CORS_METHODS` y `CORS_HEADERS`. `CORS_CREDENTIALS=true`
+/// no arranca sin una lista de orígenes.
+#[derive(Clone)]
+pub struct CorsConfig {
+    pub origins: Option<Vec<HeaderValue>>,
+    pub methods: Option<Vec<Method>>,
+    pub headers: Option<Vec<HeaderName>>,
+    /// `Access-Control-Allow-Credentials` (cookies de sesión entre orígenes).
+    pub credentials: bool,
+    /// Tiempo que el navegador puede reutilizar un preflight.
+    pub max_age: Duration,
+}
+
+/// Tamaños de página por ruta. `PAGE_SIZE_DEFAULT`/`PAGE_SIZE_MAX` valen para
+/// todas y `PAGE_SIZES` las cambia por ruta, con la ruta tal como está
+/// registrada: `/mensajes=5,/admin/mensajes=50:200` (`defecto[:máximo]`).
+#[derive(Clone)]
+pub struct PaginationConfig {
+    pub default: PageSize,
+    pub routes: Vec<(String, PageSize)>,
+}
+
+impl PaginationConfig {
+    pub fn size(&self, route: &str) -> PageSize {
+        self.routes
+            .iter()
+            .find(|(r, _)| r == route)
+            .map_or(self.default, |(_, size)| *size)
+    }
+}
+
+impl Config {
+    pub fn from_env() -> Self {
+        Config::from_vars(&Vars(&|key| env::var(key).ok()))
+    }
+
+    pub fn from_vars(v: &Vars) -> Self {
+        Config {
+            metrics: MetricsConfig::from_vars(v),
+            access_log: AccessLogConfig::from_vars(v),
+            log: LogConfig::from_vars(v),
+            db: DbConfig::from_vars(v),
+            admin: AdminConfig::from_vars(v),
+            server: ServerConfig::from_vars(v),
+            uploads: UploadsConfig::from_vars(v),
+            content: ContentConfig::from_vars(v),
+            alerts: AlertConfig::from_vars(v),
+            trace: TraceConfig::from_vars(v),
+            reads: ReadsConfig::from_vars(v),
+            writes: WritesConfig::from_vars(v),
+            mail: MailConfig::from_vars(v),
+            captcha: CaptchaConfig::from_vars(v),
+            akismet: AkismetConfig::from_vars(v),
+            oauth: OAuthConfig::from_vars(v),
+            events: EventsConfig::from_vars(v),
+            security_headers: SecurityHeadersConfig::from_vars(v),
+            cors: CorsConfig::from_vars(v),
+            accounts: AccountsConfig::from_vars(v),
+            pagination: PaginationConfig::from_vars(v),
+        }
+    }
+}
+
+impl MetricsConfig {
+    fn from_vars(v: &Vars) -> Self {
+        let buckets = match v.get("METRICS_BUCKETS") {
+            Some(raw) => parse_buckets(&raw).expect("METRICS_BUCKETS inválido"),
+            None => DEFAULT_BUCKETS.to_vec(),
+        };
+
+        MetricsConfig {
+            buckets,
+            max_routes: v.or("METRICS_MAX_ROUTES", 100),
+        }
+    }
+}
+
+impl AccessLogConfig {
+    fn from_vars(v: &Vars) -> Self {
+        AccessLogConfig {
+            target: v.get("ACCESS_LOG").filter(|t| !t.is_empty() && t != "off"),
+            format: v.or("ACCESS_LOG_FORMAT", access_log::Format::Combined),
+        }
+    }
+}
+
+impl LogConfig {
+    fn from_vars(v: &Vars) -> Self {
+        LogConfig {
+            dir: v.get("LOG_DIR").filter(|d| !d.is_empty()),
+            rotation: v.or("LOG_ROTATION", "daily".to_string()),
+            max_files: v.or("LOG_MAX_FILES", 7),
+            format: v.or("LOG_FORMAT", LogFormat::Pretty),
+            debug_payloads: v
+                .or("DEBUG_PAYLOADS", false)
+                .then(|| v.or("DEBUG_PAYLOAD_MAX_BYTES", 2048)),
+        }
+    }
+}
+
+impl DbConfig {
+    fn from_vars(v: &Vars) -> Self {
+        DbConfig {
+            url: v.get("DATABASE_URL").expect("DATABASE_URL no definida"),
+            max_connections: v.or("DB_MAX_CONNECTIONS", 10),
+            min_connections: v.or("DB_MIN_CONNECTIONS", 1),
+            acquire_timeout: Duration::from_millis(v.or("DB_ACQUIRE_TIMEOUT_MS", 3000)),
+            statement_timeout: Duration::from_millis(v.or("DB_STATEMENT_TIMEOUT_MS", 5000)),
+            jobs_max_connections: v.or("DB_JOBS_MAX_CONNECTIONS", 2),
+            jobs_statement_timeout: Duration::from_millis(v.or("DB_JOBS_STATEMENT_TIMEOUT_MS", 60000)),
+            slow_query: Duration::from_millis(v.or("SLOW_QUERY_MS", 200)),
+            query_budget: v.or("DB_QUERY_BUDGET", 5),
+            debug_query_count: v.or("DEBUG_QUERY_COUNT", false),
+        }
+    }
+}
+
+impl AdminConfig {
+    fn from_vars(v: &Vars) -> Self {
+        AdminConfig {
+            token: v.get("ADMIN_TOKEN").filter(|t| !t.is_empty()),
+            rate_burst: v.or("ADMIN_RATE_BURST", 10),
+            rate_per_minute: v.nonzero("ADMIN_RATE_PER_MINUTE", 30),
+            session_ttl: Duration::from_secs(3600 * v.or("ADMIN_SESSION_TTL_HOURS", 12)),
+            jwt_secret: v.get("JWT_SECRET").filter(|s| !s.is_empty()).inspect(|s| {
+                assert!(s.len() >= 32, "JWT_SECRET demasiado corto (mínimo 32 caracteres)");
+            }),
+            jwt_ttl: Duration::from_secs(60 * v.or("JWT_TTL_MINUTES", 60)),
+            lockout_threshold: v.or("LOGIN_LOCKOUT_THRESHOLD", 5),
+            lockout_base: Duration::from_secs(v.or("LOGIN_LOCKOUT_BASE_SECS", 30)),
+            lockout_max: Duration::from_secs(v.or("LOGIN_LOCKOUT_MAX_SECS", 3600)),
+        }
+    }
+}
+
+impl ServerConfig {
+    fn from_vars(v: &Vars) -> Self {
+        let listen = match v.get("LISTEN") {
+            Some(listen) => parse_listen(&listen).expect("LISTEN inválido"),
+            None => vec![Listen::Tcp(SocketAddr::from(([0, 0, 0, 0], v.or("PORT", 3000))))],
+        };
+        let internal_listen = match v.get("INTERNAL_LISTEN") {
+            Some(listen) => parse_listen(&listen).expect("INTERNAL_LISTEN inválido"),
+            None => Vec::new(),
+        };
+        let socket_mode = v
+            .get("LISTEN_MODE")
+            .map(|m| u32::from_str_radix(&m, 8).expect("LISTEN_MODE inválido"))
+            .unwrap_or(0o660);
+
+        ServerConfig {
+            listen,
+            internal_listen,
+            socket_mode,
+            reuse_port: v.or("REUSE_PORT", false),
+            public_url: v.get("PUBLIC_URL").filter(|u| !u.is_empty()),
+            json_case: v.or("JSON_CASE", json_case::Case::Snake),
+            tls: TlsConfig::from_vars(v),
+            max_body: file_types::parse_size(&v.or("MAX_BODY_SIZE", "2M".to_string())).expect("MAX_BODY_SIZE inválido"),
+            timing: v.or("SERVER_TIMING", false),
+            trusted_proxies: v.or("TRUSTED_PROXIES", TrustedProxies::default()),
+        }
+    }
+}
+
+impl TlsConfig {
+    fn from_vars(v: &Vars) -> Option<Self> {
+        let cert = v.get("TLS_CERT").filter(|c| !c.is_empty());
+        let key = v.get("TLS_KEY").filter(|k| !k.is_empty());
+        let (cert, key) = match (cert, key) {
+            (Some(cert), Some(key)) => (cert, key),
+            (None, None) => return None,
+            _ => panic!("TLS_CERT y TLS_KEY van juntas"),
+        };
+        Some(TlsConfig {
+            cert: PathBuf::from(cert),
+            key: PathBuf::from(key),
+            redirect_listen: v
+                .get("TLS_REDIRECT_LISTEN")
+                .filter(|a| !a.is_empty())
+                .map(|a| a.parse().expect("TLS_REDIRECT_LISTEN inválido")),
+        })
+    }
+}
+
+impl UploadsConfig {
+    fn from_vars(v: &Vars) -> Self {
+        UploadsConfig {
+            enabled: v.or("IMAGES_ENABLED", true),
+            dir: PathBuf::from(v.or("UPLOADS_DIR", "./uploads".to_string())),
+            quota_uploads: v.or("UPLOAD_QUOTA_DAILY", 50),
+            quota_bytes: v.or("UPLOAD_QUOTA_DAILY_BYTES", 100 * 1024 * 1024),
+            trash_retention: Duration::from_secs(3600 * v.or("UPLOAD_TRASH_RETENTION_HOURS", 72)),
+            from_url: v.or("UPLOAD_FROM_URL", false),
+            fetch_timeout: Duration::from_secs(v.or("UPLOAD_FETCH_TIMEOUT_SECS", 10)),
+            types: upload_types(v),
+            dimensions: DimensionLimits {
+                max_width: v.or("MAX_IMAGE_WIDTH", 10000),
+                max_height: v.or("MAX_IMAGE_HEIGHT", 10000),
+                max_pixels: (v.or("MAX_IMAGE_MEGAPIXELS", 40.0) * 1_000_000.0) as u64,
+            },
+            private: SignedUrlConfig::from_vars(v),
+        }
+    }
+}
+
+impl SignedUrlConfig {
+    fn from_vars(v: &Vars) -> Option<Self> {
+        if !v.or("UPLOADS_PRIVATE", false) {
+            return None;
+        }
+        let secret = v.get("UPLOAD_URL_SECRET").filter(|s| !s.is_empty()).expect("UPLOADS_PRIVATE necesita UPLOAD_URL_SECRET");
+        assert!(secret.len() >= 32, "UPLOAD_URL_SECRET demasiado corto (mínimo 32 caracteres)");
+        Some(SignedUrlConfig { secret, ttl: Duration::from_secs(v.or("UPLOAD_URL_TTL_SECS", 300)) })
+    }
+}
+
+/// `UPLOAD_TYPES` con el tope común de `MAX_IMAGE_SIZE`, si lo hay.
+fn upload_types(v: &Vars) -> FileTypePolicy {
+    let types = FileTypePolicy::parse(&v.or("UPLOAD_TYPES", file_types::DEFAULT.to_string()))
+        .expect("UPLOAD_TYPES inválido");
+    match v.get("MAX_IMAGE_SIZE").filter(|s| !s.is_empty()) {
+        Some(max) => types.cap(file_types::parse_size(&max).expect("MAX_IMAGE_SIZE inválido")),
+        None => types,
+    }
+}
+
+impl ContentConfig {
+    fn from_vars(v: &Vars) -> Self {
+        ContentConfig {
+            max_links: v.or("CONTENT_MAX_LINKS", 2),
+            max_mentions: v.or("CONTENT_MAX_MENTIONS", 3),
+            max_uppercase_pct: v.or("CONTENT_MAX_UPPERCASE_PCT", 60),
+            max_repeated_lines: v.or("CONTENT_MAX_REPEATED_LINES", 2),
+            daily_per_author: v.or("MESSAGES_DAILY_PER_AUTHOR", 10),
+            excerpt_chars: v.or("EXCERPT_CHARS", 160),
+            archive_after: Some(v.or::<u64>("MESSAGES_ARCHIVE_AFTER_DAYS", 0))
+                .filter(|days| *days > 0)
+                .map(|days| Duration::from_secs(86400 * days)),
+            spam_keywords: v
+                .or("SPAM_KEYWORDS", String::new())
+                .split(',')
+                .map(|k| k.trim().to_lowercase())
+                .filter(|k| !k.is_empty())
+                .collect(),
+            quarantine_score: Some(v.or("SPAM_QUARANTINE_SCORE", 80)).filter(|score| *score > 0),
+            collation: collation(&v.or("SORT_LANGUAGE", "es".to_string())),
+        }
+    }
+}
+
+/// `es` → `es-x-icu`, las colaciones ICU que trae Postgres. Va entre comillas
+/// en el SQL, así que solo se admite una etiqueta de idioma (`pt-BR`, `ca`...).
+fn collation(language: &str) -> String {
+    let valid = !language.is_empty() && language.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+    assert!(valid, "SORT_LANGUAGE inválido");
+    format!("{language}-x-icu")
+}
+
+impl AlertConfig {
+    fn from_vars(v: &Vars) -> Self {
+        AlertConfig {
+            webhook: v.get("ALERT_WEBHOOK_URL").filter(|u| !u.is_empty()),
+            interval: Duration::from_secs(v.nonzero("ALERT_INTERVAL_SECS", 60).into()),
+            cooldown: Duration::from_secs(v.or("ALERT_COOLDOWN_SECS", 900)),
+            error_rate: v.or("ALERT_ERROR_RATE", 0.05),
+            min_requests: v.or("ALERT_MIN_REQUESTS", 20),
+            db_latency: Duration::from_millis(v.or("ALERT_DB_LATENCY_MS", 500)),
+            disk_usage_pct: v.or("ALERT_DISK_USAGE_PCT", 90.0),
+        }
+    }
+}
+
+impl TraceConfig {
+    fn from_vars(v: &Vars) -> Self {
+        TraceConfig {
+            sampler: v.or("TRACE_SAMPLER", Sampler::ParentBased),
+            ratio: v.or("TRACE_SAMPLE_RATIO", 0.01),
+            debug_token: v.get("TRACE_DEBUG_TOKEN").filter(|t| !t.is_empty()),
+        }
+    }
+}
+
+impl ReadsConfig {
+    fn from_vars(v: &Vars) -> Self {
+        ReadsConfig {
+            rate_burst: v.or("READ_RATE_BURST", 60),
+            rate_per_minute: v.nonzero("READ_RATE_PER_MINUTE", 120),
+            cache_ttl: Duration::from_secs(v.or("READ_CACHE_TTL_SECS", 300)),
+            cache_entries: v.or("READ_CACHE_ENTRIES", 256),
+            settings_ttl: Duration::from_secs(v.or("SETTINGS_CACHE_TTL_SECS", 30)),
+        }
+    }
+}
+
+impl WritesConfig {
+    fn from_vars(v: &Vars) -> Self {
+        WritesConfig {
+            rate_burst: v.or("WRITE_RATE_BURST", 5),
+            rate_per_minute: v.nonzero("WRITE_RATE_PER_MINUTE", 10),
+        }
+    }
+}
+
+impl MailConfig {
+    fn from_vars(v: &Vars) -> Self {
+        let api_url = v.get("MAIL_API_URL").filter(|u| !u.is_empty());
+        let confirmation_required = v.or("EMAIL_CONFIRMATION_REQUIRED", false);
+        assert!(
+            v.get("PUBLIC_URL").is_some_and(|u| !u.is_empty()) || (api_url.is_none() && !confirmation_required),
+            "MAIL_API_URL y EMAIL_CONFIRMATION_REQUIRED necesitan PUBLIC_URL para los enlaces de los correos"
+        );
+        MailConfig {
+            api_url,
+            api_token: v.get("MAIL_API_TOKEN").filter(|t| !t.is_empty()),
+            from: v.or("MAIL_FROM", "Axum Motors <no-reply@localhost>".to_string()),
+            verify_ttl: Duration::from_secs(3600 * v.or("EMAIL_VERIFY_TTL_HOURS", 48)),
+            confirmation_required,
+        }
+    }
+}
+
+impl CaptchaConfig {
+    /// Los `RECAPTCHA_*` de antes de poder elegir proveedor siguen valiendo.
+    fn from_vars(v: &Vars) -> Self {
+        let get = |key: &str| {
+            v.get(&format!("CAPTCHA_{key}"))
+                .or_else(|| v.get(&format!("RECAPTCHA_{key}")))
+                .filter(|value| !value.is_empty())
+        };
+        let provider = v.or("CAPTCHA_PROVIDER", captcha::Provider::Recaptcha);
+        // La clave del widget que ya llevaba `contacto.html`.
+        let site_key = get("SITE_KEY").or_else(|| {
+            (provider == captcha::Provider::Recaptcha).then(|| "6Lf0A00sAAAAALUJlTs6O9l1a93nyfUyvs-yBQlQ".to_string())
+        });
+        // Sin secreto cualquier respuesta vale: solo si se pide a propósito.
+        let secret = get("SECRET");
+        assert!(
+            secret.is_some() || v.or("CAPTCHA_UNVERIFIED", false),
+            "falta CAPTCHA_SECRET (o CAPTCHA_UNVERIFIED=true para no verificar el captcha, solo en local)"
+        );
+        let min_score = get("MIN_SCORE").map_or(0.5, |s| {
+            s.parse().ok().filter(|score| (0.0..=1.0).contains(score)).expect("CAPTCHA_MIN_SCORE inválido")
+        });
+        CaptchaConfig { provider, secret, site_key, min_score, verify_url: get("VERIFY_URL") }
+    }
+}
+
+impl AkismetConfig {
+    fn from_vars(v: &Vars) -> Option<Self> {
+        let key = v.get("AKISMET_API_KEY").filter(|k| !k.is_empty())?;
+        let site = v
+            .get("AKISMET_SITE")
+            .or_else(|| v.get("PUBLIC_URL"))
+            .filter(|s| !s.is_empty())
+            .expect("AKISMET_API_KEY necesita AKISMET_SITE o PUBLIC_URL");
+        let url = v.or("AKISMET_URL", "https://rest.akismet.com".to_string());
+        Some(AkismetConfig { key, site, url: url.trim_end_matches('/').to_string() })
+    }
+}
+
+impl SecurityHeadersConfig {
+    fn from_vars(v: &Vars) -> Self {
+        let headers = security_headers::DEFAULTS
+            .iter()
+            .filter_map(|(name, default)| {
+                let key = security_headers::env_var(name);
+                let value = v.get(&key).unwrap_or_else(|| default.to_string());
+                let value = value.trim();
+                (!value.is_empty()).then(|| {
+                    let value = HeaderValue::from_str(value).unwrap_or_else(|_| panic!("{key} inválido"));
+                    (HeaderName::from_static(name), value)
+                })
+            })
+            .collect();
+        SecurityHeadersConfig { headers, csp_nonce: v.or("CSP_NONCE", false) }
+    }
+}
+
+impl OAuthConfig {
+    fn from_vars(v: &Vars) -> Self {
+        let client = |prefix: &str| {
+            let id = v.get(&format!("{prefix}_CLIENT_ID")).filter(|i| !i.is_empty())?;
+            let secret = v.get(&format!("{prefix}_CLIENT_SECRET")).filter(|s| !s.is_empty())?;
+            Some(OAuthClient { client_id: id, client_secret: secret })
+        };
+        OAuthConfig {
+            google: client("OAUTH_GOOGLE"),
+            github: client("OAUTH_GITHUB"),
+        }
+    }
+}
+
+impl EventsConfig {
+    fn from_vars(v: &Vars) -> Self {
+        EventsConfig {
+            redis_url: v.get("EVENTS_REDIS_URL").filter(|u| !u.is_empty()),
+            redis_channel: v.or("EVENTS_REDIS_CHANNEL", "hola_axum:events".to_string()),
+            webhook: v.get("EVENTS_WEBHOOK_URL").filter(|u| !u.is_empty()),
+            notify_email: v.get("NOTIFY_EMAIL").filter(|e| !e.is_empty()),
+        }
+    }
+}
+
+impl AccountsConfig {
+    fn from_vars(v: &Vars) -> Self {
+        AccountsConfig {
+            session_ttl: Duration::from_secs(3600 * v.or("ACCOUNT_SESSION_TTL_HOURS", 720)),
+        }
+    }
+}
+
+impl CorsConfig {
+    fn from_vars(v: &Vars) -> Self {
+        let origins = parse_allowlist(v, "CORS_ORIGINS", |o| HeaderValue::from_str(o.trim_end_matches('/')).ok());
+        let credentials = v.or("CORS_CREDENTIALS", false);
+        // Con cookies, cualquier origen podría leer las respuestas de quien tenga sesión.
+        assert!(
+            !credentials || origins.is_some(),
+            "CORS_CREDENTIALS=true necesita CORS_ORIGINS con los orígenes permitidos (sin `*`)"
+        );
+        CorsConfig {
+            origins,
+            methods: parse_allowlist(v, "CORS_METHODS", |m| m.to_uppercase().parse().ok()),
+            headers: parse_allowlist(v, "CORS_HEADERS", |h| h.parse().ok()),
+            credentials,
+            max_age: Duration::from_secs(v.or("CORS_MAX_AGE_SECS", 600)),
+        }
+    }
+}
+
+impl PaginationConfig {
+    fn from_vars(v: &Vars) -> Self {
+        let max = v.or("PAGE_SIZE_MAX", PageSize::default().max).max(1);
+        let default = PageSize { default: v.or("PAGE_SIZE_DEFAULT", PageSize::default().default).clamp(1, max), max };
+        let routes = v
+            .get("PAGE_SIZES")
+            .map(|raw| parse_page_sizes(&raw, max).unwrap_or_else(|| panic!("PAGE_SIZES inválido")))
+            .unwrap_or_default();
+        PaginationConfig { default, routes }
+    }
+}
+
+/// Sin máximo propio, el general (o el tamaño por defecto si es mayor).
+fn parse_page_sizes(raw: &str, max: i64) -> Option<Vec<(String, PageSize)>> {
+    let mut routes = Vec::new();
+    for item in raw.split(',').map(str::trim).filter(|i| !i.is_empty()) {
+        let (route, sizes) = item.split_once('=')?;
+        let (default, route_max) = match sizes.split_once(':') {
+            Some((default, route_max)) => (default.trim().parse().ok()?, Some(route_max.trim().parse().ok()?)),
+            None => (sizes.trim().parse::<i64>().ok()?, None),
+        };
+        let route_max = route_max.unwrap_or(max.max(default));
+        if !route.starts_with('/') || default < 1 || route_max < default {
+            return None;
+        }
+        routes.push((route.trim().to_string(), PageSize { default, max: route_max }));
+    }
+    Some(routes)
+}
+
+/// Lista separada por comas; `None` sin variable, vacía o con `*`.
+fn parse_allowlist<T>(v: &Vars, key: &str, parse: impl Fn(&str) -> Option<T>) -> Option<Vec<T>> {
+    let raw = v.get(key)?;
+    let items: Vec<&str> = raw.split(',').map(str::trim).filter(|i| !i.is_empty()).collect();
+    if items.is_empty() || items.contains(&"*") {
+        return None;
+    }
+    let parsed = items.into_iter().map(parse).collect::<Option<Vec<T>>>();
+    Some(parsed.unwrap_or_else(|| panic!("{key} inválido")))
+}
+
+/// Lista separada por comas; `systemd` se expande a los sockets heredados.
+fn parse_listen(raw: &str) -> Option<Vec<Listen>> {
+    let mut listeners = Vec::new();
+    for item in raw.split(',').map(str::trim).filter(|l| !l.is_empty()) {
+        if item == "systemd" {
+            listeners.extend(server::systemd_listeners());
+        } else {
+            listeners.push(item.parse().ok()?);
+        }
+    }
+    Some(listeners)
+}
+
+fn parse_buckets(raw: &str) -> Option<Vec<f64>> {
+    let mut buckets = raw
+        .split(',')
+        .map(|b| b.trim().parse::<f64>().ok().filter(|v| v.is_finite() && *v > 0.0))
+        .collect::<Option<Vec<f64>>>()?;
+
+    if buckets.is_empty() {
+        return None;
+    }
+
+    buckets.sort_by(f64::total_cmp);
+    buckets.dedup();
+    Some(buckets)
+}
+
+/* ---------- UTIL ---------- */
+
+/// Origen de las variables de configuración: el entorno en producción, un mapa en tests.
+pub struct Vars<'a>(pub &'a dyn Fn(&str) -> Option<String>);
+
+impl Vars<'_> {
+    fn get(&self, key: &str) -> Option<String> {
+        (self.0)(key)
+    }
+
+    fn or<T: std::str::FromStr>(&self, key: &str, default: T) -> T {
+        match self.get(key) {
+            Some(v) => v.parse().unwrap_or_else(|_| panic!("{key} inválido")),
+            None => default,
+        }
+    }
+
+    /// Como `or`, pero con 0 tampoco arranca: un ritmo de 0 no recarga nunca y
+    /// un intervalo de 0 hace fallar a `tokio::time::interval`.
+    fn nonzero(&self, key: &str, default: u32) -> u32 {
+        let value = self.or(key, default);
+        assert!(value > 0, "{key} inválido (tiene que ser mayor que 0)");
+        value
+    }
+}
@@ -0,0 +1,190 @@
+//! Heurísticas contra abuso en el texto de los mensajes: demasiados enlaces o
+//! menciones, mayúsculas sostenidas, líneas o caracteres repetidos y palabras
+//! de `SPAM_KEYWORDS`. Cada señal suma a la puntuación de spam del mensaje;
+//! pasar un límite lo rechaza. La puntuación sigue en `quarantine`, que le
+//! añade el ritmo de envíos de la IP.
+
+use std::collections::HashMap;
+
+use crate::config::ContentConfig;
+
+/// Por debajo de estas letras no se juzgan las mayúsculas ("OK GRACIAS").
+const MIN_LETTERS_FOR_CASE: usize = 20;
+
+/// Puntos extra por cada límite superado.
+const VIOLATION_POINTS: u32 = 50;
+
+/// Un mismo carácter tantas veces seguidas cuenta como relleno ("!!!!!", "aaaaa").
+const CHAR_RUN: usize = 5;
+const CHAR_RUN_POINTS: u32 = 10;
+
+/// Por cada palabra de `SPAM_KEYWORDS` distinta que aparece.
+const KEYWORD_POINTS: u32 = 40;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Violation {
+    TooManyLinks,
+    TooManyMentions,
+    ExcessiveUppercase,
+    RepeatedLines,
+}
+
+impl Violation {
+    /// Código estable para que el frontend explique el rechazo.
+    pub fn code(self) -> &'static str {
+        match self {
+            Violation::TooManyLinks => "too_many_links",
+            Violation::TooManyMentions => "too_many_mentions",
+            Violation::ExcessiveUppercase => "excessive_uppercase",
+            Violation::RepeatedLines => "repeated_lines",
+        }
+    }
+
+    pub fn message(self) -> &'static str {
+        match self {
+            Violation::TooManyLinks => "❌ El mensaje tiene demasiados enlaces",
+            Violation::TooManyMentions => "❌ El mensaje tiene demasiadas menciones",
+            Violation::ExcessiveUppercase => "❌ Escribe el mensaje sin tantas mayúsculas",
+            Violation::RepeatedLines => "❌ El mensaje repite la misma línea demasiadas veces",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Assessment {
+    pub score: i32,
+    /// Primer límite superado, si alguno.
+    pub violation: Option<Violation>,
+}
+
+pub fn assess(rules: &ContentConfig, text: &str) -> Assessment {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let links = words.iter().filter(|w| is_link(w)).count();
+    let mentions = words.iter().filter(|w| is_mention(w)).count();
+    let uppercase_pct = uppercase_pct(text);
+    let repeats = max_repeats(text);
+    let runs = char_runs(text);
+    let lower = text.to_lowercase();
+    let keywords = rules.spam_keywords.iter().filter(|k| lower.contains(k.as_str())).count();
+
+    let checks = [
+        (links > rules.max_links, Violation::TooManyLinks),
+        (mentions > rules.max_mentions, Violation::TooManyMentions),
+        (uppercase_pct.is_some_and(|p| p > rules.max_uppercase_pct), Violation::ExcessiveUppercase),
+        (repeats > rules.max_repeated_lines, Violation::RepeatedLines),
+    ];
+    let violations = checks.iter().filter(|(hit, _)| *hit).count() as u32;
+
+    let score = 10 * links as u32
+        + 5 * mentions as u32
+        + uppercase_pct.unwrap_or(0) / 2
+        + 10 * repeats.saturating_sub(1) as u32
+        + CHAR_RUN_POINTS * runs as u32
+        + KEYWORD_POINTS * keywords as u32
+        + VIOLATION_POINTS * violations;
+
+    Assessment {
+        score: score.min(i32::MAX as u32) as i32,
+        violation: checks.iter().find(|(hit, _)| *hit).map(|(_, v)| *v),
+    }
+}
+
+fn is_link(word: &str) -> bool {
+    let word = word.to_ascii_lowercase();
+    word.contains("://") || word.starts_with("www.")
+}
+
+fn is_mention(word: &str) -> bool {
+    word.strip_prefix('@')
+        .and_then(|rest| rest.chars().next())
+        .is_some_and(char::is_alphanumeric)
+}
+
+/// Porcentaje de letras en mayúscula, si hay letras suficientes para juzgarlo.
+fn uppercase_pct(text: &str) -> Option<u32> {
+    let (mut letters, mut upper) = (0usize, 0usize);
+    for c in text.chars().filter(|c| c.is_alphabetic()) {
+        letters += 1;
+        if c.is_uppercase() {
+            upper += 1;
+        }
+    }
+    (letters >= MIN_LETTERS_FOR_CASE).then(|| (upper * 100 / letters) as u32)
+}
+
+/// Veces que aparece la línea más repetida (sin contar vacías ni mayúsculas).
+fn max_repeats(text: &str) -> usize {
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        *seen.entry(line.to_lowercase()).or_default() += 1;
+    }
+    seen.into_values().max().unwrap_or(0)
+}
+
+/// Tramos de `CHAR_RUN` o más caracteres iguales seguidos, sin contar espacios.
+fn char_runs(text: &str) -> usize {
+    let (mut runs, mut len, mut prev) = (0, 0, None);
+    for c in text.chars() {
+        len = if Some(c) == prev { len + 1 } else { 1 };
+        prev = Some(c);
+        if len == CHAR_RUN && !c.is_whitespace() {
+            runs += 1;
+        }
+    }
+    runs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules() -> ContentConfig {
+        ContentConfig {
+            max_links: 2,
+            max_mentions: 3,
+            max_uppercase_pct: 60,
+            max_repeated_lines: 2,
+            daily_per_author: 0,
+            excerpt_chars: 160,
+            archive_after: None,
+            spam_keywords: vec!["casino".to_string(), "viagra".to_string()],
+            quarantine_score: Some(80),
+            collation: "es-x-icu".to_string(),
+        }
+    }
+
+    #[test]
+    fn flags_each_rule() {
+        let cases = [
+            ("Hola, me interesa la moto roja del catálogo", None),
+            ("Mirad https://a.example y www.b.example", None),
+            ("http://a.example http://b.example https://c.example", Some(Violation::TooManyLinks)),
+            ("@ana @luis @eva @pep mirad esto", Some(Violation::TooManyMentions)),
+            ("QUIERO ESTA MOTO YA MISMO POR FAVOR", Some(Violation::ExcessiveUppercase)),
+            ("OK GRACIAS", None),
+            ("compra\nCOMPRA\n compra ", Some(Violation::RepeatedLines)),
+        ];
+
+        for (text, expected) in cases {
+            assert_eq!(assess(&rules(), text).violation, expected, "{text:?}");
+        }
+    }
+
+    #[test]
+    fn violations_raise_the_score() {
+        let clean = assess(&rules(), "Un enlace: https://a.example");
+        let spam = assess(&rules(), "https://a.example https://a.example https://a.example");
+        assert!(clean.score > 0);
+        assert!(spam.score >= clean.score + VIOLATION_POINTS as i32);
+    }
+
+    #[test]
+    fn keywords_and_filler_add_points() {
+        let base = assess(&rules(), "hola, me interesa la moto si").score;
+        let filler = assess(&rules(), "hola, me interesa la moto!!!!!! siiiiii").score;
+        let keyword = assess(&rules(), "hola, me interesa la moto. Gana en el CASINO").score;
+        assert_eq!(filler, base + 2 * CHAR_RUN_POINTS as i32);
+        assert!(keyword >= base + KEYWORD_POINTS as i32);
+        assert_eq!(char_runs("Hola     qué tal"), 0);
+    }
+}
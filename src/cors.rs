@@ -0,0 +1,111 @@
+//! `OPTIONS` y preflight CORS por ruta. Los métodos que se anuncian son los
+//! que la ruta tiene registrados: se consulta el router con un `OPTIONS` de
+//! prueba y se lee el `Allow` que pone axum. Un preflight para un método que
+//! la ruta no tiene se rechaza ahí mismo.
+
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::{header, HeaderValue, Method, StatusCode, Uri},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Router,
+};
+use tower::ServiceExt;
+use tower_http::cors::{AllowHeaders, AllowMethods, AllowOrigin, Any, CorsLayer};
+
+use crate::config::CorsConfig;
+
+/// Política de `CorsConfig`. Con credenciales el navegador no acepta
+/// comodines: los orígenes tienen que venir en la lista (lo exige la
+/// configuración) y los métodos y cabeceras sin acotar se reflejan de la
+/// petición. El origen no se refleja nunca.
+pub fn layer(config: &CorsConfig) -> CorsLayer {
+    let credentials = config.credentials;
+    let origin = match &config.origins {
+        Some(origins) => AllowOrigin::list(origins.clone()),
+        None => AllowOrigin::any(),
+    };
+    let methods = match &config.methods {
+        Some(methods) => AllowMethods::list(methods.clone()),
+        None if credentials => AllowMethods::mirror_request(),
+        None => AllowMethods::any(),
+    };
+    let headers = match &config.headers {
+        Some(headers) => AllowHeaders::list(headers.clone()),
+        None if credentials => AllowHeaders::mirror_request(),
+        None => AllowHeaders::any(),
+    };
+
+    let layer = CorsLayer::new()
+        .allow_origin(origin)
+        .allow_methods(methods)
+        .allow_headers(headers)
+        .allow_credentials(credentials)
+        .max_age(config.max_age);
+    if credentials { layer } else { layer.expose_headers(Any) }
+}
+
+/// Estado de `preflight`: el router sin CORS y los métodos que admite la política.
+#[derive(Clone)]
+pub struct Preflight {
+    pub routes: Router,
+    pub methods: Option<Vec<Method>>,
+}
+
+/// Va por fuera de `layer()`.
+pub async fn preflight(State(preflight): State<Preflight>, req: Request, next: Next) -> Response {
+    if req.method() != Method::OPTIONS {
+        return next.run(req).await;
+    }
+    let Some(allowed) = allowed_methods(preflight.routes, req.uri()).await else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let allow = HeaderValue::from_str(&allowed.join(",")).unwrap();
+
+    let requested = req
+        .headers()
+        .get(header::ACCESS_CONTROL_REQUEST_METHOD)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_owned);
+    let Some(requested) = requested else {
+        // `OPTIONS` normal, sin CORS: solo los métodos.
+        return (StatusCode::NO_CONTENT, [(header::ALLOW, allow)]).into_response();
+    };
+    if !allowed.iter().any(|m| m.eq_ignore_ascii_case(&requested)) {
+        return (StatusCode::METHOD_NOT_ALLOWED, [(header::ALLOW, allow)]).into_response();
+    }
+
+    // De los métodos de la ruta, los que deja pasar la política.
+    let permitted: Vec<&str> = allowed
+        .iter()
+        .map(String::as_str)
+        .filter(|m| preflight.methods.as_ref().is_none_or(|list| list.iter().any(|p| p.as_str() == *m)))
+        .collect();
+    if !permitted.iter().any(|m| m.eq_ignore_ascii_case(&requested)) {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+
+    let mut res = next.run(req).await;
+    res.headers_mut().insert(header::ACCESS_CONTROL_ALLOW_METHODS, HeaderValue::from_str(&permitted.join(",")).unwrap());
+    res
+}
+
+/// Métodos de la ruta de `uri`, `OPTIONS` incluido; `None` si no existe.
+async fn allowed_methods(routes: Router, uri: &Uri) -> Option<Vec<String>> {
+    let probe = Request::builder().method(Method::OPTIONS).uri(uri.clone()).body(Body::empty()).unwrap();
+    let res = match routes.oneshot(probe).await {
+        Ok(res) => res,
+        Err(never) => match never {},
+    };
+    // axum pone `Allow` en cualquier respuesta a un método no registrado,
+    // también si antes la corta un middleware (autenticación de `/api/admin`).
+    let allow = res.headers().get(header::ALLOW)?.to_str().ok()?;
+    let mut methods: Vec<String> = allow
+        .split(',')
+        .map(|m| m.trim().to_string())
+        .filter(|m| !m.is_empty())
+        .collect();
+    methods.push(Method::OPTIONS.to_string());
+    Some(methods)
+}
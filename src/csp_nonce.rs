@@ -0,0 +1,140 @@
+//! CSP con nonce (con `CSP_NONCE=true`): cada página HTML sale con un nonce
+//! nuevo en sus `<script>` y `<style>`, y en la `Content-Security-Policy`
+//! (la de `security_headers`) `script-src` y `style-src` cambian
+//! `'unsafe-inline'` por ese nonce. Así solo corre el código en línea que
+//! escribimos nosotros: lo que mandan los usuarios sale escapado y no puede
+//! traer un `<script>` con el nonce. Los atributos `style="..."` siguen
+//! permitidos con `style-src-attr`; los `onclick` ya no, por eso no hay.
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Request, State},
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::config::SecurityHeadersConfig;
+
+/// Hueco del nonce en la política ya reescrita.
+const MARKER: &str = "{nonce}";
+
+/// Tope de las páginas en las que se inyecta, como en `csrf`.
+const MAX_BODY: usize = 2 * 1024 * 1024;
+
+/// La política con el hueco del nonce; `None` si no está activado o no hay CSP.
+pub fn policy(config: &SecurityHeadersConfig) -> Option<Arc<str>> {
+    if !config.csp_nonce {
+        return None;
+    }
+    let (_, csp) = config.headers.iter().find(|(name, _)| name == header::CONTENT_SECURITY_POLICY)?;
+    Some(with_nonce(csp.to_str().ok()?).into())
+}
+
+fn with_nonce(csp: &str) -> String {
+    let mut inline_styles = false;
+    let mut directives: Vec<String> = csp
+        .split(';')
+        .map(str::trim)
+        .filter(|d| !d.is_empty())
+        .map(|directive| {
+            let name = directive.split_whitespace().next().unwrap_or_default();
+            if name != "script-src" && name != "style-src" {
+                return directive.to_string();
+            }
+            let sources: Vec<&str> = directive.split_whitespace().filter(|s| *s != "'unsafe-inline'").collect();
+            inline_styles |= name == "style-src" && sources.len() < directive.split_whitespace().count();
+            format!("{} 'nonce-{MARKER}'", sources.join(" "))
+        })
+        .collect();
+    if inline_styles && !directives.iter().any(|d| d.starts_with("style-src-attr")) {
+        directives.push("style-src-attr 'unsafe-inline'".to_string());
+    }
+    directives.join("; ")
+}
+
+/// Pone `nonce` en cada `<script>` y `<style>` de `page`.
+fn tag(page: &str, nonce: &str) -> String {
+    let mut out = String::with_capacity(page.len());
+    let mut rest = page;
+    while let Some(at) = rest.find('<') {
+        let (before, from) = rest.split_at(at);
+        out.push_str(before);
+        let opening = ["<script", "<style"].into_iter().find(|t| {
+            from.get(..t.len()).is_some_and(|head| head.eq_ignore_ascii_case(t))
+                && from[t.len()..].starts_with(|c: char| c == '>' || c.is_ascii_whitespace())
+        });
+        let len = opening.map_or(1, str::len);
+        out.push_str(&from[..len]);
+        if opening.is_some() {
+            out.push_str(&format!(r#" nonce="{nonce}""#));
+        }
+        rest = &from[len..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/* ---------- MIDDLEWARE ---------- */
+
+/// Por fuera de `csrf::inject`, para marcar también el script que añade él.
+pub async fn inject(State(policy): State<Option<Arc<str>>>, req: Request, next: Next) -> Response {
+    let res = next.run(req).await;
+    let Some(policy) = policy else { return res };
+
+    let is_html = res
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("text/html"));
+    if res.status() != StatusCode::OK || !is_html {
+        return res;
+    }
+
+    let (mut parts, body) = res.into_parts();
+    let Ok(bytes) = to_bytes(body, MAX_BODY).await else {
+        tracing::error!("página HTML demasiado grande para inyectar el nonce de la CSP");
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+    let nonce = Uuid::new_v4().simple().to_string();
+    let page = tag(&String::from_utf8_lossy(&bytes), &nonce);
+    let Ok(csp) = HeaderValue::from_str(&policy.replace(MARKER, &nonce)) else {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+
+    for h in [header::CONTENT_LENGTH, header::ETAG, header::LAST_MODIFIED] {
+        parts.headers.remove(h);
+    }
+    parts.headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-store"));
+    parts.headers.insert(header::CONTENT_SECURITY_POLICY, csp);
+    Response::from_parts(parts, Body::from(page))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_script_and_style_tags_get_the_nonce() {
+        let page = "<head><STYLE>p{}</STYLE><script src=\"/a.js\"></script></head><scripts><p>a < b</p><script>x()</script>";
+        assert_eq!(
+            tag(page, "n1"),
+            "<head><STYLE nonce=\"n1\">p{}</STYLE><script nonce=\"n1\" src=\"/a.js\"></script></head><scripts><p>a < b</p><script nonce=\"n1\">x()</script>"
+        );
+        assert_eq!(tag("sin etiquetas <", "n1"), "sin etiquetas <");
+    }
+
+    #[test]
+    fn unsafe_inline_becomes_the_nonce() {
+        let csp = "default-src 'self'; script-src 'self' 'unsafe-inline' https://unpkg.com; style-src 'self' 'unsafe-inline'; img-src 'self'";
+        assert_eq!(
+            with_nonce(csp),
+            "default-src 'self'; script-src 'self' https://unpkg.com 'nonce-{nonce}'; \
+             style-src 'self' 'nonce-{nonce}'; img-src 'self'; style-src-attr 'unsafe-inline'"
+        );
+        // Sin `'unsafe-inline'` en `style-src` no se abren los atributos.
+        assert_eq!(with_nonce("script-src 'self'; style-src 'self'"), "script-src 'self' 'nonce-{nonce}'; style-src 'self' 'nonce-{nonce}'");
+    }
+}
@@ -0,0 +1,153 @@
+//! Protección CSRF de los formularios con cookie: `POST /enviar` y
+//! `PUT`/`DELETE /mensajes/:id`. Doble envío: la cookie `csrf_token` lleva un
+//! token aleatorio que `inject` mete también en cada página HTML (`<meta
+//! name="csrf-token">` y `js/csrf.js`, que lo añade a formularios, `fetch` y
+//! htmx), y `verify` exige que la petición lo repita en `X-CSRF-Token` o en el
+//! campo `csrf_token`. Otro sitio puede hacer que el navegador mande la
+//! cookie, pero no leerla.
+//!
+//! Solo se exige a lo que viene de un navegador (trae `Cookie`, `Origin` o
+//! `Sec-Fetch-Site`) sin credenciales propias: un script sin cookies, o con
+//! `Authorization` o `X-Api-Key`, no puede ser víctima de CSRF.
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::Request,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{Html, IntoResponse, Response},
+};
+use uuid::Uuid;
+
+use crate::admin::constant_time_eq;
+use crate::api_keys;
+
+const COOKIE: &str = "csrf_token";
+const HEADER: &str = "x-csrf-token";
+const FIELD: &[u8] = b"csrf_token=";
+
+/// Tope de las páginas en las que se inyecta el token y de los formularios que se leen.
+const MAX_BODY: usize = 2 * 1024 * 1024;
+
+fn cookie_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .find_map(|pair| pair.trim().strip_prefix(COOKIE)?.strip_prefix('='))
+        .filter(|token| token.len() == 32 && token.bytes().all(|b| b.is_ascii_hexdigit()))
+}
+
+fn from_browser(headers: &HeaderMap) -> bool {
+    let credentials = headers.contains_key(header::AUTHORIZATION) || headers.contains_key(api_keys::HEADER);
+    let browser = [header::COOKIE.as_str(), header::ORIGIN.as_str(), "sec-fetch-site"]
+        .iter()
+        .any(|h| headers.contains_key(*h));
+    browser && !credentials
+}
+
+/// Token del campo `csrf_token` de un cuerpo `application/x-www-form-urlencoded`.
+/// El token es hexadecimal, así que no hace falta decodificarlo.
+fn form_token(body: &[u8]) -> Option<&[u8]> {
+    body.split(|b| *b == b'&').find_map(|pair| pair.strip_prefix(FIELD))
+}
+
+/* ---------- MIDDLEWARE ---------- */
+
+/// Rechaza con 403 las peticiones de navegador que no repiten el token de la cookie.
+pub async fn verify(req: Request, next: Next) -> Response {
+    if req.method().is_safe() || !from_browser(req.headers()) {
+        return next.run(req).await;
+    }
+    let Some(expected) = cookie_token(req.headers()).map(str::to_owned) else {
+        return rejected();
+    };
+
+    if let Some(given) = req.headers().get(HEADER) {
+        return if constant_time_eq(&expected, given.to_str().unwrap_or_default()) {
+            next.run(req).await
+        } else {
+            rejected()
+        };
+    }
+
+    let (parts, body) = req.into_parts();
+    let Ok(bytes) = to_bytes(body, MAX_BODY).await else {
+        return (StatusCode::PAYLOAD_TOO_LARGE, Html("❌ Petición demasiado grande")).into_response();
+    };
+    let matches = form_token(&bytes).is_some_and(|given| constant_time_eq(&expected, &String::from_utf8_lossy(given)));
+    if !matches {
+        return rejected();
+    }
+    next.run(Request::from_parts(parts, Body::from(bytes))).await
+}
+
+fn rejected() -> Response {
+    (
+        StatusCode::FORBIDDEN,
+        [("x-error-code", "csrf")],
+        Html("❌ El formulario ha caducado; recarga la página e inténtalo de nuevo"),
+    )
+        .into_response()
+}
+
+/// Mete el token en las páginas HTML (y lo fija en la cookie si no estaba).
+/// Las páginas con token no se cachean: una copia vieja traería otro token.
+pub async fn inject(req: Request, next: Next) -> Response {
+    let existing = cookie_token(req.headers()).map(str::to_owned);
+    let res = next.run(req).await;
+
+    let is_html = res
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("text/html"));
+    if res.status() != StatusCode::OK || !is_html {
+        return res;
+    }
+
+    let (mut parts, body) = res.into_parts();
+    let Ok(bytes) = to_bytes(body, MAX_BODY).await else {
+        tracing::error!("página HTML demasiado grande para inyectar el token CSRF");
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+    let page = String::from_utf8_lossy(&bytes);
+    let token = existing.clone().unwrap_or_else(|| Uuid::new_v4().simple().to_string());
+    let tags = format!(r#"<meta name="csrf-token" content="{token}"><script src="/js/csrf.js"></script>"#);
+    let page = match page.find("</head>") {
+        Some(at) => format!("{}{tags}\n{}", &page[..at], &page[at..]),
+        None => page.into_owned(),
+    };
+
+    for h in [header::CONTENT_LENGTH, header::ETAG, header::LAST_MODIFIED] {
+        parts.headers.remove(h);
+    }
+    parts.headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-store"));
+    if existing.is_none() {
+        let cookie = format!("{COOKIE}={token}; Path=/; HttpOnly; Secure; SameSite=Strict");
+        parts.headers.append(header::SET_COOKIE, cookie.parse().unwrap());
+    }
+    Response::from_parts(parts, Body::from(page))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_token_from_form_body() {
+        assert_eq!(form_token(b"nombre=Ana&csrf_token=abc&mensaje=x"), Some(&b"abc"[..]));
+        assert_eq!(form_token(b"xcsrf_token=abc"), None);
+    }
+
+    #[test]
+    fn only_browser_requests_without_credentials_are_checked() {
+        let mut headers = HeaderMap::new();
+        assert!(!from_browser(&headers));
+        headers.insert(header::ORIGIN, "https://otro.example".parse().unwrap());
+        assert!(from_browser(&headers));
+        headers.insert(header::AUTHORIZATION, "Bearer x".parse().unwrap());
+        assert!(!from_browser(&headers));
+    }
+}
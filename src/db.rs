@@ -0,0 +1,187 @@
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use sqlx::{postgres::PgPoolOptions, Executor, PgPool};
+use std::{
+    future::Future,
+    sync::{Arc, OnceLock},
+    time::{Duration, Instant},
+};
+
+use crate::config::DbConfig;
+use crate::metrics::Metrics;
+use crate::query_budget;
+use crate::server_timing;
+
+/// Código de Postgres para `query_canceled`, que es lo que produce `statement_timeout`.
+const QUERY_CANCELED: &str = "57014";
+
+const REQUIRED_TABLES: [&str; 3] = ["mensajes", "images", "upload_quota"];
+
+struct Instrumentation {
+    slow_threshold: Duration,
+    metrics: Arc<Metrics>,
+}
+
+static INSTRUMENTATION: OnceLock<Instrumentation> = OnceLock::new();
+
+pub fn init_instrumentation(slow_threshold: Duration, metrics: Arc<Metrics>) {
+    let _ = INSTRUMENTATION.set(Instrumentation {
+        slow_threshold,
+        metrics,
+    });
+}
+
+pub async fn connect(config: &DbConfig) -> PgPool {
+    options(config.max_connections, config.acquire_timeout, config.statement_timeout)
+        .min_connections(config.min_connections)
+        .connect(&config.url)
+        .await
+        .unwrap()
+}
+
+/// Pool de los trabajos en segundo plano (purgas, archivo) y las
+/// exportaciones, separado del de las peticiones para que uno pesado no deje
+/// sin conexiones al tráfico interactivo. Se abre sin conectar: las
+/// conexiones llegan con el primer trabajo.
+pub fn connect_jobs(config: &DbConfig) -> PgPool {
+    options(config.jobs_max_connections, config.acquire_timeout, config.jobs_statement_timeout)
+        .connect_lazy(&config.url)
+        .expect("DATABASE_URL inválida")
+}
+
+fn options(max_connections: u32, acquire_timeout: Duration, statement_timeout: Duration) -> PgPoolOptions {
+    let statement_timeout_ms = statement_timeout.as_millis() as u64;
+
+    PgPoolOptions::new()
+        .max_connections(max_connections)
+        .acquire_timeout(acquire_timeout)
+        .after_connect(move |conn, _meta| {
+            Box::pin(async move {
+                conn.execute(format!("SET statement_timeout = {statement_timeout_ms}").as_str())
+                    .await?;
+                Ok(())
+            })
+        })
+}
+
+pub async fn migrate(pool: &PgPool) {
+    sqlx::migrate!()
+        .run(pool)
+        .await
+        .expect("no se pudieron aplicar las migraciones");
+}
+
+/// La colación de `SORT_LANGUAGE` tiene que existir y valer para la
+/// codificación de la base (ICU exige UTF8); si no, ordenar por nombre
+/// fallaría en cada petición.
+pub async fn check_collation(pool: &PgPool, collation: &str) {
+    if let Err(e) = sqlx::query(&format!(r#"SELECT 'a' COLLATE "{collation}" < 'b'"#)).execute(pool).await {
+        panic!("no se puede ordenar con la colación {collation} (revisa SORT_LANGUAGE): {e}");
+    }
+}
+
+/// Abre `min_connections` de golpe, comprueba que el esquema esperado existe y
+/// registra versión y latencia del servidor. Cualquier fallo aborta el arranque.
+pub async fn warm_up(pool: &PgPool, min_connections: u32) {
+    let mut conns = Vec::new();
+    for _ in 0..min_connections {
+        conns.push(pool.acquire().await.expect("no se pudo abrir conexión inicial"));
+    }
+    drop(conns);
+
+    let start = Instant::now();
+    let version: String = sqlx::query_scalar("SHOW server_version")
+        .fetch_one(pool)
+        .await
+        .expect("no se pudo consultar la versión de Postgres");
+    let latency = start.elapsed();
+
+    let missing: Vec<String> = sqlx::query_scalar(
+        "SELECT t FROM unnest($1::text[]) AS t WHERE to_regclass(t) IS NULL",
+    )
+    .bind(&REQUIRED_TABLES[..])
+    .fetch_all(pool)
+    .await
+    .expect("no se pudo comprobar el esquema");
+
+    if !missing.is_empty() {
+        panic!("faltan tablas en la base de datos: {}", missing.join(", "));
+    }
+
+    tracing::info!(
+        server_version = %version,
+        latency_ms = latency.as_secs_f64() * 1000.0,
+        connections = pool.size(),
+        "base de datos lista"
+    );
+}
+
+/// Ejecuta una consulta midiendo su duración. Si supera el umbral se registra
+/// a nivel WARN con el nombre, un resumen de parámetros y el tiempo empleado.
+/// También cuenta para el presupuesto de consultas de la petición y para su
+/// `Server-Timing`.
+pub async fn timed<F, T>(name: &'static str, params: impl FnOnce() -> String, query: F) -> T
+where
+    F: Future<Output = T>,
+{
+    query_budget::record();
+    let start = Instant::now();
+    let result = query.await;
+    let elapsed = start.elapsed();
+    server_timing::record_db(elapsed);
+
+    if let Some(inst) = INSTRUMENTATION.get()
+        && elapsed >= inst.slow_threshold
+    {
+        tracing::warn!(
+            query = name,
+            params = %params(),
+            elapsed_ms = elapsed.as_millis() as u64,
+            "consulta lenta"
+        );
+        inst.metrics.incr_slow_query(name);
+    }
+
+    result
+}
+
+/* ---------- ERRORES ---------- */
+
+#[derive(Debug)]
+pub enum DbError {
+    /// No se obtuvo conexión del pool dentro de `DB_ACQUIRE_TIMEOUT_MS`.
+    PoolTimeout,
+    /// Postgres canceló la consulta por `statement_timeout`.
+    StatementTimeout,
+    Other(sqlx::Error),
+}
+
+impl From<sqlx::Error> for DbError {
+    fn from(err: sqlx::Error) -> Self {
+        match err {
+            sqlx::Error::PoolTimedOut => DbError::PoolTimeout,
+            sqlx::Error::Database(ref e) if e.code().as_deref() == Some(QUERY_CANCELED) => {
+                DbError::StatementTimeout
+            }
+            other => DbError::Other(other),
+        }
+    }
+}
+
+impl IntoResponse for DbError {
+    fn into_response(self) -> Response {
+        let (status, msg) = match &self {
+            DbError::PoolTimeout => (StatusCode::SERVICE_UNAVAILABLE, "Base de datos saturada"),
+            DbError::StatementTimeout => (StatusCode::GATEWAY_TIMEOUT, "Consulta demasiado lenta"),
+            DbError::Other(err) => {
+                tracing::error!(error = %err, "error de base de datos");
+                (StatusCode::INTERNAL_SERVER_ERROR, "Error de base de datos")
+            }
+        };
+
+        (status, Json(serde_json::json!({ "error": msg }))).into_response()
+    }
+}
@@ -0,0 +1,83 @@
+//! `GET /admin/db`: tamaño y crecimiento de las tablas según las vistas
+//! `pg_stat_*`, para vigilar `mensajes` e `images` sin entrar con psql.
+
+use axum::{
+    extract::State,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+
+use crate::db::DbError;
+use crate::queries;
+use crate::state::SharedState;
+
+#[derive(Serialize)]
+pub struct DbStats {
+    database_bytes: i64,
+    tables: Vec<TableStats>,
+    indexes: Vec<IndexStats>,
+}
+
+#[derive(Serialize)]
+pub struct TableStats {
+    name: String,
+    /// Estimación de PostgreSQL (`n_live_tup`): no recorre la tabla.
+    rows: i64,
+    dead_rows: i64,
+    /// Filas muertas sobre el total: aproximación de la hinchazón pendiente de VACUUM.
+    bloat_ratio: f64,
+    table_bytes: i64,
+    index_bytes: i64,
+    total_bytes: i64,
+    last_vacuum: Option<DateTime<Utc>>,
+    last_analyze: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize)]
+pub struct IndexStats {
+    table: String,
+    name: String,
+    bytes: i64,
+    scans: i64,
+}
+
+pub async fn db_stats(State(app): State<SharedState>) -> Response {
+    match collect(&app.db).await {
+        Ok(stats) => Json(stats).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+async fn collect(pool: &PgPool) -> Result<DbStats, DbError> {
+    let database_bytes = queries::db_stats::database_size(pool).await?;
+    let tables = queries::db_stats::tables(pool).await?;
+    let indexes = queries::db_stats::indexes(pool).await?;
+
+    Ok(DbStats {
+        database_bytes,
+        tables: tables
+            .into_iter()
+            .map(|(name, rows, dead_rows, table_bytes, index_bytes, total_bytes, last_vacuum, last_analyze)| {
+                let total = rows + dead_rows;
+                TableStats {
+                    name,
+                    rows,
+                    dead_rows,
+                    bloat_ratio: if total > 0 { dead_rows as f64 / total as f64 } else { 0.0 },
+                    table_bytes,
+                    index_bytes,
+                    total_bytes,
+                    last_vacuum,
+                    last_analyze,
+                }
+            })
+            .collect(),
+        indexes: indexes
+            .into_iter()
+            .map(|(table, name, bytes, scans)| IndexStats { table, name, bytes, scans })
+            .collect(),
+    })
+}
@@ -0,0 +1,38 @@
+//! `?dry_run=true` en las operaciones destructivas (borrar, restaurar, purgar
+//! la papelera): se comprueban permisos y se busca lo afectado, pero en vez
+//! de ejecutar se responde con cuántos son y algunos de sus ids.
+
+use axum::{
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+/// Ids que se devuelven como muestra.
+pub const SAMPLE: usize = 20;
+
+#[derive(Deserialize, Default)]
+pub struct DryRunQuery {
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Serialize)]
+pub struct Preview {
+    dry_run: bool,
+    count: i64,
+    sample_ids: Vec<i32>,
+}
+
+impl Preview {
+    pub fn new(count: i64, mut ids: Vec<i32>) -> Self {
+        ids.truncate(SAMPLE);
+        Preview { dry_run: true, count, sample_ids: ids }
+    }
+}
+
+impl IntoResponse for Preview {
+    fn into_response(self) -> Response {
+        Json(self).into_response()
+    }
+}
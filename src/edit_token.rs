@@ -0,0 +1,45 @@
+//! Token de edición de los mensajes enviados sin cuenta: `/enviar` lo manda en
+//! una cookie `HttpOnly` limitada a `/mensajes/<id>` y que dura lo mismo que
+//! `policy::EDIT_WINDOW`, y en `mensajes` solo se guarda su hash. Con él (y no
+//! con la IP, que comparten una oficina entera o un proxy) se edita y se borra.
+
+use axum::http::{header, HeaderMap, HeaderValue};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::policy::EDIT_WINDOW;
+
+const COOKIE: &str = "edit_token";
+
+/// Token nuevo y su hash, que es lo que se guarda.
+pub fn issue() -> (String, String) {
+    let token = format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+    let hash = hash(&token);
+    (token, hash)
+}
+
+pub fn hash(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+/// Solo viaja a `/mensajes/<id>`: cada mensaje tiene la suya.
+pub fn cookie(id: i32, token: &str) -> HeaderValue {
+    format!(
+        "{COOKIE}={token}; Path=/mensajes/{id}; Max-Age={}; HttpOnly; Secure; SameSite=Strict",
+        EDIT_WINDOW.num_seconds()
+    )
+    .parse()
+    .unwrap()
+}
+
+/// Hash del token de la cookie, si la trae.
+pub fn from_headers(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .find_map(|pair| pair.trim().strip_prefix(COOKIE)?.strip_prefix('='))
+        .filter(|token| !token.is_empty())
+        .map(hash)
+}
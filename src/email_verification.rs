@@ -0,0 +1,125 @@
+//! Verificación del email de los autores. Un mensaje enviado con email guarda
+//! un token de un solo uso y se manda el enlace `/verificar/<token>`; al abrirlo
+//! el mensaje queda verificado y se muestra con su distintivo. El email nunca
+//! sale en las respuestas públicas.
+//!
+//! Con `EMAIL_CONFIRMATION_REQUIRED` el email es obligatorio y el mensaje no
+//! llega a `mensajes`: espera en `mensajes_unconfirmed` hasta que se confirma
+//! con el botón de `/confirmar/<token>`, y entonces se publica ya verificado. Los que caducan
+//! sin confirmar se borran al guardar otro. Cada email puede tener como mucho
+//! `MAX_PENDING_PER_EMAIL` esperando, para que no se use el formulario para
+//! mandar correos a quien no los pidió.
+
+use axum::{
+    extract::{Path, State},
+    response::{Html, IntoResponse, Response},
+};
+use sqlx::PgConnection;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::db::DbError;
+use crate::events::{self, Event};
+use crate::flash::{self, Flash};
+use crate::html;
+use crate::mailer::Email;
+use crate::outbox;
+use crate::queries::{self, NewMensaje};
+use crate::state::SharedState;
+use crate::unit_of_work::UnitOfWork;
+
+/// Mensajes sin confirmar a la vez por email.
+pub const MAX_PENDING_PER_EMAIL: i64 = 3;
+
+pub async fn create_token(conn: &mut PgConnection, mensaje_id: i32, ttl: Duration) -> Result<String, DbError> {
+    let token = Uuid::new_v4().simple().to_string();
+    queries::email_verification::insert_token(conn, &token, mensaje_id, ttl).await?;
+    Ok(token)
+}
+
+pub fn email(to: &str, nombre: &str, link: &str) -> Email {
+    Email {
+        to: to.to_string(),
+        subject: "Verifica tu email | Axum Motors".to_string(),
+        text: format!(
+            "Hola {nombre}:\n\nPara verificar el email de tu mensaje abre este enlace:\n{link}\n\n\
+             Si no has escrito en Axum Motors, ignora este correo."
+        ),
+        html: html::verification_email(nombre, link),
+    }
+}
+
+/// `GET /verificar/:token`: marca el mensaje y lleva a su página.
+pub async fn verify(State(app): State<SharedState>, Path(token): Path<String>) -> Response {
+    match queries::email_verification::use_token(&app.db, &token).await {
+        Ok(Some(id)) => flash::redirect(&format!("/mensajes/{id}/view"), Flash::success("✅ Email verificado")),
+        Ok(None) => flash::redirect("/", Flash::error("❌ Enlace de verificación inválido o caducado")),
+        Err(e) => e.into_response(),
+    }
+}
+
+/* ---------- Confirmación antes de publicar ---------- */
+
+/// Guarda el mensaje sin publicar; devuelve el token de `/confirmar/<token>`,
+/// o `None` si su email ya tiene `MAX_PENDING_PER_EMAIL` esperando.
+pub async fn hold_unconfirmed(
+    conn: &mut PgConnection,
+    new: &NewMensaje<'_>,
+    ttl: Duration,
+) -> Result<Option<String>, DbError> {
+    queries::email_verification::purge_unconfirmed(conn).await?;
+
+    let token = Uuid::new_v4().simple().to_string();
+    let held = queries::email_verification::insert_unconfirmed(conn, &token, new, ttl, MAX_PENDING_PER_EMAIL).await?;
+    Ok(held.then_some(token))
+}
+
+pub fn confirmation_email(to: &str, nombre: &str, link: &str) -> Email {
+    Email {
+        to: to.to_string(),
+        subject: "Confirma tu mensaje | Axum Motors".to_string(),
+        text: format!(
+            "Hola {nombre}:\n\nTu mensaje se publicará cuando lo confirmes en este enlace:\n{link}\n\n\
+             Si no has escrito en Axum Motors, ignora este correo."
+        ),
+        html: html::confirmation_email(nombre, link),
+    }
+}
+
+/// `GET /confirmar/:token`: solo enseña el mensaje con el botón que lo publica;
+/// los escáneres de enlaces del correo abren el GET sin que nadie lo pida.
+pub async fn confirm_page(State(app): State<SharedState>, Path(token): Path<String>) -> Response {
+    match queries::email_verification::unconfirmed(&app.db, &token).await {
+        Ok(Some((nombre, mensaje))) => {
+            Html(html::confirm_page(&app.setup.site(), &token, &nombre, &mensaje)).into_response()
+        }
+        Ok(None) => invalid_link(),
+        Err(e) => e.into_response(),
+    }
+}
+
+fn invalid_link() -> Response {
+    flash::redirect("/", Flash::error("❌ Enlace de confirmación inválido o caducado"))
+}
+
+/// `POST /confirmar/:token`: publica el mensaje, con el mismo id y ya verificado.
+pub async fn confirm(State(app): State<SharedState>, Path(token): Path<String>) -> Response {
+    let mut uow = match UnitOfWork::begin(&app.db).await {
+        Ok(uow) => uow,
+        Err(e) => return e.into_response(),
+    };
+    let id = match queries::email_verification::confirm(uow.conn(), &token).await {
+        Ok(Some(id)) => id,
+        Ok(None) => return invalid_link(),
+        Err(e) => return e.into_response(),
+    };
+    let event = Event::MessageCreated { id };
+    if let Err(e) = outbox::enqueue(uow.conn(), &event).await {
+        return e.into_response();
+    }
+    if let Err(e) = uow.commit().await {
+        return e.into_response();
+    }
+    events::publish(&app, event);
+    flash::redirect(&format!("/mensajes/{id}/view"), Flash::success("✅ Mensaje confirmado y publicado"))
+}
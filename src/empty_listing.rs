@@ -0,0 +1,123 @@
+//! Atajo para `GET /mensajes` con la tabla vacía: en un despliegue recién hecho
+//! cada visita costaría un `count(*)` y un `SELECT` para devolver nada. Cuando
+//! una consulta ve cero mensajes se recuerda y las siguientes se responden sin
+//! tocar la base de datos hasta que entra el primero.
+//!
+//! Las inserciones llegan por `NOTIFY mensajes_insert` (ver la migración 0013),
+//! así que cuentan también las de otras instancias o de `psql`. El atajo solo se
+//! usa mientras la escucha está conectada: si se cae, no se sabría de las
+//! inserciones y se vuelve a contar como siempre hasta reconectar.
+
+use sqlx::{postgres::PgListener, PgPool};
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+const CHANNEL: &str = "mensajes_insert";
+
+/// Espera antes de volver a conectar la escucha.
+const RETRY: Duration = Duration::from_secs(5);
+
+pub struct EmptyListing {
+    /// La escucha de avisos está conectada.
+    armed: AtomicBool,
+    /// Sube con cada inserción y cada reconexión.
+    epoch: AtomicU64,
+    /// `epoch + 1` de cuando se vio la tabla vacía; 0 si no se sabe.
+    empty_at: AtomicU64,
+}
+
+impl EmptyListing {
+    pub fn new() -> Arc<Self> {
+        Arc::new(EmptyListing {
+            armed: AtomicBool::new(false),
+            epoch: AtomicU64::new(0),
+            empty_at: AtomicU64::new(0),
+        })
+    }
+
+    /// Se puede responder el listado vacío sin consultar.
+    pub fn is_empty(&self) -> bool {
+        self.armed.load(Ordering::Acquire)
+            && self.empty_at.load(Ordering::Acquire) == self.epoch.load(Ordering::Acquire) + 1
+    }
+
+    /// Se toma antes de contar; si entre tanto llega una inserción, el recuento
+    /// ya no vale para activar el atajo.
+    pub fn ticket(&self) -> u64 {
+        self.epoch.load(Ordering::Acquire)
+    }
+
+    /// Resultado del recuento hecho con `ticket`.
+    pub fn observed(&self, ticket: u64, total: i64) {
+        if total == 0 && self.armed.load(Ordering::Acquire) {
+            self.empty_at.store(ticket + 1, Ordering::Release);
+        }
+    }
+
+    /// Hay al menos un mensaje. Quien inserta lo avisa directamente para no
+    /// depender de que el `NOTIFY` llegue antes que su siguiente lectura.
+    pub fn inserted(&self) {
+        self.epoch.fetch_add(1, Ordering::AcqRel);
+    }
+
+    /// Escucha los avisos de inserción para siempre, reconectando si se cae.
+    pub async fn listen(self: Arc<Self>, pool: PgPool) {
+        loop {
+            if let Err(e) = self.listen_once(&pool).await {
+                tracing::warn!(error = %e, "escucha de mensajes nuevos caída; listado vacío sin atajo");
+            }
+            self.armed.store(false, Ordering::Release);
+            tokio::time::sleep(RETRY).await;
+        }
+    }
+
+    async fn listen_once(&self, pool: &PgPool) -> Result<(), sqlx::Error> {
+        let mut listener = PgListener::connect_with(pool).await?;
+        listener.listen(CHANNEL).await?;
+
+        // Lo insertado sin escucha no se ha visto: hay que volver a contar.
+        self.inserted();
+        self.armed.store(true, Ordering::Release);
+
+        // `None`: conexión perdida.
+        while listener.try_recv().await?.is_some() {
+            self.inserted();
+        }
+        Ok(())
+    }
+
+    #[cfg(test)]
+    pub fn is_armed(&self) -> bool {
+        self.armed.load(Ordering::Acquire)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_during_count_keeps_fast_path_off() {
+        let listing = EmptyListing::new();
+        listing.observed(listing.ticket(), 0);
+        assert!(!listing.is_empty(), "sin escucha no hay atajo");
+
+        listing.armed.store(true, Ordering::Release);
+        listing.observed(listing.ticket(), 0);
+        assert!(listing.is_empty());
+
+        listing.inserted();
+        assert!(!listing.is_empty());
+
+        // El recuento empezó antes de la inserción: su cero ya no vale.
+        let ticket = listing.ticket();
+        listing.inserted();
+        listing.observed(ticket, 0);
+        assert!(!listing.is_empty());
+    }
+}
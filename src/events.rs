@@ -0,0 +1,224 @@
+//! Eventos del dominio. Los handlers publican qué ha pasado (`MessageCreated`,
+//! `MessageDeleted`, `ImageUploaded`) y los consumidores reaccionan: cachés y
+//! el flujo SSE de `GET /events`. El webhook (`EVENTS_WEBHOOK_URL`) y el aviso
+//! por correo (`NOTIFY_EMAIL`) no salen de aquí sino de `outbox`, que guarda el
+//! evento con el cambio para no perderlo.
+//!
+//! Dentro del proceso el reparto es un `broadcast`. Con `EVENTS_REDIS_URL`
+//! además se publican en Redis y se reciben los de las otras réplicas, para
+//! que todas invaliden sus cachés y emitan por SSE.
+
+use axum::{
+    extract::State,
+    response::sse::{self, KeepAlive, Sse},
+};
+use futures_util::stream::{self, Stream};
+use serde::{Deserialize, Serialize};
+use std::{
+    convert::Infallible,
+    sync::{Arc, OnceLock},
+    time::Duration,
+};
+use tokio::sync::{broadcast, mpsc};
+use uuid::Uuid;
+
+use crate::redis::{self, Connection};
+use crate::state::{AppState, SharedState};
+
+/// Eventos en cola por consumidor; al que se queda atrás se le saltan.
+const CAPACITY: usize = 256;
+
+/// Espera antes de volver a conectar con Redis.
+const RETRY: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum Event {
+    MessageCreated { id: i32 },
+    MessageDeleted { id: i32 },
+    /// Imagen ya visible: subida por administración o aprobada en revisión.
+    ImageUploaded { id: i32 },
+    /// Aviso del sitio o ajustes de `/setup` (ver `settings_cache`).
+    SettingsChanged,
+}
+
+impl Event {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Event::MessageCreated { .. } => "MessageCreated",
+            Event::MessageDeleted { .. } => "MessageDeleted",
+            Event::ImageUploaded { .. } => "ImageUploaded",
+            Event::SettingsChanged => "SettingsChanged",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Delivery {
+    pub event: Event,
+    /// Publicado en esta réplica, no recibido de otra por Redis.
+    pub local: bool,
+}
+
+/// Lo que viaja por Redis: el evento y la réplica que lo publicó.
+#[derive(Serialize, Deserialize)]
+struct Envelope {
+    origin: Uuid,
+    event: Event,
+}
+
+pub struct EventBus {
+    origin: Uuid,
+    local: broadcast::Sender<Delivery>,
+    /// Cola hacia Redis, si hay `EVENTS_REDIS_URL` y ya arrancó el puente.
+    remote: OnceLock<mpsc::UnboundedSender<String>>,
+}
+
+impl EventBus {
+    pub fn new() -> Arc<Self> {
+        Arc::new(EventBus {
+            origin: Uuid::new_v4(),
+            local: broadcast::channel(CAPACITY).0,
+            remote: OnceLock::new(),
+        })
+    }
+
+    /// Nunca bloquea ni falla: sin consumidores el evento se pierde.
+    pub fn publish(&self, event: Event) {
+        if let Some(remote) = self.remote.get() {
+            let envelope = Envelope { origin: self.origin, event: event.clone() };
+            let _ = remote.send(serde_json::to_string(&envelope).unwrap());
+        }
+        let _ = self.local.send(Delivery { event, local: true });
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Delivery> {
+        self.local.subscribe()
+    }
+
+    /// Puente con Redis: publica lo local y reparte lo de otras réplicas.
+    /// Si Redis no está, lo local sigue funcionando y se reintenta cada `RETRY`.
+    pub async fn bridge_redis(self: Arc<Self>, url: String, channel: String) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        if self.remote.set(tx).is_err() {
+            return;
+        }
+        tokio::spawn(forward_to_redis(url.clone(), channel.clone(), rx));
+
+        loop {
+            if let Err(e) = self.receive_from_redis(&url, &channel).await {
+                tracing::warn!(error = %e, "suscripción a Redis caída; se reintenta");
+            }
+            tokio::time::sleep(RETRY).await;
+        }
+    }
+
+    async fn receive_from_redis(&self, url: &str, channel: &str) -> std::io::Result<()> {
+        let mut conn = Connection::open(url).await?;
+        conn.command(&[b"SUBSCRIBE", channel.as_bytes()]).await?;
+        tracing::info!(channel, "suscrito a eventos en Redis");
+
+        loop {
+            let Some(payload) = redis::message_payload(conn.read().await?) else { continue };
+            match serde_json::from_slice::<Envelope>(&payload) {
+                Ok(envelope) if envelope.origin == self.origin => {}
+                Ok(envelope) => {
+                    let _ = self.local.send(Delivery { event: envelope.event, local: false });
+                }
+                Err(e) => tracing::warn!(error = %e, "evento de Redis ilegible"),
+            }
+        }
+    }
+}
+
+/// Un `PUBLISH` por evento sobre una conexión que se reabre si falla. Lo que
+/// no se pudo publicar no se reintenta: las demás réplicas se lo pierden.
+async fn forward_to_redis(url: String, channel: String, mut rx: mpsc::UnboundedReceiver<String>) {
+    let mut conn = None;
+    while let Some(payload) = rx.recv().await {
+        if conn.is_none() {
+            match Connection::open(&url).await {
+                Ok(c) => conn = Some(c),
+                Err(e) => tracing::warn!(error = %e, "no se pudo conectar con Redis para publicar"),
+            }
+        }
+        let Some(c) = conn.as_mut() else { continue };
+        if let Err(e) = c.command(&[b"PUBLISH", channel.as_bytes(), payload.as_bytes()]).await {
+            tracing::warn!(error = %e, "no se pudo publicar el evento en Redis");
+            conn = None;
+        }
+    }
+}
+
+/* ---------- PUBLICAR ---------- */
+
+/// Publica el evento. Las cachés de esta réplica se invalidan ya, antes de
+/// responder, para que quien acaba de escribir lea lo que escribió.
+pub fn publish(app: &AppState, event: Event) {
+    invalidate(app, &event);
+    app.events.publish(event);
+}
+
+/* ---------- CONSUMIDORES ---------- */
+
+fn invalidate(app: &AppState, event: &Event) {
+    match event {
+        Event::MessageCreated { .. } => {
+            app.mensajes_empty.inserted();
+            app.mensajes_cache.clear();
+        }
+        Event::MessageDeleted { .. } => app.mensajes_cache.clear(),
+        Event::ImageUploaded { .. } => {}
+        Event::SettingsChanged => app.settings.clear(),
+    }
+}
+
+/// Consumidor en segundo plano: cachés para lo que llega de otras réplicas.
+pub async fn consume(app: SharedState) {
+    let mut rx = app.events.subscribe();
+
+    loop {
+        let delivery = match rx.recv().await {
+            Ok(delivery) => delivery,
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                tracing::warn!(skipped, "consumidor de eventos desbordado");
+                continue;
+            }
+            Err(broadcast::error::RecvError::Closed) => return,
+        };
+
+        if delivery.local {
+            continue;
+        }
+        invalidate(&app, &delivery.event);
+        // El título y el idioma viven en `Setup`, no en la caché.
+        if delivery.event == Event::SettingsChanged
+            && let Err(e) = app.setup.load(&app.db).await
+        {
+            tracing::warn!(error = ?e, "no se pudieron recargar los ajustes del sitio");
+        }
+    }
+}
+
+/* ---------- GET /events ---------- */
+
+/// Flujo SSE con los eventos de todas las réplicas; solo ids, el detalle se
+/// pide a la API como siempre.
+pub async fn stream_events(State(app): State<SharedState>) -> Sse<impl Stream<Item = Result<sse::Event, Infallible>>> {
+    let events = stream::unfold(app.events.subscribe(), |mut rx| async move {
+        loop {
+            match rx.recv().await {
+                Ok(delivery) => {
+                    let event = sse::Event::default()
+                        .event(delivery.event.name())
+                        .json_data(&delivery.event)
+                        .unwrap();
+                    return Some((Ok(event), rx));
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    });
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
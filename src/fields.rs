@@ -0,0 +1,114 @@
+//! `?fields=id,nombre` en los listados JSON: se consultan y se devuelven solo
+//! los campos pedidos. Cada listado declara qué campos admite y de qué
+//! expresión SQL sale cada uno; un campo desconocido es un 400.
+
+use axum::{
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::html;
+
+/// Campo del JSON y expresión del `SELECT` de la que sale.
+pub type Field = (&'static str, &'static str);
+
+#[derive(Deserialize, Default)]
+pub struct FieldsQuery {
+    pub fields: Option<String>,
+}
+
+pub struct Selection {
+    available: &'static [Field],
+    /// `None`: todos los campos, como sin `?fields=`.
+    chosen: Option<Vec<&'static str>>,
+}
+
+impl Selection {
+    pub fn parse(query: &FieldsQuery, available: &'static [Field]) -> Result<Self, UnknownField> {
+        let Some(list) = query.fields.as_deref().filter(|s| !s.trim().is_empty()) else {
+            return Ok(Selection { available, chosen: None });
+        };
+        let mut chosen = Vec::new();
+        for name in list.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let Some((field, _)) = available.iter().find(|(f, _)| *f == name) else {
+                return Err(UnknownField(name.to_string()));
+            };
+            if !chosen.contains(field) {
+                chosen.push(*field);
+            }
+        }
+        Ok(Selection { available, chosen: Some(chosen) })
+    }
+
+    fn wants(&self, field: &str) -> bool {
+        self.chosen.as_ref().is_none_or(|c| c.contains(&field))
+    }
+
+    /// Lista del `SELECT`: `always` (lo que necesita la paginación) y las
+    /// expresiones de los campos pedidos, sin repetir.
+    pub fn columns(&self, always: &[&'static str]) -> String {
+        let mut columns: Vec<&str> = always.to_vec();
+        for (field, expr) in self.available {
+            if self.wants(field) && !columns.contains(expr) {
+                columns.push(expr);
+            }
+        }
+        columns.join(", ")
+    }
+
+    /// El elemento serializado, solo con los campos pedidos.
+    pub fn project<T: Serialize>(&self, item: &T) -> Value {
+        let mut value = serde_json::to_value(item).unwrap_or(Value::Null);
+        if let Value::Object(map) = &mut value {
+            map.retain(|k, _| self.wants(k));
+        }
+        value
+    }
+
+    /// Para claves de caché: dos selecciones iguales dan la misma clave.
+    pub fn key(&self) -> String {
+        match &self.chosen {
+            None => "*".to_string(),
+            Some(chosen) => {
+                let mut names = chosen.clone();
+                names.sort_unstable();
+                names.join(",")
+            }
+        }
+    }
+}
+
+pub struct UnknownField(pub String);
+
+impl IntoResponse for UnknownField {
+    fn into_response(self) -> Response {
+        (StatusCode::BAD_REQUEST, Html(format!("❌ Campo desconocido: {}", html::escape(&self.0)))).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIELDS: &[Field] = &[("id", "id"), ("nombre", "nombre"), ("mensaje", "mensaje"), ("excerpt", "mensaje")];
+
+    fn parse(fields: Option<&str>) -> Result<Selection, UnknownField> {
+        Selection::parse(&FieldsQuery { fields: fields.map(str::to_string) }, FIELDS)
+    }
+
+    #[test]
+    fn selects_only_known_fields() {
+        let all = parse(None).ok().unwrap();
+        assert_eq!(all.columns(&["id"]), "id, nombre, mensaje");
+
+        let some = parse(Some("excerpt, nombre,excerpt")).ok().unwrap();
+        assert_eq!(some.columns(&["id"]), "id, nombre, mensaje");
+        assert_eq!(some.key(), "excerpt,nombre");
+        let projected = some.project(&serde_json::json!({"id": 1, "nombre": "a", "excerpt": "b"}));
+        assert_eq!(projected, serde_json::json!({"nombre": "a", "excerpt": "b"}));
+
+        assert!(matches!(parse(Some("id,email")), Err(UnknownField(f)) if f == "email"));
+    }
+}
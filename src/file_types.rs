@@ -0,0 +1,173 @@
+//! Tipos de fichero que admite cada despliegue en las subidas, con `UPLOAD_TYPES`:
+//! qué formatos se aceptan, el tamaño máximo de cada uno y qué se hace con
+//! ellos. Una entrada por formato, separadas por comas:
+//!
+//! `formato:tamaño[:pasos]`, p. ej. `jpg:5M:thumb,png:2M,webp:5M:thumb+to-jpg`
+//!
+//! El tamaño admite los sufijos `K` y `M`. Los pasos van separados por `+`:
+//! `thumb` genera miniatura para la galería (sin él se sirve el original) y
+//! `to-<formato>` convierte la imagen antes de guardarla. Solo se admiten los
+//! formatos que se saben reconocer por contenido: jpg, png y webp.
+
+use std::io::Cursor;
+
+pub const DEFAULT: &str = "jpg:5M:thumb,png:5M:thumb,webp:5M:thumb";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FileType {
+    pub ext: &'static str,
+    pub max_bytes: usize,
+    pub thumbnail: bool,
+    /// Formato en el que se guarda, si no es el mismo.
+    pub transcode: Option<&'static str>,
+}
+
+#[derive(Clone, Debug)]
+pub struct FileTypePolicy {
+    types: Vec<FileType>,
+}
+
+impl FileTypePolicy {
+    /// `None` si alguna entrada no se entiende o no queda ningún formato.
+    pub fn parse(raw: &str) -> Option<Self> {
+        let mut types: Vec<FileType> = Vec::new();
+        for entry in raw.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let mut parts = entry.split(':');
+            let ext = canonical(parts.next()?)?;
+            let max_bytes = parse_size(parts.next()?)?;
+
+            let mut file_type = FileType { ext, max_bytes, thumbnail: false, transcode: None };
+            for step in parts.next().into_iter().flat_map(|s| s.split('+')) {
+                match step.trim() {
+                    "thumb" => file_type.thumbnail = true,
+                    step => file_type.transcode = Some(canonical(step.strip_prefix("to-")?)?),
+                }
+            }
+            if parts.next().is_some() || types.iter().any(|t| t.ext == ext) {
+                return None;
+            }
+            types.push(file_type);
+        }
+        (!types.is_empty()).then_some(FileTypePolicy { types })
+    }
+
+    pub fn for_ext(&self, ext: &str) -> Option<&FileType> {
+        self.types.iter().find(|t| t.ext == ext)
+    }
+
+    /// Tipo admitido para un `Content-Type` declarado.
+    pub fn for_mime(&self, mime: &str) -> Option<&FileType> {
+        self.types.iter().find(|t| mimes(t.ext).contains(&mime))
+    }
+
+    pub fn mimes(&self) -> Vec<&'static str> {
+        self.types.iter().flat_map(|t| mimes(t.ext)).copied().collect()
+    }
+
+    /// Rebaja a `max` el tamaño de los tipos que lo superan (`MAX_IMAGE_SIZE`).
+    pub fn cap(mut self, max: usize) -> Self {
+        for t in &mut self.types {
+            t.max_bytes = t.max_bytes.min(max);
+        }
+        self
+    }
+
+    /// El mayor de los máximos, para los límites que se aplican antes de saber el tipo.
+    pub fn max_bytes(&self) -> usize {
+        self.types.iter().map(|t| t.max_bytes).max().unwrap_or(0)
+    }
+
+    /// Si la galería usa miniatura para un fichero guardado. Se decide por su
+    /// formato final; uno que solo llega por conversión la lleva.
+    pub fn thumbnail(&self, filename: &str) -> bool {
+        let ext = filename.rsplit('.').next().unwrap_or_default();
+        self.for_ext(ext).is_none_or(|t| t.thumbnail)
+    }
+}
+
+fn canonical(ext: &str) -> Option<&'static str> {
+    match ext.trim().to_ascii_lowercase().as_str() {
+        "jpg" | "jpeg" => Some("jpg"),
+        "png" => Some("png"),
+        "webp" => Some("webp"),
+        _ => None,
+    }
+}
+
+fn mimes(ext: &str) -> &'static [&'static str] {
+    match ext {
+        "jpg" => &["image/jpeg", "image/jpg"],
+        "png" => &["image/png"],
+        "webp" => &["image/webp"],
+        _ => &[],
+    }
+}
+
+/// `5M`, `512K` o bytes.
+pub fn parse_size(raw: &str) -> Option<usize> {
+    let raw = raw.trim();
+    let (digits, unit) = match raw.char_indices().last()? {
+        (i, 'K' | 'k') => (&raw[..i], 1024),
+        (i, 'M' | 'm') => (&raw[..i], 1024 * 1024),
+        _ => (raw, 1),
+    };
+    digits.parse::<usize>().ok().filter(|n| *n > 0).map(|n| n * unit)
+}
+
+/// `5MB`, `512KB` o bytes, para los mensajes de error.
+pub fn human_size(bytes: usize) -> String {
+    match bytes {
+        b if b % (1024 * 1024) == 0 => format!("{}MB", b / (1024 * 1024)),
+        b if b % 1024 == 0 => format!("{}KB", b / 1024),
+        b => format!("{b} bytes"),
+    }
+}
+
+/// Paso `to-<formato>`. Decodifica y vuelve a codificar, así que hay que
+/// llamarlo fuera del runtime (`spawn_blocking`).
+pub fn transcode(bytes: &[u8], to: &str) -> Option<Vec<u8>> {
+    let img = image::load_from_memory(bytes).ok()?;
+    let (img, format) = match to {
+        // JPEG no tiene canal alfa.
+        "jpg" => (image::DynamicImage::ImageRgb8(img.to_rgb8()), image::ImageFormat::Jpeg),
+        "png" => (img, image::ImageFormat::Png),
+        "webp" => (image::DynamicImage::ImageRgba8(img.to_rgba8()), image::ImageFormat::WebP),
+        _ => return None,
+    };
+
+    let mut out = Cursor::new(Vec::new());
+    img.write_to(&mut out, format).ok()?;
+    Some(out.into_inner())
+}
+
+/// Cambia la extensión de un nombre ya saneado por `uploads::original_name`.
+pub fn with_extension(name: &str, ext: &str) -> String {
+    let stem = name.rsplit_once('.').map_or(name, |(stem, _)| stem);
+    format!("{stem}.{ext}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_types_sizes_and_steps() {
+        let policy = FileTypePolicy::parse("jpeg:5M:thumb, png:512K, webp:2M:thumb+to-jpg").unwrap();
+
+        let jpg = policy.for_mime("image/jpg").unwrap();
+        assert_eq!((jpg.ext, jpg.max_bytes, jpg.thumbnail, jpg.transcode), ("jpg", 5 * 1024 * 1024, true, None));
+        let webp = policy.for_ext("webp").unwrap();
+        assert_eq!((webp.thumbnail, webp.transcode), (true, Some("jpg")));
+        assert!(!policy.for_ext("png").unwrap().thumbnail);
+
+        assert_eq!(policy.max_bytes(), 5 * 1024 * 1024);
+        let capped = policy.clone().cap(1024 * 1024);
+        assert_eq!((capped.max_bytes(), capped.for_ext("png").unwrap().max_bytes), (1024 * 1024, 512 * 1024));
+        assert!(!policy.thumbnail("abc.png") && policy.thumbnail("abc.jpg"));
+        assert_eq!(human_size(512 * 1024), "512KB");
+
+        for bad in ["", "gif:1M", "png", "png:0", "png:1M:resize", "png:1M,png:2M", "png:1M:thumb:x"] {
+            assert!(FileTypePolicy::parse(bad).is_none(), "{bad}");
+        }
+    }
+}
@@ -0,0 +1,99 @@
+//! Mensajes flash: el resultado de un formulario viaja en una cookie de vida
+//! corta hasta la página a la que se redirige, que lo muestra como aviso y la
+//! borra (`static/js/flash.js`).
+
+use axum::{
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Redirect, Response},
+};
+
+use crate::html::url_encode;
+
+const COOKIE: &str = "flash";
+
+/// Segundos que sobrevive la cookie si nadie la consume.
+const MAX_AGE: u32 = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    Success,
+    Error,
+}
+
+pub struct Flash {
+    kind: Kind,
+    message: String,
+}
+
+impl Flash {
+    pub fn success(message: impl Into<String>) -> Self {
+        Flash { kind: Kind::Success, message: message.into() }
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        Flash { kind: Kind::Error, message: message.into() }
+    }
+
+    /// `flash=success|<mensaje codificado>`. La lee JavaScript, así que no es `HttpOnly`;
+    /// el mensaje se muestra como texto, nunca como HTML.
+    fn cookie(&self) -> String {
+        let kind = match self.kind {
+            Kind::Success => "success",
+            Kind::Error => "error",
+        };
+        format!(
+            "{COOKIE}={kind}|{}; Path=/; Max-Age={MAX_AGE}; SameSite=Lax",
+            url_encode(&self.message)
+        )
+    }
+}
+
+/// Envío de formulario clásico desde el navegador (no `fetch` ni htmx): se
+/// contesta con redirección y flash en vez de con un fragmento suelto.
+pub fn wants_html(headers: &HeaderMap) -> bool {
+    let accepts_html = headers
+        .get(header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.contains("text/html"));
+
+    accepts_html && !headers.contains_key("hx-request")
+}
+
+/// `303 See Other` a `to` con el flash en una cookie.
+pub fn redirect(to: &str, flash: Flash) -> Response {
+    let mut res = Redirect::to(to).into_response();
+    debug_assert_eq!(res.status(), StatusCode::SEE_OTHER);
+    res.headers_mut()
+        .append(header::SET_COOKIE, flash.cookie().parse().unwrap());
+    res
+}
+
+/// Ruta local de la página de origen (`Referer`), para volver al formulario tras
+/// un error. Solo se usa la ruta, nunca el host, así que no sirve para saltar a
+/// otro sitio.
+pub fn back(headers: &HeaderMap, fallback: &str) -> String {
+    headers
+        .get(header::REFERER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|r| r.parse::<axum::http::Uri>().ok())
+        .map(|uri| uri.path().to_string())
+        .filter(|path| path.starts_with('/') && !path.starts_with("//"))
+        .unwrap_or_else(|| fallback.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn back_keeps_only_local_path() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(back(&headers, "/"), "/");
+
+        headers.insert(header::REFERER, "https://evil.example/phish".parse().unwrap());
+        assert_eq!(back(&headers, "/"), "/phish");
+
+        headers.insert(header::REFERER, "http://localhost:3000/contacto.html?x=1".parse().unwrap());
+        assert_eq!(back(&headers, "/"), "/contacto.html");
+    }
+}
@@ -0,0 +1,715 @@
+//! Páginas y fragmentos HTML generados en el servidor.
+
+use axum::http::{header, HeaderMap};
+
+use crate::oauth::Provider;
+use crate::setup::Site;
+use crate::timezone::LocalTime;
+
+/// Longitud (en caracteres) del extracto para las vistas previas.
+const EXCERPT_CHARS: usize = 160;
+
+/// Imagen por defecto para las vistas previas al compartir.
+const SHARE_IMAGE: &str = "/uploads/Logo.png";
+
+/// Distintivo de los mensajes cuyo autor verificó su email.
+const VERIFIED_BADGE: &str = r#" <span class="badge-verified" title="Email verificado">✔ Verificado</span>"#;
+
+/// Escapa texto para insertarlo en HTML, tanto en contenido como en atributos.
+pub fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Extracto para las vistas previas (ver `excerpt_chars`).
+pub fn excerpt(text: &str) -> String {
+    excerpt_chars(text, EXCERPT_CHARS)
+}
+
+/// Texto en una sola línea de hasta `max` caracteres (no bytes), con `…` si se
+/// corta. Corta entre palabras salvo que la primera ya no quepa.
+pub fn excerpt_chars(text: &str, max: usize) -> String {
+    let flat = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if flat.chars().count() <= max {
+        return flat;
+    }
+    let cut: String = flat.chars().take(max.saturating_sub(1)).collect();
+    let mid_word = flat.chars().nth(cut.chars().count()).is_some_and(|next| next != ' ');
+    let cut = match cut.rfind(' ') {
+        Some(space) if mid_word => &cut[..space],
+        _ => cut.as_str(),
+    };
+    format!("{}…", cut.trim_end())
+}
+
+/// URL pública del sitio: `PUBLIC_URL` si está configurada, si no la que usó el
+/// cliente (`Host` y `X-Forwarded-Proto`).
+pub fn base_url(public_url: Option<&str>, headers: &HeaderMap) -> String {
+    if let Some(url) = public_url {
+        return url.trim_end_matches('/').to_string();
+    }
+    let host = headers
+        .get(header::HOST)
+        .and_then(|h| h.to_str().ok())
+        .unwrap_or("localhost");
+    let proto = headers
+        .get("x-forwarded-proto")
+        .and_then(|p| p.to_str().ok())
+        .unwrap_or("http");
+    format!("{proto}://{host}")
+}
+
+pub struct MensajePage<'a> {
+    pub id: i32,
+    pub nombre: &'a str,
+    pub mensaje: &'a str,
+    pub created_at: LocalTime,
+    pub base_url: &'a str,
+    /// El autor confirmó su email.
+    pub verified: bool,
+    /// Mensajes parecidos, para la sección «También te puede interesar».
+    pub related: &'a [MensajeRow],
+    pub site: &'a Site,
+    /// Aviso del sitio vigente (ver `announcement`).
+    pub announcement: Option<&'a str>,
+}
+
+/// Página de un mensaje con etiquetas OpenGraph y Twitter para que el enlace
+/// se vea bien al compartirlo.
+pub fn mensaje_page(page: &MensajePage) -> String {
+    let (site, lang) = (escape(&page.site.title), escape(&page.site.language));
+    let title = escape(&format!("Mensaje de {} | {}", page.nombre, page.site.title));
+    let description = escape(&excerpt(page.mensaje));
+    let url = escape(&format!("{}/mensajes/{}/view", page.base_url, page.id));
+    let image = escape(&format!("{}{SHARE_IMAGE}", page.base_url));
+    let nombre = escape(page.nombre);
+    let badge = if page.verified { VERIFIED_BADGE } else { "" };
+    let mensaje = mensaje_body(page.mensaje);
+    let fecha = page.created_at.format("%d/%m/%Y %H:%M");
+    let related = related_section(page.related);
+    let announcement = page.announcement.map_or_else(String::new, |text| {
+        format!("    <div class=\"announcement\" role=\"note\">{}</div>\n", escape(text))
+    });
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="{lang}">
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>{title}</title>
+    <meta name="description" content="{description}">
+    <link rel="canonical" href="{url}">
+    <meta property="og:type" content="article">
+    <meta property="og:site_name" content="{site}">
+    <meta property="og:title" content="{title}">
+    <meta property="og:description" content="{description}">
+    <meta property="og:url" content="{url}">
+    <meta property="og:image" content="{image}">
+    <meta name="twitter:card" content="summary">
+    <meta name="twitter:title" content="{title}">
+    <meta name="twitter:description" content="{description}">
+    <meta name="twitter:image" content="{image}">
+    <link rel="stylesheet" href="/css/styles.css">
+</head>
+
+<body>
+
+<div class="sidebar">
+    <h2>{site}</h2>
+    <a href="/index.html">🏠 Inicio</a>
+    <a href="/motos.html">🏍 Motos</a>
+    <a href="/otros.html">🚲 Otros</a>
+    <a href="/contacto.html">✉️ Contacto</a>
+    <a href="/admin.html">📋 Admin</a>
+</div>
+
+<div class="main-content">
+{announcement}    <div class="page-title">Mensaje de {nombre}{badge}</div>
+    <p class="subtitle">{fecha}</p>
+    <div class="form-container">
+        {mensaje}
+    </div>
+{related}</div>
+
+<script src="/js/flash.js"></script>
+</body>
+</html>
+"#
+    )
+}
+
+/// Cuerpo del mensaje tal como se publica; también lo usa la vista previa.
+pub fn mensaje_body(mensaje: &str) -> String {
+    format!("<p>{}</p>", escape(mensaje))
+}
+
+/// Enlaces a los relacionados con su extracto; nada si no hay ninguno.
+fn related_section(related: &[MensajeRow]) -> String {
+    if related.is_empty() {
+        return String::new();
+    }
+    let mut items = String::new();
+    for m in related {
+        let (id, nombre, extracto) = (m.id, escape(&m.nombre), escape(&excerpt(&m.mensaje)));
+        let badge = if m.verified { VERIFIED_BADGE } else { "" };
+        items.push_str(&format!(
+            r#"
+            <li><a href="/mensajes/{id}/view"><strong>{nombre}</strong></a>{badge}<br>{extracto}</li>"#
+        ));
+    }
+    format!(
+        r#"    <div class="form-container related">
+        <h3>También te puede interesar</h3>
+        <ul>{items}
+        </ul>
+    </div>
+"#
+    )
+}
+
+/* ---------- EXPORTACIÓN ESTÁTICA ---------- */
+
+pub struct GuestbookEntry<'a> {
+    pub id: i32,
+    pub nombre: &'a str,
+    pub mensaje: &'a str,
+    pub created_at: LocalTime,
+    pub verified: bool,
+}
+
+pub struct GuestbookPage<'a> {
+    pub entries: &'a [GuestbookEntry<'a>],
+    pub page: usize,
+    pub pages: usize,
+    pub site: &'a Site,
+}
+
+/// Fichero de cada página del listado exportado.
+pub fn guestbook_path(page: usize) -> String {
+    if page == 1 {
+        "mensajes/index.html".to_string()
+    } else {
+        format!("mensajes/pagina/{page}/index.html")
+    }
+}
+
+fn guestbook_href(page: usize) -> String {
+    if page == 1 { "/mensajes/".to_string() } else { format!("/mensajes/pagina/{page}/") }
+}
+
+/// Página del libro de visitas para la web estática, con enlace a cada mensaje.
+pub fn guestbook_page(page: &GuestbookPage) -> String {
+    let mut entries = String::new();
+    for e in page.entries {
+        let (id, nombre, mensaje) = (e.id, escape(e.nombre), escape(e.mensaje));
+        let badge = if e.verified { VERIFIED_BADGE } else { "" };
+        let fecha = e.created_at.format("%d/%m/%Y %H:%M");
+        entries.push_str(&format!(
+            r#"
+    <div class="form-container">
+        <p><a href="/mensajes/{id}/view/"><strong>{nombre}</strong></a>{badge} · <span class="subtitle">{fecha}</span></p>
+        <p>{mensaje}</p>
+    </div>"#
+        ));
+    }
+    if page.entries.is_empty() {
+        entries.push_str(r#"
+    <p>Todavía no hay mensajes.</p>"#);
+    }
+
+    let nav = |target: usize, label: &str, enabled: bool| {
+        if enabled {
+            format!(r#"<a class="page-link" href="{}">{label}</a>"#, guestbook_href(target))
+        } else {
+            String::new()
+        }
+    };
+    let prev = nav(page.page.saturating_sub(1), "Anterior", page.page > 1);
+    let next = nav(page.page + 1, "Siguiente", page.page < page.pages);
+    let (current, pages) = (page.page, page.pages);
+    let (site, lang) = (escape(&page.site.title), escape(&page.site.language));
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="{lang}">
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>Libro de visitas | {site}</title>
+    <link rel="stylesheet" href="/css/styles.css">
+</head>
+
+<body>
+
+<div class="sidebar">
+    <h2>{site}</h2>
+    <a href="/index.html">🏠 Inicio</a>
+    <a href="/motos.html">🏍 Motos</a>
+    <a href="/otros.html">🚲 Otros</a>
+    <a href="/contacto.html">✉️ Contacto</a>
+    <a href="/mensajes/" class="active">📖 Libro de visitas</a>
+</div>
+
+<div class="main-content">
+    <div class="page-title">Libro de visitas</div>{entries}
+    <div class="pagination">
+        {prev}
+        <span>Página {current} de {pages}</span>
+        {next}
+    </div>
+</div>
+
+</body>
+</html>
+"#
+    )
+}
+
+/* ---------- FRAGMENTOS DE ADMINISTRACIÓN ---------- */
+
+pub struct MensajeRow {
+    pub id: i32,
+    pub nombre: String,
+    pub mensaje: String,
+    pub verified: bool,
+}
+
+pub struct MensajesTable<'a> {
+    pub rows: &'a [MensajeRow],
+    pub page: i64,
+    pub pages: i64,
+    /// Búsqueda activa, que se conserva al cambiar de página.
+    pub q: &'a str,
+    /// Ordenada por nombre (`?sort=nombre`) en vez de por fecha.
+    pub by_name: bool,
+}
+
+/// Tabla de mensajes paginada como fragmento (`#mensajes-panel`), pensada para
+/// sustituirse entera con htmx (`hx-get` + `hx-swap="outerHTML"`).
+pub fn mensajes_table(table: &MensajesTable) -> String {
+    let mut rows = String::new();
+    for m in table.rows {
+        let (id, nombre, mensaje) = (m.id, escape(&m.nombre), escape(&m.mensaje));
+        let badge = if m.verified { VERIFIED_BADGE } else { "" };
+        rows.push_str(&format!(
+            r#"
+            <tr>
+                <td class="name-cell">{nombre}{badge}</td>
+                <td class="msg-cell">{mensaje}</td>
+                <td class="actions-cell">
+                    <div style="display:flex; gap:5px; justify-content:center;">
+                        <a class="btn-edit" href="/mensajes/{id}/view" title="Ver">🔗</a>
+                        <button class="btn-edit" data-id="{id}" data-nombre="{nombre}" data-mensaje="{mensaje}">✏️</button>
+                        <button class="btn-delete" hx-delete="/api/admin/mensajes/{id}" hx-confirm="¿Eliminar este registro?" hx-target="closest tr" hx-swap="delete">🗑</button>
+                    </div>
+                </td>
+            </tr>"#
+        ));
+    }
+    if table.rows.is_empty() {
+        rows.push_str(r#"<tr><td colspan="3">No hay mensajes</td></tr>"#);
+    }
+
+    let q = escape(&url_encode(table.q));
+    let sort = if table.by_name { "nombre" } else { "id" };
+    // Enlaces normales que htmx intercepta; sin JavaScript navegan a la página completa.
+    let nav = |page: i64, label: &str, enabled: bool| {
+        if !enabled {
+            return format!(r#"<button disabled>{label}</button>"#);
+        }
+        let href = format!("/admin/mensajes?page={page}&amp;q={q}&amp;sort={sort}");
+        format!(
+            r##"<a class="page-link" href="{href}" hx-get="{href}" hx-target="#mensajes-panel" hx-swap="outerHTML">{label}</a>"##
+        )
+    };
+    let prev = nav(table.page - 1, "Anterior", table.page > 1);
+    let next = nav(table.page + 1, "Siguiente", table.page < table.pages);
+    // La cabecera alterna entre el orden por nombre y el de siempre.
+    let (toggle, arrow) = if table.by_name { ("id", " ▲") } else { ("nombre", "") };
+    let toggle = format!("/admin/mensajes?page=1&amp;q={q}&amp;sort={toggle}");
+    let (page, pages) = (table.page, table.pages);
+
+    format!(
+        r##"<div id="mensajes-panel">
+    <table class="admin-table">
+        <thead>
+            <tr>
+                <th><a href="{toggle}" hx-get="{toggle}" hx-target="#mensajes-panel" hx-swap="outerHTML">Nombre{arrow}</a></th>
+                <th>Mensaje</th>
+                <th style="text-align: center;">Acciones</th>
+            </tr>
+        </thead>
+        <tbody>{rows}
+        </tbody>
+    </table>
+    <div class="pagination">
+        {prev}
+        <span>Página {page} de {pages}</span>
+        {next}
+    </div>
+</div>
+"##
+    )
+}
+
+pub struct AuditRow {
+    pub created_at: LocalTime,
+    pub method: String,
+    pub uri: String,
+    pub ip: Option<String>,
+    pub status: i16,
+}
+
+pub struct AuditTable<'a> {
+    pub rows: &'a [AuditRow],
+    pub page: i64,
+    pub pages: i64,
+    /// Filtros activos, que se conservan al cambiar de página.
+    pub method: &'a str,
+    pub status: &'a str,
+    pub q: &'a str,
+    /// `?tz=` de la petición; vacío en UTC.
+    pub tz: &'a str,
+}
+
+const AUDIT_METHODS: [&str; 4] = ["POST", "PUT", "PATCH", "DELETE"];
+const AUDIT_STATUSES: [&str; 4] = ["2xx", "3xx", "4xx", "5xx"];
+
+/// Auditoría de administración como fragmento (`#audit-panel`), con el
+/// formulario de filtros dentro para que htmx lo sustituya todo junto.
+pub fn audit_table(table: &AuditTable) -> String {
+    let mut rows = String::new();
+    for a in table.rows {
+        let fecha = a.created_at.format("%d/%m/%Y %H:%M:%S");
+        let (method, uri, status) = (escape(&a.method), escape(&a.uri), a.status);
+        let ip = escape(a.ip.as_deref().unwrap_or("-"));
+        rows.push_str(&format!(
+            r#"
+            <tr>
+                <td>{fecha}</td>
+                <td>{method}</td>
+                <td class="msg-cell">{uri}</td>
+                <td>{ip}</td>
+                <td>{status}</td>
+            </tr>"#
+        ));
+    }
+    if table.rows.is_empty() {
+        rows.push_str(r#"<tr><td colspan="5">No hay acciones registradas</td></tr>"#);
+    }
+
+    let options = |values: &[&str], selected: &str| {
+        let mut out = String::from(r#"<option value="">Todos</option>"#);
+        for v in values {
+            let attr = if *v == selected { " selected" } else { "" };
+            out.push_str(&format!(r#"<option value="{v}"{attr}>{v}</option>"#));
+        }
+        out
+    };
+    let methods = options(&AUDIT_METHODS, table.method);
+    let statuses = options(&AUDIT_STATUSES, table.status);
+    let q_value = escape(table.q);
+    let tz_value = escape(table.tz);
+
+    let filters = format!(
+        "method={}&amp;status={}&amp;q={}&amp;tz={}",
+        escape(&url_encode(table.method)),
+        escape(&url_encode(table.status)),
+        escape(&url_encode(table.q)),
+        escape(&url_encode(table.tz)),
+    );
+    let nav = |page: i64, label: &str, enabled: bool| {
+        if !enabled {
+            return format!(r#"<button disabled>{label}</button>"#);
+        }
+        let href = format!("/admin/audit?page={page}&amp;{filters}");
+        format!(
+            r##"<a class="page-link" href="{href}" hx-get="{href}" hx-target="#audit-panel" hx-swap="outerHTML">{label}</a>"##
+        )
+    };
+    let prev = nav(table.page - 1, "Anterior", table.page > 1);
+    let next = nav(table.page + 1, "Siguiente", table.page < table.pages);
+    let (page, pages) = (table.page, table.pages);
+
+    format!(
+        r##"<div id="audit-panel">
+    <form class="search-box" action="/admin/audit" hx-get="/admin/audit" hx-target="#audit-panel" hx-swap="outerHTML">
+        <select name="method">{methods}</select>
+        <select name="status">{statuses}</select>
+        <input type="search" name="q" value="{q_value}" placeholder="Ruta contiene...">
+        <input type="hidden" name="tz" value="{tz_value}">
+        <button type="submit" class="btn-secondary">Filtrar</button>
+    </form>
+    <table class="admin-table">
+        <thead>
+            <tr>
+                <th>Fecha</th>
+                <th>Método</th>
+                <th>Ruta</th>
+                <th>IP</th>
+                <th>Estado</th>
+            </tr>
+        </thead>
+        <tbody>{rows}
+        </tbody>
+    </table>
+    <div class="pagination">
+        {prev}
+        <span>Página {page} de {pages}</span>
+        {next}
+    </div>
+</div>
+"##
+    )
+}
+
+/* ---------- CORREOS ---------- */
+
+/// Cuerpo HTML del correo con el enlace de verificación.
+pub fn verification_email(nombre: &str, link: &str) -> String {
+    link_email(nombre, "Para verificar el email de tu mensaje pulsa el botón:", "Verificar email", link)
+}
+
+pub fn confirmation_email(nombre: &str, link: &str) -> String {
+    link_email(nombre, "Tu mensaje se publicará cuando lo confirmes con el botón:", "Confirmar mensaje", link)
+}
+
+/// Correo con un solo botón que lleva a `link`.
+fn link_email(nombre: &str, intro: &str, button: &str, link: &str) -> String {
+    let (nombre, link) = (escape(nombre), escape(link));
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="es">
+<body style="font-family: sans-serif; color: #222;">
+    <h2>Axum Motors</h2>
+    <p>Hola {nombre}:</p>
+    <p>{intro}</p>
+    <p><a href="{link}" style="display: inline-block; padding: 10px 18px; background: #1f2937; color: #fff; text-decoration: none; border-radius: 6px;">{button}</a></p>
+    <p style="font-size: 12px; color: #666;">Si el botón no funciona copia este enlace: {link}<br>
+    Si no has escrito en Axum Motors, ignora este correo.</p>
+</body>
+</html>
+"#
+    )
+}
+
+/// Página mínima alrededor de un fragmento, para cuando se pide sin htmx.
+pub fn admin_page(site: &Site, title: &str, fragment: &str) -> String {
+    let (title, site, lang) = (escape(title), escape(&site.title), escape(&site.language));
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="{lang}">
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>{title} | {site}</title>
+    <link rel="stylesheet" href="/css/styles.css">
+</head>
+<body>
+<div class="main-content">
+    <div class="page-title">{title}</div>
+    <div class="table-container">
+{fragment}
+    </div>
+</div>
+<script src="/js/flash.js"></script>
+</body>
+</html>
+"#
+    )
+}
+
+/// Formulario de `GET /admin/login`; el error llega como flash.
+/// Con proveedores OAuth configurados, un botón por cada uno bajo el formulario.
+pub fn login_page(site: &Site, providers: &[Provider]) -> String {
+    let buttons: String = providers
+        .iter()
+        .map(|p| format!(r#"        <a class="btn-secondary" href="/auth/{}">Entrar con {}</a>
+"#, p.slug(), p.label()))
+        .collect();
+    let oauth = if buttons.is_empty() {
+        String::new()
+    } else {
+        format!("    <div class=\"oauth-login\">\n{buttons}    </div>\n")
+    };
+
+    r#"<!DOCTYPE html>
+<html lang="{lang}">
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>Acceso | {site}</title>
+    <link rel="stylesheet" href="/css/styles.css">
+</head>
+<body>
+<div class="main-content">
+    <div class="page-title">Acceso de administración</div>
+    <form class="contact-form" method="post" action="/admin/login">
+        <div class="form-group">
+            <label for="username">Usuario:</label>
+            <input type="text" id="username" name="username" required autofocus autocomplete="username">
+        </div>
+        <div class="form-group">
+            <label for="password">Contraseña:</label>
+            <input type="password" id="password" name="password" required autocomplete="current-password">
+        </div>
+        <button type="submit" class="btn-primary">Entrar</button>
+    </form>
+    <form class="contact-form" method="post" action="/admin/login">
+        <div class="form-group">
+            <label for="token">Token de administrador:</label>
+            <input type="password" id="token" name="token" required>
+        </div>
+        <button type="submit" class="btn-secondary">Entrar con token</button>
+    </form>
+{oauth}</div>
+<script src="/js/flash.js"></script>
+</body>
+</html>
+"#
+    .replace("{oauth}", &oauth)
+    .replace("{site}", &escape(&site.title))
+    .replace("{lang}", &escape(&site.language))
+}
+
+/// Formulario de `GET /setup`; el error llega como flash.
+pub fn setup_page(site: &Site, languages: &[(&str, &str)]) -> String {
+    let options: String = languages
+        .iter()
+        .map(|(code, name)| {
+            let selected = if *code == site.language { " selected" } else { "" };
+            format!("                <option value=\"{code}\"{selected}>{name}</option>\n")
+        })
+        .collect();
+    let title = escape(&site.title);
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="es">
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>Configuración inicial</title>
+    <link rel="stylesheet" href="/css/styles.css">
+</head>
+<body>
+<div class="main-content">
+    <div class="page-title">Configuración inicial</div>
+    <p class="subtitle">El código de configuración aparece en el log del servidor al arrancar.</p>
+    <form class="contact-form" method="post" action="/setup">
+        <div class="form-group">
+            <label for="code">Código de configuración:</label>
+            <input type="text" id="code" name="code" required autofocus autocomplete="off">
+        </div>
+        <div class="form-group">
+            <label for="username">Usuario de administración:</label>
+            <input type="text" id="username" name="username" required autocomplete="username">
+        </div>
+        <div class="form-group">
+            <label for="password">Contraseña (mínimo 10 caracteres):</label>
+            <input type="password" id="password" name="password" required minlength="10" autocomplete="new-password">
+        </div>
+        <div class="form-group">
+            <label for="title">Título del sitio:</label>
+            <input type="text" id="title" name="title" value="{title}" required maxlength="80">
+        </div>
+        <div class="form-group">
+            <label for="language">Idioma:</label>
+            <select id="language" name="language">
+{options}            </select>
+        </div>
+        <button type="submit" class="btn-primary">Guardar y entrar</button>
+    </form>
+</div>
+<script src="/js/flash.js"></script>
+</body>
+</html>
+"#
+    )
+}
+
+/// `GET /confirmar/:token`: el mensaje pendiente y el botón que lo publica.
+/// Publicar en el GET dejaría que lo hiciera cualquier escáner de enlaces.
+pub fn confirm_page(site: &Site, token: &str, nombre: &str, mensaje: &str) -> String {
+    let (site, lang) = (escape(&site.title), escape(&site.language));
+    let (token, nombre, body) = (escape(token), escape(nombre), mensaje_body(mensaje));
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="{lang}">
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>Confirmar mensaje | {site}</title>
+    <link rel="stylesheet" href="/css/styles.css">
+</head>
+<body>
+<div class="main-content">
+    <div class="page-title">Confirmar mensaje</div>
+    <p class="subtitle">Mensaje de {nombre}: se publicará cuando pulses el botón.</p>
+    <div class="form-container">
+        {body}
+    </div>
+    <form class="contact-form" method="post" action="/confirmar/{token}">
+        <button type="submit" class="btn-primary">Confirmar y publicar</button>
+    </form>
+</div>
+<script src="/js/flash.js"></script>
+</body>
+</html>
+"#
+    )
+}
+
+/// Codificación de porcentaje para valores de query y cookies.
+pub fn url_encode(text: &str) -> String {
+    let mut out = String::new();
+    for b in text.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => out.push(b as char),
+            b => out.push_str(&format!("%{b:02X}")),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_markup_and_quotes() {
+        assert_eq!(
+            escape(r#"<a href="x">'&'</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;&#39;&amp;&#39;&lt;/a&gt;"
+        );
+    }
+
+    #[test]
+    fn excerpt_flattens_and_cuts_on_chars() {
+        assert_eq!(excerpt("hola\n\n  mundo"), "hola mundo");
+
+        let long = "ñ".repeat(200);
+        let cut = excerpt(&long);
+        assert_eq!(cut.chars().count(), EXCERPT_CHARS);
+        assert!(cut.ends_with('…'));
+    }
+
+    #[test]
+    fn excerpt_does_not_split_words() {
+        assert_eq!(excerpt_chars("uno dos tres", 9), "uno dos…");
+        assert_eq!(excerpt_chars("uno dos tres", 8), "uno dos…");
+        assert_eq!(excerpt_chars("supercalifragilístico", 6), "super…");
+        assert_eq!(excerpt_chars("año 🏍🏍 camión", 7), "año 🏍🏍…");
+    }
+}
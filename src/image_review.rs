@@ -0,0 +1,126 @@
+//! Revisión de imágenes antes de publicarlas. Lo que sube un visitante queda
+//! pendiente (`approved_at` vacío) en `.pending/`, fuera de `/uploads`, de la
+//! galería y de las miniaturas, hasta que administración lo aprueba; si lo
+//! rechaza, se borran el registro y el fichero. Lo que sube administración se
+//! publica directamente.
+
+use axum::{
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::{Html, IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::io;
+
+use crate::audit_log::{self, Change};
+use crate::db::DbError;
+use crate::events::{self, Event};
+use crate::outbox;
+use crate::pagination::{PageQuery, Paginated};
+use crate::policy::Principal;
+use crate::queries;
+use crate::state::SharedState;
+use crate::thumbs;
+use crate::unit_of_work::UnitOfWork;
+use crate::uploads::UploadsRoot;
+
+#[derive(Serialize)]
+pub struct PendingImage {
+    pub id: i32,
+    pub filename: String,
+    pub original_name: Option<String>,
+    pub size_bytes: Option<i64>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Publica el fichero: de `.pending/` a la raíz de las subidas.
+async fn publish(root: &UploadsRoot, filename: &str) -> io::Result<()> {
+    let (Ok(from), Ok(to)) = (root.pending(filename), root.file(filename)) else {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "ruta fuera del directorio de subidas"));
+    };
+    tokio::fs::rename(from, to).await
+}
+
+fn not_found() -> Response {
+    (StatusCode::NOT_FOUND, Html("❌ Imagen pendiente no encontrada")).into_response()
+}
+
+/* ---------- HANDLERS ---------- */
+
+/// `GET /api/admin/images/pending`: cola de revisión, las más antiguas primero.
+pub async fn pending_images(
+    State(app): State<SharedState>,
+    page: PageQuery,
+) -> Result<Json<Paginated<PendingImage>>, DbError> {
+    let total = queries::image_review::count_pending(&app.db).await?;
+    let images = queries::image_review::pending(&app.db, page.per_page(), page.offset()).await?;
+    Ok(Json(Paginated::new(images, total, &page)))
+}
+
+/// `GET /api/admin/images/:id/file`: la imagen pendiente, para revisarla.
+pub async fn pending_file(State(app): State<SharedState>, Path(id): Path<i32>) -> Response {
+    let filename = match queries::image_review::pending_filename(&app.db, id).await {
+        Ok(Some(filename)) => filename,
+        Ok(None) => return not_found(),
+        Err(e) => return e.into_response(),
+    };
+
+    let Ok(path) = app.uploads.pending(&filename) else { return not_found() };
+    match tokio::fs::read(&path).await {
+        Ok(bytes) => (
+            [(header::CONTENT_TYPE, thumbs::content_type(&filename)), (header::CACHE_CONTROL, "no-store")],
+            bytes,
+        )
+            .into_response(),
+        Err(_) => not_found(),
+    }
+}
+
+/// `POST /api/admin/images/:id/approve`
+pub async fn approve_image(State(app): State<SharedState>, principal: Principal, Path(id): Path<i32>) -> Response {
+    let mut uow = match UnitOfWork::begin(&app.db).await {
+        Ok(uow) => uow,
+        Err(e) => return e.into_response(),
+    };
+    let filename = match queries::image_review::approve(uow.conn(), id).await {
+        Ok(Some(filename)) => filename,
+        Ok(None) => return not_found(),
+        Err(e) => return e.into_response(),
+    };
+    let event = Event::ImageUploaded { id };
+    if let Err(e) = outbox::enqueue(uow.conn(), &event).await {
+        return e.into_response();
+    }
+    if let Err(e) = uow.commit().await {
+        return e.into_response();
+    }
+
+    if let Err(err) = publish(&app.uploads, &filename).await {
+        tracing::warn!(error = %err, filename, "no se pudo publicar la imagen aprobada");
+    }
+    let change = Change::new("image.approve", format!("image:{id}")).new_value(serde_json::json!({ "filename": filename }));
+    audit_log::record_change(&app.db, &principal, change).await;
+    events::publish(&app, event);
+    Html("✅ Imagen aprobada").into_response()
+}
+
+/// `POST /api/admin/images/:id/reject`: sin papelera, se borra todo.
+pub async fn reject_image(State(app): State<SharedState>, principal: Principal, Path(id): Path<i32>) -> Response {
+    let filename = match queries::image_review::reject(&app.db, id).await {
+        Ok(Some(filename)) => filename,
+        Ok(None) => return not_found(),
+        Err(e) => return e.into_response(),
+    };
+
+    if let Ok(path) = app.uploads.pending(&filename)
+        && let Err(err) = tokio::fs::remove_file(&path).await
+        && err.kind() != io::ErrorKind::NotFound
+    {
+        tracing::warn!(error = %err, filename, "no se pudo borrar la imagen rechazada");
+    }
+    let change = Change::new("image.reject", format!("image:{id}")).old(serde_json::json!({ "filename": filename }));
+    audit_log::record_change(&app.db, &principal, change).await;
+    Html("✅ Imagen rechazada y borrada").into_response()
+}
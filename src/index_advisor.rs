@@ -0,0 +1,118 @@
+//! Índices recomendados: al arrancar (o con `hola_axum check`) se comprueba que
+//! existen y, si falta alguno, se avisa con el `CREATE INDEX` listo para copiar.
+//! Solo se revisan columnas que existen en la base de datos.
+
+use sqlx::PgPool;
+
+use crate::db;
+use crate::queries;
+
+pub struct Recommended {
+    pub table: &'static str,
+    /// Primera columna del índice.
+    pub column: &'static str,
+    pub create: &'static str,
+    /// Para qué consultas hace falta.
+    pub reason: &'static str,
+}
+
+pub const RECOMMENDED: [Recommended; 4] = [
+    Recommended {
+        table: "mensajes",
+        column: "created_at",
+        create: "CREATE INDEX CONCURRENTLY IF NOT EXISTS mensajes_created_at_idx ON mensajes (created_at);",
+        reason: "listados por fecha",
+    },
+    Recommended {
+        table: "images",
+        column: "created_at",
+        create: "CREATE INDEX CONCURRENTLY IF NOT EXISTS images_created_at_idx ON images (created_at);",
+        reason: "galería ordenada y filtrada por fecha",
+    },
+    Recommended {
+        table: "mensajes",
+        column: "search",
+        create: "CREATE INDEX CONCURRENTLY IF NOT EXISTS mensajes_search_idx ON mensajes USING gin (search);",
+        reason: "búsqueda de texto completo",
+    },
+    Recommended {
+        table: "images",
+        column: "hash",
+        create: "CREATE INDEX CONCURRENTLY IF NOT EXISTS images_hash_idx ON images (hash);",
+        reason: "detección de imágenes duplicadas",
+    },
+];
+
+/// Recomendaciones aplicables (la columna existe) sin ningún índice que empiece por esa columna.
+pub async fn missing(pool: &PgPool) -> Result<Vec<&'static Recommended>, db::DbError> {
+    let mut missing = Vec::new();
+    for rec in &RECOMMENDED {
+        if !queries::index_advisor::column_exists(pool, rec.table, rec.column).await? {
+            continue;
+        }
+        let defs = queries::index_advisor::index_defs(pool, rec.table).await?;
+        if !defs.iter().any(|def| leads_with(def, rec.column)) {
+            missing.push(rec);
+        }
+    }
+    Ok(missing)
+}
+
+/// Registra un aviso por cada índice que falta. Nunca aborta el arranque.
+pub async fn advise(pool: &PgPool) {
+    match missing(pool).await {
+        Ok(missing) => {
+            for rec in missing {
+                tracing::warn!(
+                    table = rec.table,
+                    column = rec.column,
+                    reason = rec.reason,
+                    "falta un índice recomendado; créalo con: {}",
+                    rec.create
+                );
+            }
+        }
+        Err(err) => tracing::warn!(error = ?err, "no se pudieron revisar los índices"),
+    }
+}
+
+/// `true` si la primera columna de la definición (`... USING btree (col, ...)`) es `column`.
+fn leads_with(indexdef: &str, column: &str) -> bool {
+    indexdef
+        .split_once(" USING ")
+        .and_then(|(_, rest)| rest.split_once('('))
+        .and_then(|(_, cols)| cols.strip_prefix(column))
+        .is_some_and(|rest| rest.starts_with([',', ')', ' ']))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestDb;
+
+    #[test]
+    fn matches_only_the_leading_column() {
+        let def = "CREATE INDEX a ON public.mensajes USING btree (created_at)";
+        assert!(leads_with(def, "created_at"));
+
+        let def = "CREATE INDEX b ON public.mensajes USING btree (author_ip, created_at)";
+        assert!(!leads_with(def, "created_at"));
+
+        let def = "CREATE INDEX c ON public.images USING btree (created_at_local)";
+        assert!(!leads_with(def, "created_at"));
+    }
+
+    #[tokio::test]
+    async fn reports_dropped_index() {
+        let Some(db) = TestDb::new().await else { return };
+
+        assert!(missing(&db.pool).await.unwrap().is_empty());
+
+        sqlx::query("DROP INDEX mensajes_created_at_idx").execute(&db.pool).await.unwrap();
+        let missing = missing(&db.pool).await.unwrap();
+        assert_eq!(missing.len(), 1);
+        assert_eq!((missing[0].table, missing[0].column), ("mensajes", "created_at"));
+
+        db.finish().await;
+    }
+}
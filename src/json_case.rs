@@ -0,0 +1,113 @@
+//! Nombres de las claves del JSON de la API. Los structs siguen en snake_case
+//! y las respuestas JSON se reescriben a camelCase si lo pide la configuración
+//! (`JSON_CASE`) o el cliente con la cabecera `X-Json-Case: camel|snake`.
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Request, State},
+    http::{header, HeaderName, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde_json::{Map, Value};
+
+pub const HEADER: HeaderName = HeaderName::from_static("x-json-case");
+
+/// Respuestas JSON mayores no se reescriben.
+const MAX_BODY: usize = 8 * 1024 * 1024;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Case {
+    Snake,
+    Camel,
+}
+
+impl std::str::FromStr for Case {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "snake" | "snake_case" => Ok(Case::Snake),
+            "camel" | "camelCase" => Ok(Case::Camel),
+            _ => Err(()),
+        }
+    }
+}
+
+pub async fn rewrite(State(default): State<Case>, req: Request, next: Next) -> Response {
+    let case = req
+        .headers()
+        .get(HEADER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(default);
+    let mut res = next.run(req).await;
+    res.headers_mut().append(header::VARY, HeaderValue::from_static("x-json-case"));
+
+    let is_json = res
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/json"));
+    if case == Case::Snake || !is_json {
+        return res;
+    }
+
+    let (mut parts, body) = res.into_parts();
+    let Ok(bytes) = to_bytes(body, MAX_BODY).await else {
+        tracing::error!("respuesta JSON demasiado grande para pasarla a camelCase");
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+    let Ok(value) = serde_json::from_slice::<Value>(&bytes) else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+    // El `ETag` era del cuerpo en snake_case.
+    parts.headers.remove(header::CONTENT_LENGTH);
+    parts.headers.remove(header::ETAG);
+    Response::from_parts(parts, Body::from(camel_keys(value).to_string()))
+}
+
+/// Pasa a camelCase las claves de todos los objetos, a cualquier profundidad.
+fn camel_keys(value: Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(k, v)| (to_camel(&k), camel_keys(v)))
+                .collect::<Map<_, _>>(),
+        ),
+        Value::Array(items) => Value::Array(items.into_iter().map(camel_keys).collect()),
+        other => other,
+    }
+}
+
+fn to_camel(key: &str) -> String {
+    let mut out = String::with_capacity(key.len());
+    let mut upper = false;
+    for c in key.chars() {
+        if c == '_' && !out.is_empty() {
+            upper = true;
+        } else if upper {
+            out.extend(c.to_uppercase());
+            upper = false;
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renames_nested_keys() {
+        assert_eq!(to_camel("next_cursor"), "nextCursor");
+        assert_eq!(to_camel("_private"), "_private");
+        let value = serde_json::json!({"total_pages": 1, "data": [{"size_bytes": 2, "id": 3}]});
+        assert_eq!(
+            camel_keys(value),
+            serde_json::json!({"totalPages": 1, "data": [{"sizeBytes": 2, "id": 3}]})
+        );
+    }
+}
@@ -0,0 +1,169 @@
+//! JWT para la API: `POST /api/login` cambia el token de administración por un
+//! JWT HS256 firmado con `JWT_SECRET` que caduca a los `JWT_TTL_MINUTES`, y a
+//! partir de ahí `Authorization: Bearer <jwt>` vale donde vale el token. No se
+//! guarda nada en el servidor: basta con comprobar firma y caducidad.
+//! Sin `JWT_SECRET` no hay login ni se acepta ningún JWT.
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+    Json,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::time::Duration;
+
+use crate::admin;
+use crate::client_ip::ClientIp;
+use crate::login_lockout;
+use crate::state::SharedState;
+
+/// Cabecera fija: solo se emite y se acepta HS256.
+const HEADER: &str = r#"{"alg":"HS256","typ":"JWT"}"#;
+
+/// Único sujeto por ahora: no hay más cuentas que la de administración.
+const SUBJECT: &str = "admin";
+
+#[derive(Serialize, Deserialize)]
+struct Claims {
+    sub: String,
+    iat: i64,
+    exp: i64,
+}
+
+fn mac(secret: &str) -> Hmac<Sha256> {
+    Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC admite claves de cualquier longitud")
+}
+
+pub fn issue(secret: &str, ttl: Duration, now: i64) -> String {
+    let claims = Claims { sub: SUBJECT.to_string(), iat: now, exp: now + ttl.as_secs() as i64 };
+    let signing_input = format!(
+        "{}.{}",
+        URL_SAFE_NO_PAD.encode(HEADER),
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(&claims).unwrap())
+    );
+
+    let mut mac = mac(secret);
+    mac.update(signing_input.as_bytes());
+    let signature = URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes());
+    format!("{signing_input}.{signature}")
+}
+
+/// Firma correcta (en tiempo constante), cabecera HS256 y sin caducar.
+pub fn verify(secret: &str, token: &str, now: i64) -> bool {
+    let mut parts = token.split('.');
+    let (Some(header), Some(claims), Some(signature), None) = (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return false;
+    };
+
+    let Ok(signature) = URL_SAFE_NO_PAD.decode(signature) else { return false };
+    let mut mac = mac(secret);
+    mac.update(header.as_bytes());
+    mac.update(b".");
+    mac.update(claims.as_bytes());
+    if mac.verify_slice(&signature).is_err() {
+        return false;
+    }
+
+    // Firmada por nosotros, pero se comprueba igual: nada de `alg: none` ni otros algoritmos.
+    let header_ok = URL_SAFE_NO_PAD
+        .decode(header)
+        .ok()
+        .and_then(|h| serde_json::from_slice::<serde_json::Value>(&h).ok())
+        .is_some_and(|h| h["alg"] == "HS256");
+
+    let claims = URL_SAFE_NO_PAD
+        .decode(claims)
+        .ok()
+        .and_then(|c| serde_json::from_slice::<Claims>(&c).ok());
+
+    header_ok && claims.is_some_and(|c| c.sub == SUBJECT && c.exp > now)
+}
+
+/* ---------- POST /api/login ---------- */
+
+#[derive(Deserialize)]
+pub struct LoginRequest {
+    token: String,
+}
+
+#[derive(Serialize)]
+struct LoginResponse {
+    token: String,
+    token_type: &'static str,
+    /// Segundos de validez.
+    expires_in: u64,
+}
+
+pub async fn login(
+    State(app): State<SharedState>,
+    ClientIp(ip): ClientIp,
+    Json(req): Json<LoginRequest>,
+) -> Response {
+    let config = &app.config.admin;
+    let Some(secret) = &config.jwt_secret else {
+        return (StatusCode::NOT_FOUND, Html("❌ JWT no configurado")).into_response();
+    };
+
+    // Mismo bloqueo que `/admin/login` con token: por IP y común al token (ver `login_lockout`).
+    let keys = login_lockout::keys(ip, None);
+    match login_lockout::locked(&app.db, &keys).await {
+        Ok(Some(locked)) => return locked.into_response(),
+        Ok(None) => {}
+        Err(e) => return e.into_response(),
+    }
+
+    if !admin::is_admin_token(config, &req.token) {
+        tracing::warn!(target: "audit", "login de API fallido");
+        return match login_lockout::record_failure(&app.db, config, &keys).await {
+            Ok(Some(locked)) => locked.into_response(),
+            Ok(None) => (StatusCode::UNAUTHORIZED, Html("❌ Token incorrecto")).into_response(),
+            Err(e) => e.into_response(),
+        };
+    }
+
+    tracing::info!(target: "audit", "JWT emitido");
+    if let Err(e) = login_lockout::clear(&app.db, &keys).await {
+        tracing::warn!(error = ?e, "no se pudieron borrar los fallos de acceso");
+    }
+    Json(LoginResponse {
+        token: issue(secret, config.jwt_ttl, chrono::Utc::now().timestamp()),
+        token_type: "Bearer",
+        expires_in: config.jwt_ttl.as_secs(),
+    })
+    .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "secreto-de-test-suficientemente-largo";
+
+    #[test]
+    fn accepts_own_tokens_until_they_expire() {
+        let token = issue(SECRET, Duration::from_secs(60), 1_000);
+        assert!(verify(SECRET, &token, 1_059));
+        assert!(!verify(SECRET, &token, 1_060));
+        assert!(!verify("otro-secreto", &token, 1_000));
+    }
+
+    #[test]
+    fn rejects_tampered_and_unsigned_tokens() {
+        let token = issue(SECRET, Duration::from_secs(60), 1_000);
+        let (signing_input, _) = token.rsplit_once('.').unwrap();
+
+        let forged_claims = URL_SAFE_NO_PAD.encode(r#"{"sub":"admin","iat":0,"exp":99999999999}"#);
+        let header = signing_input.split('.').next().unwrap();
+        assert!(!verify(SECRET, &format!("{header}.{forged_claims}.{}", token.rsplit('.').next().unwrap()), 1_000));
+
+        let none = URL_SAFE_NO_PAD.encode(r#"{"alg":"none","typ":"JWT"}"#);
+        let claims = signing_input.split('.').nth(1).unwrap();
+        assert!(!verify(SECRET, &format!("{none}.{claims}."), 1_000));
+        assert!(!verify(SECRET, "basura", 1_000));
+    }
+}
@@ -0,0 +1,80 @@
+use tracing_appender::{
+    non_blocking::WorkerGuard,
+    rolling::{RollingFileAppender, Rotation},
+};
+use tracing_subscriber::{fmt, prelude::*, EnvFilter};
+
+use crate::config::LogConfig;
+use crate::trace;
+
+#[derive(Clone, Copy)]
+pub enum LogFormat {
+    /// Líneas JSON, una por evento, para ingesta en producción.
+    Json,
+    /// Formato legible para desarrollo local.
+    Pretty,
+}
+
+impl std::str::FromStr for LogFormat {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(LogFormat::Json),
+            "pretty" => Ok(LogFormat::Pretty),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Inicializa `tracing`. Con `LOG_DIR` escribe en ficheros rotados; si no, en stdout.
+/// El filtro sale de `RUST_LOG` (p. ej. `info,sqlx=warn`) y por defecto es `info`;
+/// dentro de una traza forzada (`X-Debug-Trace`) se ve también DEBUG.
+/// El `WorkerGuard` devuelto debe vivir hasta el final de `main` para no perder líneas.
+pub fn init(config: &LogConfig) -> WorkerGuard {
+    let filter = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::new("info"))
+        .add_directive(trace::FORCED_DIRECTIVE.parse().unwrap());
+
+    let (writer, guard) = match &config.dir {
+        Some(dir) => {
+            std::fs::create_dir_all(dir).expect("no se pudo crear LOG_DIR");
+            let appender = RollingFileAppender::builder()
+                .rotation(parse_rotation(&config.rotation))
+                .filename_prefix("hola_axum")
+                .filename_suffix("log")
+                .max_log_files(config.max_files)
+                .build(dir)
+                .expect("no se pudo crear LOG_DIR");
+            tracing_appender::non_blocking(appender)
+        }
+        None => tracing_appender::non_blocking(std::io::stdout()),
+    };
+
+    let (json, pretty) = match config.format {
+        LogFormat::Json => (Some(fmt::layer().json().with_writer(writer)), None),
+        LogFormat::Pretty => (
+            None,
+            Some(fmt::layer().with_writer(writer).with_ansi(config.dir.is_none())),
+        ),
+    };
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(json)
+        .with(pretty)
+        .init();
+
+    guard
+}
+
+fn parse_rotation(rotation: &str) -> Rotation {
+    match rotation {
+        "minutely" => Rotation::MINUTELY,
+        "hourly" => Rotation::HOURLY,
+        "daily" => Rotation::DAILY,
+        "weekly" => Rotation::WEEKLY,
+        "never" => Rotation::NEVER,
+        other => panic!("LOG_ROTATION inválido: {other}"),
+    }
+}
@@ -0,0 +1,122 @@
+//! Bloqueo de los accesos de administración (`/admin/login`, `/api/login`)
+//! tras varios fallos seguidos. Se cuentan por IP y por usuario (o por el
+//! token, en los accesos sin usuario) en `login_failures`, así que sobrevive a
+//! reinicios y vale entre réplicas.
+//! Pasado `LOGIN_LOCKOUT_THRESHOLD`, cada fallo duplica el bloqueo (desde
+//! `LOGIN_LOCKOUT_BASE_SECS` hasta `LOGIN_LOCKOUT_MAX_SECS`); un acceso
+//! correcto, o un rato largo sin fallos, pone la cuenta a cero.
+
+use std::{net::IpAddr, time::Duration};
+
+use axum::{
+    http::{header, StatusCode},
+    response::{Html, IntoResponse, Response},
+};
+use sqlx::PgPool;
+
+use crate::config::AdminConfig;
+use crate::db::DbError;
+use crate::queries;
+
+const TOKEN_KEY: &str = "token:admin";
+
+/// Claves de un intento: la IP y el usuario o, sin usuario, una común para
+/// el token de administración, para que repartir los intentos entre muchas IP
+/// no sirva para adivinarlo.
+pub fn keys(ip: Option<IpAddr>, username: Option<&str>) -> Vec<String> {
+    let ip = ip.map(|ip| format!("ip:{ip}"));
+    let target = match username {
+        Some(u) => format!("user:{}", u.trim().to_lowercase()),
+        None => TOKEN_KEY.to_string(),
+    };
+    ip.into_iter().chain([target]).collect()
+}
+
+/// Tiempo de bloqueo tras `failures` fallos seguidos; cero antes del umbral.
+fn lock_for(config: &AdminConfig, failures: i32) -> Duration {
+    let Some(over) = failures.checked_sub(config.lockout_threshold).filter(|o| *o >= 0) else {
+        return Duration::ZERO;
+    };
+    let factor = 2u32.saturating_pow(over.min(31) as u32);
+    config.lockout_base.saturating_mul(factor).min(config.lockout_max)
+}
+
+/// Bloqueo pendiente más largo entre `keys`, si lo hay.
+pub async fn locked(pool: &PgPool, keys: &[String]) -> Result<Option<Locked>, DbError> {
+    Ok(queries::login_lockout::remaining(pool, keys).await?.map(Locked))
+}
+
+/// Apunta el fallo en cada clave; `Some` si con él queda bloqueada.
+pub async fn record_failure(pool: &PgPool, config: &AdminConfig, keys: &[String]) -> Result<Option<Locked>, DbError> {
+    let mut longest = Duration::ZERO;
+    for key in keys {
+        let failures = queries::login_lockout::record_failure(pool, key, config.lockout_max).await?;
+        let lock = lock_for(config, failures);
+        if lock.is_zero() {
+            continue;
+        }
+        queries::login_lockout::lock(pool, key, lock).await?;
+        tracing::warn!(target: "audit", key, failures, secs = lock.as_secs(), "acceso de administración bloqueado");
+        longest = longest.max(lock);
+    }
+    Ok((!longest.is_zero()).then_some(Locked(longest)))
+}
+
+/// Tras un acceso correcto.
+pub async fn clear(pool: &PgPool, keys: &[String]) -> Result<(), DbError> {
+    queries::login_lockout::clear(pool, keys).await
+}
+
+/// 429 con el tiempo que falta en `Retry-After` y en el texto.
+pub struct Locked(pub Duration);
+
+impl Locked {
+    pub fn secs(&self) -> u64 {
+        self.0.as_secs_f64().ceil().max(1.0) as u64
+    }
+
+    pub fn message(&self) -> String {
+        format!("❌ Demasiados intentos fallidos. Inténtalo de nuevo en {} s", self.secs())
+    }
+}
+
+impl IntoResponse for Locked {
+    fn into_response(self) -> Response {
+        (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(header::RETRY_AFTER, self.secs().to_string())],
+            Html(self.message()),
+        )
+            .into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        let config = AdminConfig {
+            token: None,
+            rate_burst: 1,
+            rate_per_minute: 1,
+            session_ttl: Duration::ZERO,
+            jwt_secret: None,
+            jwt_ttl: Duration::ZERO,
+            lockout_threshold: 3,
+            lockout_base: Duration::from_secs(30),
+            lockout_max: Duration::from_secs(100),
+        };
+        let secs: Vec<u64> = (1..=6).map(|f| lock_for(&config, f).as_secs()).collect();
+        assert_eq!(secs, [0, 0, 30, 60, 100, 100]);
+    }
+
+    #[test]
+    fn token_logins_share_a_key() {
+        let ip = Some("10.0.0.1".parse().unwrap());
+        assert_eq!(keys(ip, Some(" Marta ")), ["ip:10.0.0.1", "user:marta"]);
+        assert_eq!(keys(ip, None), ["ip:10.0.0.1", TOKEN_KEY]);
+        assert_eq!(keys(None, None), [TOKEN_KEY]);
+    }
+}
@@ -0,0 +1,57 @@
+//! Envío de correos por una API HTTP: `POST MAIL_API_URL` con el correo en JSON
+//! (`from`, `to`, `subject`, `text`, `html`) y, si hay `MAIL_API_TOKEN`, como
+//! bearer. Sin URL no se envía nada y el correo queda en el log, que en local es
+//! la forma de ver los enlaces.
+
+use serde::{Deserialize, Serialize};
+use std::{sync::Arc, time::Duration};
+
+use crate::config::MailConfig;
+
+#[derive(Serialize, Deserialize)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub text: String,
+    pub html: String,
+}
+
+#[derive(Serialize)]
+struct Payload<'a> {
+    from: &'a str,
+    #[serde(flatten)]
+    email: &'a Email,
+}
+
+pub struct Mailer {
+    config: MailConfig,
+    client: reqwest::Client,
+}
+
+impl Mailer {
+    pub fn new(config: &MailConfig) -> Arc<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .expect("no se pudo crear el cliente HTTP de correo");
+        Arc::new(Mailer { config: config.clone(), client })
+    }
+
+    /// Con `idempotency_key` (ver `outbox`), el proveedor puede descartar un reenvío.
+    pub async fn send(&self, email: &Email, idempotency_key: Option<&str>) -> Result<(), reqwest::Error> {
+        let Some(url) = &self.config.api_url else {
+            tracing::info!(to = %email.to, subject = %email.subject, text = %email.text, "correo sin enviar (MAIL_API_URL vacío)");
+            return Ok(());
+        };
+
+        let mut req = self.client.post(url).json(&Payload { from: &self.config.from, email });
+        if let Some(token) = &self.config.api_token {
+            req = req.bearer_auth(token);
+        }
+        if let Some(key) = idempotency_key {
+            req = req.header(crate::outbox::IDEMPOTENCY_HEADER, key);
+        }
+        req.send().await?.error_for_status()?;
+        Ok(())
+    }
+}
@@ -1,33 +1,202 @@
+mod access_log;
+mod accounts;
+mod admin;
+mod admin_session;
+mod akismet;
+mod announcement;
+mod api_keys;
+mod archive;
+mod audit_log;
+mod author_cap;
+mod bans;
+mod body_limit;
+mod captcha;
+mod client_ip;
+mod config;
+mod conditional;
+mod content_rules;
+mod cors;
+mod csp_nonce;
+mod csrf;
+mod db;
+mod db_stats;
+mod dry_run;
+mod edit_token;
+mod email_verification;
+mod empty_listing;
+mod events;
+mod fields;
+mod file_types;
+mod flash;
+mod html;
+mod image_review;
+mod index_advisor;
+mod json_case;
+mod jwt;
+mod logging;
+mod login_lockout;
+mod mailer;
+mod metrics;
+mod outbox;
+mod oauth;
+mod overview;
+mod pagination;
+mod payload_log;
+mod policy;
+mod quarantine;
+mod queries;
+mod query_budget;
+mod quota;
+mod rate_limit;
+mod read_cache;
+mod reimport;
+mod redis;
+mod remote_image;
+mod security_headers;
+mod server;
+mod server_timing;
+mod settings_cache;
+mod signed_urls;
+mod setup;
+mod spam_log;
+mod state;
+mod static_export;
+#[cfg(test)]
+mod test_support;
+mod thumbs;
+mod timezone;
+mod tls;
+mod trace;
+mod trash;
+mod unit_of_work;
+mod upload_pipeline;
+mod upload_progress;
+mod uploads;
+mod users;
+mod validation;
+mod verify_uploads;
+mod version;
+mod watchdog;
+mod zip;
+
 use axum::{
-    extract::{Form, State, Multipart, Path},
+    extract::{DefaultBodyLimit, Form, State, Multipart, Path, Query},
     routing::{get, post},
-    response::{Html, IntoResponse},
-    Json, Router,
+    response::{Html, IntoResponse, Response},
+    Extension, Json, Router,
 };
+use axum::http::{header, HeaderMap, StatusCode};
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::{PgPool, Row};
-use std::{env, net::SocketAddr};
-use tower_http::{cors::CorsLayer, services::ServeDir};
-use tokio::io::AsyncWriteExt;
+use std::sync::Arc;
+use tower_http::services::ServeDir;
 use uuid::Uuid;
-use regex::Regex;
 
-const MAX_IMAGE_SIZE: usize = 5 * 1024 * 1024;
-const ALLOWED_MIME: [&str; 4] = ["image/jpeg", "image/png", "image/webp", "image/jpg"];
+use access_log::AccessLog;
+use author_cap::CapReached;
+use client_ip::ClientIp;
+use config::Config;
+use db::DbError;
+use dry_run::{DryRunQuery, Preview};
+use events::Event;
+use flash::Flash;
+use metrics::Metrics;
+use fields::{FieldsQuery, Selection};
+use pagination::{PageQuery, Paginated};
+use policy::{Action, Forbidden, Permission, Principal, Resource};
+use rate_limit::RateLimiter;
+use captcha::{Provider, Verdict};
+use state::{AppState, SharedState};
+use timezone::Zone;
+use unit_of_work::UnitOfWork;
+use upload_pipeline::{Upload, UploadError, Uploader};
+use upload_progress::Reporter;
+use uploads::UploadsRoot;
+use validation::{valid_email, valid_mensaje, valid_nombre};
+
+/// Páginas y recursos estáticos del sitio.
+const STATIC_DIR: &str = "./static";
 
 #[derive(Deserialize)]
 struct FormData {
     nombre: String,
     mensaje: String,
-    #[serde(rename = "g-recaptcha-response")]
+    /// Respuesta del captcha, en el campo de cada proveedor (ver `captcha`).
+    #[serde(rename = "g-recaptcha-response", default)]
     recaptcha: String,
+    #[serde(rename = "cf-turnstile-response", default)]
+    turnstile: String,
+    #[serde(rename = "h-captcha-response", default)]
+    hcaptcha: String,
+    /// Opcional; si viene se manda un enlace para verificarlo.
+    #[serde(default)]
+    email: String,
+    /// Campo trampa, oculto en el formulario: si viene relleno es un bot (ver `spam_log`).
+    #[serde(default)]
+    website: String,
+    /// No viene del formulario: la cuenta con sesión que lo envía (ver `accounts`).
+    #[serde(skip)]
+    user_id: Option<i32>,
+}
+
+impl FormData {
+    fn captcha(&self, provider: Provider) -> &str {
+        match provider {
+            Provider::Recaptcha => &self.recaptcha,
+            Provider::Turnstile => &self.turnstile,
+            Provider::Hcaptcha => &self.hcaptcha,
+        }
+    }
 }
 
 #[derive(Serialize)]
 struct Mensaje {
     id: i32,
     nombre: String,
-    mensaje: String,
+    /// Cuerpo completo; los listados con `?full=false` lo omiten.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mensaje: Option<String>,
+    /// Vista previa de `EXCERPT_CHARS` caracteres como mucho.
+    excerpt: String,
+    /// El autor confirmó su email (que nunca se publica).
+    verified: bool,
+}
+
+impl Mensaje {
+    /// Campos que admite `?fields=` y la expresión de la que sale cada uno.
+    const FIELDS: &[fields::Field] = &[
+        ("id", "id"),
+        ("nombre", "nombre"),
+        ("mensaje", "mensaje"),
+        ("excerpt", "mensaje"),
+        ("verified", "email_verified_at IS NOT NULL AS verified"),
+    ];
+
+    /// De una fila con `id`, `nombre`, `mensaje` y `verified`; con `?fields=`
+    /// pueden faltar columnas, que quedan vacías (y no se serializan).
+    fn from_row(r: &sqlx::postgres::PgRow, excerpt_chars: usize, full: bool) -> Self {
+        let mensaje: String = r.try_get("mensaje").unwrap_or_default();
+        Mensaje {
+            id: r.get("id"),
+            nombre: r.try_get("nombre").unwrap_or_default(),
+            excerpt: html::excerpt_chars(&mensaje, excerpt_chars),
+            mensaje: full.then_some(mensaje),
+            verified: r.try_get("verified").unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ListShape {
+    /// Con `false` el listado lleva solo el extracto; el cuerpo, en `GET /mensajes/:id`.
+    full: Option<bool>,
+}
+
+#[derive(Deserialize)]
+struct UploadQuery {
+    /// Id emitido por `POST /upload-image/progress` para seguir la subida por WebSocket.
+    upload_id: Option<Uuid>,
 }
 
 #[derive(Deserialize)]
@@ -38,125 +207,752 @@ struct UpdateData {
 
 #[tokio::main]
 async fn main() {
+    std::sync::LazyLock::force(&version::STARTED_AT);
     dotenvy::dotenv().ok();
 
-    let pool = PgPool::connect(&env::var("DATABASE_URL").unwrap())
-        .await
-        .unwrap();
+    let config = Arc::new(Config::from_env());
+    let _log_guard = logging::init(&config.log);
+    let metrics = Arc::new(Metrics::new(&config.metrics));
+    let access_log = AccessLog::start(&config.access_log).await;
+    db::init_instrumentation(config.db.slow_query, metrics.clone());
+
+    let pool = db::connect(&config.db).await;
+
+    // `hola_axum check`: revisa los índices recomendados sin migrar ni arrancar el servidor.
+    if std::env::args().nth(1).as_deref() == Some("check") {
+        std::process::exit(check(&pool).await);
+    }
+
+    // `hola_axum verify-uploads [--fix]`: cruza `images` con los ficheros, para cron.
+    if std::env::args().nth(1).as_deref() == Some("verify-uploads") {
+        let apply = std::env::args().skip(2).any(|arg| arg == "--fix");
+        let uploads = UploadsRoot::open(&config.uploads.dir);
+        std::process::exit(verify_uploads::run(&pool, &uploads, apply).await);
+    }
+
+    // `hola_axum reimport-mensajes <fichero.jsonl> [--apply]`: textos que recortó `sanitize_text`.
+    if std::env::args().nth(1).as_deref() == Some("reimport-mensajes") {
+        let Some(file) = std::env::args().nth(2) else {
+            eprintln!("uso: hola_axum reimport-mensajes <fichero.jsonl> [--apply]");
+            std::process::exit(2);
+        };
+        let apply = std::env::args().skip(3).any(|arg| arg == "--apply");
+        std::process::exit(reimport::run(&pool, std::path::Path::new(&file), apply).await);
+    }
+
+    db::migrate(&pool).await;
+    db::warm_up(&pool, config.db.min_connections).await;
+    db::check_collation(&pool, &config.content.collation).await;
+    index_advisor::advise(&pool).await;
+
+    let uploads = Arc::new(UploadsRoot::open(&config.uploads.dir));
+    tracing::info!(dir = %uploads.dir().display(), "directorio de subidas");
+    let jobs_pool = db::connect_jobs(&config.db);
+    if config.uploads.enabled {
+        tokio::spawn(trash::purge_loop(jobs_pool.clone(), uploads.clone(), config.uploads.trash_retention));
+    }
+    if let Some(after) = config.content.archive_after {
+        tokio::spawn(archive::archive_loop(jobs_pool.clone(), after));
+    }
+    watchdog::spawn(&config.alerts, metrics.clone(), pool.clone(), uploads.dir());
+
+    let state = AppState::new(pool, jobs_pool, config.clone(), uploads, metrics);
+    if let Err(e) = state.setup.load(&state.db).await {
+        tracing::warn!(error = ?e, "no se pudieron leer los ajustes del sitio; se usan los de por defecto");
+    }
+    tokio::spawn(state.mensajes_empty.clone().listen(state.db.clone()));
+    tokio::spawn(events::consume(state.clone()));
+    tokio::spawn(outbox::dispatch(state.clone()));
+    if let Some(url) = &config.events.redis_url {
+        tokio::spawn(state.events.clone().bridge_redis(url.clone(), config.events.redis_channel.clone()));
+    }
+    let (public, internal) = build_routers(&state, &access_log);
+
+    let mut listeners = Vec::new();
+    if let Some(internal) = internal {
+        for listen in &config.server.internal_listen {
+            listeners.push((listen.clone(), internal.clone(), false));
+        }
+    }
+    for listen in &config.server.listen {
+        listeners.push((listen.clone(), public.clone(), true));
+    }
+
+    server::run(listeners, &config.server).await;
+}
+
+/// Imprime los `CREATE INDEX` que faltan; el código de salida es 1 si falta alguno.
+async fn check(pool: &PgPool) -> i32 {
+    match index_advisor::missing(pool).await {
+        Ok(missing) if missing.is_empty() => {
+            println!("-- todos los índices recomendados existen");
+            0
+        }
+        Ok(missing) => {
+            for rec in missing {
+                println!("-- {}.{}: {}\n{}", rec.table, rec.column, rec.reason, rec.create);
+            }
+            1
+        }
+        Err(err) => {
+            eprintln!("no se pudieron revisar los índices: {err:?}");
+            2
+        }
+    }
+}
+
+/// Router público y, si hay `INTERNAL_LISTEN`, el router interno de operación.
+fn build_routers(state: &SharedState, access_log: &Option<Arc<AccessLog>>) -> (Router, Option<Router>) {
+    let config = &state.config;
+    let upload_max_body = config.uploads.types.max_bytes() + body_limit::MULTIPART_OVERHEAD;
+    // Un solo bucket por IP para todo lo que escribe: frena inundaciones de spam.
+    let write_limit = axum::middleware::from_fn_with_state(
+        RateLimiter::new(config.writes.rate_burst, config.writes.rate_per_minute),
+        rate_limit::limit,
+    );
+    let banned = axum::middleware::from_fn_with_state(state.clone(), bans::reject);
+    let images = |router: Router<SharedState>| images_gate(router, config.uploads.enabled);
+
+    // Cada grupo con el permiso que exige (ver `policy::Role`).
+    let admin_api = Router::new()
+        .merge(admin::require(
+            Router::new()
+                .route("/audit", get(audit_log::list_changes))
+                .route("/spam", get(spam_log::list)),
+            Permission::ViewPanel,
+        ))
+        .merge(admin::require(
+            images(
+                Router::new()
+                    .route("/images/pending", get(image_review::pending_images))
+                    .route("/images/:id/file", get(image_review::pending_file)),
+            ),
+            Permission::ViewPanel,
+        ))
+        .merge(admin::require(
+            Router::new()
+                .route("/mensajes/:id", mensaje_routes())
+                .route("/mensajes/archive", post(archive::archive_now))
+                .route("/mensajes/quarantine", get(quarantine::list))
+                .route("/mensajes/quarantine/:id", axum::routing::delete(quarantine::discard))
+                .route("/mensajes/quarantine/:id/release", post(quarantine::release))
+                .route("/bans", get(bans::list).post(bans::create))
+                .route("/bans/:id", axum::routing::delete(bans::remove)),
+            Permission::ModerateMessages,
+        ))
+        .merge(admin::require(
+            images(
+                Router::new()
+                    .route("/images/:id", axum::routing::delete(delete_image))
+                    .route("/images/:id/restore", post(restore_image))
+                    .route("/images/trash/purge", post(trash::purge_now))
+                    .route("/images/:id/approve", post(image_review::approve_image))
+                    .route("/images/:id/reject", post(image_review::reject_image)),
+            ),
+            Permission::ManageImages,
+        ))
+        .merge(admin::require(
+            Router::new()
+                .route("/users", get(users::list_users).post(users::create_user))
+                .route("/users/:id", axum::routing::delete(users::disable_user))
+                .route("/users/:id/role", post(users::set_role))
+                .route("/api-keys", get(api_keys::list).post(api_keys::create))
+                .route("/api-keys/:id", axum::routing::delete(api_keys::revoke))
+                .route("/accounts", get(oauth::list_accounts).post(oauth::add_account))
+                .route("/accounts/:id", axum::routing::delete(oauth::remove_account)),
+            Permission::ManageUsers,
+        ));
+
+    // Fragmentos HTML del panel, para cargar con htmx, y estado de la base de datos.
+    let admin_pages = Router::new()
+        .route("/overview", get(overview::overview))
+        .route("/mensajes", get(admin_mensajes))
+        .route("/audit", get(audit_log::audit_page))
+        .route("/export/static", get(static_export::static_export))
+        .route("/db", get(db_stats::db_stats))
+        .route("/announcement", get(announcement::get_announcement));
+    let admin_pages = admin::require(admin_pages, Permission::ViewPanel).merge(admin::require(
+        Router::new().route("/announcement", axum::routing::put(announcement::put_announcement)),
+        Permission::ManageSite,
+    ));
 
-    let app = Router::new()
+    // Rutas de operación: van a su propio puerto si hay INTERNAL_LISTEN.
+    let ops = Router::new()
+        // ===== ADMIN =====
+        .nest("/api/admin", admin::protect(admin_api, state))
+        .nest("/admin", admin::protect(admin_pages, state).merge(admin_session::routes(state)))
+        .route("/admin.html", get(admin_session::admin_html))
+        .nest("/auth", oauth::routes(state))
+        .merge(setup::routes(state))
+        .merge(admin::throttle(Router::new().route("/api/login", post(jwt::login)), state))
+
+        // ===== MÉTRICAS =====
+        .route("/metrics", get(metrics::metrics_handler))
+        .route("/version", get(version::version));
+
+    // `ETag`, `Content-Length` y 304 en listados y medios, también con `HEAD`.
+    let validated = axum::middleware::from_fn(conditional::validate);
+
+    let mut public = Router::new()
         // ===== RUTAS PRINCIPALES =====
-        .route("/enviar", post(enviar))
-        .route("/upload-image", post(upload_image))
-        .route("/images", get(list_images))
+        .route(
+            "/enviar",
+            body_limit::limit(post(enviar), body_limit::FORM)
+                .layer(axum::middleware::from_fn(csrf::verify))
+                .layer(write_limit.clone())
+                .layer(banned.clone()),
+        )
+        .merge(images(
+            Router::new()
+                .route(
+                    "/upload-image",
+                    body_limit::limit(post(upload_image), upload_max_body)
+                        .layer(write_limit.clone())
+                        .layer(banned.clone()),
+                )
+                .route("/upload-image/progress", post(upload_progress::issue))
+                .route("/ws/uploads/:id", get(upload_progress::progress_ws))
+                .route("/images", get(list_images).layer(validated.clone()))
+                .route("/images/:id", axum::routing::delete(delete_image))
+                .route("/images/:id/restore", post(restore_image))
+                .route("/images/:id/thumb", get(thumbs::thumbnail).layer(validated.clone()))
+                .route("/images/:id/url", get(signed_urls::image_url)),
+        ))
+        .route("/me/quota", get(quota::me_quota))
+        .route("/events", get(events::stream_events))
+        .route("/announcement", get(announcement::public_announcement))
+        .route("/captcha", get(captcha::widget))
 
         // ===== CRUD MENSAJES =====
-        .route("/mensajes", get(list_mensajes))
-        .route("/mensajes/:id", axum::routing::delete(delete_mensaje))
-        .route("/mensajes/:id", axum::routing::put(update_mensaje))
+        .route("/mensajes", get(list_mensajes).layer(validated.clone()))
+        .route(
+            "/mensajes/:id",
+            get(get_mensaje).layer(validated).merge(mensaje_routes().layer(axum::middleware::from_fn(csrf::verify))),
+        )
+        .route(
+            "/mensajes/preview",
+            body_limit::limit(post(preview_mensaje), body_limit::FORM).layer(axum::middleware::from_fn(csrf::verify)),
+        )
+        .route("/mensajes/:id/view", get(view_mensaje))
+        .route("/mensajes/:id/related", get(related_mensajes))
+        .route("/verificar/:token", get(email_verification::verify))
+        .route(
+            "/confirmar/:token",
+            get(email_verification::confirm_page)
+                .merge(post(email_verification::confirm).layer(axum::middleware::from_fn(csrf::verify))),
+        )
+        .nest("/cuenta", accounts::routes(state));
+
+    if config.uploads.from_url {
+        let route = body_limit::limit(post(upload_image_url), body_limit::FORM).layer(write_limit).layer(banned);
+        public = public.merge(images(Router::new().route("/upload-image-url", route)));
+    }
+
+    let mut internal = None;
+    if config.server.internal_listen.is_empty() {
+        public = public.merge(ops);
+    } else {
+        let ops = ops.route_layer(axum::middleware::from_fn(server_timing::handler));
+        internal = Some(common_layers(ops.with_state(state.clone()), state, access_log));
+    }
 
-        // ===== ARCHIVOS ESTÁTICOS =====
-        .nest_service("/uploads", ServeDir::new("./uploads"))
-        .nest_service("/", ServeDir::new("./static")) // 👈 CAMBIO AQUÍ
+    // ===== ARCHIVOS ESTÁTICOS =====
+    let hidden = axum::middleware::from_fn(hide_dotfiles);
+    let public = if !config.uploads.enabled {
+        public.nest_service("/uploads", Router::new().fallback(images_disabled))
+    } else if config.uploads.private.is_some() {
+        public.nest("/uploads", Router::new().route("/:filename", get(signed_urls::serve_signed)).layer(hidden))
+    } else {
+        public.nest_service("/uploads", Router::new().fallback_service(ServeDir::new(state.uploads.dir())).layer(hidden))
+    };
+    let public = public
+        .nest_service("/", ServeDir::new(STATIC_DIR)) // 👈 CAMBIO AQUÍ
+        .route_layer(axum::middleware::from_fn(server_timing::handler))
 
-        .with_state(pool)
-        .layer(CorsLayer::permissive());
+        .with_state(state.clone())
+        .layer(axum::middleware::from_fn(csrf::inject))
+        .layer(axum::middleware::from_fn_with_state(csp_nonce::policy(&config.security_headers), csp_nonce::inject));
+    // `CorsLayer` responde a cualquier `OPTIONS`: la consulta de métodos va por debajo.
+    let preflight = cors::Preflight { routes: public.clone(), methods: config.cors.methods.clone() };
+    let public = public
+        .layer(cors::layer(&config.cors))
+        .layer(axum::middleware::from_fn_with_state(preflight, cors::preflight));
 
-    let port: u16 = env::var("PORT")
-        .unwrap_or("3000".into())
-        .parse()
-        .unwrap();
+    (common_layers(public, state, access_log), internal)
+}
+
+fn mensaje_routes() -> axum::routing::MethodRouter<SharedState> {
+    body_limit::limit(axum::routing::put(update_mensaje).delete(delete_mensaje), body_limit::FORM)
+}
+
+/// `uploads/.trash` y demás rutas ocultas no se sirven.
+async fn hide_dotfiles(req: axum::extract::Request, next: axum::middleware::Next) -> Response {
+    if req.uri().path().split('/').any(|segment| segment.starts_with('.')) {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    next.run(req).await
+}
+
+/// Rutas de imágenes tal cual, o todas en 503 si están desactivadas.
+fn images_gate(router: Router<SharedState>, enabled: bool) -> Router<SharedState> {
+    if enabled {
+        router
+    } else {
+        router.route_layer(axum::middleware::from_fn(reject_images))
+    }
+}
+
+async fn reject_images(_req: axum::extract::Request, _next: axum::middleware::Next) -> Response {
+    images_disabled().await
+}
+
+async fn images_disabled() -> Response {
+    (StatusCode::SERVICE_UNAVAILABLE, Html("❌ Las imágenes están desactivadas")).into_response()
+}
+
+fn query_budget_of(state: &SharedState) -> query_budget::Budget {
+    query_budget::Budget {
+        max: state.config.db.query_budget,
+        header: state.config.db.debug_query_count,
+        metrics: state.metrics.clone(),
+    }
+}
+
+/// Middleware compartido por todos los listeners.
+fn common_layers(router: Router, state: &SharedState, access_log: &Option<Arc<AccessLog>>) -> Router {
+    let config = &state.config;
+    let mut router = router
+        // Por fuera de los límites de cada ruta, que prevalecen.
+        .layer(DefaultBodyLimit::max(config.server.max_body))
+        .layer(axum::middleware::from_fn_with_state(query_budget_of(state), query_budget::count_queries))
+        .layer(axum::middleware::from_fn_with_state(config.server.json_case, json_case::rewrite))
+        .layer(axum::middleware::from_fn_with_state(state.metrics.clone(), metrics::track))
+        // Para extractores genéricos sobre el estado, como `Principal`.
+        .layer(Extension(config.clone()))
+        .layer(Extension(state.db.clone()));
 
-    let addr = SocketAddr::from(([0, 0, 0, 0], port));
+    if let Some(max_bytes) = config.log.debug_payloads {
+        router = router.layer(axum::middleware::from_fn_with_state(max_bytes, payload_log::log_payloads));
+    }
+
+    if let Some(log) = access_log {
+        router = router.layer(axum::middleware::from_fn_with_state(log.clone(), access_log::log_request));
+    }
 
-    axum::serve(
-        tokio::net::TcpListener::bind(addr).await.unwrap(),
-        app
-    )
-    .await
-    .unwrap();
+    let router = router
+        .layer(axum::middleware::from_fn_with_state(Arc::new(config.trace.clone()), trace::trace))
+        .layer(axum::middleware::from_fn_with_state(config.server.timing, server_timing::track))
+        .layer(axum::middleware::from_fn_with_state(Arc::new(config.server.trusted_proxies.clone()), client_ip::resolve));
+    security_headers::apply(router, &config.security_headers.headers)
 }
 
 /* ---------- ENVIAR MENSAJE ---------- */
 
+/// Desde un formulario del navegador se redirige con un aviso flash (a `/` si
+/// fue bien, de vuelta al formulario si no); con `fetch` se responde el texto y,
+/// si se rechaza, el motivo en `X-Error-Code`.
 async fn enviar(
-    State(pool): State<PgPool>,
+    State(app): State<SharedState>,
+    ClientIp(ip): ClientIp,
+    principal: Principal,
+    headers: HeaderMap,
     Form(mut data): Form<FormData>,
-) -> impl IntoResponse {
+) -> Response {
+    if let Principal::Author { id } = principal {
+        data.user_id = Some(id);
+    }
+    let mut edit_cookie = None;
+    let result = if data.website.trim().is_empty() {
+        let user_agent = headers.get(header::USER_AGENT).and_then(|v| v.to_str().ok());
+        let result = guardar_mensaje(&app, ip, user_agent, data).await;
+        result.map(|(publicado, msg)| {
+            if let Some(Publicado { id, edit_token }) = publicado {
+                events::publish(&app, Event::MessageCreated { id });
+                edit_cookie = edit_token.map(|token| edit_token::cookie(id, &token));
+            }
+            msg
+        })
+    } else {
+        // Se responde como si se hubiera guardado para no delatar la trampa.
+        let attempt = spam_log::Attempt {
+            reason: "honeypot",
+            ip,
+            user_agent: headers.get(header::USER_AGENT).and_then(|v| v.to_str().ok()),
+            nombre: &data.nombre,
+            mensaje: &data.mensaje,
+        };
+        spam_log::record(&app.db, &attempt).await;
+        Ok("✅ Mensaje enviado correctamente")
+    };
+
+    let mut res = if flash::wants_html(&headers) {
+        let back = flash::back(&headers, "/contacto.html");
+        match result {
+            Ok(msg) => flash::redirect("/", Flash::success(msg)),
+            Err(EnviarError::Rejected(r)) => flash::redirect(&back, Flash::error(r.msg)),
+            Err(EnviarError::Cap(cap)) => flash::redirect(&back, Flash::error(cap.message())),
+        }
+    } else {
+        match result {
+            Ok(msg) => Html(msg).into_response(),
+            Err(EnviarError::Rejected(r)) => ([(ERROR_CODE, r.code)], Html(r.msg)).into_response(),
+            Err(EnviarError::Cap(cap)) => cap.into_response(),
+        }
+    };
+    if let Some(cookie) = edit_cookie {
+        res.headers_mut().append(header::SET_COOKIE, cookie);
+    }
+    res
+}
+
+/// Mensaje ya publicado; sin cuenta, con el token para editarlo (ver `edit_token`).
+struct Publicado {
+    id: i32,
+    edit_token: Option<String>,
+}
+
+enum EnviarError {
+    Rejected(Rejected),
+    /// Tope diario del autor: 429 en vez de un aviso normal.
+    Cap(CapReached),
+}
 
-    sanitize_text(&mut data.nombre);
-    sanitize_text(&mut data.mensaje);
+impl From<Rejected> for EnviarError {
+    fn from(r: Rejected) -> Self {
+        EnviarError::Rejected(r)
+    }
+}
 
-    let name_re = Regex::new(r"^[a-zA-ZáéíóúÁÉÍÓÚñÑ\s]{3,50}$").unwrap();
+/// Cabecera con el código estable de un rechazo.
+const ERROR_CODE: &str = "x-error-code";
+
+struct Rejected {
+    code: &'static str,
+    msg: &'static str,
+}
 
-    if !name_re.is_match(&data.nombre) {
-        return Html("❌ Nombre inválido");
+impl Rejected {
+    fn new(code: &'static str, msg: &'static str) -> Self {
+        Rejected { code, msg }
     }
+}
 
-    if data.mensaje.len() < 10 || data.mensaje.len() > 500 {
-        return Html("❌ Mensaje inválido");
+impl From<content_rules::Violation> for Rejected {
+    fn from(v: content_rules::Violation) -> Self {
+        Rejected::new(v.code(), v.message())
     }
+}
 
-    if data.recaptcha.is_empty() {
-        return Html("❌ Completa el reCAPTCHA");
+/// Comprobaciones de los campos ya saneados, en el orden de `/enviar`. Todas,
+/// no solo la primera, para que la vista previa las muestre juntas.
+fn validar_campos(config: &Config, data: &FormData, email: &str) -> (Vec<Rejected>, content_rules::Assessment) {
+    let mut rejected = Vec::new();
+    if !valid_nombre(&data.nombre) {
+        rejected.push(Rejected::new("invalid_nombre", "❌ Nombre inválido"));
+    }
+    if !valid_mensaje(&data.mensaje) {
+        rejected.push(Rejected::new("invalid_mensaje", "❌ Mensaje inválido"));
+    }
+    if email.is_empty() && config.mail.confirmation_required {
+        rejected.push(Rejected::new("email_required", "❌ Indica tu email para confirmar el mensaje"));
+    } else if !email.is_empty() && !valid_email(email) {
+        rejected.push(Rejected::new("invalid_email", "❌ Email inválido"));
     }
+    let assessment = content_rules::assess(&config.content, &data.mensaje);
+    if let Some(violation) = assessment.violation {
+        rejected.push(Rejected::from(violation));
+    }
+    (rejected, assessment)
+}
 
-    match sqlx::query("INSERT INTO mensajes (nombre, mensaje) VALUES ($1,$2)")
-        .bind(&data.nombre)
-        .bind(&data.mensaje)
-        .execute(&pool)
-        .await
-    {
-        Ok(_) => Html("✅ Mensaje enviado correctamente"),
-        Err(_) => Html("❌ Error guardando mensaje"),
+async fn guardar_mensaje(
+    app: &AppState,
+    ip: Option<std::net::IpAddr>,
+    user_agent: Option<&str>,
+    data: FormData,
+) -> Result<(Option<Publicado>, &'static str), EnviarError> {
+    let (pool, config, captcha) = (&app.db, &app.config, app.captcha.as_ref());
+    // Los enlaces de los correos llevan token: nunca se arman con `Host`. Sin
+    // `PUBLIC_URL` no hay proveedor de correo (ver `MailConfig`) y salen al log.
+    let base_url = config.server.public_url.as_deref().unwrap_or("").trim_end_matches('/');
+
+    let email = data.email.trim().to_lowercase();
+
+    let (rejected, assessment) = validar_campos(config, &data, &email);
+    if let Some(r) = rejected.into_iter().next() {
+        if let Some(violation) = assessment.violation {
+            tracing::info!(code = violation.code(), score = assessment.score, "mensaje rechazado por contenido");
+        }
+        return Err(r.into());
+    }
+
+    // Los códigos conservan el nombre de cuando solo había reCAPTCHA.
+    let response = data.captcha(captcha.provider());
+    if response.is_empty() {
+        return Err(Rejected::new("recaptcha_missing", "❌ Completa el captcha").into());
+    }
+    match captcha.verify(response, ip).await {
+        Verdict::Passed => {}
+        Verdict::Failed => {
+            return Err(Rejected::new("recaptcha_failed", "❌ No se pudo comprobar el captcha, inténtalo de nuevo").into());
+        }
+        Verdict::Unavailable => {
+            return Err(Rejected::new("recaptcha_unavailable", "❌ No se pudo comprobar el captcha ahora mismo").into());
+        }
+    }
+
+    match author_cap::check(pool, config.content.daily_per_author, ip, &data.nombre).await {
+        Ok(Ok(())) => {}
+        Ok(Err(cap)) => return Err(EnviarError::Cap(cap)),
+        Err(_) => return Err(Rejected::new("db_error", "❌ Error guardando mensaje").into()),
+    }
+
+    let db_error = |_| EnviarError::from(Rejected::new("db_error", "❌ Error guardando mensaje"));
+
+    // Texto más ritmo de envíos: pasado el umbral se retiene sin avisar de por qué.
+    let score = assessment.score.saturating_add(quarantine::velocity_points(pool, ip).await.map_err(db_error)?);
+    let email = Some(email.as_str()).filter(|e| !e.is_empty());
+    let edit_token = data.user_id.is_none().then(edit_token::issue);
+    let new = queries::NewMensaje {
+        nombre: &data.nombre,
+        mensaje: &data.mensaje,
+        ip,
+        score,
+        email,
+        user_id: data.user_id,
+        edit_token_hash: edit_token.as_ref().map(|(_, hash)| hash.as_str()),
+    };
+    let akismet_spam = match &app.akismet {
+        Some(akismet) => {
+            let comment = akismet::Comment { ip, user_agent, nombre: &data.nombre, email, mensaje: &data.mensaje };
+            akismet.check(&comment).await == akismet::Verdict::Spam
+        }
+        None => false,
+    };
+    if akismet_spam || config.content.quarantine_score.is_some_and(|limit| score >= limit) {
+        let id = quarantine::hold(pool, &new, user_agent, akismet_spam).await.map_err(db_error)?;
+        tracing::info!(id, score, akismet_spam, "mensaje retenido en cuarentena");
+        return Ok((None, "✅ Mensaje recibido; se publicará cuando se revise"));
+    }
+
+    // `validar_campos` ya exige el email si hace falta confirmar.
+    // El enlace sale por la outbox, con el mensaje: sin él no hay forma de publicarlo.
+    if config.mail.confirmation_required && let Some(email) = email {
+        let mut uow = UnitOfWork::begin(pool).await.map_err(db_error)?;
+        let Some(token) = email_verification::hold_unconfirmed(uow.conn(), &new, config.mail.verify_ttl)
+            .await
+            .map_err(db_error)?
+        else {
+            return Err(Rejected::new("too_many_pending", "❌ Ya tienes mensajes esperando confirmación; revisa tu correo").into());
+        };
+        let link = format!("{base_url}/confirmar/{token}");
+        let confirmation = email_verification::confirmation_email(email, &data.nombre, &link);
+        outbox::enqueue_email(uow.conn(), &confirmation).await.map_err(db_error)?;
+        uow.commit().await.map_err(db_error)?;
+        outbox::wake(app);
+        return Ok((None, "✅ Mensaje recibido. Revisa tu correo para confirmarlo y publicarlo"));
     }
+
+    // Mensaje, token de verificación y su correo van juntos: sin token no hay enlace que mandar.
+    let mut uow = UnitOfWork::begin(pool).await.map_err(db_error)?;
+
+    let id = queries::insert_mensaje(uow.conn(), &new).await.map_err(db_error)?;
+    outbox::enqueue(uow.conn(), &Event::MessageCreated { id }).await.map_err(db_error)?;
+
+    let publicado = Publicado { id, edit_token: edit_token.map(|(token, _)| token) };
+    let Some(email) = email else {
+        uow.commit().await.map_err(db_error)?;
+        return Ok((Some(publicado), "✅ Mensaje enviado correctamente"));
+    };
+
+    let token = email_verification::create_token(uow.conn(), id, config.mail.verify_ttl)
+        .await
+        .map_err(db_error)?;
+    let link = format!("{base_url}/verificar/{token}");
+    let verification = email_verification::email(email, &data.nombre, &link);
+    outbox::enqueue_email(uow.conn(), &verification).await.map_err(db_error)?;
+    uow.commit().await.map_err(db_error)?;
+    outbox::wake(app);
+
+    Ok((Some(publicado), "✅ Mensaje enviado. Revisa tu correo para verificar tu email"))
+}
+
+/* ---------- VISTA PREVIA ---------- */
+
+#[derive(Serialize)]
+struct MensajePreview {
+    nombre: String,
+    /// Igual que en `/mensajes/:id/view`.
+    html: String,
+    errors: Vec<PreviewError>,
+}
+
+#[derive(Serialize)]
+struct PreviewError {
+    code: &'static str,
+    message: &'static str,
+}
+
+/// `POST /mensajes/preview`: lo que haría `/enviar` con el formulario, sin
+/// guardar nada ni pedir captcha.
+async fn preview_mensaje(State(app): State<SharedState>, Form(data): Form<FormData>) -> Json<MensajePreview> {
+    let email = data.email.trim().to_lowercase();
+
+    let (rejected, _) = validar_campos(&app.config, &data, &email);
+    Json(MensajePreview {
+        html: html::mensaje_body(&data.mensaje),
+        nombre: data.nombre,
+        errors: rejected.into_iter().map(|r| PreviewError { code: r.code, message: r.msg }).collect(),
+    })
 }
 
 /* ---------- UPDATE ---------- */
 
+/// Igual que `enviar`: desde el navegador, redirección con flash a la página de
+/// origen (o al listado de administración) para que recargar no repita el PUT.
 async fn update_mensaje(
-    State(pool): State<PgPool>,
+    State(app): State<SharedState>,
+    principal: Principal,
     Path(id): Path<i32>,
-    Form(mut data): Form<UpdateData>,
-) -> impl IntoResponse {
+    headers: HeaderMap,
+    Form(data): Form<UpdateData>,
+) -> Response {
+    let fallback = if principal.role().is_some() { "/admin.html" } else { "/" };
+    let result = actualizar_mensaje(&app.db, &app.config, &principal, id, data).await;
+
+    if flash::wants_html(&headers) {
+        let back = flash::back(&headers, fallback);
+        return match result {
+            Ok(msg) => flash::redirect(&back, Flash::success(msg)),
+            Err(UpdateError::Rejected(_, r)) => flash::redirect(&back, Flash::error(r.msg)),
+            Err(UpdateError::Db(e)) => e.into_response(),
+        };
+    }
 
-    sanitize_text(&mut data.nombre);
-    sanitize_text(&mut data.mensaje);
+    match result {
+        Ok(msg) => Html(msg).into_response(),
+        Err(UpdateError::Rejected(status, r)) => (status, [(ERROR_CODE, r.code)], Html(r.msg)).into_response(),
+        Err(UpdateError::Db(e)) => e.into_response(),
+    }
+}
 
-    let name_re = Regex::new(r"^[a-zA-ZáéíóúÁÉÍÓÚñÑ\s]{3,50}$").unwrap();
+enum UpdateError {
+    Rejected(StatusCode, Rejected),
+    Db(DbError),
+}
 
-    if !name_re.is_match(&data.nombre) {
-        return Html("❌ Nombre inválido");
+impl UpdateError {
+    fn rejected(status: StatusCode, code: &'static str, msg: &'static str) -> Self {
+        UpdateError::Rejected(status, Rejected::new(code, msg))
     }
+}
+
+async fn actualizar_mensaje(
+    pool: &PgPool,
+    config: &Config,
+    principal: &Principal,
+    id: i32,
+    data: UpdateData,
+) -> Result<&'static str, UpdateError> {
 
-    if data.mensaje.len() < 10 || data.mensaje.len() > 500 {
-        return Html("❌ Mensaje inválido");
+    match queries::mensaje_meta(pool, id).await {
+        Ok(Some(meta)) => {
+            if policy::authorize(principal, Action::Update, &Resource::Mensaje(&meta)).is_err() {
+                return Err(UpdateError::rejected(StatusCode::FORBIDDEN, "forbidden", Forbidden::MESSAGE));
+            }
+        }
+        Ok(None) => {
+            return Err(UpdateError::rejected(StatusCode::NOT_FOUND, "not_found", "❌ Mensaje no encontrado"));
+        }
+        Err(e) => return Err(UpdateError::Db(e)),
     }
 
-    match sqlx::query("UPDATE mensajes SET nombre=$1, mensaje=$2 WHERE id=$3")
-        .bind(&data.nombre)
-        .bind(&data.mensaje)
-        .bind(id)
-        .execute(&pool)
-        .await
-    {
-        Ok(_) => Html("✅ Mensaje actualizado correctamente"),
-        Err(_) => Html("❌ Error al actualizar mensaje"),
+    if !valid_nombre(&data.nombre) {
+        return Err(UpdateError::rejected(StatusCode::OK, "invalid_nombre", "❌ Nombre inválido"));
+    }
+
+    if !valid_mensaje(&data.mensaje) {
+        return Err(UpdateError::rejected(StatusCode::OK, "invalid_mensaje", "❌ Mensaje inválido"));
+    }
+
+    let assessment = content_rules::assess(&config.content, &data.mensaje);
+    if let Some(violation) = assessment.violation {
+        return Err(UpdateError::Rejected(StatusCode::OK, violation.into()));
+    }
+
+    // `old` es la fila antes del cambio, para la auditoría.
+    match queries::update_mensaje(pool, id, &data.nombre, &data.mensaje, assessment.score).await {
+        Ok(old) => {
+            let mut change = audit_log::Change::new("mensaje.update", format!("mensaje:{id}"))
+                .new_value(serde_json::json!({ "nombre": data.nombre, "mensaje": data.mensaje }));
+            if let Some((nombre, mensaje)) = old {
+                change = change.old(serde_json::json!({ "nombre": nombre, "mensaje": mensaje }));
+            }
+            audit_log::record_change(pool, principal, change).await;
+            Ok("✅ Mensaje actualizado correctamente")
+        }
+        Err(_) => Err(UpdateError::rejected(StatusCode::OK, "db_error", "❌ Error al actualizar mensaje")),
     }
 }
 
 /* ---------- SUBIR IMAGEN ---------- */
 
 async fn upload_image(
-    State(pool): State<PgPool>,
-    mut multipart: Multipart,
+    State(app): State<SharedState>,
+    principal: Principal,
+    Query(query): Query<UploadQuery>,
+    headers: HeaderMap,
+    multipart: Multipart,
 ) -> impl IntoResponse {
 
-    tokio::fs::create_dir_all("./uploads").await.unwrap();
+    let reporter = app.progress.reporter(query.upload_id);
+    let total = headers
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse().ok());
+
+    let uploader = Uploader::new(&principal);
+
+    match save_image(&app, &uploader, multipart, total, &reporter).await {
+        Ok(ids) => {
+            uploader.published(&app, ids);
+            reporter.done();
+            Html(uploader.uploaded_message()).into_response()
+        }
+        Err(UploadError::Invalid(msg)) => {
+            reporter.failed(msg);
+            Html(msg).into_response()
+        }
+        Err(UploadError::TooLarge(max)) => {
+            let msg = UploadError::too_large(max);
+            reporter.failed(&msg);
+            Html(msg).into_response()
+        }
+        Err(UploadError::TooManyPixels(limits)) => {
+            let msg = UploadError::too_many_pixels(limits);
+            reporter.failed(&msg);
+            Html(msg).into_response()
+        }
+        Err(UploadError::Quota(exceeded)) => {
+            reporter.failed(&exceeded.message());
+            exceeded.into_response()
+        }
+    }
+}
+
+/// Etapa de recepción de `upload_pipeline`: lee cada campo `file` con su
+/// tope y pasa el resto al pipeline.
+async fn save_image(
+    app: &AppState,
+    uploader: &Uploader,
+    mut multipart: Multipart,
+    total: Option<u64>,
+    reporter: &Reporter,
+) -> Result<Vec<i32>, UploadError> {
 
-    let mut file_saved = false;
+    let mut saved = Vec::new();
 
-    while let Some(field) = multipart.next_field().await.unwrap() {
+    while let Ok(Some(mut field)) = multipart.next_field().await {
 
         if field.name() != Some("file") {
             continue;
@@ -167,111 +963,3225 @@ async fn upload_image(
             .map(|m| m.to_string())
             .unwrap_or_default();
 
-        if !ALLOWED_MIME.contains(&mime.as_str()) {
-            return Html("❌ Tipo de archivo no permitido").into_response();
-        }
-
-        let bytes = field.bytes().await.unwrap();
-
-        if bytes.len() > MAX_IMAGE_SIZE {
-            return Html("❌ Imagen demasiado grande (máx 5MB)").into_response();
-        }
-
-        let extension = match mime.as_str() {
-            "image/jpeg" | "image/jpg" => "jpg",
-            "image/png" => "png",
-            "image/webp" => "webp",
-            _ => return Html("❌ Formato inválido").into_response(),
+        let Some(file_type) = app.config.uploads.types.for_mime(&mime) else {
+            return Err(UploadError::Invalid("❌ Tipo de archivo no permitido"));
         };
 
-        let filename = format!("{}.{}", Uuid::new_v4(), extension);
-        let path = format!("./uploads/{}", filename);
+        let raw_name = field.file_name().unwrap_or_default().to_string();
 
-        if let Ok(mut file) = tokio::fs::File::create(&path).await {
-            if file.write_all(&bytes).await.is_ok() {
-                let insert_result = sqlx::query("INSERT INTO images (filename) VALUES ($1)")
-                    .bind(&filename)
-                    .execute(&pool)
-                    .await;
+        let mut bytes = Vec::new();
+        reporter.receiving(0, total);
 
-                if insert_result.is_ok() {
-                    file_saved = true;
-                }
+        while let Some(chunk) = field
+            .chunk()
+            .await
+            .map_err(|_| UploadError::Invalid("❌ No se pudo guardar la imagen"))?
+        {
+            bytes.extend_from_slice(&chunk);
+
+            if bytes.len() > file_type.max_bytes {
+                return Err(UploadError::TooLarge(file_type.max_bytes));
             }
+            reporter.receiving(bytes.len() as u64, total);
         }
+
+        reporter.processing();
+        let upload = Upload::new(mime, raw_name, bytes);
+        saved.push(app.upload_pipeline.store(&app.db, &app.uploads, uploader, upload).await?);
     }
 
-    if file_saved {
-        Html("✅ Imagen subida correctamente").into_response()
+    if !saved.is_empty() {
+        Ok(saved)
     } else {
-        Html("❌ No se pudo guardar la imagen").into_response()
+        Err(UploadError::Invalid("❌ No se pudo guardar la imagen"))
+    }
+}
+
+/* ---------- SUBIR IMAGEN POR URL ---------- */
+
+#[derive(Deserialize)]
+struct UrlUpload {
+    url: String,
+}
+
+/// Solo con `UPLOAD_FROM_URL`: descarga la imagen (ver `remote_image`) y sigue
+/// el mismo camino que una subida directa.
+async fn upload_image_url(
+    State(app): State<SharedState>,
+    principal: Principal,
+    Form(form): Form<UrlUpload>,
+) -> impl IntoResponse {
+
+    // El máximo de cada tipo se comprueba después, en `upload_pipeline`.
+    let types = &app.config.uploads.types;
+    let limits = remote_image::Limits {
+        allowed_mime: &types.mimes(),
+        max_bytes: types.max_bytes(),
+        too_large: "❌ Imagen demasiado grande",
+        not_allowed: "❌ Tipo de archivo no permitido",
+        timeout: app.config.uploads.fetch_timeout,
+    };
+
+    let fetched = match remote_image::fetch(&form.url, &limits).await {
+        Ok(fetched) => fetched,
+        Err(msg) => return Html(msg).into_response(),
+    };
+
+    let uploader = Uploader::new(&principal);
+    let upload = Upload::new(fetched.mime, fetched.name, fetched.bytes);
+    let stored = app.upload_pipeline.store(&app.db, &app.uploads, &uploader, upload).await;
+
+    match stored {
+        Ok(id) => {
+            uploader.published(&app, [id]);
+            Html(uploader.uploaded_message()).into_response()
+        }
+        Err(UploadError::Invalid(msg)) => Html(msg).into_response(),
+        Err(UploadError::TooLarge(max)) => Html(UploadError::too_large(max)).into_response(),
+        Err(UploadError::TooManyPixels(limits)) => Html(UploadError::too_many_pixels(limits)).into_response(),
+        Err(UploadError::Quota(exceeded)) => exceeded.into_response(),
     }
 }
 
 /* ---------- LISTAR MENSAJES ---------- */
 
-async fn list_mensajes(State(pool): State<PgPool>) -> Json<Vec<Mensaje>> {
-    let rows = sqlx::query("SELECT id, nombre, mensaje FROM mensajes ORDER BY id DESC")
-        .fetch_all(&pool)
-        .await
-        .unwrap();
+/// Con `cursor` se sigue por id (más estable si entran mensajes nuevos); si no, por página.
+/// Quien pasa el límite de lectura recibe la última copia (ver `read_cache`).
+async fn list_mensajes(
+    State(app): State<SharedState>,
+    ClientIp(ip): ClientIp,
+    principal: Principal,
+    page: PageQuery,
+    Query(shape): Query<ListShape>,
+    Query(fields): Query<FieldsQuery>,
+) -> Response {
+    let full = shape.full.unwrap_or(true);
+    let selection = match Selection::parse(&fields, Mensaje::FIELDS) {
+        Ok(selection) => selection,
+        Err(e) => return e.into_response(),
+    };
+    let cursor = match page.cursor.as_deref().map(str::parse::<i32>) {
+        None => None,
+        Some(Ok(id)) => Some(id),
+        Some(Err(_)) => return (StatusCode::BAD_REQUEST, Html("❌ Cursor inválido")).into_response(),
+    };
+    let per_page = page.per_page();
+
+    // Tabla vacía y sin inserciones desde que se contó: nada que consultar.
+    if app.mensajes_empty.is_empty() {
+        return Json(Paginated::<Mensaje>::new(Vec::new(), 0, &page)).into_response();
+    }
+
+    let key = format!("{}:{per_page}:{cursor:?}:{full}:{}", page.page(), selection.key());
+    // Las claves de API no pasan por el límite suave de lectura.
+    let ip = ip.filter(|_| !matches!(principal, Principal::ApiKey { .. }));
+    if let Some(cached) = app.mensajes_cache.throttled(ip, &key) {
+        return cached;
+    }
+
+    let ticket = app.mensajes_empty.ticket();
+    let total = match queries::count_mensajes(&app.db).await {
+        Ok(total) => total,
+        Err(e) => return e.into_response(),
+    };
+    app.mensajes_empty.observed(ticket, total);
 
-    let data = rows
+    // Se pide una fila de más para saber si hay siguiente. `id` va siempre: de
+    // él sale el cursor.
+    let columns = selection.columns(&["id"]);
+    let rows = match queries::list_mensajes(&app.db, &columns, cursor, per_page + 1, page.offset()).await {
+        Ok(rows) => rows,
+        Err(e) => return e.into_response(),
+    };
+
+    let mut data: Vec<Mensaje> = rows
         .into_iter()
-        .map(|r| Mensaje {
-            id: r.get("id"),
-            nombre: r.get("nombre"),
-            mensaje: r.get("mensaje"),
-        })
+        .map(|r| Mensaje::from_row(&r, app.config.content.excerpt_chars, full))
         .collect();
 
-    Json(data)
+    let more = data.len() as i64 > per_page;
+    data.truncate(per_page as usize);
+    let next_cursor = data.last().filter(|_| more).map(|m| m.id.to_string());
+
+    let data: Vec<_> = data.iter().map(|m| selection.project(m)).collect();
+    let body = Paginated::new(data, total, &page).with_cursor(next_cursor);
+    match server_timing::render(|| serde_json::to_vec(&body)) {
+        Ok(body) => app.mensajes_cache.store(key, body),
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    }
 }
 
-#[derive(Serialize)]
-struct Image {
-    id: i32,
-    filename: String,
+/* ---------- PANEL DE ADMINISTRACIÓN ---------- */
+
+#[derive(Deserialize)]
+struct AdminMensajesQuery {
+    #[serde(default)]
+    q: String,
+    #[serde(default)]
+    sort: MensajeSort,
 }
 
-async fn list_images(State(pool): State<PgPool>) -> Json<Vec<Image>> {
-    let rows = sqlx::query("SELECT id, filename FROM images ORDER BY id DESC")
-        .fetch_all(&pool)
-        .await
-        .unwrap();
+#[derive(Deserialize, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum MensajeSort {
+    /// Los más nuevos primero.
+    #[default]
+    Id,
+    /// Alfabético con la colación del idioma (`SORT_LANGUAGE`).
+    Nombre,
+}
 
-    let images = rows
+impl MensajeSort {
+    fn order_by(self, collation: &str) -> String {
+        match self {
+            MensajeSort::Id => "id DESC".to_string(),
+            MensajeSort::Nombre => format!(r#"nombre COLLATE "{collation}" ASC, id DESC"#),
+        }
+    }
+}
+
+/// Tabla paginada de mensajes. Con `HX-Request` devuelve solo el fragmento;
+/// sin él, una página completa que también funciona sin JavaScript.
+async fn admin_mensajes(
+    State(app): State<SharedState>,
+    Query(query): Query<AdminMensajesQuery>,
+    page: PageQuery,
+    headers: HeaderMap,
+) -> Result<Html<String>, DbError> {
+    let q = query.q.trim();
+    let total = queries::count_admin_mensajes(&app.db, q).await?;
+
+    let per_page = page.per_page();
+    let pages = pagination::total_pages(total, per_page);
+    let page = page.page().min(pages);
+
+    let order_by = query.sort.order_by(&app.config.content.collation);
+    let rows = queries::admin_mensajes(&app.db, q, &order_by, per_page, (page - 1) * per_page).await?;
+
+    let table = html::MensajesTable {
+        rows: &rows,
+        page,
+        pages,
+        q,
+        by_name: query.sort == MensajeSort::Nombre,
+    };
+
+    Ok(Html(server_timing::render(|| {
+        let fragment = html::mensajes_table(&table);
+        if headers.contains_key("hx-request") {
+            fragment
+        } else {
+            html::admin_page(&app.setup.site(), "Mensajes", &fragment)
+        }
+    })))
+}
+
+/* ---------- DETALLE ---------- */
+
+/// El mensaje con el cuerpo completo, que los listados con `?full=false` omiten.
+async fn get_mensaje(State(app): State<SharedState>, Path(id): Path<i32>) -> Response {
+    match queries::get_mensaje(&app.db, id).await {
+        Ok(Some(row)) => Json(Mensaje::from_row(&row, app.config.content.excerpt_chars, true)).into_response(),
+        Ok(None) => (StatusCode::NOT_FOUND, Html("❌ Mensaje no encontrado")).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+/* ---------- PERMALINK ---------- */
+
+async fn view_mensaje(
+    State(app): State<SharedState>,
+    Path(id): Path<i32>,
+    zone: Zone,
+    headers: HeaderMap,
+) -> Response {
+    let row = match queries::view_mensaje(&app.db, id).await {
+        Ok(Some(row)) => row,
+        Ok(None) => return (StatusCode::NOT_FOUND, Html("❌ Mensaje no encontrado")).into_response(),
+        Err(e) => return e.into_response(),
+    };
+    let created_at = match zone.localize_one(&app.db, row.created_at).await {
+        Ok(created_at) => created_at,
+        Err(e) => return e.into_response(),
+    };
+
+    // Sin relacionados la página sigue sirviendo.
+    let related: Vec<html::MensajeRow> = fetch_related(&app.db, id, RELATED_ON_PAGE, app.config.content.excerpt_chars)
+        .await
+        .unwrap_or_default()
         .into_iter()
-        .map(|r| Image {
-            id: r.get("id"),
-            filename: r.get("filename"),
+        .map(|m| html::MensajeRow {
+            id: m.id,
+            nombre: m.nombre,
+            mensaje: m.mensaje.unwrap_or(m.excerpt),
+            verified: m.verified,
         })
         .collect();
 
-    Json(images)
+    let base_url = html::base_url(app.config.server.public_url.as_deref(), &headers);
+    let site = app.setup.site();
+    // Sin aviso la página sigue sirviendo.
+    let announcement = app.settings.announcement(&app.db).await.ok().flatten();
+    let page = html::MensajePage {
+        id,
+        nombre: &row.nombre,
+        mensaje: &row.mensaje,
+        created_at,
+        base_url: &base_url,
+        verified: row.verified,
+        related: &related,
+        site: &site,
+        announcement: announcement.as_ref().map(|a| a.message.as_str()),
+    };
+
+    Html(server_timing::render(|| html::mensaje_page(&page))).into_response()
 }
 
-/* ---------- DELETE ---------- */
+/* ---------- RELACIONADOS ---------- */
 
-async fn delete_mensaje(
-    State(pool): State<PgPool>,
+const RELATED_ON_PAGE: i64 = 3;
+const RELATED_DEFAULT: i64 = 5;
+const RELATED_MAX: i64 = 20;
+
+#[derive(Deserialize)]
+struct RelatedQuery {
+    limit: Option<i64>,
+}
+
+async fn related_mensajes(
+    State(app): State<SharedState>,
     Path(id): Path<i32>,
-) -> impl IntoResponse {
-    match sqlx::query("DELETE FROM mensajes WHERE id = $1")
-        .bind(id)
-        .execute(&pool)
-        .await
-    {
-        Ok(_) => Html("✅ Mensaje eliminado"),
-        Err(_) => Html("❌ Error al eliminar"),
+    Query(query): Query<RelatedQuery>,
+) -> Response {
+    match queries::mensaje_exists(&app.db, id).await {
+        Ok(true) => {}
+        Ok(false) => return (StatusCode::NOT_FOUND, Html("❌ Mensaje no encontrado")).into_response(),
+        Err(e) => return e.into_response(),
+    }
+
+    let limit = query.limit.unwrap_or(RELATED_DEFAULT).clamp(1, RELATED_MAX);
+    match fetch_related(&app.db, id, limit, app.config.content.excerpt_chars).await {
+        Ok(related) => Json(related).into_response(),
+        Err(e) => e.into_response(),
     }
 }
 
-/* ---------- UTIL ---------- */
+/// Ver `queries::related_mensajes`.
+async fn fetch_related(pool: &PgPool, id: i32, limit: i64, excerpt_chars: usize) -> Result<Vec<Mensaje>, DbError> {
+    let rows = queries::related_mensajes(pool, id, limit).await?;
 
-fn sanitize_text(text: &mut String) {
-    let forbidden = ["<", ">", "\"", "'", ";", "--", "script"];
-    for f in forbidden {
-        *text = text.replace(f, "");
-    }
-}
\ No newline at end of file
+    Ok(rows
+        .into_iter()
+        .map(|r| Mensaje::from_row(&r, excerpt_chars, true))
+        .collect())
+}
+
+#[derive(Serialize)]
+struct Image {
+    id: i32,
+    filename: String,
+    original_name: Option<String>,
+    size_bytes: Option<i64>,
+    created_at: DateTime<Utc>,
+}
+
+impl Image {
+    /// Campos que admite `?fields=` en `GET /images`.
+    const FIELDS: &[fields::Field] = &[
+        ("id", "id"),
+        ("filename", "filename"),
+        ("original_name", "original_name"),
+        ("size_bytes", "size_bytes"),
+        ("created_at", "created_at"),
+    ];
+}
+
+/// Criterios de orden admitidos; cualquier otro valor es un 400.
+#[derive(Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+enum ImageSort {
+    #[default]
+    Date,
+    Size,
+    Filename,
+}
+
+impl ImageSort {
+    fn order_by(self) -> &'static str {
+        match self {
+            ImageSort::Date => "created_at DESC, id DESC",
+            ImageSort::Size => "size_bytes DESC NULLS LAST, id DESC",
+            ImageSort::Filename => "filename ASC",
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+enum ImageType {
+    Png,
+    #[serde(alias = "jpeg")]
+    Jpg,
+    Webp,
+}
+
+impl ImageType {
+    fn extension(self) -> &'static str {
+        match self {
+            ImageType::Png => "png",
+            ImageType::Jpg => "jpg",
+            ImageType::Webp => "webp",
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ImageQuery {
+    #[serde(default)]
+    sort: ImageSort,
+    #[serde(rename = "type")]
+    kind: Option<ImageType>,
+    /// Fechas de subida (`YYYY-MM-DD`), ambas incluidas.
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+}
+
+async fn list_images(
+    State(app): State<SharedState>,
+    Query(query): Query<ImageQuery>,
+    page: PageQuery,
+    Query(fields): Query<FieldsQuery>,
+) -> Response {
+    let selection = match Selection::parse(&fields, Image::FIELDS) {
+        Ok(selection) => selection,
+        Err(e) => return e.into_response(),
+    };
+    let filter = queries::ImageFilter {
+        extension: query.kind.map(ImageType::extension),
+        from: query.from,
+        to: query.to,
+    };
+
+    let total = match queries::count_images(&app.db, &filter).await {
+        Ok(total) => total,
+        Err(e) => return e.into_response(),
+    };
+
+    let columns = selection.columns(&[]);
+    let list = queries::list_images(&app.db, &filter, &columns, query.sort.order_by(), page.per_page(), page.offset());
+    let rows = match list.await {
+        Ok(rows) => rows,
+        Err(e) => return e.into_response(),
+    };
+
+    let images = rows
+        .into_iter()
+        .map(|r| Image {
+            id: r.try_get("id").unwrap_or_default(),
+            filename: r.try_get("filename").unwrap_or_default(),
+            original_name: r.try_get("original_name").unwrap_or_default(),
+            size_bytes: r.try_get("size_bytes").unwrap_or_default(),
+            created_at: r.try_get("created_at").unwrap_or_default(),
+        })
+        .map(|image| selection.project(&image))
+        .collect();
+
+    Json(Paginated::new(images, total, &page)).into_response()
+}
+
+/* ---------- PAPELERA DE IMÁGENES ---------- */
+
+async fn delete_image(
+    State(app): State<SharedState>,
+    principal: Principal,
+    Path(id): Path<i32>,
+    Query(dry): Query<DryRunQuery>,
+) -> Response {
+    if let Err(e) = policy::authorize(&principal, Action::Delete, &Resource::Image) {
+        return e.into_response();
+    }
+
+    if dry.dry_run {
+        return match queries::trashable_image(&app.db, id).await {
+            Ok(Some(id)) => Preview::new(1, vec![id]).into_response(),
+            Ok(None) => (StatusCode::NOT_FOUND, Html("❌ Imagen no encontrada")).into_response(),
+            Err(e) => e.into_response(),
+        };
+    }
+
+    // Las pendientes no pasan por la papelera: se rechazan.
+    let filename = match queries::trash_image(&app.db, id).await {
+        Ok(Some(filename)) => filename,
+        Ok(None) => return (StatusCode::NOT_FOUND, Html("❌ Imagen no encontrada")).into_response(),
+        Err(e) => return e.into_response(),
+    };
+
+    if let Err(err) = trash::move_to_trash(&app.uploads, &filename).await {
+        tracing::warn!(error = %err, filename, "no se pudo mover la imagen a la papelera");
+    }
+    let change = audit_log::Change::new("image.trash", format!("image:{id}")).old(serde_json::json!({ "filename": filename }));
+    audit_log::record_change(&app.db, &principal, change).await;
+
+    Html("✅ Imagen movida a la papelera").into_response()
+}
+
+async fn restore_image(
+    State(app): State<SharedState>,
+    principal: Principal,
+    Path(id): Path<i32>,
+    Query(dry): Query<DryRunQuery>,
+) -> Response {
+    if let Err(e) = policy::authorize(&principal, Action::Restore, &Resource::Image) {
+        return e.into_response();
+    }
+
+    let retention = app.config.uploads.trash_retention;
+    if dry.dry_run {
+        return match queries::restorable_image(&app.db, id, retention).await {
+            Ok(Some(id)) => Preview::new(1, vec![id]).into_response(),
+            Ok(None) => (StatusCode::NOT_FOUND, Html("❌ Imagen no encontrada en la papelera")).into_response(),
+            Err(e) => e.into_response(),
+        };
+    }
+
+    let filename = match queries::restore_image(&app.db, id, retention).await {
+        Ok(Some(filename)) => filename,
+        Ok(None) => {
+            return (StatusCode::NOT_FOUND, Html("❌ Imagen no encontrada en la papelera")).into_response()
+        }
+        Err(e) => return e.into_response(),
+    };
+
+    if let Err(err) = trash::move_from_trash(&app.uploads, &filename).await {
+        tracing::warn!(error = %err, filename, "no se pudo sacar la imagen de la papelera");
+    }
+    let change =
+        audit_log::Change::new("image.restore", format!("image:{id}")).new_value(serde_json::json!({ "filename": filename }));
+    audit_log::record_change(&app.db, &principal, change).await;
+
+    Html("✅ Imagen restaurada").into_response()
+}
+
+/* ---------- DELETE ---------- */
+
+async fn delete_mensaje(
+    State(app): State<SharedState>,
+    principal: Principal,
+    Path(id): Path<i32>,
+    Query(dry): Query<DryRunQuery>,
+) -> Response {
+    match queries::mensaje_meta(&app.db, id).await {
+        Ok(Some(meta)) => {
+            if let Err(e) = policy::authorize(&principal, Action::Delete, &Resource::Mensaje(&meta)) {
+                return e.into_response();
+            }
+        }
+        Ok(None) => return (StatusCode::NOT_FOUND, Html("❌ Mensaje no encontrado")).into_response(),
+        Err(e) => return e.into_response(),
+    }
+    if dry.dry_run {
+        return Preview::new(1, vec![id]).into_response();
+    }
+
+    let deleted = async {
+        let mut uow = UnitOfWork::begin(&app.db).await?;
+        let old = queries::delete_mensaje(uow.conn(), id).await?;
+        if old.is_some() {
+            outbox::enqueue(uow.conn(), &Event::MessageDeleted { id }).await?;
+        }
+        uow.commit().await?;
+        Ok::<_, DbError>(old)
+    };
+    match deleted.await {
+        Ok(old) => {
+            let mut change = audit_log::Change::new("mensaje.delete", format!("mensaje:{id}"));
+            if let Some((nombre, mensaje)) = old {
+                change = change.old(serde_json::json!({ "nombre": nombre, "mensaje": mensaje }));
+            }
+            audit_log::record_change(&app.db, &principal, change).await;
+            events::publish(&app, Event::MessageDeleted { id });
+            Html("✅ Mensaje eliminado").into_response()
+        }
+        Err(_) => Html("❌ Error al eliminar").into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{Method, StatusCode};
+
+    use crate::test_support::{
+        self, as_admin, form, from_ip, image_bytes, send, MultipartBuilder, TestDb,
+    };
+
+    fn valid_message() -> [(&'static str, &'static str); 3] {
+        [
+            ("nombre", "Ana García"),
+            ("mensaje", "Un mensaje de prueba suficientemente largo"),
+            ("g-recaptcha-response", "token"),
+        ]
+    }
+
+    #[tokio::test]
+    async fn enviar_then_list() {
+        let Some(db) = TestDb::new().await else { return };
+        let app = db.app();
+
+        let (_, body) = send(&app, form(Method::POST, "/enviar", &valid_message())).await;
+        assert!(body.contains("✅"), "{body}");
+
+        let (status, body) = send(&app, test_support::get("/mensajes")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("Ana García"));
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn mensajes_paginate_by_page_and_cursor() {
+        let Some(db) = TestDb::new().await else { return };
+        let app = db.app();
+
+        sqlx::query(
+            "INSERT INTO mensajes (nombre, mensaje)
+             SELECT 'Visitante ' || i, 'Mensaje número ' || i FROM generate_series(1, 5) AS i",
+        )
+        .execute(&db.pool)
+        .await
+        .unwrap();
+        let page = |uri: &str| {
+            let app = app.clone();
+            let uri = uri.to_string();
+            async move {
+                let (_, body) = send(&app, test_support::get(&uri)).await;
+                serde_json::from_str::<serde_json::Value>(&body).unwrap()
+            }
+        };
+        let names = |page: &serde_json::Value| -> Vec<String> {
+            let data = page["data"].as_array().unwrap();
+            data.iter().map(|m| m["nombre"].as_str().unwrap().to_string()).collect()
+        };
+
+        let first = page("/mensajes?per_page=2").await;
+        assert_eq!((first["total"].as_i64(), first["total_pages"].as_i64()), (Some(5), Some(3)));
+        assert_eq!(names(&first), ["Visitante 5", "Visitante 4"]);
+
+        let cursor = first["next_cursor"].as_str().unwrap();
+        let second = page(&format!("/mensajes?per_page=2&cursor={cursor}")).await;
+        assert_eq!(names(&second), ["Visitante 3", "Visitante 2"]);
+        assert_eq!(names(&page("/mensajes?per_page=2&page=2").await), names(&second));
+
+        let last = page("/mensajes?per_page=2&page=3").await;
+        assert_eq!(names(&last), ["Visitante 1"]);
+        assert!(last["next_cursor"].is_null());
+
+        let (status, _) = send(&app, test_support::get("/mensajes?cursor=abc")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn page_sizes_are_configured_per_route() {
+        let Some(db) = TestDb::with_config(&[("PAGE_SIZES", "/mensajes=2:3, /admin/mensajes=10")]).await else {
+            return;
+        };
+        let app = db.app();
+
+        sqlx::query(
+            "INSERT INTO mensajes (nombre, mensaje)
+             SELECT 'Visitante ' || i, 'Mensaje número ' || i FROM generate_series(1, 12) AS i",
+        )
+        .execute(&db.pool)
+        .await
+        .unwrap();
+        let per_page = |body: &str| serde_json::from_str::<serde_json::Value>(body).unwrap()["per_page"].as_i64();
+
+        let (_, body) = send(&app, test_support::get("/mensajes")).await;
+        assert_eq!(per_page(&body), Some(2));
+        let (_, body) = send(&app, test_support::get("/mensajes?per_page=50")).await;
+        assert_eq!(per_page(&body), Some(3));
+
+        // Las rutas sin entrada siguen con los valores generales.
+        let (_, body) = send(&app, test_support::get("/images")).await;
+        assert_eq!(per_page(&body), Some(crate::pagination::DEFAULT_PER_PAGE));
+
+        let mut req = as_admin(test_support::get("/admin/mensajes"));
+        req.headers_mut().insert("hx-request", "true".parse().unwrap());
+        let (_, body) = send(&app, req).await;
+        assert!(body.contains("Página 1 de 2"), "{body}");
+        assert_eq!(body.matches("<tr>").count(), 1 + 10);
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn admin_table_sorts_names_with_the_language_collation() {
+        let Some(db) = TestDb::new().await else { return };
+        let app = db.app();
+
+        sqlx::query(
+            "INSERT INTO mensajes (nombre, mensaje)
+             SELECT n, 'Hola' FROM unnest(ARRAY['Zoe', 'Ñandú', 'Nora', 'Ángel', 'Bea', 'alba']) AS n",
+        )
+        .execute(&db.pool)
+        .await
+        .unwrap();
+
+        let (status, _) = send(&app, as_admin(test_support::get("/admin/mensajes?sort=nombre;DROP"))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        // Las colaciones ICU solo existen en bases UTF8.
+        let encoding: String = sqlx::query_scalar("SHOW server_encoding").fetch_one(&db.pool).await.unwrap();
+        if encoding != "UTF8" {
+            db.finish().await;
+            return;
+        }
+        let mut req = as_admin(test_support::get("/admin/mensajes?sort=nombre"));
+        req.headers_mut().insert("hx-request", "true".parse().unwrap());
+        let (_, body) = send(&app, req).await;
+        let names: Vec<&str> = body
+            .split(r#"<td class="name-cell">"#)
+            .skip(1)
+            .map(|cell| cell.split('<').next().unwrap())
+            .collect();
+        assert_eq!(names, ["alba", "Ángel", "Bea", "Nora", "Ñandú", "Zoe"]);
+        assert!(body.contains("Nombre ▲"), "{body}");
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn spammy_messages_wait_in_quarantine() {
+        let Some(db) = TestDb::with_config(&[("SPAM_KEYWORDS", "casino, viagra"), ("SPAM_QUARANTINE_SCORE", "50")]).await
+        else {
+            return;
+        };
+        let app = db.app();
+        let quarantined = || async {
+            let (_, body) = send(&app, as_admin(test_support::get("/api/admin/mensajes/quarantine"))).await;
+            serde_json::from_str::<serde_json::Value>(&body).unwrap()
+        };
+
+        let spam = [
+            ("nombre", "Ana García"),
+            ("mensaje", "Visitad el casino online ahora!!!!!"),
+            ("g-recaptcha-response", "token"),
+        ];
+        let (_, body) = send(&app, from_ip(form(Method::POST, "/enviar", &spam), "10.0.0.5")).await;
+        assert!(body.contains("se publicará cuando se revise"), "{body}");
+        let (_, body) = send(&app, test_support::get("/mensajes")).await;
+        assert!(!body.contains("casino"), "{body}");
+
+        // Cada envío reciente de la misma IP suma: el cuarto ya no se publica.
+        for _ in 0..3 {
+            let (_, body) = send(&app, from_ip(form(Method::POST, "/enviar", &valid_message()), "10.0.0.6")).await;
+            assert!(body.contains("enviado correctamente"), "{body}");
+        }
+        send(&app, from_ip(form(Method::POST, "/enviar", &valid_message()), "10.0.0.6")).await;
+
+        let held = quarantined().await;
+        assert_eq!(held["total"], 2);
+        let id_of = |text: &str| {
+            let held = held["data"].as_array().unwrap();
+            held.iter().find(|m| m["mensaje"] == text).unwrap()["id"].as_i64().unwrap()
+        };
+        let ids = [id_of("Visitad el casino online ahora!!!!!"), id_of(valid_message()[1].1)];
+
+        let release = format!("/api/admin/mensajes/quarantine/{}/release", ids[0]);
+        let (status, _) = send(&app, as_admin(form(Method::POST, &release, &[]))).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, body) = send(&app, test_support::get(&format!("/mensajes/{}", ids[0]))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("casino"), "{body}");
+
+        let discard = format!("/api/admin/mensajes/quarantine/{}", ids[1]);
+        let (status, _) = send(&app, as_admin(form(Method::DELETE, &discard, &[]))).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = send(&app, as_admin(form(Method::DELETE, &discard, &[]))).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(quarantined().await["total"], 0);
+
+        db.finish().await;
+    }
+
+    /// Akismet de mentira: spam si el mensaje habla de "viagra"; guarda los `submit-ham`.
+    async fn fake_akismet() -> (String, std::sync::Arc<std::sync::Mutex<Vec<String>>>) {
+        use axum::{routing::post, Form, Router};
+        use std::collections::HashMap;
+
+        let ham = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let reported = ham.clone();
+        let akismet = Router::new()
+            .route(
+                "/1.1/comment-check",
+                post(|Form(f): Form<HashMap<String, String>>| async move {
+                    assert_eq!((f["api_key"].as_str(), f["blog"].as_str()), ("clave", "https://motos.example"));
+                    f["comment_content"].contains("viagra").to_string()
+                }),
+            )
+            .route(
+                "/1.1/submit-ham",
+                post(move |Form(f): Form<HashMap<String, String>>| async move {
+                    reported.lock().unwrap().push(f["comment_content"].clone());
+                    "Thanks for making the web a better place."
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, akismet).await });
+        (url, ham)
+    }
+
+    #[tokio::test]
+    async fn akismet_spam_is_held_and_released_as_ham() {
+        let (url, ham) = fake_akismet().await;
+        let overrides = [("AKISMET_API_KEY", "clave"), ("AKISMET_SITE", "https://motos.example"), ("AKISMET_URL", url.as_str())];
+        let Some(db) = TestDb::with_config(&overrides).await else { return };
+        let app = db.app();
+
+        let spam = [("nombre", "Ana García"), ("mensaje", "Oferta de viagra barata"), ("g-recaptcha-response", "token")];
+        let (_, body) = send(&app, from_ip(form(Method::POST, "/enviar", &spam), "10.0.0.5")).await;
+        assert!(body.contains("se publicará cuando se revise"), "{body}");
+        let (_, body) = send(&app, from_ip(form(Method::POST, "/enviar", &valid_message()), "10.0.0.6")).await;
+        assert!(body.contains("enviado correctamente"), "{body}");
+
+        let (_, body) = send(&app, as_admin(test_support::get("/api/admin/mensajes/quarantine"))).await;
+        let held: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(held["total"], 1);
+        assert_eq!(held["data"][0]["akismet_spam"], true);
+
+        let release = format!("/api/admin/mensajes/quarantine/{}/release", held["data"][0]["id"]);
+        let (status, _) = send(&app, as_admin(form(Method::POST, &release, &[]))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(*ham.lock().unwrap(), ["Oferta de viagra barata"]);
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn honeypot_submissions_are_discarded_and_logged() {
+        let Some(db) = TestDb::new().await else { return };
+        let app = db.app();
+
+        let mut fields = valid_message().to_vec();
+        fields.push(("website", "http://spam.example"));
+        let mut req = from_ip(form(Method::POST, "/enviar", &fields), "10.0.0.9");
+        req.headers_mut().insert("user-agent", "spambot/1.0".parse().unwrap());
+        let (status, body) = send(&app, req).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, "✅ Mensaje enviado correctamente");
+
+        let saved: i64 = sqlx::query_scalar("SELECT count(*) FROM mensajes").fetch_one(&db.pool).await.unwrap();
+        assert_eq!(saved, 0);
+
+        let (status, _) = send(&app, test_support::get("/api/admin/spam")).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (_, body) = send(&app, as_admin(test_support::get("/api/admin/spam"))).await;
+        let log: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(log["total"], 1);
+        let entry = &log["data"][0];
+        assert_eq!((entry["reason"].as_str(), entry["ip"].as_str()), (Some("honeypot"), Some("10.0.0.9")));
+        assert_eq!(entry["user_agent"], "spambot/1.0");
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn preview_renders_and_validates_without_saving() {
+        let Some(db) = TestDb::new().await else { return };
+        let app = db.app();
+
+        let fields = [("nombre", "Ana María"), ("mensaje", "Precio de la <b>R6</b> & extras, por favor")];
+        let (status, body) = send(&app, form(Method::POST, "/mensajes/preview", &fields)).await;
+        assert_eq!(status, StatusCode::OK);
+        let preview: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(preview["nombre"], "Ana María");
+        // Escapado como al publicar.
+        assert_eq!(preview["html"], "<p>Precio de la &lt;b&gt;R6&lt;/b&gt; &amp; extras, por favor</p>");
+        assert_eq!(preview["errors"], serde_json::json!([]));
+
+        let fields = [("nombre", ""), ("mensaje", "Un mensaje de prueba suficientemente largo"), ("email", "no")];
+        let (_, body) = send(&app, form(Method::POST, "/mensajes/preview", &fields)).await;
+        let preview: serde_json::Value = serde_json::from_str(&body).unwrap();
+        let codes: Vec<&str> = preview["errors"].as_array().unwrap().iter().map(|e| e["code"].as_str().unwrap()).collect();
+        assert_eq!(codes, ["invalid_nombre", "invalid_email"]);
+
+        let saved: i64 = sqlx::query_scalar("SELECT count(*) FROM mensajes").fetch_one(&db.pool).await.unwrap();
+        assert_eq!(saved, 0);
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn text_is_stored_as_written_and_escaped_on_output() {
+        let Some(db) = TestDb::new().await else { return };
+        let app = db.app();
+
+        let mensaje = "La description dice <script>alert('hola');</script> -- ojo";
+        let fields = [("nombre", "Ana García"), ("mensaje", mensaje), ("g-recaptcha-response", "token")];
+        let (_, body) = send(&app, form(Method::POST, "/enviar", &fields)).await;
+        assert!(body.contains("✅"), "{body}");
+
+        let (id, stored): (i32, String) =
+            sqlx::query_as("SELECT id, mensaje FROM mensajes").fetch_one(&db.pool).await.unwrap();
+        assert_eq!(stored, mensaje);
+
+        let (_, body) = send(&app, test_support::get(&format!("/mensajes/{id}/view"))).await;
+        assert!(body.contains("La description dice &lt;script&gt;alert(&#39;hola&#39;);&lt;/script&gt; -- ojo"), "{body}");
+        assert!(!body.contains("<script>alert"));
+
+        let (status, body) = send(&app, test_support::get("/mensajes?fields=%3Cimg%3E")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.contains("&lt;img&gt;") && !body.contains("<img>"), "{body}");
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn browser_form_redirects_with_flash() {
+        use tower::ServiceExt;
+
+        let Some(db) = TestDb::new().await else { return };
+        let app = db.app();
+        let browser = |fields: &[(&str, &str)]| {
+            let mut req = form(Method::POST, "/enviar", fields);
+            req.headers_mut().insert("accept", "text/html,*/*".parse().unwrap());
+            req.headers_mut().insert("referer", "http://localhost/contacto.html".parse().unwrap());
+            req
+        };
+
+        let res = app.clone().oneshot(browser(&valid_message())).await.unwrap();
+        assert_eq!(res.status(), StatusCode::SEE_OTHER);
+        assert_eq!(res.headers()["location"], "/");
+        let cookie = res.headers()["set-cookie"].to_str().unwrap();
+        assert!(cookie.starts_with("flash=success|"), "{cookie}");
+
+        let fields = [("nombre", "Ana"), ("mensaje", "corto"), ("g-recaptcha-response", "t")];
+        let res = app.clone().oneshot(browser(&fields)).await.unwrap();
+        assert_eq!(res.headers()["location"], "/contacto.html");
+        assert!(res.headers()["set-cookie"].to_str().unwrap().starts_with("flash=error|"));
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn browser_edit_redirects_instead_of_resubmitting() {
+        use tower::ServiceExt;
+
+        let Some(db) = TestDb::new().await else { return };
+        let app = db.app();
+
+        send(&app, form(Method::POST, "/enviar", &valid_message())).await;
+        let id: i32 = sqlx::query_scalar("SELECT id FROM mensajes").fetch_one(&db.pool).await.unwrap();
+        let browser = |fields: &[(&str, &str)]| {
+            let mut req = as_admin(form(Method::PUT, &format!("/api/admin/mensajes/{id}"), fields));
+            req.headers_mut().insert("accept", "text/html".parse().unwrap());
+            req
+        };
+
+        let edit = [("nombre", "Ana García"), ("mensaje", "Texto corregido desde el panel")];
+        let res = app.clone().oneshot(browser(&edit)).await.unwrap();
+        assert_eq!(res.status(), StatusCode::SEE_OTHER);
+        assert_eq!(res.headers()["location"], "/admin.html");
+        assert!(res.headers()["set-cookie"].to_str().unwrap().starts_with("flash=success|"));
+
+        let res = app.clone().oneshot(browser(&[("nombre", "Ana"), ("mensaje", "x")])).await.unwrap();
+        assert_eq!(res.status(), StatusCode::SEE_OTHER);
+        assert!(res.headers()["set-cookie"].to_str().unwrap().starts_with("flash=error|"));
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn link_spam_is_rejected_with_code() {
+        let Some(db) = TestDb::with_config(&[("CONTENT_MAX_LINKS", "1")]).await else { return };
+        let app = db.app();
+
+        let fields = [
+            ("nombre", "Ana García"),
+            ("mensaje", "Ofertas en https://a.example y https://b.example"),
+            ("g-recaptcha-response", "token"),
+        ];
+        let res = tower::ServiceExt::oneshot(app.clone(), form(Method::POST, "/enviar", &fields))
+            .await
+            .unwrap();
+        assert_eq!(res.headers()["x-error-code"], "too_many_links");
+
+        let fields = [("mensaje", "Solo una: https://a.example"), fields[0], fields[2]];
+        let (_, body) = send(&app, form(Method::POST, "/enviar", &fields)).await;
+        assert!(body.contains("✅"), "{body}");
+        let score: i32 = sqlx::query_scalar("SELECT spam_score FROM mensajes").fetch_one(&db.pool).await.unwrap();
+        assert!(score > 0);
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn daily_cap_counts_name_and_ip() {
+        let Some(db) = TestDb::with_config(&[("MESSAGES_DAILY_PER_AUTHOR", "2")]).await else { return };
+        let app = db.app();
+        let post = |ip| from_ip(form(Method::POST, "/enviar", &valid_message()), ip);
+
+        for ip in ["10.0.0.1", "10.0.0.2"] {
+            let (status, _) = send(&app, post(ip)).await;
+            assert_eq!(status, StatusCode::OK);
+        }
+
+        // Mismo nombre desde otra IP: cuenta igual.
+        let res = tower::ServiceExt::oneshot(app.clone(), post("10.0.0.3")).await.unwrap();
+        assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(res.headers().contains_key("retry-after"));
+        assert_eq!(res.headers()["x-error-code"], "daily_cap");
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn writes_are_rate_limited_per_ip() {
+        use tower::ServiceExt;
+
+        let overrides = [("WRITE_RATE_BURST", "2"), ("WRITE_RATE_PER_MINUTE", "1")];
+        let Some(db) = TestDb::with_config(&overrides).await else { return };
+        let app = db.app();
+        let post = |ip| from_ip(form(Method::POST, "/enviar", &[("nombre", "Ana")]), ip);
+
+        for _ in 0..2 {
+            let (status, _) = send(&app, post("10.0.0.1")).await;
+            assert_ne!(status, StatusCode::TOO_MANY_REQUESTS);
+        }
+        let res = app.clone().oneshot(post("10.0.0.1")).await.unwrap();
+        assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+        let wait: u64 = res.headers()["retry-after"].to_str().unwrap().parse().unwrap();
+        assert!((1..=60).contains(&wait), "{wait}");
+
+        // Las subidas comparten el bucket; otra IP va aparte.
+        let upload = MultipartBuilder::new()
+            .file("file", "moto.png", "image/png", &image_bytes("png", 64))
+            .into_request("/upload-image");
+        let (status, _) = send(&app, from_ip(upload, "10.0.0.1")).await;
+        assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+        let (status, _) = send(&app, post("10.0.0.2")).await;
+        assert_ne!(status, StatusCode::TOO_MANY_REQUESTS);
+
+        db.finish().await;
+    }
+
+    /// `siteverify` de mentira: "humano" pasa, "bot" saca poca puntuación, el resto falla.
+    async fn fake_siteverify() -> String {
+        use axum::{routing::post, Form, Json, Router};
+        use std::collections::HashMap;
+
+        let siteverify = Router::new().route(
+            "/siteverify",
+            post(|Form(f): Form<HashMap<String, String>>| async move {
+                assert_eq!(f["secret"], "secreto");
+                let reply = match f["response"].as_str() {
+                    "humano" => serde_json::json!({ "success": true, "score": 0.9 }),
+                    "bot" => serde_json::json!({ "success": true, "score": 0.1 }),
+                    _ => serde_json::json!({ "success": false, "error-codes": ["invalid-input-response"] }),
+                };
+                Json(reply)
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/siteverify", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, siteverify).await });
+        url
+    }
+
+    #[tokio::test]
+    async fn recaptcha_is_verified_against_siteverify() {
+        use crate::ERROR_CODE;
+
+        let url = fake_siteverify().await;
+        let overrides = [("RECAPTCHA_SECRET", "secreto"), ("RECAPTCHA_VERIFY_URL", url.as_str())];
+        let Some(db) = TestDb::with_config(&overrides).await else { return };
+        let app = db.app();
+        let post = |token| {
+            let [nombre, mensaje, _] = valid_message();
+            let fields = [nombre, mensaje, ("g-recaptcha-response", token)];
+            tower::ServiceExt::oneshot(app.clone(), form(Method::POST, "/enviar", &fields))
+        };
+
+        for (token, code) in [("falso", "recaptcha_failed"), ("bot", "recaptcha_failed")] {
+            let res = post(token).await.unwrap();
+            assert_eq!(res.headers()[ERROR_CODE], code, "{token}");
+        }
+        let res = post("humano").await.unwrap();
+        assert!(!res.headers().contains_key(ERROR_CODE));
+        let count: i64 = sqlx::query_scalar("SELECT count(*) FROM mensajes").fetch_one(&db.pool).await.unwrap();
+        assert_eq!(count, 1);
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn captcha_provider_is_chosen_by_config() {
+        use crate::ERROR_CODE;
+
+        let url = fake_siteverify().await;
+        let overrides = [
+            ("CAPTCHA_PROVIDER", "turnstile"),
+            ("CAPTCHA_SECRET", "secreto"),
+            ("CAPTCHA_SITE_KEY", "0x4AAA"),
+            ("CAPTCHA_VERIFY_URL", url.as_str()),
+        ];
+        let Some(db) = TestDb::with_config(&overrides).await else { return };
+        let app = db.app();
+        let post = |field| {
+            let [nombre, mensaje, _] = valid_message();
+            tower::ServiceExt::oneshot(app.clone(), form(Method::POST, "/enviar", &[nombre, mensaje, (field, "humano")]))
+        };
+
+        let (_, body) = send(&app, test_support::get("/captcha")).await;
+        let widget: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(widget["provider"], "turnstile");
+        assert_eq!(widget["site_key"], "0x4AAA");
+        assert_eq!(widget["field"], "cf-turnstile-response");
+
+        let res = post("g-recaptcha-response").await.unwrap();
+        assert_eq!(res.headers()[ERROR_CODE], "recaptcha_missing");
+        let res = post("cf-turnstile-response").await.unwrap();
+        assert!(!res.headers().contains_key(ERROR_CODE));
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn listings_carry_excerpts_and_full_body_is_optional() {
+        let Some(db) = TestDb::with_config(&[("EXCERPT_CHARS", "12")]).await else { return };
+        let app = db.app();
+        let id: i32 = sqlx::query_scalar(
+            "INSERT INTO mensajes (nombre, mensaje) VALUES ('Ana', 'Vendo moto   clásica en buen estado') RETURNING id",
+        )
+        .fetch_one(&db.pool)
+        .await
+        .unwrap();
+
+        let (_, body) = send(&app, test_support::get("/mensajes")).await;
+        let listing: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(listing["data"][0]["excerpt"], "Vendo moto…");
+        assert_eq!(listing["data"][0]["mensaje"], "Vendo moto   clásica en buen estado");
+
+        let (_, body) = send(&app, test_support::get("/mensajes?full=false")).await;
+        let listing: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(listing["data"][0]["excerpt"], "Vendo moto…");
+        assert!(listing["data"][0].get("mensaje").is_none(), "{body}");
+
+        let (_, body) = send(&app, test_support::get(&format!("/mensajes/{id}"))).await;
+        let detail: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(detail["mensaje"], "Vendo moto   clásica en buen estado");
+        let (status, _) = send(&app, test_support::get(&format!("/mensajes/{}", id + 1))).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn listings_return_only_requested_fields() {
+        let Some(db) = TestDb::new().await else { return };
+        let app = db.app();
+        sqlx::query("INSERT INTO mensajes (nombre, mensaje) VALUES ('Ana', 'Vendo moto clásica'), ('Luis', 'Compro moto antigua')")
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO images (filename, size_bytes, original_name, approved_at) VALUES ('a.png', 10, 'a.png', now())")
+            .execute(&db.pool)
+            .await
+            .unwrap();
+
+        let (_, body) = send(&app, test_support::get("/mensajes?fields=nombre&per_page=1")).await;
+        let listing: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(listing["data"][0], serde_json::json!({"nombre": "Luis"}));
+        // El cursor sigue saliendo del id aunque no se pida.
+        assert!(listing["next_cursor"].is_string(), "{body}");
+
+        let (_, body) = send(&app, test_support::get("/mensajes?fields=id,excerpt")).await;
+        let listing: serde_json::Value = serde_json::from_str(&body).unwrap();
+        let keys: Vec<_> = listing["data"][0].as_object().unwrap().keys().cloned().collect();
+        assert_eq!(keys, ["excerpt", "id"]);
+
+        let (_, body) = send(&app, test_support::get("/images?fields=filename")).await;
+        let listing: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(listing["data"][0], serde_json::json!({"filename": "a.png"}));
+
+        let (status, body) = send(&app, test_support::get("/mensajes?fields=nombre,email")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.contains("email"));
+        let (status, _) = send(&app, test_support::get("/images?fields=path")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn json_keys_follow_configured_or_requested_case() {
+        let Some(db) = TestDb::with_config(&[("JSON_CASE", "camel")]).await else { return };
+        let app = db.app();
+        sqlx::query("INSERT INTO images (filename, size_bytes, original_name, approved_at) VALUES ('a.png', 10, 'a.png', now())")
+            .execute(&db.pool)
+            .await
+            .unwrap();
+
+        let (_, body) = send(&app, test_support::get("/images")).await;
+        let listing: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(listing["totalPages"], 1);
+        assert_eq!(listing["data"][0]["sizeBytes"], 10);
+        assert!(listing.get("total_pages").is_none(), "{body}");
+
+        let mut req = test_support::get("/images");
+        req.headers_mut().insert("x-json-case", "snake".parse().unwrap());
+        let (_, body) = send(&app, req).await;
+        let listing: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(listing["data"][0]["original_name"], "a.png");
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn security_headers_are_set_and_configurable() {
+        use tower::ServiceExt;
+
+        let config = [("HEADER_X_FRAME_OPTIONS", "SAMEORIGIN"), ("HEADER_STRICT_TRANSPORT_SECURITY", "")];
+        let Some(db) = TestDb::with_config(&config).await else { return };
+        let app = db.app();
+
+        for uri in ["/", "/mensajes", "/no-existe"] {
+            let res = app.clone().oneshot(test_support::get(uri)).await.unwrap();
+            let headers = res.headers();
+            assert!(headers["content-security-policy"].to_str().unwrap().contains("frame-ancestors 'none'"));
+            assert_eq!(headers["x-content-type-options"], "nosniff");
+            assert_eq!(headers["referrer-policy"], "strict-origin-when-cross-origin");
+            assert_eq!(headers["x-frame-options"], "SAMEORIGIN", "{uri}");
+            assert!(!headers.contains_key("strict-transport-security"), "{uri}");
+        }
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn csp_nonce_marks_each_page_and_its_scripts() {
+        use tower::ServiceExt;
+
+        let Some(db) = TestDb::with_config(&[("CSP_NONCE", "true")]).await else { return };
+        let app = db.app();
+
+        let page = |uri: &'static str| {
+            let app = app.clone();
+            async move {
+                let res = app.oneshot(test_support::get(uri)).await.unwrap();
+                let csp = res.headers()["content-security-policy"].to_str().unwrap().to_string();
+                let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+                (csp, String::from_utf8(body.to_vec()).unwrap())
+            }
+        };
+
+        let (csp, body) = page("/").await;
+        let nonce = csp.split("'nonce-").nth(1).unwrap().split('\'').next().unwrap().to_string();
+        assert!(csp.contains("frame-ancestors 'none'"));
+        assert!(!csp.split(';').any(|d| d.trim().starts_with("script-src") && d.contains("'unsafe-inline'")), "{csp}");
+        assert!(csp.contains("style-src-attr 'unsafe-inline'"));
+        // Los de la página y el que añade `csrf::inject`.
+        assert_eq!(body.matches("<script").count(), body.matches(&format!(r#"<script nonce="{nonce}""#)).count());
+        assert!(body.contains(&format!(r#"<script nonce="{nonce}" src="/js/csrf.js">"#)));
+
+        let (again, _) = page("/").await;
+        assert_ne!(csp, again, "un nonce por petición");
+
+        // Lo que no es HTML conserva la política de siempre.
+        let (csp, _) = page("/mensajes").await;
+        assert!(!csp.contains("'nonce-"));
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn options_and_preflight_follow_registered_methods() {
+        use tower::ServiceExt;
+
+        let Some(db) = TestDb::new().await else { return };
+        let app = db.app();
+        let options = |uri: &str, method: Option<&str>| {
+            let mut req = test_support::get(uri);
+            *req.method_mut() = Method::OPTIONS;
+            if let Some(method) = method {
+                req.headers_mut().insert("origin", "https://app.example".parse().unwrap());
+                req.headers_mut().insert("access-control-request-method", method.parse().unwrap());
+            }
+            req
+        };
+
+        let res = app.clone().oneshot(options("/mensajes", None)).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+        assert_eq!(res.headers()["allow"], "GET,HEAD,OPTIONS");
+
+        let res = app.clone().oneshot(options("/mensajes/1", Some("PUT"))).await.unwrap();
+        assert!(res.status().is_success());
+        let methods = res.headers()["access-control-allow-methods"].to_str().unwrap();
+        assert!(methods.contains("PUT") && methods.contains("DELETE") && !methods.contains("POST"), "{methods}");
+        assert_eq!(res.headers()["access-control-max-age"], "600");
+        assert_eq!(res.headers()["access-control-allow-origin"], "*");
+
+        let res = app.clone().oneshot(options("/mensajes", Some("DELETE"))).await.unwrap();
+        assert_eq!(res.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert!(!res.headers().contains_key("access-control-allow-origin"));
+
+        // Las rutas de administración responden al preflight sin credenciales.
+        let res = app.clone().oneshot(options("/api/admin/users", Some("POST"))).await.unwrap();
+        assert!(res.status().is_success(), "{}", res.status());
+        let (status, _) = send(&app, options("/api/admin/users", None)).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn cors_policy_is_limited_by_config() {
+        use tower::ServiceExt;
+
+        let config = [
+            ("CORS_ORIGINS", "https://app.example"),
+            ("CORS_METHODS", "GET,PUT"),
+            ("CORS_HEADERS", "content-type,x-api-key"),
+            ("CORS_CREDENTIALS", "true"),
+        ];
+        let Some(db) = TestDb::with_config(&config).await else { return };
+        let app = db.app();
+        let preflight = |origin: &str, method: &str| {
+            let mut req = test_support::get("/mensajes/1");
+            *req.method_mut() = Method::OPTIONS;
+            req.headers_mut().insert("origin", origin.parse().unwrap());
+            req.headers_mut().insert("access-control-request-method", method.parse().unwrap());
+            req
+        };
+
+        let res = app.clone().oneshot(preflight("https://app.example", "PUT")).await.unwrap();
+        let headers = res.headers();
+        assert_eq!(headers["access-control-allow-origin"], "https://app.example");
+        assert_eq!(headers["access-control-allow-credentials"], "true");
+        assert_eq!(headers["access-control-allow-methods"], "GET,PUT");
+        assert_eq!(headers["access-control-allow-headers"], "content-type,x-api-key");
+
+        // DELETE existe en la ruta pero la política no lo admite.
+        let res = app.clone().oneshot(preflight("https://app.example", "DELETE")).await.unwrap();
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+        let res = app.clone().oneshot(preflight("https://otro.example", "PUT")).await.unwrap();
+        assert!(!res.headers().contains_key("access-control-allow-origin"));
+
+        let mut req = test_support::get("/mensajes");
+        req.headers_mut().insert("origin", "https://app.example".parse().unwrap());
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(res.headers()["access-control-allow-origin"], "https://app.example");
+
+        db.finish().await;
+    }
+
+    #[test]
+    #[should_panic(expected = "necesitan PUBLIC_URL")]
+    fn email_links_need_a_public_url() {
+        test_support::test_config("postgres://localhost/hola", &[("EMAIL_CONFIRMATION_REQUIRED", "true")]);
+    }
+
+    #[test]
+    #[should_panic(expected = "CORS_CREDENTIALS=true necesita CORS_ORIGINS")]
+    fn cors_credentials_need_an_origin_list() {
+        test_support::test_config("postgres://localhost/hola", &[("CORS_CREDENTIALS", "true"), ("CORS_ORIGINS", "*")]);
+    }
+
+    #[tokio::test]
+    async fn head_returns_validators_without_body() {
+        use axum::body::to_bytes;
+        use tower::ServiceExt;
+
+        let Some(db) = TestDb::new().await else { return };
+        let app = db.app();
+        sqlx::query("INSERT INTO mensajes (nombre, mensaje) VALUES ('Ana', 'Vendo moto clásica')")
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        let mut png = std::io::Cursor::new(Vec::new());
+        image::RgbImage::new(40, 30).write_to(&mut png, image::ImageFormat::Png).unwrap();
+        let req = MultipartBuilder::new()
+            .file("file", "foto.png", "image/png", png.get_ref())
+            .into_request("/upload-image");
+        send(&app, as_admin(req)).await;
+        let id: i32 = sqlx::query_scalar("SELECT id FROM images").fetch_one(&db.pool).await.unwrap();
+        let head = |uri: &str| {
+            let mut req = test_support::get(uri);
+            *req.method_mut() = Method::HEAD;
+            req
+        };
+
+        for uri in ["/mensajes".to_string(), "/images".to_string(), format!("/images/{id}/thumb")] {
+            let get = app.clone().oneshot(test_support::get(&uri)).await.unwrap();
+            let etag = get.headers()["etag"].clone();
+            let body = to_bytes(get.into_body(), usize::MAX).await.unwrap();
+
+            let res = app.clone().oneshot(head(&uri)).await.unwrap();
+            assert_eq!(res.status(), StatusCode::OK, "{uri}");
+            assert_eq!(res.headers()["etag"], etag, "{uri}");
+            assert_eq!(res.headers()["content-length"], body.len().to_string().as_str(), "{uri}");
+            assert!(to_bytes(res.into_body(), usize::MAX).await.unwrap().is_empty());
+
+            let mut req = head(&uri);
+            req.headers_mut().insert("if-none-match", etag.clone());
+            let res = app.clone().oneshot(req).await.unwrap();
+            assert_eq!(res.status(), StatusCode::NOT_MODIFIED, "{uri}");
+        }
+
+        let res = app.clone().oneshot(head(&format!("/images/{id}/thumb"))).await.unwrap();
+        let modified = res.headers()["last-modified"].clone();
+        let mut req = test_support::get(&format!("/images/{id}/thumb"));
+        req.headers_mut().insert("if-modified-since", modified);
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn registered_authors_own_their_messages() {
+        use tower::ServiceExt;
+
+        let Some(db) = TestDb::new().await else { return };
+        let app = db.app();
+        let csrf = "0123456789abcdef0123456789abcdef";
+        // Con cookie de sesión es un navegador: lleva también el token CSRF.
+        let signed = |mut req: axum::http::Request<axum::body::Body>, session: &str| {
+            req.headers_mut().insert(axum::http::header::COOKIE, format!("{session}; csrf_token={csrf}").parse().unwrap());
+            req.headers_mut().insert("x-csrf-token", csrf.parse().unwrap());
+            req
+        };
+        let register = |username: &str| {
+            let (app, username) = (app.clone(), username.to_string());
+            async move {
+                let fields = [("username", username.as_str()), ("password", "una contraseña larga")];
+                let res = app.oneshot(form(Method::POST, "/cuenta/registro", &fields)).await.unwrap();
+                assert_eq!(res.status(), StatusCode::CREATED);
+                let set_cookie = res.headers()[axum::http::header::SET_COOKIE].to_str().unwrap();
+                set_cookie.split(';').next().unwrap().to_string()
+            }
+        };
+
+        let lucia = register("lucia").await;
+        let stored: String = sqlx::query_scalar("SELECT password_hash FROM users WHERE username = 'lucia'")
+            .fetch_one(&db.pool)
+            .await
+            .unwrap();
+        assert!(stored.starts_with("$argon2id$"), "{stored}");
+        let fields = [("username", "Lucia"), ("password", "una contraseña larga")];
+        let (status, _) = send(&app, form(Method::POST, "/cuenta/registro", &fields)).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+
+        let (status, _) = send(&app, form(Method::POST, "/cuenta/login", &[("username", "lucia"), ("password", "otra cosa distinta")])).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = send(&app, form(Method::POST, "/cuenta/login", &fields)).await;
+        assert_eq!(status, StatusCode::OK);
+        // Una cuenta de autor no abre el panel.
+        let (status, _) = send(&app, form(Method::POST, "/admin/login", &fields)).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let (_, body) = send(&app, signed(form(Method::POST, "/enviar", &valid_message()), &lucia)).await;
+        assert!(body.contains("✅"), "{body}");
+        let (id, owner): (i32, Option<i32>) = sqlx::query_as("SELECT id, user_id FROM mensajes")
+            .fetch_one(&db.pool)
+            .await
+            .unwrap();
+        let lucia_id: i32 = sqlx::query_scalar("SELECT id FROM users WHERE username = 'lucia'")
+            .fetch_one(&db.pool)
+            .await
+            .unwrap();
+        assert_eq!(owner, Some(lucia_id));
+
+        let edit = [("nombre", "Lucía"), ("mensaje", "Texto corregido por su autora")];
+        let uri = format!("/mensajes/{id}");
+        let pablo = register("pablo").await;
+        let (status, _) = send(&app, signed(form(Method::PUT, &uri, &edit), &pablo)).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        // Sin sesión tampoco: el mensaje es de la cuenta.
+        let (status, _) = send(&app, form(Method::PUT, &uri, &edit)).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (_, body) = send(&app, signed(form(Method::PUT, &uri, &edit), &lucia)).await;
+        assert!(body.contains("✅"), "{body}");
+
+        send(&app, signed(form(Method::POST, "/cuenta/logout", &[]), &lucia)).await;
+        let (status, _) = send(&app, signed(form(Method::DELETE, &uri, &[]), &lucia)).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn admin_login_locks_out_after_repeated_failures() {
+        use tower::ServiceExt;
+
+        let config = [("LOGIN_LOCKOUT_THRESHOLD", "2"), ("LOGIN_LOCKOUT_BASE_SECS", "60")];
+        let Some(db) = TestDb::with_config(&config).await else { return };
+        let app = db.app();
+        let fields = [("username", "marta"), ("password", "una contraseña larga"), ("role", "viewer")];
+        send(&app, as_admin(form(Method::POST, "/api/admin/users", &fields))).await;
+        let login = |username: &str, password: &str, ip: &str| {
+            from_ip(form(Method::POST, "/admin/login", &[("username", username), ("password", password)]), ip)
+        };
+
+        let (status, _) = send(&app, login("marta", "no es la buena", "10.0.0.1")).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let res = app.clone().oneshot(login("marta", "tampoco es la buena", "10.0.0.1")).await.unwrap();
+        assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(res.headers()["retry-after"], "60");
+
+        // Bloqueada la IP y también el usuario, aunque la contraseña sea buena.
+        let res = app.clone().oneshot(login("marta", "una contraseña larga", "10.0.0.1")).await.unwrap();
+        assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+        let (status, body) = send(&app, login("marta", "una contraseña larga", "10.0.0.2")).await;
+        assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+        assert!(body.contains("Inténtalo de nuevo en"), "{body}");
+        let (status, _) = send(&app, login("otra", "lo que sea aquí", "10.0.0.3")).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        // Pasado el bloqueo, un acceso correcto pone la cuenta a cero.
+        sqlx::query("UPDATE login_failures SET locked_until = now() - interval '1 second'")
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        let (status, _) = send(&app, login("marta", "una contraseña larga", "10.0.0.1")).await;
+        assert_eq!(status, StatusCode::OK);
+        let left: i64 = sqlx::query_scalar("SELECT count(*) FROM login_failures WHERE key LIKE '%marta' OR key = 'ip:10.0.0.1'")
+            .fetch_one(&db.pool)
+            .await
+            .unwrap();
+        assert_eq!(left, 0);
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn enviar_rejects_short_message() {
+        let Some(db) = TestDb::new().await else { return };
+        let app = db.app();
+
+        let fields = [("nombre", "Ana"), ("mensaje", "corto"), ("g-recaptcha-response", "t")];
+        let (_, body) = send(&app, form(Method::POST, "/enviar", &fields)).await;
+        assert!(body.contains("Mensaje inválido"));
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn admin_api_requires_token() {
+        let Some(db) = TestDb::new().await else { return };
+        let app = db.app();
+
+        send(&app, form(Method::POST, "/enviar", &valid_message())).await;
+        let id: i32 = sqlx::query_scalar("SELECT id FROM mensajes").fetch_one(&db.pool).await.unwrap();
+        let uri = format!("/api/admin/mensajes/{id}");
+
+        let (status, _) = send(&app, form(Method::DELETE, &uri, &[])).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let (status, _) = send(&app, as_admin(form(Method::DELETE, &uri, &[]))).await;
+        assert_eq!(status, StatusCode::OK);
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn only_author_can_edit() {
+        use axum::http::header;
+
+        let Some(db) = TestDb::new().await else { return };
+        let app = db.app();
+
+        let post = from_ip(form(Method::POST, "/enviar", &valid_message()), "10.0.0.1");
+        let res = tower::ServiceExt::oneshot(app.clone(), post).await.unwrap();
+        let id: i32 = sqlx::query_scalar("SELECT id FROM mensajes").fetch_one(&db.pool).await.unwrap();
+        let cookie = res.headers()[header::SET_COOKIE].to_str().unwrap();
+        assert!(cookie.contains(&format!("Path=/mensajes/{id};")) && cookie.contains("HttpOnly"), "{cookie}");
+        let token = cookie.split(';').next().unwrap().to_string();
+        let uri = format!("/mensajes/{id}");
+        let edit = [("nombre", "Ana García"), ("mensaje", "Texto corregido por la autora")];
+        // Desde el navegador, así que con CSRF como cualquier formulario con cookie.
+        let csrf = "0123456789abcdef0123456789abcdef";
+        let with_token = |ip, token: &str| {
+            let mut req = from_ip(form(Method::PUT, &uri, &edit), ip);
+            req.headers_mut().insert(header::COOKIE, format!("{token}; csrf_token={csrf}").parse().unwrap());
+            req.headers_mut().insert("x-csrf-token", csrf.parse().unwrap());
+            req
+        };
+
+        // Ni la misma IP ni un token inventado bastan.
+        let (status, _) = send(&app, from_ip(form(Method::PUT, &uri, &edit), "10.0.0.1")).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = send(&app, with_token("10.0.0.1", "edit_token=inventado")).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        // El token sí, aunque cambie la IP.
+        let (status, _) = send(&app, with_token("10.0.0.2", &token)).await;
+        assert_eq!(status, StatusCode::OK);
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn db_stats_list_tables_and_indexes() {
+        let Some(db) = TestDb::new().await else { return };
+        let app = db.app();
+
+        let (status, _) = send(&app, test_support::get("/admin/db")).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let (status, body) = send(&app, as_admin(test_support::get("/admin/db"))).await;
+        assert_eq!(status, StatusCode::OK);
+        let stats: serde_json::Value = serde_json::from_str(&body).unwrap();
+        let tables: Vec<&str> = stats["tables"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| t["name"].as_str().unwrap())
+            .collect();
+        assert!(tables.contains(&"mensajes") && tables.contains(&"images"), "{tables:?}");
+        assert!(stats["indexes"].as_array().unwrap().iter().any(|i| i["name"] == "mensajes_pkey"));
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn admin_session_cookie_unlocks_panel_and_mutations() {
+        use axum::http::header;
+        use tower::ServiceExt;
+
+        let Some(db) = TestDb::new().await else { return };
+        let app = db.app();
+        let with_cookie = |mut req: axum::http::Request<axum::body::Body>, cookie: &str| {
+            req.headers_mut().insert(header::COOKIE, cookie.parse().unwrap());
+            req
+        };
+
+        let res = app.clone().oneshot(test_support::get("/admin.html")).await.unwrap();
+        assert_eq!(res.status(), StatusCode::SEE_OTHER);
+        assert_eq!(res.headers()[header::LOCATION], "/admin/login");
+
+        let (status, _) = send(&app, form(Method::POST, "/admin/login", &[("token", "otro")])).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let login = form(Method::POST, "/admin/login", &[("token", test_support::ADMIN_TOKEN)]);
+        let res = app.clone().oneshot(login).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let set_cookie = res.headers()[header::SET_COOKIE].to_str().unwrap().to_string();
+        for attr in ["HttpOnly", "Secure", "SameSite=Strict"] {
+            assert!(set_cookie.contains(attr), "{set_cookie}");
+        }
+        let cookie = set_cookie.split(';').next().unwrap().to_string();
+        // En la base solo queda el hash del token de la cookie.
+        let token = cookie.strip_prefix("admin_session=").unwrap();
+        let stored: String = sqlx::query_scalar("SELECT token_hash FROM admin_sessions").fetch_one(&db.pool).await.unwrap();
+        assert_eq!(stored, crate::edit_token::hash(token));
+
+        let (status, body) = send(&app, with_cookie(test_support::get("/admin.html"), &cookie)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("Panel de Administración"));
+
+        let id: i32 = sqlx::query_scalar("INSERT INTO mensajes (nombre, mensaje) VALUES ('Ana', 'hola') RETURNING id")
+            .fetch_one(&db.pool)
+            .await
+            .unwrap();
+        // Con cookie es un formulario de navegador: lleva también el token CSRF.
+        let csrf = "0123456789abcdef0123456789abcdef";
+        let mut delete = with_cookie(form(Method::DELETE, &format!("/mensajes/{id}"), &[]), &format!("{cookie}; csrf_token={csrf}"));
+        delete.headers_mut().insert("x-csrf-token", csrf.parse().unwrap());
+        let (_, body) = send(&app, delete).await;
+        assert!(body.contains("✅"), "{body}");
+
+        send(&app, with_cookie(form(Method::POST, "/admin/logout", &[]), &cookie)).await;
+        let (status, _) = send(&app, with_cookie(test_support::get("/admin/mensajes"), &cookie)).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn user_roles_limit_admin_route_groups() {
+        use axum::http::header;
+        use tower::ServiceExt;
+
+        let Some(db) = TestDb::new().await else { return };
+        let app = db.app();
+        let with_cookie = |mut req: axum::http::Request<axum::body::Body>, cookie: &str| {
+            req.headers_mut().insert(header::COOKIE, cookie.parse().unwrap());
+            req
+        };
+
+        let mut ids = Vec::new();
+        for (username, role) in [("mod", "moderator"), ("vista", "viewer")] {
+            let fields = [("username", username), ("password", "contraseña-larga"), ("role", role)];
+            let (status, body) = send(&app, as_admin(form(Method::POST, "/api/admin/users", &fields))).await;
+            assert_eq!(status, StatusCode::CREATED, "{body}");
+            ids.push(serde_json::from_str::<serde_json::Value>(&body).unwrap()["id"].as_i64().unwrap());
+        }
+        let fields = [("username", "MOD"), ("password", "contraseña-larga"), ("role", "admin")];
+        let (status, _) = send(&app, as_admin(form(Method::POST, "/api/admin/users", &fields))).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        let roles: Vec<String> =
+            sqlx::query_scalar("SELECT new_value->>'role' FROM audit_log WHERE action = 'user.create' ORDER BY id")
+                .fetch_all(&db.pool)
+                .await
+                .unwrap();
+        assert_eq!(roles, ["moderator", "viewer"]);
+
+        let login = |username: &str, password: &str| {
+            form(Method::POST, "/admin/login", &[("username", username), ("password", password)])
+        };
+        let (status, _) = send(&app, login("mod", "otra-contraseña")).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let mut cookies = Vec::new();
+        for username in ["mod", "vista"] {
+            let res = app.clone().oneshot(login(username, "contraseña-larga")).await.unwrap();
+            assert_eq!(res.status(), StatusCode::OK);
+            let set_cookie = res.headers()[header::SET_COOKIE].to_str().unwrap();
+            cookies.push(set_cookie.split(';').next().unwrap().to_string());
+        }
+        let (moderator, viewer) = (&cookies[0], &cookies[1]);
+
+        let id: i32 = sqlx::query_scalar("INSERT INTO mensajes (nombre, mensaje) VALUES ('Ana', 'hola') RETURNING id")
+            .fetch_one(&db.pool)
+            .await
+            .unwrap();
+        let delete = || form(Method::DELETE, &format!("/api/admin/mensajes/{id}"), &[]);
+
+        let (status, _) = send(&app, with_cookie(test_support::get("/admin/mensajes"), viewer)).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = send(&app, with_cookie(delete(), viewer)).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        let (status, _) = send(&app, with_cookie(test_support::get("/api/admin/images/pending"), moderator)).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = send(&app, with_cookie(form(Method::POST, "/api/admin/images/1/approve", &[]), moderator)).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = send(&app, with_cookie(test_support::get("/api/admin/users"), moderator)).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, body) = send(&app, with_cookie(delete(), moderator)).await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+
+        let uri = format!("/api/admin/users/{}/role", ids[1]);
+        let (status, _) = send(&app, as_admin(form(Method::POST, &uri, &[("role", "moderator")]))).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = send(&app, with_cookie(test_support::get("/api/admin/users"), viewer)).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        let (status, _) = send(&app, as_admin(form(Method::DELETE, &format!("/api/admin/users/{}", ids[0]), &[]))).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = send(&app, with_cookie(test_support::get("/admin/mensajes"), moderator)).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = send(&app, login("mod", "contraseña-larga")).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn first_run_setup_creates_admin_once() {
+        use axum::http::header;
+        use tower::ServiceExt;
+
+        let Some(db) = TestDb::new().await else { return };
+        let metrics = std::sync::Arc::new(crate::metrics::Metrics::new(&db.config.metrics));
+        let state = crate::state::AppState::new(db.pool.clone(), db.jobs.clone(), db.config.clone(), db.uploads.clone(), metrics);
+        state.setup.load(&db.pool).await.unwrap();
+        let app = crate::build_routers(&state, &None).0;
+        let code = state.setup.code().expect("base de datos vacía: configuración pendiente");
+        let setup = |code: &str, language: &str| {
+            let fields = [
+                ("code", code),
+                ("username", "dueña"),
+                ("password", "contraseña-larga"),
+                ("title", "Motos Ana"),
+                ("language", language),
+            ];
+            form(Method::POST, "/setup", &fields)
+        };
+
+        let (status, body) = send(&app, test_support::get("/setup")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains(r#"name="code""#), "{body}");
+        let (status, _) = send(&app, setup("otro", "es")).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = send(&app, setup(&code, "klingon")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let res = app.clone().oneshot(setup(&code, "en")).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let set_cookie = res.headers()[header::SET_COOKIE].to_str().unwrap();
+        let mut req = test_support::get("/api/admin/users");
+        req.headers_mut().insert(header::COOKIE, set_cookie.split(';').next().unwrap().parse().unwrap());
+        let (status, body) = send(&app, req).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains(r#""role":"admin""#), "{body}");
+
+        let (_, body) = send(&app, test_support::get("/admin/login")).await;
+        assert!(body.contains(r#"<html lang="en">"#) && body.contains("Acceso | Motos Ana"), "{body}");
+
+        // Completada, desaparece; y en el siguiente arranque ya no se pide.
+        for req in [test_support::get("/setup"), setup(&code, "es")] {
+            let (status, _) = send(&app, req).await;
+            assert_eq!(status, StatusCode::NOT_FOUND);
+        }
+        let restarted = crate::setup::Setup::new();
+        restarted.load(&db.pool).await.unwrap();
+        assert!(restarted.code().is_none());
+        assert_eq!(restarted.site().title, "Motos Ana");
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn announcement_is_managed_by_admins_and_shown_publicly() {
+        let Some(db) = TestDb::new().await else { return };
+        let app = db.app();
+        let put = |fields: &[(&str, &str)]| as_admin(form(Method::PUT, "/admin/announcement", fields));
+
+        let (status, _) = send(&app, test_support::get("/announcement")).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        let (status, _) = send(&app, form(Method::PUT, "/admin/announcement", &[("message", "Cerrado")])).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = send(&app, put(&[("message", "Cerrado"), ("expires_at", "mañana")])).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (_, body) = send(&app, put(&[("message", "Cerrado por <vacaciones>")])).await;
+        assert!(body.contains("✅"), "{body}");
+        let (_, body) = send(&app, test_support::get("/announcement")).await;
+        assert!(body.contains("Cerrado por <vacaciones>"), "{body}");
+
+        let id: i32 = sqlx::query_scalar("INSERT INTO mensajes (nombre, mensaje) VALUES ('Ana', 'hola') RETURNING id")
+            .fetch_one(&db.pool)
+            .await
+            .unwrap();
+        let (_, body) = send(&app, test_support::get(&format!("/mensajes/{id}/view"))).await;
+        assert!(body.contains("Cerrado por &lt;vacaciones&gt;"), "{body}");
+
+        // Caducado deja de verse en público, pero administración lo sigue viendo.
+        send(&app, put(&[("message", "Oferta"), ("expires_at", "2020-01-01T00:00:00Z")])).await;
+        let (status, _) = send(&app, test_support::get("/announcement")).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        let (_, body) = send(&app, as_admin(test_support::get("/admin/announcement"))).await;
+        assert!(body.contains(r#""message":"Oferta""#) && body.contains(r#""active":false"#), "{body}");
+
+        send(&app, put(&[("message", "")])).await;
+        let (status, _) = send(&app, as_admin(test_support::get("/admin/announcement"))).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn announcement_is_cached_until_it_changes() {
+        let Some(db) = TestDb::new().await else { return };
+        let app = db.app();
+        let put = |message: &str| as_admin(form(Method::PUT, "/admin/announcement", &[("message", message)]));
+
+        send(&app, put("Abierto el sábado")).await;
+        let (_, body) = send(&app, test_support::get("/announcement")).await;
+        assert!(body.contains("Abierto el sábado"), "{body}");
+
+        // Un cambio por fuera de la API no se ve hasta que caduca la copia...
+        sqlx::query("UPDATE announcement SET message = 'Cambiado a mano'").execute(&db.pool).await.unwrap();
+        let (_, body) = send(&app, test_support::get("/announcement")).await;
+        assert!(body.contains("Abierto el sábado"), "{body}");
+
+        // ...pero uno por la API invalida la copia al momento.
+        send(&app, put("Cerrado el domingo")).await;
+        let (_, body) = send(&app, test_support::get("/announcement")).await;
+        assert!(body.contains("Cerrado el domingo"), "{body}");
+
+        let (_, metrics) = send(&app, test_support::get("/metrics")).await;
+        assert!(metrics.contains(r#"cache_requests_total{cache="settings",result="hit"} 1"#), "{metrics}");
+        assert!(metrics.contains(r#"cache_requests_total{cache="settings",result="miss"} 2"#), "{metrics}");
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn browser_forms_need_the_csrf_token_from_the_page() {
+        use axum::http::header;
+        use tower::ServiceExt;
+
+        let Some(db) = TestDb::new().await else { return };
+        let app = db.app();
+
+        let res = app.clone().oneshot(test_support::get("/contacto.html")).await.unwrap();
+        assert_eq!(res.headers()[header::CACHE_CONTROL], "no-store");
+        let set_cookie = res.headers()[header::SET_COOKIE].to_str().unwrap();
+        let cookie = set_cookie.split(';').next().unwrap().to_string();
+        let token = cookie.strip_prefix("csrf_token=").unwrap().to_string();
+        let body = String::from_utf8(axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap().to_vec()).unwrap();
+        assert!(body.contains(&format!(r#"<meta name="csrf-token" content="{token}">"#)), "{body}");
+
+        // Con la cookie ya puesta, la página repite el mismo token.
+        let mut again = test_support::get("/index.html");
+        again.headers_mut().insert(header::COOKIE, cookie.parse().unwrap());
+        let (_, body) = send(&app, again).await;
+        assert!(body.contains(&token));
+
+        let enviar = |extra: Option<(&str, &str)>| {
+            let mut fields = valid_message().to_vec();
+            fields.extend(extra);
+            let mut req = form(Method::POST, "/enviar", &fields);
+            req.headers_mut().insert(header::ORIGIN, "https://otro.example".parse().unwrap());
+            req.headers_mut().insert(header::COOKIE, cookie.parse().unwrap());
+            req
+        };
+        for forged in [None, Some(("csrf_token", "ffffffffffffffffffffffffffffffff"))] {
+            let (status, _) = send(&app, enviar(forged)).await;
+            assert_eq!(status, StatusCode::FORBIDDEN);
+        }
+        let (_, body) = send(&app, enviar(Some(("csrf_token", token.as_str())))).await;
+        assert!(body.contains("✅"), "{body}");
+
+        // Un script sin cookies no es víctima de CSRF.
+        let (_, body) = send(&app, form(Method::POST, "/enviar", &valid_message())).await;
+        assert!(!body.contains("caducado"), "{body}");
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn api_login_issues_jwt_accepted_as_bearer() {
+        use axum::http::{header, Request};
+
+        let secret = "clave-de-test-de-al-menos-32-caracteres";
+        let Some(db) = TestDb::with_config(&[("JWT_SECRET", secret)]).await else { return };
+        let app = db.app();
+        let login = |token: &str| {
+            Request::post("/api/login")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(axum::body::Body::from(serde_json::json!({ "token": token }).to_string()))
+                .unwrap()
+        };
+        let with_bearer = |token: &str| {
+            Request::get("/admin/db")
+                .header(header::AUTHORIZATION, format!("Bearer {token}"))
+                .body(axum::body::Body::empty())
+                .unwrap()
+        };
+
+        let (status, _) = send(&app, login("otro")).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let (status, body) = send(&app, login(test_support::ADMIN_TOKEN)).await;
+        assert_eq!(status, StatusCode::OK);
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["token_type"], "Bearer");
+        let jwt = body["token"].as_str().unwrap();
+
+        let (status, _) = send(&app, with_bearer(jwt)).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let tampered = format!("{jwt}x");
+        let (status, _) = send(&app, with_bearer(&tampered)).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn oauth_login_checks_state_and_authorized_accounts() {
+        use axum::http::header;
+        use tower::ServiceExt;
+
+        let config = [("OAUTH_GITHUB_CLIENT_ID", "cliente"), ("OAUTH_GITHUB_CLIENT_SECRET", "secreto")];
+        let Some(db) = TestDb::with_config(&config).await else { return };
+        let app = db.app();
+
+        let (_, body) = send(&app, test_support::get("/admin/login")).await;
+        assert!(body.contains(r#"href="/auth/github""#) && !body.contains("/auth/google"), "{body}");
+        let (status, _) = send(&app, test_support::get("/auth/google")).await;
+        assert_eq!(status, StatusCode::NOT_FOUND, "Google sin configurar");
+
+        let res = app.clone().oneshot(test_support::get("/auth/github")).await.unwrap();
+        let location = res.headers()[header::LOCATION].to_str().unwrap();
+        assert!(location.starts_with("https://github.com/login/oauth/authorize?"), "{location}");
+        let cookie = res.headers()[header::SET_COOKIE].to_str().unwrap();
+        let pending = cookie.split(';').next().unwrap().to_string();
+        let state = pending.strip_prefix("oauth_state=github.").unwrap();
+        assert!(location.contains(&format!("state={state}")));
+
+        let callback = |query: &str| {
+            let mut req = test_support::get(&format!("/auth/callback?{query}"));
+            req.headers_mut().insert(header::COOKIE, pending.parse().unwrap());
+            app.clone().oneshot(req)
+        };
+        for (query, message) in [("code=x&state=otro", "caduc"), (&format!("error=access_denied&state={state}"), "cancelado")] {
+            let res = callback(query).await.unwrap();
+            assert_eq!(res.headers()[header::LOCATION], "/admin/login");
+            let cookies: Vec<_> = res.headers().get_all(header::SET_COOKIE).iter().map(|c| c.to_str().unwrap()).collect();
+            assert!(cookies.iter().any(|c| c.starts_with("flash=error") && c.contains(message)), "{cookies:?}");
+            assert!(!cookies.iter().any(|c| c.starts_with("admin_session=")));
+        }
+
+        let add = || as_admin(form(Method::POST, "/api/admin/accounts", &[("provider", "github"), ("email", "Ana@example.com")]));
+        let (status, _) = send(&app, add()).await;
+        assert_eq!(status, StatusCode::CREATED);
+        let (status, body) = send(&app, add()).await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+        let (_, body) = send(&app, as_admin(test_support::get("/api/admin/accounts"))).await;
+        assert!(body.contains("Ana@example.com"), "{body}");
+
+        let id: i32 = sqlx::query_scalar("SELECT id FROM admin_accounts").fetch_one(&db.pool).await.unwrap();
+        let (_, body) = send(&app, as_admin(form(Method::DELETE, &format!("/api/admin/accounts/{id}"), &[]))).await;
+        assert!(body.contains("✅"), "{body}");
+        let audited: Vec<(String, String)> = sqlx::query_as(
+            "SELECT action, coalesce(new_value, old_value)->>'provider' FROM audit_log WHERE action LIKE 'account.%' ORDER BY id",
+        )
+        .fetch_all(&db.pool)
+        .await
+        .unwrap();
+        assert_eq!(audited, [("account.add".to_string(), "github".to_string()), ("account.remove".to_string(), "github".to_string())]);
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn query_count_goes_in_debug_header() {
+        use tower::ServiceExt;
+
+        let Some(db) = TestDb::with_config(&[("DEBUG_QUERY_COUNT", "true")]).await else { return };
+        sqlx::query("INSERT INTO mensajes (nombre, mensaje) VALUES ('Ana', 'hola')")
+            .execute(&db.pool)
+            .await
+            .unwrap();
+
+        let res = db.app().oneshot(test_support::get("/mensajes")).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()[crate::query_budget::HEADER], "2", "COUNT y SELECT");
+
+        let res = db.app().oneshot(test_support::get("/me/quota")).await.unwrap();
+        assert!(res.headers().contains_key(crate::query_budget::HEADER));
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn server_timing_breaks_down_each_response() {
+        use tower::ServiceExt;
+
+        let Some(db) = TestDb::with_config(&[("SERVER_TIMING", "true")]).await else { return };
+        sqlx::query("INSERT INTO mensajes (nombre, mensaje) VALUES ('Ana', 'hola')")
+            .execute(&db.pool)
+            .await
+            .unwrap();
+
+        let res = db.app().oneshot(test_support::get("/mensajes")).await.unwrap();
+        let timing = res.headers()[crate::server_timing::HEADER].to_str().unwrap().to_string();
+        for metric in ["db;dur=", r#"desc="2 consultas""#, "render;dur=", "app;dur=", "mw;dur=", "total;dur="] {
+            assert!(timing.contains(metric), "{timing}");
+        }
+
+        // Los ficheros estáticos también: sin consultas.
+        let res = db.app().oneshot(test_support::get("/contacto.html")).await.unwrap();
+        assert!(res.headers()[crate::server_timing::HEADER].to_str().unwrap().contains(r#""0 consultas""#));
+
+        db.finish().await;
+
+        let Some(db) = TestDb::new().await else { return };
+        let res = db.app().oneshot(test_support::get("/mensajes")).await.unwrap();
+        assert!(!res.headers().contains_key(crate::server_timing::HEADER));
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn api_keys_are_hashed_scoped_and_revocable() {
+        let Some(db) = TestDb::new().await else { return };
+        let app = db.app();
+        let create = |scope: &str| {
+            let req = as_admin(form(Method::POST, "/api/admin/api-keys", &[("name", "script"), ("scope", scope)]));
+            let app = app.clone();
+            async move {
+                let (status, body) = send(&app, req).await;
+                assert_eq!(status, StatusCode::CREATED, "{body}");
+                let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+                (body["id"].as_i64().unwrap(), body["key"].as_str().unwrap().to_string())
+            }
+        };
+        let with_key = |mut req: axum::http::Request<axum::body::Body>, key: &str| {
+            req.headers_mut().insert("x-api-key", key.parse().unwrap());
+            req
+        };
+
+        let (_, read_key) = create("read").await;
+        let (write_id, write_key) = create("write").await;
+        let stored: Vec<String> = sqlx::query_scalar("SELECT key_hash FROM api_keys").fetch_all(&db.pool).await.unwrap();
+        assert!(!stored.contains(&read_key) && !stored.contains(&write_key));
+        let scopes: Vec<String> =
+            sqlx::query_scalar("SELECT new_value->>'scope' FROM audit_log WHERE action = 'api_key.create' ORDER BY id")
+                .fetch_all(&db.pool)
+                .await
+                .unwrap();
+        assert_eq!(scopes, ["read", "write"]);
+
+        let id: i32 = sqlx::query_scalar("INSERT INTO mensajes (nombre, mensaje) VALUES ('Ana', 'hola') RETURNING id")
+            .fetch_one(&db.pool)
+            .await
+            .unwrap();
+        let uri = format!("/mensajes/{id}");
+
+        let (status, _) = send(&app, with_key(test_support::get("/mensajes"), &read_key)).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = send(&app, with_key(form(Method::DELETE, &uri, &[]), &read_key)).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = send(&app, with_key(test_support::get("/mensajes"), "hak_inventada")).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = send(&app, with_key(form(Method::DELETE, &uri, &[]), "hak_inventada")).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let revoke = format!("/api/admin/api-keys/{write_id}");
+        send(&app, as_admin(form(Method::DELETE, &revoke, &[]))).await;
+        let (status, _) = send(&app, with_key(form(Method::DELETE, &uri, &[]), &write_key)).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let (write_id, write_key) = create("write").await;
+        let (_, body) = send(&app, with_key(form(Method::DELETE, &uri, &[]), &write_key)).await;
+        assert!(body.contains("✅"), "{body}");
+
+        let (_, body) = send(&app, as_admin(test_support::get("/api/admin/api-keys"))).await;
+        assert!(body.contains(&format!(r#""id":{write_id}"#)) && !body.contains(&write_key), "{body}");
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn admin_actions_show_up_in_audit_page() {
+        let Some(db) = TestDb::new().await else { return };
+        let app = db.app();
+
+        let (status, _) = send(&app, as_admin(form(Method::DELETE, "/api/admin/mensajes/999", &[]))).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        send(&app, as_admin(test_support::get("/admin/db"))).await;
+
+        let mut req = as_admin(test_support::get("/admin/audit?method=delete"));
+        req.headers_mut().insert("hx-request", "true".parse().unwrap());
+        let (status, body) = send(&app, req).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.starts_with(r#"<div id="audit-panel">"#));
+        assert!(body.contains("/api/admin/mensajes/999") && body.contains("<td>404</td>"), "{body}");
+        assert!(!body.contains("/admin/db"), "las lecturas no se guardan");
+
+        let (_, body) = send(&app, as_admin(test_support::get("/admin/audit?status=2xx"))).await;
+        assert!(body.contains("No hay acciones registradas"), "{body}");
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn read_bursts_are_served_from_cache() {
+        use axum::{body::to_bytes, http::header};
+        use tower::ServiceExt;
+
+        let config = [("READ_RATE_BURST", "2"), ("READ_RATE_PER_MINUTE", "1")];
+        let Some(db) = TestDb::with_config(&config).await else { return };
+        let app = db.app();
+        let insert = "INSERT INTO mensajes (nombre, mensaje) VALUES ('Ana', 'hola')";
+        sqlx::query(insert).execute(&db.pool).await.unwrap();
+
+        for _ in 0..2 {
+            let res = app.clone().oneshot(from_ip(test_support::get("/mensajes"), "10.0.0.9")).await.unwrap();
+            assert!(res.headers().get(header::WARNING).is_none());
+        }
+
+        sqlx::query(insert).execute(&db.pool).await.unwrap();
+
+        let res = app.clone().oneshot(from_ip(test_support::get("/mensajes"), "10.0.0.9")).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(res.headers().get(header::WARNING).is_some());
+        let body = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let page: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(page["total"], 1, "copia anterior al segundo mensaje");
+
+        // Otra IP sigue leyendo de la base de datos.
+        let (_, body) = send(&app, from_ip(test_support::get("/mensajes"), "10.0.0.10")).await;
+        let page: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(page["total"], 2);
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn author_email_is_verified_by_link() {
+        let Some(db) = TestDb::new().await else { return };
+        let app = db.app();
+
+        let mut fields = valid_message().to_vec();
+        fields.push(("email", "no-es-un-email"));
+        let (_, body) = send(&app, form(Method::POST, "/enviar", &fields)).await;
+        assert!(body.contains("Email inválido"), "{body}");
+
+        fields.pop();
+        fields.push(("email", " Ana@Example.com "));
+        let (_, body) = send(&app, form(Method::POST, "/enviar", &fields)).await;
+        assert!(body.contains("Revisa tu correo"), "{body}");
+
+        let (id, email): (i32, String) = sqlx::query_as("SELECT id, author_email FROM mensajes")
+            .fetch_one(&db.pool)
+            .await
+            .unwrap();
+        assert_eq!(email, "ana@example.com");
+        let token: String = sqlx::query_scalar("SELECT token FROM email_verifications WHERE mensaje_id = $1")
+            .bind(id)
+            .fetch_one(&db.pool)
+            .await
+            .unwrap();
+        let queued: i64 = sqlx::query_scalar("SELECT count(*) FROM outbox WHERE email->>'text' LIKE '%/verificar/' || $1 || '%'")
+            .bind(&token)
+            .fetch_one(&db.pool)
+            .await
+            .unwrap();
+        assert_eq!(queued, 1);
+
+        let (_, body) = send(&app, test_support::get("/mensajes")).await;
+        assert!(body.contains(r#""verified":false"#) && !body.contains("example.com"), "{body}");
+
+        let uri = format!("/verificar/{token}");
+        let (status, _) = send(&app, test_support::get(&uri)).await;
+        assert_eq!(status, StatusCode::SEE_OTHER);
+
+        let (_, body) = send(&app, test_support::get("/mensajes")).await;
+        assert!(body.contains(r#""verified":true"#), "{body}");
+        let (_, body) = send(&app, test_support::get(&format!("/mensajes/{id}/view"))).await;
+        assert!(body.contains("badge-verified"));
+
+        // Un solo uso.
+        let res = tower::ServiceExt::oneshot(app.clone(), test_support::get(&uri)).await.unwrap();
+        assert_eq!(res.headers()["location"], "/");
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn confirmation_link_publishes_the_message() {
+        let config = [("EMAIL_CONFIRMATION_REQUIRED", "true"), ("PUBLIC_URL", "https://motos.example/")];
+        let Some(db) = TestDb::with_config(&config).await else { return };
+        let app = db.app();
+
+        let (_, body) = send(&app, form(Method::POST, "/enviar", &valid_message())).await;
+        assert!(body.contains("Indica tu email"), "{body}");
+
+        let mut fields = valid_message().to_vec();
+        fields.push(("email", "ana@example.com"));
+        let mut req = form(Method::POST, "/enviar", &fields);
+        req.headers_mut().insert(axum::http::header::HOST, "atacante.example".parse().unwrap());
+        req.headers_mut().insert("x-forwarded-proto", "https".parse().unwrap());
+        let (_, body) = send(&app, req).await;
+        assert!(body.contains("confirmarlo"), "{body}");
+        let (_, body) = send(&app, test_support::get("/mensajes")).await;
+        assert!(body.contains(r#""total":0"#), "{body}");
+
+        let (id, token): (i32, String) = sqlx::query_as("SELECT id, token FROM mensajes_unconfirmed")
+            .fetch_one(&db.pool)
+            .await
+            .unwrap();
+        // El enlace espera en la outbox, guardado con el mensaje, y apunta a
+        // `PUBLIC_URL` digan lo que digan las cabeceras.
+        let (to, text): (String, String) = sqlx::query_as("SELECT email->>'to', email->>'text' FROM outbox WHERE email IS NOT NULL")
+            .fetch_one(&db.pool)
+            .await
+            .unwrap();
+        assert_eq!(to, "ana@example.com");
+        assert!(text.contains(&format!("https://motos.example/confirmar/{token}")), "{text}");
+        // Abrir el enlace (como hace un escáner de correo) no publica nada.
+        let uri = format!("/confirmar/{token}");
+        let (status, body) = send(&app, test_support::get(&uri)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains(&format!(r#"action="/confirmar/{token}""#)), "{body}");
+        let (_, body) = send(&app, test_support::get("/mensajes")).await;
+        assert!(body.contains(r#""total":0"#), "{body}");
+
+        let res = tower::ServiceExt::oneshot(app.clone(), form(Method::POST, &uri, &[])).await.unwrap();
+        assert_eq!(res.headers()["location"], format!("/mensajes/{id}/view"));
+
+        let (_, body) = send(&app, test_support::get("/mensajes")).await;
+        assert!(body.contains(&format!(r#""id":{id}"#)) && body.contains(r#""verified":true"#), "{body}");
+
+        // Un solo uso.
+        let res = tower::ServiceExt::oneshot(app.clone(), test_support::get(&uri)).await.unwrap();
+        assert_eq!(res.headers()["location"], "/");
+        let res = tower::ServiceExt::oneshot(app.clone(), form(Method::POST, &uri, &[])).await.unwrap();
+        assert_eq!(res.headers()["location"], "/");
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn pending_confirmations_are_capped_per_email() {
+        let config = [("EMAIL_CONFIRMATION_REQUIRED", "true"), ("PUBLIC_URL", "https://motos.example")];
+        let Some(db) = TestDb::with_config(&config).await else { return };
+        let app = db.app();
+        let mut fields = valid_message().to_vec();
+        fields.push(("email", "victima@example.com"));
+
+        // Desde IPs distintas, para no tocar el ritmo de envíos.
+        for n in 0..crate::email_verification::MAX_PENDING_PER_EMAIL {
+            let (_, body) = send(&app, from_ip(form(Method::POST, "/enviar", &fields), &format!("10.1.0.{n}"))).await;
+            assert!(body.contains("confirmarlo"), "{body}");
+        }
+        let (_, body) = send(&app, from_ip(form(Method::POST, "/enviar", &fields), "10.1.1.1")).await;
+        assert!(body.contains("esperando confirmación"), "{body}");
+
+        let pending: i64 = sqlx::query_scalar("SELECT count(*) FROM mensajes_unconfirmed").fetch_one(&db.pool).await.unwrap();
+        assert_eq!(pending, crate::email_verification::MAX_PENDING_PER_EMAIL);
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn verify_uploads_reports_and_fixes_mismatches() {
+        let Some(db) = TestDb::new().await else { return };
+        let app = db.app();
+        let upload = || MultipartBuilder::new().file("file", "moto.png", "image/png", &image_bytes("png", 16));
+        send(&app, as_admin(upload().into_request("/upload-image"))).await;
+        send(&app, as_admin(upload().into_request("/upload-image"))).await;
+        send(&app, upload().into_request("/upload-image")).await;
+
+        let report = crate::verify_uploads::reconcile(&db.pool, &db.uploads).await.unwrap();
+        assert!(report.is_clean(), "{report:?}");
+
+        // Uno publicado sin fichero, un huérfano con nombre de subida y un fichero del sitio.
+        let gone: String = sqlx::query_scalar("SELECT filename FROM images WHERE approved_at IS NOT NULL ORDER BY id LIMIT 1")
+            .fetch_one(&db.pool)
+            .await
+            .unwrap();
+        tokio::fs::remove_file(db.uploads.file(&gone).unwrap()).await.unwrap();
+        let orphan = db.uploads.file(&format!("{}.png", uuid::Uuid::new_v4())).unwrap();
+        tokio::fs::write(&orphan, b"x").await.unwrap();
+        tokio::fs::write(db.uploads.file("Logo.png").unwrap(), b"x").await.unwrap();
+
+        let report = crate::verify_uploads::reconcile(&db.pool, &db.uploads).await.unwrap();
+        assert_eq!(report.missing.iter().map(|m| m.filename.as_str()).collect::<Vec<_>>(), [gone.as_str()]);
+        assert_eq!(report.orphans, std::slice::from_ref(&orphan));
+        assert_eq!(crate::verify_uploads::run(&db.pool, &db.uploads, false).await, 1);
+
+        assert_eq!(crate::verify_uploads::run(&db.pool, &db.uploads, true).await, 0);
+        assert!(crate::verify_uploads::reconcile(&db.pool, &db.uploads).await.unwrap().is_clean());
+        assert!(!orphan.exists() && db.uploads.file("Logo.png").unwrap().exists());
+        let left: i64 = sqlx::query_scalar("SELECT count(*) FROM images").fetch_one(&db.pool).await.unwrap();
+        assert_eq!(left, 2);
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn handlers_publish_domain_events() {
+        use crate::events::Event;
+
+        let Some(db) = TestDb::new().await else { return };
+        let metrics = std::sync::Arc::new(crate::metrics::Metrics::new(&db.config.metrics));
+        let state = crate::state::AppState::new(db.pool.clone(), db.jobs.clone(), db.config.clone(), db.uploads.clone(), metrics);
+        let app = crate::build_routers(&state, &None).0;
+        let mut events = state.events.subscribe();
+
+        let (_, body) = send(&app, from_ip(form(Method::POST, "/enviar", &valid_message()), "10.0.0.1")).await;
+        assert!(body.contains("✅"), "{body}");
+        let created = events.try_recv().unwrap();
+        let Event::MessageCreated { id } = created.event else { panic!("{created:?}") };
+        assert!(created.local);
+
+        send(&app, as_admin(form(Method::DELETE, &format!("/api/admin/mensajes/{id}"), &[]))).await;
+        assert_eq!(events.try_recv().unwrap().event, Event::MessageDeleted { id });
+
+        // Lo que queda pendiente de revisión todavía no es visible.
+        let upload = || MultipartBuilder::new().file("file", "moto.png", "image/png", &image_bytes("png", 16));
+        let (_, body) = send(&app, from_ip(upload().into_request("/upload-image"), "10.0.0.1")).await;
+        assert!(body.contains("✅"), "{body}");
+        assert!(events.try_recv().is_err());
+        let (_, body) = send(&app, from_ip(as_admin(upload().into_request("/upload-image")), "10.0.0.1")).await;
+        assert!(body.contains("✅"), "{body}");
+        assert!(matches!(events.try_recv().unwrap().event, Event::ImageUploaded { .. }));
+
+        let res = tower::ServiceExt::oneshot(app, test_support::get("/events")).await.unwrap();
+        assert_eq!(res.headers()["content-type"], "text/event-stream");
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn empty_listing_skips_queries_until_first_insert() {
+        let Some(db) = TestDb::new().await else { return };
+        let metrics = std::sync::Arc::new(crate::metrics::Metrics::new(&db.config.metrics));
+        let state = crate::state::AppState::new(db.pool.clone(), db.jobs.clone(), db.config.clone(), db.uploads.clone(), metrics);
+        let app = crate::build_routers(&state, &None).0;
+        let listener = tokio::spawn(state.mensajes_empty.clone().listen(db.pool.clone()));
+
+        let total = |app: axum::Router| async move {
+            let (_, body) = send(&app, test_support::get("/mensajes")).await;
+            serde_json::from_str::<serde_json::Value>(&body).unwrap()["total"].as_i64().unwrap()
+        };
+
+        for _ in 0..50 {
+            if state.mensajes_empty.is_armed() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        }
+        assert_eq!(total(app.clone()).await, 0);
+        assert!(state.mensajes_empty.is_empty());
+
+        // Insertado por fuera de la app: solo se entera por el NOTIFY.
+        sqlx::query("INSERT INTO mensajes (nombre, mensaje) VALUES ('Ana', 'hola')")
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        let mut seen = 0;
+        for _ in 0..50 {
+            seen = total(app.clone()).await;
+            if seen == 1 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        }
+        assert_eq!(seen, 1);
+        assert!(!state.mensajes_empty.is_empty());
+
+        listener.abort();
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn related_messages_share_words() {
+        let Some(db) = TestDb::new().await else { return };
+        let app = db.app();
+
+        for mensaje in [
+            "La moto roja corre mucho en carretera",
+            "Mi moto roja necesita ruedas nuevas",
+            "Receta de tortilla de patatas",
+        ] {
+            sqlx::query("INSERT INTO mensajes (nombre, mensaje) VALUES ('Ana', $1)")
+                .bind(mensaje)
+                .execute(&db.pool)
+                .await
+                .unwrap();
+        }
+        let ids: Vec<i32> = sqlx::query_scalar("SELECT id FROM mensajes ORDER BY id").fetch_all(&db.pool).await.unwrap();
+
+        let (status, body) = send(&app, test_support::get(&format!("/mensajes/{}/related", ids[0]))).await;
+        assert_eq!(status, StatusCode::OK);
+        let related: Vec<serde_json::Value> = serde_json::from_str(&body).unwrap();
+        let related: Vec<i64> = related.iter().map(|m| m["id"].as_i64().unwrap()).collect();
+        assert_eq!(related, [ids[1] as i64]);
+
+        let (_, body) = send(&app, test_support::get(&format!("/mensajes/{}/view", ids[0]))).await;
+        assert!(body.contains("También te puede interesar") && body.contains("ruedas nuevas"));
+        let (_, body) = send(&app, test_support::get(&format!("/mensajes/{}/view", ids[2]))).await;
+        assert!(!body.contains("También te puede interesar"));
+
+        let (status, _) = send(&app, test_support::get("/mensajes/999999/related")).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn static_export_zips_guestbook_and_public_assets() {
+        use axum::body::to_bytes;
+        use tower::ServiceExt;
+
+        let Some(db) = TestDb::new().await else { return };
+        let app = db.app();
+        let insert = "INSERT INTO mensajes (nombre, mensaje) VALUES ('Ana', 'Mensaje de prueba') RETURNING id";
+        let id: i32 = sqlx::query_scalar(insert).fetch_one(&db.pool).await.unwrap();
+
+        let (status, _) = send(&app, test_support::get("/admin/export/static")).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let res = app.clone().oneshot(as_admin(test_support::get("/admin/export/static"))).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()["content-type"], "application/zip");
+        let zip = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+
+        // Los nombres van sin comprimir en las cabeceras.
+        let has = |name: &str| zip.windows(name.len()).any(|w| w == name.as_bytes());
+        assert!(has("mensajes/index.html"));
+        assert!(has(&format!("mensajes/{id}/view/index.html")));
+        assert!(has("css/styles.css") && has("contacto.html"));
+        assert!(!has("admin.html"));
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn exports_use_their_own_pool() {
+        let overrides = [("DB_MAX_CONNECTIONS", "1"), ("DB_ACQUIRE_TIMEOUT_MS", "300")];
+        let Some(db) = TestDb::with_config(&overrides).await else { return };
+        let app = db.app();
+
+        // Con la única conexión de las peticiones ocupada, la exportación sigue.
+        let held = db.pool.acquire().await.unwrap();
+        let (status, _) = send(&app, as_admin(test_support::get("/admin/export/static"))).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (status, _) = send(&app, test_support::get("/mensajes")).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        drop(held);
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn admin_fragment_paginates_and_searches() {
+        let Some(db) = TestDb::new().await else { return };
+        let app = db.app();
+
+        sqlx::query(
+            "INSERT INTO mensajes (nombre, mensaje)
+             SELECT 'Visitante ' || i, 'Mensaje número ' || i FROM generate_series(1, 25) AS i",
+        )
+        .execute(&db.pool)
+        .await
+        .unwrap();
+
+        let (status, _) = send(&app, test_support::get("/admin/mensajes")).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let mut req = as_admin(test_support::get("/admin/mensajes?page=2"));
+        req.headers_mut().insert("hx-request", "true".parse().unwrap());
+        let (status, body) = send(&app, req).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.starts_with(r#"<div id="mensajes-panel">"#), "{body}");
+        assert!(body.contains("Página 2 de 2"));
+        assert_eq!(body.matches("<tr>").count(), 1 + 5);
+
+        let (_, body) = send(&app, as_admin(test_support::get("/admin/mensajes?q=visitante%2013"))).await;
+        assert!(body.starts_with("<!DOCTYPE html>"));
+        assert!(body.contains("Visitante 13") && !body.contains("Visitante 12"));
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn permalink_has_escaped_opengraph_tags() {
+        let Some(db) = TestDb::with_config(&[("PUBLIC_URL", "https://motos.example/")]).await else {
+            return;
+        };
+        let app = db.app();
+
+        let id: i32 = sqlx::query_scalar(
+            "INSERT INTO mensajes (nombre, mensaje) VALUES ('Ana', 'Precio de la R6 & <b>extras</b>') RETURNING id",
+        )
+        .fetch_one(&db.pool)
+        .await
+        .unwrap();
+
+        let (status, body) = send(&app, test_support::get(&format!("/mensajes/{id}/view"))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains(r#"<meta property="og:title" content="Mensaje de Ana | Axum Motors">"#));
+        assert!(body.contains(&format!(r#"content="https://motos.example/mensajes/{id}/view""#)));
+        assert!(body.contains("Precio de la R6 &amp; &lt;b&gt;extras&lt;/b&gt;"));
+        assert!(!body.contains("<b>extras"));
+
+        let (status, _) = send(&app, test_support::get("/mensajes/999999/view")).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn oversized_content_length_is_rejected_before_reading() {
+        let Some(db) = TestDb::new().await else { return };
+        let app = db.app();
+
+        let mut req = form(Method::POST, "/enviar", &valid_message());
+        req.headers_mut().insert("content-length", "100000".parse().unwrap());
+        let (status, _) = send(&app, req).await;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+
+        let mut req = MultipartBuilder::new()
+            .file("file", "a.png", "image/png", &image_bytes("png", 16))
+            .into_request("/upload-image");
+        req.headers_mut().insert("content-length", (20 * 1024 * 1024).to_string().parse().unwrap());
+        let (status, _) = send(&app, req).await;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+
+        let count: i64 = sqlx::query_scalar("SELECT count(*) FROM mensajes").fetch_one(&db.pool).await.unwrap();
+        assert_eq!(count, 0);
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn body_and_image_limits_come_from_config() {
+        let Some(db) = TestDb::with_config(&[("MAX_IMAGE_SIZE", "1K"), ("MAX_BODY_SIZE", "1K")]).await else {
+            return;
+        };
+        let app = db.app();
+
+        let req = MultipartBuilder::new()
+            .file("file", "a.png", "image/png", &image_bytes("png", 2048))
+            .into_request("/upload-image");
+        let (_, body) = send(&app, as_admin(req)).await;
+        assert!(body.contains("máx 1KB"), "{body}");
+
+        // Una ruta sin límite propio se queda con `MAX_BODY_SIZE`.
+        let reason = "x".repeat(2048);
+        let req = form(Method::POST, "/api/admin/bans", &[("network", "10.0.0.0/8"), ("reason", &reason)]);
+        let (status, _) = send(&app, as_admin(req)).await;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+        let req = form(Method::POST, "/api/admin/bans", &[("network", "10.0.0.0/8")]);
+        let (status, _) = send(&app, as_admin(req)).await;
+        assert_eq!(status, StatusCode::CREATED);
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn oversized_dimensions_are_rejected_before_decoding() {
+        let Some(db) = TestDb::with_config(&[("MAX_IMAGE_WIDTH", "500"), ("MAX_IMAGE_MEGAPIXELS", "0.3")]).await else {
+            return;
+        };
+        let app = db.app();
+
+        let upload = |width, height| {
+            let mut png = std::io::Cursor::new(Vec::new());
+            image::RgbImage::new(width, height).write_to(&mut png, image::ImageFormat::Png).unwrap();
+            MultipartBuilder::new()
+                .file("file", "a.png", "image/png", png.get_ref())
+                .into_request("/upload-image")
+        };
+
+        let (_, body) = send(&app, as_admin(upload(800, 10))).await;
+        assert!(body.contains("máx 500×10000 píxeles, 0.3 megapíxeles"), "{body}");
+        let (_, body) = send(&app, as_admin(upload(400, 1000))).await;
+        assert!(body.contains("megapíxeles"), "{body}");
+        let (_, body) = send(&app, as_admin(upload(400, 300))).await;
+        assert!(body.contains("✅"), "{body}");
+
+        let images: i64 = sqlx::query_scalar("SELECT count(*) FROM images").fetch_one(&db.pool).await.unwrap();
+        assert_eq!(images, 1);
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn upload_by_url_is_opt_in_and_refuses_internal_hosts() {
+        let fields = [("url", "http://127.0.0.1:3000/uploads/secreto.png")];
+
+        let Some(db) = TestDb::new().await else { return };
+        // Sin la ruta la petición cae en los estáticos, que no admiten POST.
+        let (status, _) = send(&db.app(), form(Method::POST, "/upload-image-url", &fields)).await;
+        assert_eq!(status, StatusCode::METHOD_NOT_ALLOWED);
+        db.finish().await;
+
+        let Some(db) = TestDb::with_config(&[("UPLOAD_FROM_URL", "true")]).await else { return };
+        let app = db.app();
+
+        let (_, body) = send(&app, form(Method::POST, "/upload-image-url", &fields)).await;
+        assert!(body.contains("direcciones internas"), "{body}");
+
+        let fields = [("url", "file:///etc/passwd")];
+        let (_, body) = send(&app, form(Method::POST, "/upload-image-url", &fields)).await;
+        assert!(body.contains("URL no válida"), "{body}");
+
+        let count: i64 = sqlx::query_scalar("SELECT count(*) FROM images").fetch_one(&db.pool).await.unwrap();
+        assert_eq!(count, 0);
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn upload_rejects_disallowed_mime() {
+        let Some(db) = TestDb::new().await else { return };
+        let app = db.app();
+
+        let req = MultipartBuilder::new()
+            .text("descripcion", "no es una imagen")
+            .file("file", "script.sh", "text/x-shellscript", b"#!/bin/sh")
+            .into_request("/upload-image");
+        let (_, body) = send(&app, req).await;
+        assert!(body.contains("Tipo de archivo no permitido"));
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn upload_checks_content_against_name_and_type() {
+        let Some(db) = TestDb::new().await else { return };
+        let app = db.app();
+        let upload = |name: &str, mime: &str, bytes: Vec<u8>| {
+            let req = MultipartBuilder::new()
+                .file("file", name, mime, &bytes)
+                .into_request("/upload-image");
+            send(&app, as_admin(req))
+        };
+
+        let (_, body) = upload("moto.jpg", "image/png", image_bytes("png", 64)).await;
+        assert!(body.contains("extensión del archivo no coincide"), "{body}");
+
+        let (_, body) = upload("moto.png", "image/jpeg", image_bytes("png", 64)).await;
+        assert!(body.contains("tipo declarado no coincide"), "{body}");
+
+        let (_, body) = upload("shell.png", "image/png", b"<?php system($_GET['c']); ?>".to_vec()).await;
+        assert!(body.contains("no es una imagen"), "{body}");
+
+        let (_, body) = upload("photo.php.png", "image/png", image_bytes("png", 64)).await;
+        assert!(body.contains("✅"), "{body}");
+        let original: String = sqlx::query_scalar("SELECT original_name FROM images")
+            .fetch_one(&db.pool)
+            .await
+            .unwrap();
+        assert_eq!(original, "photo_php.png");
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn private_uploads_need_a_signed_url() {
+        let Some(db) = TestDb::with_config(&[
+            ("UPLOADS_PRIVATE", "true"),
+            ("UPLOAD_URL_SECRET", "0123456789abcdef0123456789abcdef"),
+        ])
+        .await
+        else {
+            return;
+        };
+        let app = db.app();
+
+        let req = MultipartBuilder::new()
+            .file("file", "moto.png", "image/png", &image_bytes("png", 64))
+            .into_request("/upload-image");
+        send(&app, as_admin(req)).await;
+        let (id, filename): (i32, String) = sqlx::query_as("SELECT id, filename FROM images")
+            .fetch_one(&db.pool)
+            .await
+            .unwrap();
+
+        let (status, _) = send(&app, test_support::get(&format!("/uploads/{filename}"))).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        let (status, body) = send(&app, test_support::get(&format!("/images/{id}/url"))).await;
+        assert_eq!(status, StatusCode::OK);
+        let signed: serde_json::Value = serde_json::from_str(&body).unwrap();
+        let url = signed["url"].as_str().unwrap();
+        assert!(signed["expires_at"].is_string(), "{body}");
+
+        let (status, _) = send(&app, test_support::get(url)).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = send(&app, test_support::get(&url.replace("expires=", "expires=9"))).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        // La miniatura pide la misma firma.
+        let (status, _) = send(&app, test_support::get(&format!("/images/{id}/thumb"))).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let thumb_url = signed["thumb_url"].as_str().unwrap();
+        let (status, _) = send(&app, test_support::get(thumb_url)).await;
+        assert_eq!(status, StatusCode::OK);
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn disabled_images_answer_503_and_the_rest_keeps_working() {
+        let Some(db) = TestDb::with_config(&[("IMAGES_ENABLED", "false")]).await else { return };
+        let app = db.app();
+
+        let req = MultipartBuilder::new()
+            .file("file", "moto.png", "image/png", &image_bytes("png", 64))
+            .into_request("/upload-image");
+        for req in [
+            as_admin(req),
+            test_support::get("/images"),
+            test_support::get("/uploads/a.png"),
+            as_admin(test_support::get("/api/admin/images/pending")),
+            as_admin(form(Method::POST, "/api/admin/images/1/approve", &[])),
+        ] {
+            let uri = req.uri().to_string();
+            let (status, body) = send(&app, req).await;
+            assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE, "{uri}");
+            assert!(body.contains("desactivadas"), "{uri}: {body}");
+        }
+
+        let (_, body) = send(&app, form(Method::POST, "/enviar", &valid_message())).await;
+        assert!(body.contains("enviado correctamente"), "{body}");
+        let (status, _) = send(&app, as_admin(test_support::get("/api/admin/audit"))).await;
+        assert_eq!(status, StatusCode::OK);
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn deleted_image_can_be_restored_from_trash() {
+        let Some(db) = TestDb::new().await else { return };
+        let app = db.app();
+
+        let req = MultipartBuilder::new()
+            .file("file", "moto.png", "image/png", &image_bytes("png", 64))
+            .into_request("/upload-image");
+        // Publicada directamente: las pendientes no pasan por la papelera.
+        send(&app, as_admin(req)).await;
+        let (id, filename): (i32, String) = sqlx::query_as("SELECT id, filename FROM images")
+            .fetch_one(&db.pool)
+            .await
+            .unwrap();
+        let uri = format!("/images/{id}");
+
+        let (status, _) = send(&app, from_ip(form(Method::DELETE, &uri, &[]), "10.0.0.1")).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        let (status, _) = send(&app, as_admin(form(Method::DELETE, &uri, &[]))).await;
+        assert_eq!(status, StatusCode::OK);
+        let (_, body) = send(&app, test_support::get("/images")).await;
+        assert!(!body.contains(&filename));
+        let (status, _) = send(&app, test_support::get(&format!("/uploads/.trash/{filename}"))).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let restore = format!("{uri}/restore");
+        let (status, _) = send(&app, as_admin(form(Method::POST, &restore, &[]))).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = send(&app, test_support::get(&format!("/uploads/{filename}"))).await;
+        assert_eq!(status, StatusCode::OK);
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn dry_run_reports_without_deleting() {
+        let Some(db) = TestDb::new().await else { return };
+        let app = db.app();
+
+        let req = MultipartBuilder::new()
+            .file("file", "moto.png", "image/png", &image_bytes("png", 64))
+            .into_request("/upload-image");
+        send(&app, as_admin(req)).await;
+        send(&app, form(Method::POST, "/enviar", &valid_message())).await;
+        let image: i32 = sqlx::query_scalar("SELECT id FROM images").fetch_one(&db.pool).await.unwrap();
+        let mensaje: i32 = sqlx::query_scalar("SELECT id FROM mensajes").fetch_one(&db.pool).await.unwrap();
+        let trashed = || async {
+            sqlx::query_scalar::<_, bool>("SELECT deleted_at IS NOT NULL FROM images")
+                .fetch_optional(&db.pool)
+                .await
+                .unwrap()
+        };
+
+        let uri = format!("/images/{image}?dry_run=true");
+        let (status, body) = send(&app, as_admin(form(Method::DELETE, &uri, &[]))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, format!(r#"{{"dry_run":true,"count":1,"sample_ids":[{image}]}}"#));
+        assert_eq!(trashed().await, Some(false));
+
+        let uri = format!("/api/admin/mensajes/{mensaje}?dry_run=true");
+        let (_, body) = send(&app, as_admin(form(Method::DELETE, &uri, &[]))).await;
+        assert!(body.contains(r#""count":1"#), "{body}");
+        let left: i64 = sqlx::query_scalar("SELECT count(*) FROM mensajes").fetch_one(&db.pool).await.unwrap();
+        assert_eq!(left, 1);
+
+        send(&app, as_admin(form(Method::DELETE, &format!("/images/{image}"), &[]))).await;
+        let uri = format!("/api/admin/images/{image}/restore?dry_run=true");
+        let (_, body) = send(&app, as_admin(form(Method::POST, &uri, &[]))).await;
+        assert!(body.contains(r#""count":1"#), "{body}");
+        assert_eq!(trashed().await, Some(true));
+
+        // Aún dentro de la retención: no hay nada que purgar.
+        let purge = "/api/admin/images/trash/purge?dry_run=true";
+        let (_, body) = send(&app, as_admin(form(Method::POST, purge, &[]))).await;
+        assert!(body.contains(r#""count":0,"sample_ids":[]"#), "{body}");
+
+        sqlx::query("UPDATE images SET deleted_at = now() - interval '100 hours'")
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        let (_, body) = send(&app, as_admin(form(Method::POST, purge, &[]))).await;
+        assert!(body.contains(&format!(r#""count":1,"sample_ids":[{image}]"#)), "{body}");
+        assert_eq!(trashed().await, Some(true));
+
+        let (status, _) = send(&app, as_admin(form(Method::POST, "/api/admin/images/trash/purge", &[]))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(trashed().await, None);
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn banned_ranges_cannot_post_or_upload() {
+        let Some(db) = TestDb::new().await else { return };
+        let app = db.app();
+
+        let ban = [("network", "10.1.2.3/16"), ("reason", "spam")];
+        let (status, body) = send(&app, as_admin(form(Method::POST, "/api/admin/bans", &ban))).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert!(body.contains(r#""network":"10.1.0.0/16""#), "{body}");
+        let (status, _) = send(&app, as_admin(form(Method::POST, "/api/admin/bans", &ban))).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        let (status, _) = send(&app, as_admin(form(Method::POST, "/api/admin/bans", &[("network", "10.0.0.0/40")]))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, _) = send(&app, from_ip(form(Method::POST, "/enviar", &valid_message()), "10.1.200.7")).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let upload = MultipartBuilder::new()
+            .file("file", "moto.png", "image/png", &image_bytes("png", 64))
+            .into_request("/upload-image");
+        let (status, _) = send(&app, from_ip(upload, "10.1.0.1")).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = send(&app, from_ip(test_support::get("/mensajes"), "10.1.0.1")).await;
+        assert_eq!(status, StatusCode::OK);
+
+        // Conectando directamente, `X-Forwarded-For` no sirve para saltarse el bloqueo.
+        let mut direct = form(Method::POST, "/enviar", &valid_message());
+        direct.headers_mut().insert("x-forwarded-for", "10.2.0.1".parse().unwrap());
+        direct.extensions_mut().insert(axum::extract::ConnectInfo(std::net::SocketAddr::from(([10, 1, 0, 9], 40000))));
+        let (status, _) = send(&app, direct).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        let (status, _) = send(&app, from_ip(form(Method::POST, "/enviar", &valid_message()), "10.2.0.1")).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let id: i32 = sqlx::query_scalar("SELECT id FROM banned_ips").fetch_one(&db.pool).await.unwrap();
+        let (status, _) = send(&app, as_admin(form(Method::DELETE, &format!("/api/admin/bans/{id}"), &[]))).await;
+        assert_eq!(status, StatusCode::OK);
+        let (_, body) = send(&app, as_admin(test_support::get("/api/admin/bans"))).await;
+        assert_eq!(body, "[]");
+        let (status, _) = send(&app, from_ip(form(Method::POST, "/enviar", &valid_message()), "10.1.200.7")).await;
+        assert_eq!(status, StatusCode::OK);
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn admin_changes_are_audited_with_old_and_new_values() {
+        let Some(db) = TestDb::new().await else { return };
+        let app = db.app();
+
+        send(&app, form(Method::POST, "/enviar", &valid_message())).await;
+        let id: i32 = sqlx::query_scalar("SELECT id FROM mensajes").fetch_one(&db.pool).await.unwrap();
+        let uri = format!("/api/admin/mensajes/{id}");
+
+        let edit = [("nombre", "Ana María"), ("mensaje", "Texto corregido desde el panel")];
+        send(&app, as_admin(form(Method::PUT, &uri, &edit))).await;
+        send(&app, as_admin(form(Method::DELETE, &uri, &[]))).await;
+        send(&app, as_admin(form(Method::POST, "/api/admin/bans", &[("network", "10.9.0.0/16")]))).await;
+
+        let (_, body) = send(&app, as_admin(test_support::get("/api/admin/audit?action=mensaje"))).await;
+        let log: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(log["total"], 2);
+        let (delete, update) = (&log["data"][0], &log["data"][1]);
+        assert_eq!((delete["action"].as_str(), delete["actor"].as_str()), (Some("mensaje.delete"), Some("admin")));
+        assert_eq!(delete["target"], format!("mensaje:{id}"));
+        assert_eq!(delete["old_value"]["nombre"], "Ana María");
+        assert_eq!(update["old_value"]["nombre"], "Ana García");
+        assert_eq!(update["new_value"]["mensaje"], "Texto corregido desde el panel");
+
+        let (_, body) = send(&app, as_admin(test_support::get("/api/admin/audit?action=ban.create"))).await;
+        assert!(body.contains(r#""network":"10.9.0.0/16""#), "{body}");
+
+        let (status, _) = send(&app, test_support::get("/api/admin/audit")).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn admin_overview_combines_panel_data() {
+        let Some(db) = TestDb::new().await else { return };
+        let app = db.app();
+
+        sqlx::query(
+            "INSERT INTO images (filename, size_bytes, approved_at, deleted_at) VALUES
+                ('a.png', 100, now(), NULL), ('b.png', 40, NULL, NULL), ('c.png', 7, now(), now())",
+        )
+        .execute(&db.pool)
+        .await
+        .unwrap();
+        send(&app, form(Method::POST, "/enviar", &valid_message())).await;
+
+        let (status, _) = send(&app, test_support::get("/admin/overview")).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let (status, body) = send(&app, as_admin(test_support::get("/admin/overview"))).await;
+        assert_eq!(status, StatusCode::OK);
+        let overview: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(overview["pending_images"], 1);
+        assert_eq!(overview["latest_mensajes"][0]["nombre"], "Ana García");
+        assert_eq!(overview["recent_spam"], serde_json::json!([]));
+        let storage = &overview["storage"];
+        assert_eq!((storage["images"].as_i64(), storage["image_bytes"].as_i64()), (Some(3), Some(147)));
+        assert_eq!(storage["trash_bytes"], 7);
+        assert_ne!(overview["health"]["status"], "down");
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn dates_are_shown_in_the_requested_time_zone() {
+        let Some(db) = TestDb::new().await else { return };
+        let app = db.app();
+
+        // Verano y luego invierno: el desfase de Madrid cambia entre los dos.
+        let ids: Vec<i32> = sqlx::query_scalar(
+            "INSERT INTO mensajes (nombre, mensaje, created_at) VALUES
+                ('Ana', 'En julio', '2026-07-01 10:30:00+00'), ('Luis', 'En enero', '2026-01-15 10:30:00+00')
+             RETURNING id",
+        )
+        .fetch_all(&db.pool)
+        .await
+        .unwrap();
+
+        let (_, body) = send(&app, test_support::get(&format!("/mensajes/{}/view", ids[0]))).await;
+        assert!(body.contains("01/07/2026 10:30 UTC"), "{body}");
+        let (_, body) = send(&app, test_support::get(&format!("/mensajes/{}/view?tz=Europe/Madrid", ids[0]))).await;
+        assert!(body.contains("01/07/2026 12:30 Europe/Madrid"), "{body}");
+        let (_, body) = send(&app, test_support::get(&format!("/mensajes/{}/view?tz=Europe/Madrid", ids[1]))).await;
+        assert!(body.contains("15/01/2026 11:30 Europe/Madrid"), "{body}");
+
+        let (status, body) = send(&app, test_support::get(&format!("/mensajes/{}/view?tz=Marte/Olympus", ids[0]))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.contains("Zona horaria desconocida"));
+
+        send(&app, as_admin(form(Method::DELETE, "/api/admin/mensajes/999", &[]))).await;
+        let (_, body) = send(&app, as_admin(test_support::get("/admin/audit?tz=America/Bogota"))).await;
+        assert!(body.contains("America/Bogota</td>"), "{body}");
+        assert!(body.contains(r#"name="tz" value="America/Bogota""#), "{body}");
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn old_mensajes_move_to_the_archive() {
+        let Some(db) = TestDb::with_config(&[("MESSAGES_ARCHIVE_AFTER_DAYS", "30")]).await else {
+            return;
+        };
+        let app = db.app();
+
+        let ids: Vec<i32> = sqlx::query_scalar(
+            "INSERT INTO mensajes (nombre, mensaje, created_at) VALUES
+                ('Antiguo', 'Del año pasado', now() - interval '400 days'), ('Reciente', 'De ayer', now() - interval '1 day')
+             RETURNING id",
+        )
+        .fetch_all(&db.pool)
+        .await
+        .unwrap();
+
+        let (_, body) = send(&app, as_admin(form(Method::POST, "/api/admin/mensajes/archive?dry_run=true", &[]))).await;
+        let preview: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!((preview["count"].as_i64(), preview["sample_ids"][0].as_i64()), (Some(1), Some(ids[0] as i64)));
+
+        let (_, body) = send(&app, as_admin(form(Method::POST, "/api/admin/mensajes/archive", &[]))).await;
+        assert!(body.contains("✅ 1 mensajes archivados"), "{body}");
+
+        let (_, body) = send(&app, test_support::get("/mensajes")).await;
+        let page: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(page["total"], 1);
+        assert_eq!(page["data"][0]["nombre"], "Reciente");
+
+        // Fuera del listado, pero el enlace permanente sigue valiendo.
+        let (status, body) = send(&app, test_support::get(&format!("/mensajes/{}", ids[0]))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("Del año pasado"), "{body}");
+        let (status, _) = send(&app, test_support::get(&format!("/mensajes/{}/view", ids[0]))).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (_, body) = send(&app, as_admin(form(Method::POST, "/api/admin/mensajes/archive", &[]))).await;
+        assert!(body.contains("✅ 0 mensajes archivados"), "{body}");
+
+        // Moderación edita y borra también lo archivado.
+        let uri = format!("/mensajes/{}", ids[0]);
+        let edit = [("nombre", "Antiguo"), ("mensaje", "Texto retirado a petición del autor")];
+        let (_, body) = send(&app, as_admin(form(Method::PUT, &uri, &edit))).await;
+        assert!(body.contains("✅ Mensaje actualizado"), "{body}");
+        let (_, body) = send(&app, test_support::get(&uri)).await;
+        assert!(body.contains("retirado a petición"), "{body}");
+
+        let (status, body) = send(&app, as_admin(form(Method::DELETE, &uri, &[]))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("✅ Mensaje eliminado"), "{body}");
+        let (status, _) = send(&app, test_support::get(&format!("{uri}/view"))).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (status, _) = send(&app, as_admin(form(Method::DELETE, &uri, &[]))).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn images_sort_and_filter() {
+        let Some(db) = TestDb::new().await else { return };
+        let app = db.app();
+
+        let files = [("a.png", "image/png", 100), ("b.png", "image/png", 3000), ("c.webp", "image/webp", 50)];
+        for (name, mime, size) in files {
+            let ext = name.rsplit('.').next().unwrap();
+            let req = MultipartBuilder::new()
+                .file("file", name, mime, &image_bytes(ext, size))
+                .into_request("/upload-image");
+            send(&app, as_admin(req)).await;
+        }
+
+        let (_, body) = send(&app, test_support::get("/images?sort=size&type=png")).await;
+        let images: serde_json::Value = serde_json::from_str(&body).unwrap();
+        let sizes: Vec<i64> = images["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|i| i["size_bytes"].as_i64().unwrap())
+            .collect();
+        assert_eq!(sizes, [3000, 100]);
+        assert_eq!(images["total"], 2);
+
+        let (_, body) = send(&app, test_support::get("/images?from=2000-01-01&to=2000-12-31")).await;
+        assert!(body.contains(r#""data":[],"total":0"#), "{body}");
+
+        let (status, _) = send(&app, test_support::get("/images?sort=id;DROP")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn thumbnail_is_generated_once_and_cached() {
+        use axum::body::to_bytes;
+        use tower::ServiceExt;
+
+        let Some(db) = TestDb::new().await else { return };
+        let app = db.app();
+
+        let mut png = std::io::Cursor::new(Vec::new());
+        image::RgbImage::new(800, 600)
+            .write_to(&mut png, image::ImageFormat::Png)
+            .unwrap();
+        let req = MultipartBuilder::new()
+            .file("file", "grande.png", "image/png", png.get_ref())
+            .into_request("/upload-image");
+        send(&app, as_admin(req)).await;
+        let id: i32 = sqlx::query_scalar("SELECT id FROM images").fetch_one(&db.pool).await.unwrap();
+
+        let uri = format!("/images/{id}/thumb");
+        let (a, b) = tokio::join!(
+            app.clone().oneshot(test_support::get(&uri)),
+            app.clone().oneshot(test_support::get(&uri)),
+        );
+        let (a, b) = (a.unwrap(), b.unwrap());
+        assert_eq!(a.status(), StatusCode::OK);
+        assert_eq!(b.status(), StatusCode::OK);
+        assert!(a.headers()["cache-control"].to_str().unwrap().contains("immutable"));
+
+        let bytes = to_bytes(a.into_body(), usize::MAX).await.unwrap();
+        let thumb = image::load_from_memory(&bytes).unwrap();
+        assert_eq!((thumb.width(), thumb.height()), (320, 240));
+
+        let (status, _) = send(&app, test_support::get("/images/999999/thumb")).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn upload_types_follow_configured_policy() {
+        use axum::body::to_bytes;
+        use tower::ServiceExt;
+
+        let overrides = [("UPLOAD_TYPES", "png:1K,webp:5M:thumb+to-png")];
+        let Some(db) = TestDb::with_config(&overrides).await else { return };
+        let app = db.app();
+        let upload = |name: &str, mime: &str, bytes: &[u8]| {
+            let req = MultipartBuilder::new().file("file", name, mime, bytes).into_request("/upload-image");
+            send(&app, as_admin(req))
+        };
+
+        let (_, body) = upload("moto.jpg", "image/jpeg", &image_bytes("jpg", 64)).await;
+        assert!(body.contains("Tipo de archivo no permitido"), "{body}");
+        let (_, body) = upload("moto.png", "image/png", &image_bytes("png", 2048)).await;
+        assert!(body.contains("máx 1KB"), "{body}");
+
+        let mut webp = std::io::Cursor::new(Vec::new());
+        image::RgbaImage::new(40, 30).write_to(&mut webp, image::ImageFormat::WebP).unwrap();
+        let (_, body) = upload("moto.webp", "image/webp", webp.get_ref()).await;
+        assert!(body.contains("✅"), "{body}");
+
+        let (id, filename, original): (i32, String, String) =
+            sqlx::query_as("SELECT id, filename, original_name FROM images")
+                .fetch_one(&db.pool)
+                .await
+                .unwrap();
+        assert!(filename.ends_with(".png") && original == "moto.png", "{filename} {original}");
+
+        // png no lleva miniatura: se sirve el original.
+        let res = app.clone().oneshot(test_support::get(&format!("/images/{id}/thumb"))).await.unwrap();
+        let bytes = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let img = image::load_from_memory_with_format(&bytes, image::ImageFormat::Png).unwrap();
+        assert_eq!((img.width(), img.height()), (40, 30));
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn visitor_uploads_wait_for_review() {
+        let Some(db) = TestDb::new().await else { return };
+        let app = db.app();
+        let upload = || {
+            let req = MultipartBuilder::new()
+                .file("file", "moto.png", "image/png", &image_bytes("png", 64))
+                .into_request("/upload-image");
+            send(&app, from_ip(req, "10.0.0.1"))
+        };
+
+        let (_, body) = upload().await;
+        assert!(body.contains("se publicará cuando se revise"), "{body}");
+        upload().await;
+        let pending: Vec<(i32, String)> = sqlx::query_as("SELECT id, filename FROM images ORDER BY id")
+            .fetch_all(&db.pool)
+            .await
+            .unwrap();
+        let [(approved, approved_file), (rejected, rejected_file)] = &pending[..] else { panic!("{pending:?}") };
+
+        let (_, body) = send(&app, test_support::get("/images")).await;
+        assert!(body.contains(r#""total":0"#), "{body}");
+        let (status, _) = send(&app, test_support::get(&format!("/uploads/{approved_file}"))).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (status, _) = send(&app, test_support::get(&format!("/uploads/.pending/{approved_file}"))).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let (status, _) = send(&app, test_support::get("/api/admin/images/pending")).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (_, body) = send(&app, as_admin(test_support::get("/api/admin/images/pending"))).await;
+        assert!(body.contains(r#""total":2"#), "{body}");
+        let (status, _) = send(&app, as_admin(test_support::get(&format!("/api/admin/images/{approved}/file")))).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let approve = format!("/api/admin/images/{approved}/approve");
+        let (_, body) = send(&app, as_admin(form(Method::POST, &approve, &[]))).await;
+        assert!(body.contains("✅"), "{body}");
+        let (status, _) = send(&app, test_support::get(&format!("/uploads/{approved_file}"))).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let reject = format!("/api/admin/images/{rejected}/reject");
+        let (_, body) = send(&app, as_admin(form(Method::POST, &reject, &[]))).await;
+        assert!(body.contains("✅"), "{body}");
+        assert!(!db.uploads.pending(rejected_file).unwrap().exists());
+
+        let (_, body) = send(&app, test_support::get("/images")).await;
+        assert!(body.contains(approved_file.as_str()) && body.contains(r#""total":1"#), "{body}");
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn upload_quota_is_enforced_per_ip() {
+        let Some(db) = TestDb::with_config(&[("UPLOAD_QUOTA_DAILY", "1")]).await else { return };
+        let app = db.app();
+        let upload = || {
+            let req = MultipartBuilder::new()
+                .file("file", "moto.png", "image/png", &image_bytes("png", 64))
+                .into_request("/upload-image");
+            from_ip(req, "10.0.0.1")
+        };
+
+        let (_, body) = send(&app, upload()).await;
+        assert!(body.contains("✅"), "{body}");
+
+        let (status, body) = send(&app, upload()).await;
+        assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+        assert!(body.contains("se renueva"), "{body}");
+
+        let (_, body) = send(&app, from_ip(test_support::get("/me/quota"), "10.0.0.1")).await;
+        assert!(body.contains("\"uploads_used\":1"), "{body}");
+        let (_, body) = send(&app, from_ip(test_support::get("/me/quota"), "10.0.0.2")).await;
+        assert!(body.contains("\"uploads_used\":0"), "{body}");
+
+        db.finish().await;
+    }
+}
@@ -0,0 +1,207 @@
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::header,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use crate::config::MetricsConfig;
+use crate::state::SharedState;
+
+pub const DEFAULT_BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+const NEST_TAIL: &str = "*__private__axum_nest_tail_param";
+
+pub struct Metrics {
+    buckets: Vec<f64>,
+    max_routes: usize,
+    inner: Mutex<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    routes: BTreeSet<String>,
+    requests: BTreeMap<(String, String, u16), u64>,
+    latency: BTreeMap<(String, String), Histogram>,
+    slow_queries: BTreeMap<&'static str, u64>,
+    budget_exceeded: BTreeMap<String, u64>,
+    /// Por caché: aciertos y fallos.
+    cache: BTreeMap<&'static str, (u64, u64)>,
+}
+
+struct Histogram {
+    counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Metrics {
+    pub fn new(config: &MetricsConfig) -> Self {
+        Metrics {
+            buckets: config.buckets.clone(),
+            max_routes: config.max_routes,
+            inner: Mutex::new(Inner::default()),
+        }
+    }
+
+    /// Protección de cardinalidad: pasado el límite, las rutas nuevas comparten etiqueta.
+    fn label(&self, inner: &mut Inner, route: String) -> String {
+        if inner.routes.contains(&route) {
+            route
+        } else if inner.routes.len() < self.max_routes {
+            inner.routes.insert(route.clone());
+            route
+        } else {
+            "other".to_string()
+        }
+    }
+
+    fn observe(&self, method: &str, route: String, status: u16, seconds: f64) {
+        let mut inner = self.inner.lock().unwrap();
+        let route = self.label(&mut inner, route);
+
+        *inner
+            .requests
+            .entry((method.to_string(), route.clone(), status))
+            .or_default() += 1;
+
+        let buckets = &self.buckets;
+        let histogram = inner
+            .latency
+            .entry((method.to_string(), route))
+            .or_insert_with(|| Histogram {
+                counts: vec![0; buckets.len()],
+                sum: 0.0,
+                count: 0,
+            });
+
+        for (i, le) in buckets.iter().enumerate() {
+            if seconds <= *le {
+                histogram.counts[i] += 1;
+            }
+        }
+        histogram.sum += seconds;
+        histogram.count += 1;
+    }
+
+    pub fn incr_slow_query(&self, query: &'static str) {
+        *self.inner.lock().unwrap().slow_queries.entry(query).or_default() += 1;
+    }
+
+    pub fn incr_query_budget_exceeded(&self, route: String) {
+        let mut inner = self.inner.lock().unwrap();
+        let route = self.label(&mut inner, route);
+        *inner.budget_exceeded.entry(route).or_default() += 1;
+    }
+
+    pub fn incr_cache(&self, cache: &'static str, hit: bool) {
+        let mut inner = self.inner.lock().unwrap();
+        let (hits, misses) = inner.cache.entry(cache).or_default();
+        *(if hit { hits } else { misses }) += 1;
+    }
+
+    /// Peticiones atendidas y cuántas acabaron en 5xx, desde el arranque.
+    pub fn request_totals(&self) -> (u64, u64) {
+        let inner = self.inner.lock().unwrap();
+        inner.requests.iter().fold((0, 0), |(total, errors), ((_, _, status), count)| {
+            (total + count, if *status >= 500 { errors + count } else { errors })
+        })
+    }
+
+    pub fn render(&self) -> String {
+        let inner = self.inner.lock().unwrap();
+        let mut out = String::new();
+
+        out.push_str("# HELP http_requests_total Total de peticiones HTTP.\n");
+        out.push_str("# TYPE http_requests_total counter\n");
+        for ((method, route, status), count) in &inner.requests {
+            let _ = writeln!(
+                out,
+                "http_requests_total{{method=\"{method}\",route=\"{route}\",status=\"{status}\"}} {count}"
+            );
+        }
+
+        out.push_str("# HELP http_request_duration_seconds Latencia de las peticiones HTTP.\n");
+        out.push_str("# TYPE http_request_duration_seconds histogram\n");
+        for ((method, route), h) in &inner.latency {
+            let labels = format!("method=\"{method}\",route=\"{route}\"");
+            for (le, count) in self.buckets.iter().zip(&h.counts) {
+                let _ = writeln!(
+                    out,
+                    "http_request_duration_seconds_bucket{{{labels},le=\"{le}\"}} {count}"
+                );
+            }
+            let _ = writeln!(
+                out,
+                "http_request_duration_seconds_bucket{{{labels},le=\"+Inf\"}} {}",
+                h.count
+            );
+            let _ = writeln!(out, "http_request_duration_seconds_sum{{{labels}}} {}", h.sum);
+            let _ = writeln!(out, "http_request_duration_seconds_count{{{labels}}} {}", h.count);
+        }
+
+        out.push_str("# HELP db_slow_queries_total Consultas que superaron el umbral de lentitud.\n");
+        out.push_str("# TYPE db_slow_queries_total counter\n");
+        for (query, count) in &inner.slow_queries {
+            let _ = writeln!(out, "db_slow_queries_total{{query=\"{query}\"}} {count}");
+        }
+
+        out.push_str("# HELP db_query_budget_exceeded_total Peticiones que superaron DB_QUERY_BUDGET.\n");
+        out.push_str("# TYPE db_query_budget_exceeded_total counter\n");
+        for (route, count) in &inner.budget_exceeded {
+            let _ = writeln!(out, "db_query_budget_exceeded_total{{route=\"{route}\"}} {count}");
+        }
+
+        out.push_str("# HELP cache_requests_total Lecturas de las cachés en memoria, por resultado.\n");
+        out.push_str("# TYPE cache_requests_total counter\n");
+        for (cache, (hits, misses)) in &inner.cache {
+            let _ = writeln!(out, "cache_requests_total{{cache=\"{cache}\",result=\"hit\"}} {hits}");
+            let _ = writeln!(out, "cache_requests_total{{cache=\"{cache}\",result=\"miss\"}} {misses}");
+        }
+
+        out
+    }
+}
+
+/* ---------- MIDDLEWARE ---------- */
+
+pub async fn track(State(metrics): State<Arc<Metrics>>, req: Request, next: Next) -> Response {
+    let start = Instant::now();
+    let method = req.method().to_string();
+    let route = route_label(&req);
+
+    let res = next.run(req).await;
+
+    metrics.observe(
+        &method,
+        route,
+        res.status().as_u16(),
+        start.elapsed().as_secs_f64(),
+    );
+    res
+}
+
+/// Etiqueta de ruta basada en la plantilla (`/mensajes/:id`), nunca en la URL real.
+pub fn route_label(req: &Request) -> String {
+    match req.extensions().get::<MatchedPath>() {
+        Some(path) => path.as_str().replace(NEST_TAIL, "*"),
+        None => "unmatched".to_string(),
+    }
+}
+
+/* ---------- ENDPOINT ---------- */
+
+pub async fn metrics_handler(State(app): State<SharedState>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        app.metrics.render(),
+    )
+}
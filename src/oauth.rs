@@ -0,0 +1,373 @@
+//! Acceso a administración con Google o GitHub (OAuth2, flujo de código).
+//! `GET /auth/google` (o `/auth/github`) lleva al proveedor y `GET /auth/callback`
+//! cambia el código por el email verificado de la cuenta; si está en
+//! `admin_accounts` se abre una sesión de administración como la de
+//! `/admin/login`. Las cuentas autorizadas se gestionan en `/api/admin/accounts`.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{Html, IntoResponse, Redirect, Response},
+    routing::get,
+    Form, Json, Router,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::admin;
+use crate::admin_session;
+use crate::audit_log::{self, Change};
+use crate::config::{OAuthClient, OAuthConfig};
+use crate::db::DbError;
+use crate::flash::{self, Flash};
+use crate::html::{self, url_encode};
+use crate::policy::Principal;
+use crate::queries;
+use crate::state::SharedState;
+
+/// Guarda proveedor y `state` entre la ida y la vuelta: `google.<aleatorio>`.
+const STATE_COOKIE: &str = "oauth_state";
+
+/// Tiempo para completar el login en el proveedor.
+const STATE_MAX_AGE: Duration = Duration::from_secs(600);
+
+const GOOGLE_USERINFO: &str = "https://openidconnect.googleapis.com/v1/userinfo";
+const GITHUB_EMAILS: &str = "https://api.github.com/user/emails";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Provider {
+    Google,
+    GitHub,
+}
+
+impl Provider {
+    const ALL: [Provider; 2] = [Provider::Google, Provider::GitHub];
+
+    /// Nombre en rutas, cookies y en la columna `provider`.
+    pub fn slug(self) -> &'static str {
+        match self {
+            Provider::Google => "google",
+            Provider::GitHub => "github",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Provider::Google => "Google",
+            Provider::GitHub => "GitHub",
+        }
+    }
+
+    fn parse(slug: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|p| p.slug() == slug)
+    }
+
+    fn client(self, config: &OAuthConfig) -> Option<&OAuthClient> {
+        match self {
+            Provider::Google => config.google.as_ref(),
+            Provider::GitHub => config.github.as_ref(),
+        }
+    }
+
+    fn authorize_url(self) -> &'static str {
+        match self {
+            Provider::Google => "https://accounts.google.com/o/oauth2/v2/auth",
+            Provider::GitHub => "https://github.com/login/oauth/authorize",
+        }
+    }
+
+    fn token_url(self) -> &'static str {
+        match self {
+            Provider::Google => "https://oauth2.googleapis.com/token",
+            Provider::GitHub => "https://github.com/login/oauth/access_token",
+        }
+    }
+
+    /// Lo justo para leer el email.
+    fn scope(self) -> &'static str {
+        match self {
+            Provider::Google => "openid email",
+            Provider::GitHub => "user:email",
+        }
+    }
+}
+
+/// Proveedores con cliente configurado, para los botones del login.
+pub fn providers(config: &OAuthConfig) -> Vec<Provider> {
+    Provider::ALL.into_iter().filter(|p| p.client(config).is_some()).collect()
+}
+
+/// `/auth/:provider` y `/auth/callback`, con el límite de administración.
+pub fn routes(state: &SharedState) -> Router<SharedState> {
+    let router = Router::new()
+        .route("/callback", get(callback))
+        .route("/:provider", get(authorize));
+    admin::throttle(router, state)
+}
+
+fn redirect_uri(app: &SharedState, headers: &HeaderMap) -> String {
+    format!("{}/auth/callback", html::base_url(app.config.server.public_url.as_deref(), headers))
+}
+
+/// `SameSite=Lax`: la vuelta desde el proveedor es una navegación de otro sitio.
+fn state_cookie(value: &str, max_age: Duration) -> HeaderValue {
+    format!(
+        "{STATE_COOKIE}={value}; Path=/auth; Max-Age={}; HttpOnly; Secure; SameSite=Lax",
+        max_age.as_secs()
+    )
+    .parse()
+    .unwrap()
+}
+
+/// Proveedor y `state` guardados en la cookie de la ida.
+fn pending_login(headers: &HeaderMap) -> Option<(Provider, &str)> {
+    let value = headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .find_map(|pair| pair.trim().strip_prefix(STATE_COOKIE)?.strip_prefix('='))?;
+    let (slug, state) = value.split_once('.')?;
+    let provider = Provider::parse(slug)?;
+    (!state.is_empty()).then_some((provider, state))
+}
+
+/* ---------- HANDLERS ---------- */
+
+async fn authorize(State(app): State<SharedState>, Path(slug): Path<String>, headers: HeaderMap) -> Response {
+    let Some((provider, client)) = Provider::parse(&slug).and_then(|p| Some((p, p.client(&app.config.oauth)?)))
+    else {
+        return (StatusCode::NOT_FOUND, Html("❌ Proveedor no disponible")).into_response();
+    };
+
+    let state = Uuid::new_v4().simple().to_string();
+    let url = format!(
+        "{}?response_type=code&client_id={}&redirect_uri={}&scope={}&state={state}",
+        provider.authorize_url(),
+        url_encode(&client.client_id),
+        url_encode(&redirect_uri(&app, &headers)),
+        url_encode(provider.scope()),
+    );
+
+    let mut res = Redirect::to(&url).into_response();
+    res.headers_mut().append(
+        header::SET_COOKIE,
+        state_cookie(&format!("{}.{state}", provider.slug()), STATE_MAX_AGE),
+    );
+    res
+}
+
+#[derive(Deserialize)]
+struct CallbackQuery {
+    code: Option<String>,
+    state: Option<String>,
+    /// El proveedor la manda si el usuario cancela.
+    error: Option<String>,
+}
+
+async fn callback(
+    State(app): State<SharedState>,
+    headers: HeaderMap,
+    Query(query): Query<CallbackQuery>,
+) -> Response {
+    let mut res = match sign_in(&app, &headers, query).await {
+        Ok(res) => res,
+        Err(message) => flash::redirect("/admin/login", Flash::error(message)),
+    };
+    res.headers_mut().append(header::SET_COOKIE, state_cookie("", Duration::ZERO));
+    res
+}
+
+async fn sign_in(app: &SharedState, headers: &HeaderMap, query: CallbackQuery) -> Result<Response, String> {
+    let expired = "❌ El inicio de sesión caducó; vuelve a intentarlo".to_string();
+    let Some((provider, expected)) = pending_login(headers) else { return Err(expired) };
+    if query.state.as_deref() != Some(expected) {
+        return Err(expired);
+    }
+    let (None, Some(code)) = (query.error, query.code) else {
+        return Err("❌ Acceso cancelado".to_string());
+    };
+    let Some(client) = provider.client(&app.config.oauth) else { return Err(expired) };
+
+    let email = match verified_email(provider, client, &code, &redirect_uri(app, headers)).await {
+        Ok(email) => email,
+        Err(e) => {
+            tracing::warn!(error = %e, provider = provider.slug(), "no se pudo completar OAuth");
+            return Err(format!("❌ No se pudo contactar con {}", provider.label()));
+        }
+    };
+
+    let authorized = match email {
+        Some(email) => authorize_account(app, provider, &email).await.map_err(|e| {
+            tracing::error!(error = ?e, "no se pudo comprobar la cuenta de administración");
+            "❌ Error de base de datos".to_string()
+        })?,
+        None => None,
+    };
+
+    let Some(email) = authorized else {
+        tracing::warn!(target: "audit", provider = provider.slug(), "cuenta externa no autorizada");
+        return Err("❌ Cuenta no autorizada".to_string());
+    };
+
+    tracing::info!(target: "audit", provider = provider.slug(), email, "sesión de administración iniciada");
+    Ok(admin_session::sign_in(app, None, true).await)
+}
+
+/// Email de la cuenta si está en `admin_accounts`; de paso apunta el acceso.
+async fn authorize_account(app: &SharedState, provider: Provider, email: &str) -> Result<Option<String>, DbError> {
+    queries::oauth::login(&app.db, provider.slug(), email).await
+}
+
+/* ---------- PROVEEDORES ---------- */
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+}
+
+#[derive(Deserialize)]
+struct GoogleUser {
+    email: Option<String>,
+    #[serde(default)]
+    email_verified: bool,
+}
+
+#[derive(Deserialize)]
+struct GitHubEmail {
+    email: String,
+    primary: bool,
+    verified: bool,
+}
+
+/// Cambia el código por un token y con él pide el email. `None` si la cuenta
+/// no tiene un email verificado: no sirve para identificarla.
+async fn verified_email(
+    provider: Provider,
+    client: &OAuthClient,
+    code: &str,
+    redirect_uri: &str,
+) -> Result<Option<String>, reqwest::Error> {
+    // GitHub exige `User-Agent` en su API.
+    let http = reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .user_agent(concat!("hola_axum/", env!("CARGO_PKG_VERSION")))
+        .build()?;
+
+    let token: TokenResponse = http
+        .post(provider.token_url())
+        .header(header::ACCEPT, "application/json")
+        .form(&[
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("client_id", &client.client_id),
+            ("client_secret", &client.client_secret),
+            ("redirect_uri", redirect_uri),
+        ])
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    match provider {
+        Provider::Google => {
+            let user: GoogleUser = http
+                .get(GOOGLE_USERINFO)
+                .bearer_auth(&token.access_token)
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+            Ok(user.email.filter(|_| user.email_verified))
+        }
+        Provider::GitHub => {
+            let emails: Vec<GitHubEmail> = http
+                .get(GITHUB_EMAILS)
+                .bearer_auth(&token.access_token)
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+            Ok(emails.into_iter().find(|e| e.primary && e.verified).map(|e| e.email))
+        }
+    }
+}
+
+/* ---------- /api/admin/accounts ---------- */
+
+#[derive(Serialize)]
+pub struct Account {
+    pub id: i32,
+    pub provider: String,
+    pub email: String,
+    pub created_at: DateTime<Utc>,
+    pub last_login_at: Option<DateTime<Utc>>,
+}
+
+pub async fn list_accounts(State(app): State<SharedState>) -> Result<Json<Vec<Account>>, DbError> {
+    Ok(Json(queries::oauth::list(&app.db).await?))
+}
+
+#[derive(Deserialize)]
+pub struct NewAccount {
+    provider: String,
+    email: String,
+}
+
+pub async fn add_account(State(app): State<SharedState>, principal: Principal, Form(new): Form<NewAccount>) -> Response {
+    let Some(provider) = Provider::parse(&new.provider) else {
+        return (StatusCode::BAD_REQUEST, Html("❌ Proveedor desconocido")).into_response();
+    };
+    let email = new.email.trim();
+    if !email.contains('@') || email.len() > 254 {
+        return (StatusCode::BAD_REQUEST, Html("❌ Email inválido")).into_response();
+    }
+
+    match queries::oauth::insert(&app.db, provider.slug(), email).await {
+        Ok(None) => Html("✅ La cuenta ya estaba autorizada").into_response(),
+        Ok(Some(id)) => {
+            let change = Change::new("account.add", format!("account:{id}"))
+                .new_value(serde_json::json!({ "provider": provider.slug(), "email": email }));
+            audit_log::record_change(&app.db, &principal, change).await;
+            (StatusCode::CREATED, Html("✅ Cuenta autorizada")).into_response()
+        }
+        Err(e) => e.into_response(),
+    }
+}
+
+/// Las sesiones ya abiertas siguen hasta caducar; solo se impiden las nuevas.
+pub async fn remove_account(State(app): State<SharedState>, principal: Principal, Path(id): Path<i32>) -> Response {
+    match queries::oauth::delete(&app.db, id).await {
+        Ok(None) => (StatusCode::NOT_FOUND, Html("❌ Cuenta no encontrada")).into_response(),
+        Ok(Some((provider, email))) => {
+            let change = Change::new("account.remove", format!("account:{id}"))
+                .old(serde_json::json!({ "provider": provider, "email": email }));
+            audit_log::record_change(&app.db, &principal, change).await;
+            Html("✅ Cuenta retirada").into_response()
+        }
+        Err(e) => e.into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_provider_and_state_from_cookie() {
+        let mut headers = HeaderMap::new();
+        headers.append(header::COOKIE, "flash=x; oauth_state=github.abc123".parse().unwrap());
+        assert_eq!(pending_login(&headers), Some((Provider::GitHub, "abc123")));
+
+        for bad in ["oauth_state=gitlab.abc", "oauth_state=google.", "oauth_state=google"] {
+            let mut headers = HeaderMap::new();
+            headers.append(header::COOKIE, bad.parse().unwrap());
+            assert_eq!(pending_login(&headers), None, "{bad}");
+        }
+    }
+}
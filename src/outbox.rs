@@ -0,0 +1,290 @@
+//! Avisos fiables de los eventos: el webhook (`EVENTS_WEBHOOK_URL`) y el correo
+//! de cada mensaje nuevo (`NOTIFY_EMAIL`). Quien escribe guarda el evento con
+//! `enqueue` en la misma `UnitOfWork` que el cambio: si la transacción se
+//! deshace no hay aviso, y si se confirma el aviso sale aunque el proceso caiga
+//! justo después. Los correos a los autores (verificación y confirmación) van
+//! igual, con `enqueue_email`. `dispatch` los envía en segundo plano, marca cada canal al
+//! terminar y reintenta lo que falle con una espera creciente; tras
+//! `MAX_ATTEMPTS` la fila se queda en la tabla, con `last_error`, para mirarla.
+//!
+//! Cada réplica reserva la fila antes de enviarla (`FOR UPDATE SKIP LOCKED` y
+//! un plazo, `LEASE`), así que dos réplicas no mandan el mismo aviso. Si el
+//! proceso cae entre el envío y la marca, se repite al vencer el plazo: para
+//! descartarlo, webhook y correo llevan `Idempotency-Key: outbox-<id>`.
+
+use sqlx::{PgConnection, PgPool};
+use std::time::Duration;
+
+use crate::db::DbError;
+use crate::events::Event;
+use crate::mailer::Email;
+use crate::queries;
+use crate::state::{AppState, SharedState};
+
+/// Sin eventos publicados aquí, cada cuánto se miran los reintentos y lo de otras réplicas.
+const POLL: Duration = Duration::from_secs(30);
+
+/// Lo que una réplica se reserva una fila; más que los dos envíos juntos.
+const LEASE: Duration = Duration::from_secs(60);
+
+const MAX_ATTEMPTS: i32 = 10;
+
+/// Lo ya enviado se borra pasado este tiempo.
+const RETENTION_DAYS: i32 = 7;
+
+pub const IDEMPOTENCY_HEADER: &str = "idempotency-key";
+
+/// Dentro de la transacción del cambio; los canales se deciden al enviar.
+pub async fn enqueue(conn: &mut PgConnection, event: &Event) -> Result<(), DbError> {
+    queries::outbox::insert(conn, event).await
+}
+
+/// Un correo ya redactado, en la misma transacción que lo que enlaza. Tras
+/// confirmarla, `wake` para que salga sin esperar a `POLL`.
+pub async fn enqueue_email(conn: &mut PgConnection, email: &Email) -> Result<(), DbError> {
+    queries::outbox::insert_email(conn, email).await
+}
+
+/// Despierta a `dispatch` en esta réplica.
+pub fn wake(app: &AppState) {
+    app.outbox.notify_one();
+}
+
+/// Espera antes del reintento número `attempts`: de 30 s a una hora.
+fn backoff(attempts: i32) -> Duration {
+    Duration::from_secs(30 << attempts.clamp(1, 8).saturating_sub(1)).min(Duration::from_secs(3600))
+}
+
+enum Message {
+    Event(Event),
+    Email(Email),
+}
+
+impl Message {
+    fn name(&self) -> &'static str {
+        match self {
+            Message::Event(event) => event.name(),
+            Message::Email(_) => "Email",
+        }
+    }
+}
+
+struct Pending {
+    id: i64,
+    message: Message,
+    attempts: i32,
+    webhook_sent: bool,
+    email_sent: bool,
+}
+
+impl Pending {
+    fn key(&self) -> String {
+        format!("outbox-{}", self.id)
+    }
+}
+
+/// La fila pendiente más antigua, reservada durante `LEASE`.
+async fn claim(pool: &PgPool) -> Result<Option<Pending>, DbError> {
+    let Some(queries::outbox::Claimed { id, payload, is_email, attempts, webhook_sent, email_sent }) =
+        queries::outbox::claim(pool, LEASE, MAX_ATTEMPTS).await?
+    else {
+        return Ok(None);
+    };
+    let message = if is_email {
+        serde_json::from_str(&payload).map(Message::Email)
+    } else {
+        serde_json::from_str(&payload).map(Message::Event)
+    };
+    match message {
+        Ok(message) => Ok(Some(Pending { id, message, attempts, webhook_sent, email_sent })),
+        Err(e) => {
+            // Un evento que ya no se entiende no se va a entender reintentando.
+            tracing::error!(error = %e, id, "evento ilegible en outbox");
+            queries::outbox::abandon(pool, id, MAX_ATTEMPTS, &e.to_string()).await?;
+            Ok(None)
+        }
+    }
+}
+
+async fn failed(pool: &PgPool, pending: &Pending, error: &str) -> Result<(), DbError> {
+    let attempts = pending.attempts + 1;
+    if attempts >= MAX_ATTEMPTS {
+        tracing::error!(id = pending.id, event = pending.message.name(), error, "aviso abandonado tras {attempts} intentos");
+    } else {
+        tracing::warn!(id = pending.id, event = pending.message.name(), error, "aviso fallido; se reintentará");
+    }
+    queries::outbox::failed(pool, pending.id, attempts, error, backoff(attempts)).await
+}
+
+/// Los canales que falten de `pending`; el primero que falle corta. Un
+/// correo a un autor no tiene más canal que el suyo.
+async fn deliver(app: &SharedState, http: &reqwest::Client, pending: &Pending) -> Result<(), String> {
+    let config = &app.config.events;
+    let pool = &app.jobs_db;
+    let event = match &pending.message {
+        Message::Event(event) => event,
+        Message::Email(email) => {
+            return app.mailer.send(email, Some(&pending.key())).await.map_err(|e| format!("correo: {e}"));
+        }
+    };
+
+    if let Some(url) = config.webhook.as_ref().filter(|_| !pending.webhook_sent) {
+        http.post(url)
+            .header(IDEMPOTENCY_HEADER, pending.key())
+            .json(event)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| format!("webhook: {e}"))?;
+        queries::outbox::mark(pool, pending.id, "webhook_sent_at").await.map_err(|e| format!("{e:?}"))?;
+    }
+
+    if let (Some(to), Event::MessageCreated { id }, false) = (&config.notify_email, event, pending.email_sent) {
+        let email = new_message_email(to, *id, app.config.server.public_url.as_deref());
+        app.mailer.send(&email, Some(&pending.key())).await.map_err(|e| format!("correo: {e}"))?;
+        queries::outbox::mark(pool, pending.id, "email_sent_at").await.map_err(|e| format!("{e:?}"))?;
+    }
+    Ok(())
+}
+
+/// Envía la siguiente fila pendiente; `false` si no quedaba ninguna.
+async fn deliver_next(app: &SharedState, http: &reqwest::Client) -> Result<bool, DbError> {
+    let Some(pending) = claim(&app.jobs_db).await? else { return Ok(false) };
+    match deliver(app, http, &pending).await {
+        Ok(()) => queries::outbox::mark(&app.jobs_db, pending.id, "done_at").await?,
+        Err(error) => failed(&app.jobs_db, &pending, &error).await?,
+    }
+    Ok(true)
+}
+
+/// En segundo plano, en cada réplica. Un evento publicado aquí (o `wake`) la
+/// despierta al momento; lo demás (reintentos, otras réplicas) espera a `POLL`.
+pub async fn dispatch(app: SharedState) {
+    let http = reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()
+        .expect("no se pudo crear el cliente HTTP de avisos");
+    let mut events = app.events.subscribe();
+
+    loop {
+        loop {
+            match deliver_next(&app, &http).await {
+                Ok(true) => {}
+                Ok(false) => break,
+                Err(e) => {
+                    tracing::warn!(error = ?e, "no se pudo leer la outbox");
+                    break;
+                }
+            }
+        }
+        if let Err(e) = queries::outbox::purge(&app.jobs_db, RETENTION_DAYS).await {
+            tracing::warn!(error = ?e, "no se pudo purgar la outbox");
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(POLL) => {}
+            _ = events.recv() => {}
+            _ = app.outbox.notified() => {}
+        }
+    }
+}
+
+fn new_message_email(to: &str, id: i32, public_url: Option<&str>) -> Email {
+    let link = format!("{}/mensajes/{id}/view", public_url.unwrap_or("").trim_end_matches('/'));
+    Email {
+        to: to.to_string(),
+        subject: format!("Nuevo mensaje #{id} | Axum Motors"),
+        text: format!("Ha llegado un mensaje nuevo:\n{link}"),
+        html: format!(r#"<p>Ha llegado un mensaje nuevo: <a href="{link}">#{id}</a></p>"#),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::Metrics;
+    use crate::state::AppState;
+    use crate::test_support::{form, from_ip, send, TestDb};
+    use crate::unit_of_work::UnitOfWork;
+    use axum::{http::HeaderMap, http::Method, http::StatusCode, routing::post, Router};
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn retries_back_off_up_to_an_hour() {
+        assert_eq!(backoff(1), Duration::from_secs(30));
+        assert_eq!(backoff(2), Duration::from_secs(60));
+        assert_eq!(backoff(9), Duration::from_secs(3600));
+    }
+
+    /// Webhook y proveedor de correo que apuntan lo que reciben (clave y
+    /// destino); el correo falla la primera vez.
+    async fn fake_receivers() -> (String, Arc<Mutex<Vec<String>>>) {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let (webhook, mail) = (calls.clone(), calls.clone());
+        let key = |h: &HeaderMap| h[IDEMPOTENCY_HEADER].to_str().unwrap().to_string();
+        let receivers = Router::new()
+            .route(
+                "/webhook",
+                post(move |headers: HeaderMap| async move {
+                    webhook.lock().unwrap().push(format!("webhook {}", key(&headers)));
+                }),
+            )
+            .route(
+                "/mail",
+                post(move |headers: HeaderMap| async move {
+                    let mut calls = mail.lock().unwrap();
+                    let first = !calls.iter().any(|c| c.starts_with("mail"));
+                    calls.push(format!("mail {}", key(&headers)));
+                    if first { StatusCode::SERVICE_UNAVAILABLE } else { StatusCode::OK }
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, receivers).await });
+        (url, calls)
+    }
+
+    #[tokio::test]
+    async fn committed_events_are_delivered_once_per_channel() {
+        let (url, calls) = fake_receivers().await;
+        let (webhook, mail) = (format!("{url}/webhook"), format!("{url}/mail"));
+        let overrides = [("EVENTS_WEBHOOK_URL", webhook.as_str()), ("NOTIFY_EMAIL", "aviso@motos.example"), ("MAIL_API_URL", mail.as_str()), ("PUBLIC_URL", "https://motos.example")];
+        let Some(db) = TestDb::with_config(&overrides).await else { return };
+        let metrics = Arc::new(Metrics::new(&db.config.metrics));
+        let app = AppState::new(db.pool.clone(), db.jobs.clone(), db.config.clone(), db.uploads.clone(), metrics);
+        let http = reqwest::Client::new();
+
+        // Lo que se deshace no deja aviso.
+        let mut uow = UnitOfWork::begin(&db.pool).await.unwrap();
+        enqueue(uow.conn(), &Event::MessageDeleted { id: 1 }).await.unwrap();
+        drop(uow);
+
+        let mensaje = [
+            ("nombre", "Ana García"),
+            ("mensaje", "Un mensaje de prueba suficientemente largo"),
+            ("g-recaptcha-response", "token"),
+        ];
+        let (status, _) = send(&db.app(), from_ip(form(Method::POST, "/enviar", &mensaje), "10.0.0.8")).await;
+        assert!(status.is_success() || status.is_redirection(), "{status}");
+        let (id, event): (i64, String) = sqlx::query_as("SELECT id, event->>'type' FROM outbox").fetch_one(&db.pool).await.unwrap();
+        assert_eq!(event, "MessageCreated");
+
+        // El webhook sale; el correo falla y queda para más tarde.
+        assert!(deliver_next(&app, &http).await.unwrap());
+        assert!(!deliver_next(&app, &http).await.unwrap());
+        let (attempts, error): (i32, Option<String>) =
+            sqlx::query_as("SELECT attempts, last_error FROM outbox").fetch_one(&db.pool).await.unwrap();
+        assert_eq!(attempts, 1);
+        assert!(error.unwrap().starts_with("correo:"));
+
+        // En el reintento solo va el correo.
+        sqlx::query("UPDATE outbox SET next_attempt_at = now()").execute(&db.pool).await.unwrap();
+        assert!(deliver_next(&app, &http).await.unwrap());
+        assert!(!deliver_next(&app, &http).await.unwrap());
+        let key = format!("outbox-{id}");
+        assert_eq!(*calls.lock().unwrap(), [format!("webhook {key}"), format!("mail {key}"), format!("mail {key}")]);
+        let done: bool = sqlx::query_scalar("SELECT done_at IS NOT NULL FROM outbox").fetch_one(&db.pool).await.unwrap();
+        assert!(done);
+
+        db.finish().await;
+    }
+}
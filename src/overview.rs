@@ -0,0 +1,118 @@
+//! `GET /admin/overview`: lo que enseña la portada del panel en una sola
+//! respuesta JSON (pendientes de revisar, spam reciente, últimos mensajes,
+//! almacenamiento y salud), con las consultas en paralelo.
+
+use std::time::Duration;
+
+use axum::{
+    extract::State,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::html;
+use crate::queries;
+use crate::state::SharedState;
+use crate::watchdog;
+
+/// Elementos de cada lista.
+const RECENT: i64 = 5;
+
+/// Para la sonda de la base de datos: más que esto cuenta como caída.
+const DB_PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Serialize)]
+pub struct Overview {
+    pending_images: i64,
+    /// Lo último de `spam_log`.
+    recent_spam: Vec<SpamItem>,
+    latest_mensajes: Vec<MensajeItem>,
+    storage: Storage,
+    health: Health,
+}
+
+#[derive(Serialize)]
+struct SpamItem {
+    id: i64,
+    reason: String,
+    ip: Option<String>,
+    created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+struct MensajeItem {
+    id: i32,
+    nombre: String,
+    excerpt: String,
+    created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+struct Storage {
+    images: i64,
+    image_bytes: i64,
+    /// De lo anterior, lo que está en la papelera.
+    trash_bytes: i64,
+    /// Ocupación del disco de subidas; `null` si no se pudo medir.
+    disk_usage_pct: Option<f64>,
+}
+
+#[derive(Serialize)]
+struct Health {
+    /// `ok`, `degraded` (pasa algún umbral de `ALERT_*`) o `down` (sin base de datos).
+    status: &'static str,
+    db_latency_ms: Option<f64>,
+}
+
+pub async fn overview(State(app): State<SharedState>) -> Response {
+    let excerpt = app.config.content.excerpt_chars;
+    let (data, db_latency) = tokio::join!(
+        async {
+            tokio::try_join!(
+                queries::overview::pending_images(&app.db),
+                queries::overview::recent_spam(&app.db, RECENT),
+                queries::overview::latest_mensajes(&app.db, RECENT),
+                queries::overview::image_storage(&app.db),
+            )
+        },
+        watchdog::probe_db(&app.db, DB_PROBE_TIMEOUT),
+    );
+    let (pending_images, recent_spam, latest_mensajes, (images, image_bytes, trash_bytes)) = match data {
+        Ok(data) => data,
+        Err(e) => return e.into_response(),
+    };
+
+    let disk_usage_pct = watchdog::disk_usage_pct(app.uploads.dir());
+    let alerts = &app.config.alerts;
+    let status = match db_latency {
+        None => "down",
+        Some(latency) if latency > alerts.db_latency => "degraded",
+        _ if disk_usage_pct.is_some_and(|pct| pct > alerts.disk_usage_pct) => "degraded",
+        _ => "ok",
+    };
+
+    let recent_spam = recent_spam
+        .into_iter()
+        .map(|(id, reason, ip, created_at)| SpamItem { id, reason, ip, created_at })
+        .collect();
+    let latest_mensajes = latest_mensajes
+        .into_iter()
+        .map(|(id, nombre, mensaje, created_at)| MensajeItem {
+            id,
+            nombre,
+            excerpt: html::excerpt_chars(&mensaje, excerpt),
+            created_at,
+        })
+        .collect();
+
+    Json(Overview {
+        pending_images,
+        recent_spam,
+        latest_mensajes,
+        storage: Storage { images, image_bytes, trash_bytes, disk_usage_pct },
+        health: Health { status, db_latency_ms: db_latency.map(|d| d.as_secs_f64() * 1000.0) },
+    })
+    .into_response()
+}
@@ -0,0 +1,132 @@
+//! Paginación común a los listados: mismos parámetros (`page`, `per_page`,
+//! `cursor`) y misma envoltura de respuesta en todos los endpoints. El tamaño
+//! por defecto y el máximo dependen de la ruta (`PAGE_SIZES`, ver
+//! `config::PaginationConfig`); `PageQuery` como extractor ya los aplica.
+
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, MatchedPath, Query},
+    http::request::Parts,
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::state::SharedState;
+
+pub const DEFAULT_PER_PAGE: i64 = 20;
+pub const MAX_PER_PAGE: i64 = 100;
+
+/// Tamaño de página sin `per_page` y tope para el que pida el cliente.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct PageSize {
+    pub default: i64,
+    pub max: i64,
+}
+
+impl Default for PageSize {
+    fn default() -> Self {
+        PageSize { default: DEFAULT_PER_PAGE, max: MAX_PER_PAGE }
+    }
+}
+
+#[derive(Deserialize, Default)]
+pub struct PageQuery {
+    pub page: Option<i64>,
+    pub per_page: Option<i64>,
+    /// `next_cursor` de la respuesta anterior; sustituye a `page` donde se admite.
+    pub cursor: Option<String>,
+    #[serde(skip)]
+    pub size: PageSize,
+}
+
+#[async_trait]
+impl FromRequestParts<SharedState> for PageQuery {
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &SharedState) -> Result<Self, Self::Rejection> {
+        let Query(mut query) = Query::<PageQuery>::from_request_parts(parts, state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+        let route = parts.extensions.get::<MatchedPath>().map_or("", MatchedPath::as_str);
+        query.size = state.config.pagination.size(route);
+        Ok(query)
+    }
+}
+
+impl PageQuery {
+    /// Con tope, para que ni el `OFFSET` ni el enlace a la página siguiente desborden.
+    pub fn page(&self) -> i64 {
+        self.page.unwrap_or(1).clamp(1, i64::MAX / self.per_page() - 1)
+    }
+
+    pub fn per_page(&self) -> i64 {
+        self.per_page.unwrap_or(self.size.default).clamp(1, self.size.max)
+    }
+
+    pub fn offset(&self) -> i64 {
+        (self.page() - 1) * self.per_page()
+    }
+}
+
+/// Número de páginas para `total` elementos; al menos una aunque no haya ninguno.
+pub fn total_pages(total: i64, per_page: i64) -> i64 {
+    ((total + per_page - 1) / per_page).max(1)
+}
+
+#[derive(Serialize)]
+pub struct Paginated<T> {
+    pub data: Vec<T>,
+    pub total: i64,
+    pub page: i64,
+    pub per_page: i64,
+    pub total_pages: i64,
+    /// Para seguir desde el último elemento aunque entren datos nuevos. Solo en
+    /// listados con orden estable por id; `null` si no hay más o no se admite.
+    pub next_cursor: Option<String>,
+}
+
+impl<T> Paginated<T> {
+    pub fn new(data: Vec<T>, total: i64, query: &PageQuery) -> Self {
+        let per_page = query.per_page();
+        Paginated {
+            data,
+            total,
+            page: query.page(),
+            per_page,
+            total_pages: total_pages(total, per_page),
+            next_cursor: None,
+        }
+    }
+
+    pub fn with_cursor(mut self, cursor: Option<String>) -> Self {
+        self.next_cursor = cursor;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clamps_query_and_counts_pages() {
+        let query = PageQuery { page: Some(0), per_page: Some(1000), ..Default::default() };
+        assert_eq!((query.page(), query.per_page(), query.offset()), (1, MAX_PER_PAGE, 0));
+
+        let query = PageQuery { page: Some(3), per_page: Some(10), ..Default::default() };
+        assert_eq!(query.offset(), 20);
+
+        for per_page in [1, MAX_PER_PAGE] {
+            let query = PageQuery { page: Some(i64::MAX), per_page: Some(per_page), ..Default::default() };
+            assert!(query.offset() > 0 && query.page().checked_add(1).is_some());
+        }
+
+        let size = PageSize { default: 5, max: 8 };
+        assert_eq!(PageQuery { size, ..Default::default() }.per_page(), 5);
+        assert_eq!(PageQuery { per_page: Some(50), size, ..Default::default() }.per_page(), 8);
+
+        assert_eq!(total_pages(0, 20), 1);
+        assert_eq!(total_pages(40, 20), 2);
+        assert_eq!(total_pages(41, 20), 3);
+    }
+}
@@ -0,0 +1,162 @@
+use axum::{
+    body::{to_bytes, Body, Bytes},
+    extract::{Request, State},
+    http::{header, HeaderMap},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde_json::Value;
+
+/// Campos cuyo valor nunca se escribe en el log: los que se llaman así...
+const REDACTED_FIELDS: [&str; 4] = [
+    "g-recaptcha-response",
+    "h-captcha-response",
+    "cf-turnstile-response",
+    // El código de un solo uso de `/setup`.
+    "code",
+];
+
+/// ...y los que llevan alguna de estas partes en el nombre (`key`, `api_key`,
+/// `edit_token`, `client_secret`...).
+const REDACTED_PARTS: [&str; 5] = ["password", "token", "secret", "key", "authorization"];
+
+/// Tope de bytes que se leen de un cuerpo para poder registrarlo.
+const MAX_BUFFERED: usize = 10 * 1024 * 1024;
+
+/// Registra (a nivel DEBUG) los cuerpos de petición y respuesta de tipo texto,
+/// truncados a `max_bytes` y con los campos sensibles ocultos.
+pub async fn log_payloads(State(max_bytes): State<usize>, req: Request, next: Next) -> Response {
+    let method = req.method().clone();
+    let uri = req.uri().clone();
+
+    let req = if is_textual(req.headers()) {
+        let (parts, body) = req.into_parts();
+        let Ok(bytes) = to_bytes(body, MAX_BUFFERED).await else {
+            return axum::http::StatusCode::PAYLOAD_TOO_LARGE.into_response();
+        };
+        tracing::debug!(%method, %uri, body = %render(&parts.headers, &bytes, max_bytes), "petición");
+        Request::from_parts(parts, Body::from(bytes))
+    } else {
+        req
+    };
+
+    let res = next.run(req).await;
+
+    if !is_textual(res.headers()) {
+        return res;
+    }
+
+    let (parts, body) = res.into_parts();
+    let Ok(bytes) = to_bytes(body, MAX_BUFFERED).await else {
+        return axum::http::StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+    tracing::debug!(
+        %method,
+        %uri,
+        status = parts.status.as_u16(),
+        body = %render(&parts.headers, &bytes, max_bytes),
+        "respuesta"
+    );
+    Response::from_parts(parts, Body::from(bytes))
+}
+
+fn content_type(headers: &HeaderMap) -> &str {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("")
+}
+
+/// Solo se registran formularios, JSON y texto; multipart e imágenes se omiten.
+fn is_textual(headers: &HeaderMap) -> bool {
+    let ct = content_type(headers);
+    ct.starts_with("application/x-www-form-urlencoded")
+        || ct.starts_with("application/json")
+        || ct.starts_with("text/")
+}
+
+fn render(headers: &HeaderMap, bytes: &Bytes, max_bytes: usize) -> String {
+    let ct = content_type(headers);
+    let text = String::from_utf8_lossy(bytes);
+
+    let redacted = if ct.starts_with("application/x-www-form-urlencoded") {
+        redact_form(&text)
+    } else if ct.starts_with("application/json") {
+        match serde_json::from_slice::<Value>(bytes) {
+            Ok(mut value) => {
+                redact_json(&mut value);
+                value.to_string()
+            }
+            Err(_) => text.into_owned(),
+        }
+    } else {
+        text.into_owned()
+    };
+
+    truncate(redacted, max_bytes)
+}
+
+fn is_redacted(key: &str) -> bool {
+    let key = key.to_ascii_lowercase();
+    REDACTED_FIELDS.iter().any(|f| key == *f) || REDACTED_PARTS.iter().any(|part| key.contains(part))
+}
+
+fn redact_form(body: &str) -> String {
+    body.split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some((key, _)) if is_redacted(key) => format!("{key}=[REDACTED]"),
+            _ => pair.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+fn redact_json(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, v) in map.iter_mut() {
+                if is_redacted(key) {
+                    *v = Value::String("[REDACTED]".into());
+                } else {
+                    redact_json(v);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact_json),
+        _ => {}
+    }
+}
+
+fn truncate(mut text: String, max_bytes: usize) -> String {
+    if text.len() <= max_bytes {
+        return text;
+    }
+    let mut cut = max_bytes;
+    while !text.is_char_boundary(cut) {
+        cut -= 1;
+    }
+    let total = text.len();
+    text.truncate(cut);
+    text + &format!("… ({total} bytes)")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn secrets_are_redacted_by_name_or_part() {
+        assert_eq!(
+            redact_form("nombre=Ana&code=123456&password=x&g-recaptcha-response=t&codigo=1"),
+            "nombre=Ana&code=[REDACTED]&password=[REDACTED]&g-recaptcha-response=[REDACTED]&codigo=1"
+        );
+
+        let mut created = serde_json::json!({ "id": 1, "key": "hk_abc", "keys": [{ "Api_Key": "x" }], "nombre": "ci" });
+        redact_json(&mut created);
+        assert_eq!(created, serde_json::json!({ "id": 1, "key": "[REDACTED]", "keys": "[REDACTED]", "nombre": "ci" }));
+
+        for name in ["edit_token", "client_secret", "new_password", "Authorization"] {
+            assert!(is_redacted(name), "{name}");
+        }
+    }
+}
@@ -0,0 +1,308 @@
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{request::Parts, StatusCode},
+    response::{Html, IntoResponse, Response},
+};
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::{net::IpAddr, sync::Arc};
+
+use crate::accounts;
+use crate::admin;
+use crate::api_keys::{self, InvalidApiKey, Scope};
+use crate::client_ip::ClientIp;
+use crate::config::Config;
+use crate::edit_token;
+
+/// Tiempo durante el que el autor de un mensaje puede editarlo o borrarlo.
+pub const EDIT_WINDOW: TimeDelta = TimeDelta::minutes(15);
+
+/// Quién hace la petición.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Principal {
+    /// Con el token de administración (o JWT, o sesión abierta con él).
+    Admin,
+    /// Usuario del panel con sesión propia (ver `users`).
+    User { id: i32, role: Role },
+    /// Script o integración con `X-Api-Key`.
+    ApiKey { id: i32, scope: Scope },
+    /// Autor con cuenta y sesión (ver `accounts`); no es del equipo.
+    Author { id: i32 },
+    /// Visitante sin cuenta, identificado por su IP. `edit_token` es el hash del
+    /// token de edición que trae, si trae (ver `edit_token`).
+    Visitor { ip: IpAddr, edit_token: Option<String> },
+    Anonymous,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Admin,
+    Moderator,
+    Viewer,
+}
+
+impl Role {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Role::Admin => "admin",
+            Role::Moderator => "moderator",
+            Role::Viewer => "viewer",
+        }
+    }
+
+    pub fn parse(role: &str) -> Option<Self> {
+        [Role::Admin, Role::Moderator, Role::Viewer].into_iter().find(|r| r.as_str() == role)
+    }
+
+    pub fn allows(self, permission: Permission) -> bool {
+        matches!(
+            (self, permission),
+            (Role::Admin, _)
+                | (Role::Moderator, Permission::ViewPanel | Permission::ModerateMessages)
+                | (Role::Viewer, Permission::ViewPanel)
+        )
+    }
+}
+
+/// Permisos por grupo de rutas del panel y de `/api/admin` (ver `admin::require`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Permission {
+    /// Ver el panel, listados, auditoría y estado.
+    ViewPanel,
+    /// Editar y borrar mensajes.
+    ModerateMessages,
+    /// Revisar, borrar y restaurar imágenes.
+    ManageImages,
+    /// Usuarios, claves de API y cuentas externas.
+    ManageUsers,
+    /// Contenido del propio sitio, como el aviso de `/admin/announcement`.
+    ManageSite,
+}
+
+impl Principal {
+    /// Rol en el panel; `None` para quien no es del equipo.
+    pub fn role(&self) -> Option<Role> {
+        match self {
+            Principal::Admin => Some(Role::Admin),
+            Principal::User { role, .. } => Some(*role),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    Update,
+    Delete,
+    Restore,
+    /// Subir una imagen que se publica sin pasar por revisión.
+    Publish,
+}
+
+/// Datos del recurso que necesitan las reglas.
+pub enum Resource<'a> {
+    Mensaje(&'a MensajeMeta),
+    /// Las imágenes no guardan autoría: solo administración las gestiona.
+    Image,
+}
+
+pub struct MensajeMeta {
+    pub created_at: DateTime<Utc>,
+    /// Cuenta del autor, si lo envió con sesión.
+    pub user_id: Option<i32>,
+    /// Hash del token de edición que se le dio al autor sin cuenta.
+    pub edit_token_hash: Option<String>,
+}
+
+pub fn can(principal: &Principal, action: Action, resource: &Resource) -> bool {
+    can_at(principal, action, resource, Utc::now())
+}
+
+fn can_at(principal: &Principal, action: Action, resource: &Resource, now: DateTime<Utc>) -> bool {
+    match (principal, action, resource) {
+        (Principal::Admin, _, _) => true,
+        (Principal::User { role: Role::Admin, .. }, _, _) => true,
+        (Principal::User { role: Role::Moderator, .. }, Action::Update | Action::Delete, Resource::Mensaje(_)) => true,
+        (Principal::User { .. }, _, _) => false,
+        (Principal::ApiKey { scope: Scope::Write, .. }, _, _) => true,
+        (Principal::ApiKey { scope: Scope::Read, .. }, _, _) => false,
+        // Con cuenta, el mensaje es de quien lo envió, sin plazo; ni su IP vale.
+        (Principal::Author { id }, Action::Update | Action::Delete, Resource::Mensaje(m)) => m.user_id == Some(*id),
+        (Principal::Author { .. }, _, _) => false,
+        // Sin cuenta, quien tenga el token de edición; la IP no basta.
+        (Principal::Visitor { edit_token: Some(token), .. }, Action::Update | Action::Delete, Resource::Mensaje(m)) => {
+            m.user_id.is_none() && m.edit_token_hash.as_ref() == Some(token) && now - m.created_at <= EDIT_WINDOW
+        }
+        (Principal::Visitor { .. }, _, _) => false,
+        (Principal::Anonymous, _, _) => false,
+    }
+}
+
+/// Igual que `can`, pero listo para usar con `?` en un handler.
+pub fn authorize(principal: &Principal, action: Action, resource: &Resource) -> Result<(), Forbidden> {
+    if can(principal, action, resource) {
+        Ok(())
+    } else {
+        Err(Forbidden)
+    }
+}
+
+pub struct Forbidden;
+
+impl Forbidden {
+    pub const MESSAGE: &'static str = "❌ No tienes permiso para esta acción";
+}
+
+impl IntoResponse for Forbidden {
+    fn into_response(self) -> Response {
+        (StatusCode::FORBIDDEN, Html(Self::MESSAGE)).into_response()
+    }
+}
+
+/* ---------- EXTRACTOR ---------- */
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Principal {
+    /// Solo si trae `X-Api-Key` y no vale: mejor un 401 claro que tratarlo como visitante.
+    type Rejection = InvalidApiKey;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let config = parts.extensions.get::<Arc<Config>>();
+        let pool = parts.extensions.get::<PgPool>();
+        let staff = match (config, pool) {
+            (Some(config), Some(pool)) => admin::staff(&config.admin, pool, &parts.headers).await,
+            (Some(config), None) => admin::has_admin_token(&config.admin, &parts.headers).then_some(Principal::Admin),
+            _ => None,
+        };
+
+        if let Some(staff) = staff {
+            return Ok(staff);
+        }
+
+        if let Some(pool) = pool
+            && let Some((id, scope)) = api_keys::authenticate(pool, &parts.headers).await?
+        {
+            return Ok(Principal::ApiKey { id, scope });
+        }
+
+        if let Some(pool) = pool
+            && let Some(id) = accounts::principal(pool, &parts.headers).await
+        {
+            return Ok(Principal::Author { id });
+        }
+
+        let Ok(ClientIp(ip)) = ClientIp::from_request_parts(parts, state).await;
+        Ok(ip.map_or(Principal::Anonymous, |ip| Principal::Visitor {
+            ip,
+            edit_token: edit_token::from_headers(&parts.headers),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mensaje(token: &str, minutes_ago: i64, now: DateTime<Utc>) -> MensajeMeta {
+        MensajeMeta {
+            created_at: now - TimeDelta::minutes(minutes_ago),
+            user_id: None,
+            edit_token_hash: Some(token.to_string()),
+        }
+    }
+
+    fn visitor(ip: &str, token: Option<&str>) -> Principal {
+        Principal::Visitor { ip: ip.parse().unwrap(), edit_token: token.map(String::from) }
+    }
+
+    #[test]
+    fn admin_can_do_anything() {
+        let now = Utc::now();
+        let m = mensaje("t1", 600, now);
+        assert!(can_at(&Principal::Admin, Action::Delete, &Resource::Mensaje(&m), now));
+        assert!(can_at(&Principal::Admin, Action::Update, &Resource::Mensaje(&m), now));
+    }
+
+    #[test]
+    fn author_can_edit_within_window() {
+        let now = Utc::now();
+        // El token vale aunque cambie la IP.
+        let author = visitor("10.0.0.2", Some("t1"));
+        let recent = mensaje("t1", 5, now);
+        let old = mensaje("t1", 16, now);
+
+        assert!(can_at(&author, Action::Update, &Resource::Mensaje(&recent), now));
+        assert!(can_at(&author, Action::Delete, &Resource::Mensaje(&recent), now));
+        assert!(!can_at(&author, Action::Update, &Resource::Mensaje(&old), now));
+    }
+
+    #[test]
+    fn others_cannot_edit() {
+        let now = Utc::now();
+        let m = mensaje("t1", 1, now);
+
+        assert!(!can_at(&visitor("10.0.0.1", Some("t2")), Action::Update, &Resource::Mensaje(&m), now));
+        // La misma IP sin el token no basta.
+        assert!(!can_at(&visitor("10.0.0.1", None), Action::Update, &Resource::Mensaje(&m), now));
+        assert!(!can_at(&Principal::Anonymous, Action::Delete, &Resource::Mensaje(&m), now));
+    }
+
+    #[test]
+    fn unknown_author_is_admin_only() {
+        let now = Utc::now();
+        let m = MensajeMeta { created_at: now, user_id: None, edit_token_hash: None };
+        assert!(!can_at(&visitor("10.0.0.1", Some("t1")), Action::Update, &Resource::Mensaje(&m), now));
+    }
+
+    #[test]
+    fn registered_author_owns_their_messages() {
+        let now = Utc::now();
+        let m = MensajeMeta { user_id: Some(7), ..mensaje("t1", 600, now) };
+        assert!(can_at(&Principal::Author { id: 7 }, Action::Update, &Resource::Mensaje(&m), now));
+        assert!(!can_at(&Principal::Author { id: 8 }, Action::Delete, &Resource::Mensaje(&m), now));
+
+        // El token ya no basta aunque esté dentro del plazo.
+        let recent = MensajeMeta { user_id: Some(7), ..mensaje("t1", 1, now) };
+        assert!(!can_at(&visitor("10.0.0.1", Some("t1")), Action::Update, &Resource::Mensaje(&recent), now));
+    }
+
+    #[test]
+    fn images_are_admin_only() {
+        let now = Utc::now();
+        assert!(!can_at(&visitor("10.0.0.1", Some("t1")), Action::Delete, &Resource::Image, now));
+        assert!(can_at(&Principal::Admin, Action::Restore, &Resource::Image, now));
+    }
+
+    #[test]
+    fn roles_grant_their_permissions() {
+        use Permission::*;
+        assert!([ViewPanel, ModerateMessages, ManageImages, ManageUsers, ManageSite].iter().all(|p| Role::Admin.allows(*p)));
+        assert!(Role::Moderator.allows(ModerateMessages) && !Role::Moderator.allows(ManageImages));
+        assert!(!Role::Moderator.allows(ManageSite));
+        assert!(Role::Viewer.allows(ViewPanel) && !Role::Viewer.allows(ModerateMessages));
+
+        let now = Utc::now();
+        let m = mensaje("t1", 600, now);
+        let moderator = Principal::User { id: 1, role: Role::Moderator };
+        let viewer = Principal::User { id: 2, role: Role::Viewer };
+        assert!(can_at(&moderator, Action::Delete, &Resource::Mensaje(&m), now));
+        assert!(!can_at(&moderator, Action::Publish, &Resource::Image, now));
+        assert!(!can_at(&viewer, Action::Update, &Resource::Mensaje(&m), now));
+    }
+
+    #[test]
+    fn api_keys_act_by_scope() {
+        let now = Utc::now();
+        let m = mensaje("t1", 600, now);
+        let read = Principal::ApiKey { id: 1, scope: Scope::Read };
+        let write = Principal::ApiKey { id: 2, scope: Scope::Write };
+
+        assert!(!can_at(&read, Action::Delete, &Resource::Mensaje(&m), now));
+        assert!(!can_at(&read, Action::Publish, &Resource::Image, now));
+        assert!(can_at(&write, Action::Update, &Resource::Mensaje(&m), now));
+        assert!(can_at(&write, Action::Publish, &Resource::Image, now));
+    }
+}
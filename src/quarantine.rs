@@ -0,0 +1,134 @@
+//! Cuarentena de mensajes: a la puntuación del texto (`content_rules`) se le
+//! suma el ritmo de envíos de la IP, y si el total llega a
+//! `SPAM_QUARANTINE_SCORE` el mensaje se guarda en `mensajes_quarantine` en
+//! vez de publicarse. Moderación lo publica (conservando el id) o lo descarta
+//! desde `/api/admin/mensajes/quarantine`. Con Akismet (ver `akismet`), lo
+//! que marque como spam también se retiene aquí.
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+use std::net::IpAddr;
+
+use crate::akismet;
+use crate::audit_log::{self, Change};
+use crate::db::DbError;
+use crate::events::{self, Event};
+use crate::outbox;
+use crate::pagination::{PageQuery, Paginated};
+use crate::policy::Principal;
+use crate::queries::{self, NewMensaje};
+use crate::state::SharedState;
+use crate::unit_of_work::UnitOfWork;
+
+/// Ventana en la que se miran los envíos anteriores de la misma IP.
+const VELOCITY_MINUTES: i32 = 10;
+
+/// Por cada mensaje de la misma IP dentro de la ventana, publicado o no.
+const VELOCITY_POINTS: i32 = 20;
+
+/// Puntos por el ritmo de envíos de `ip`; sin IP no se puede medir.
+pub async fn velocity_points(pool: &PgPool, ip: Option<IpAddr>) -> Result<i32, DbError> {
+    let Some(ip) = ip else { return Ok(0) };
+    let recent = queries::quarantine::recent_from_ip(pool, ip, VELOCITY_MINUTES).await?;
+    Ok(VELOCITY_POINTS.saturating_mul(recent.min(i32::MAX as i64) as i32))
+}
+
+/// Mismos datos que un mensaje publicado (`queries::insert_mensaje`), más lo
+/// que pide Akismet si hay que devolverlo como falso positivo.
+pub async fn hold(
+    pool: &PgPool,
+    held: &NewMensaje<'_>,
+    user_agent: Option<&str>,
+    akismet_spam: bool,
+) -> Result<i32, DbError> {
+    queries::quarantine::insert(pool, held, user_agent, akismet_spam).await
+}
+
+fn not_found() -> Response {
+    (StatusCode::NOT_FOUND, Html("❌ Mensaje en cuarentena no encontrado")).into_response()
+}
+
+/* ---------- HANDLERS ---------- */
+
+#[derive(Serialize)]
+pub struct QuarantinedMensaje {
+    pub id: i32,
+    pub nombre: String,
+    pub mensaje: String,
+    pub spam_score: i32,
+    /// Lo retuvo Akismet, no (solo) la puntuación.
+    pub akismet_spam: bool,
+    pub author_ip: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// `GET /api/admin/mensajes/quarantine`: los retenidos, la puntuación más alta primero.
+pub async fn list(
+    State(app): State<SharedState>,
+    page: PageQuery,
+) -> Result<Json<Paginated<QuarantinedMensaje>>, DbError> {
+    let total = queries::quarantine::count(&app.db).await?;
+    let mensajes = queries::quarantine::page(&app.db, page.per_page(), page.offset()).await?;
+    Ok(Json(Paginated::new(mensajes, total, &page)))
+}
+
+/// `POST /api/admin/mensajes/quarantine/:id/release`: a `mensajes`, con el mismo
+/// id. Si lo había retenido Akismet, se le avisa del falso positivo.
+pub async fn release(State(app): State<SharedState>, principal: Principal, Path(id): Path<i32>) -> Response {
+    let mut uow = match UnitOfWork::begin(&app.db).await {
+        Ok(uow) => uow,
+        Err(e) => return e.into_response(),
+    };
+    let released = match queries::quarantine::release(uow.conn(), id).await {
+        Ok(Some(released)) => released,
+        Ok(None) => return not_found(),
+        Err(e) => return e.into_response(),
+    };
+    let event = Event::MessageCreated { id };
+    if let Err(e) = outbox::enqueue(uow.conn(), &event).await {
+        return e.into_response();
+    }
+    if let Err(e) = uow.commit().await {
+        return e.into_response();
+    }
+
+    let akismet_spam = released.akismet_spam;
+    let score = released.spam_score;
+    if let Some(akismet) = app.akismet.as_ref().filter(|_| akismet_spam) {
+        let comment = akismet::Comment {
+            ip: released.author_ip.and_then(|ip| ip.parse().ok()),
+            user_agent: released.user_agent.as_deref(),
+            nombre: &released.nombre,
+            email: released.author_email.as_deref(),
+            mensaje: &released.mensaje,
+        };
+        akismet.submit_ham(&comment).await;
+    }
+
+    let change = Change::new("mensaje.release", format!("mensaje:{id}"))
+        .new_value(serde_json::json!({ "spam_score": score, "akismet_spam": akismet_spam }));
+    audit_log::record_change(&app.db, &principal, change).await;
+    events::publish(&app, event);
+    Html("✅ Mensaje publicado").into_response()
+}
+
+/// `DELETE /api/admin/mensajes/quarantine/:id`
+pub async fn discard(State(app): State<SharedState>, principal: Principal, Path(id): Path<i32>) -> Response {
+    let (nombre, mensaje, spam_score) = match queries::quarantine::discard(&app.db, id).await {
+        Ok(Some(row)) => row,
+        Ok(None) => return not_found(),
+        Err(e) => return e.into_response(),
+    };
+
+    let old = serde_json::json!({ "nombre": nombre, "mensaje": mensaje, "spam_score": spam_score });
+    let change = Change::new("mensaje.discard", format!("mensaje:{id}")).old(old);
+    audit_log::record_change(&app.db, &principal, change).await;
+    Html("✅ Mensaje descartado").into_response()
+}
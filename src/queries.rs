@@ -0,0 +1,487 @@
+//! Todo el SQL de la aplicación, con nombre y tipos: los handlers y los
+//! trabajos en segundo plano validan, autorizan y responden; lo que va a la
+//! base de datos se revisa aquí. Cada consulta pasa por `db::timed` con el
+//! mismo nombre que sale en los logs y en `/metrics`. Lo de los handlers de
+//! mensajes e imágenes está en este archivo; lo de cada módulo, en
+//! `queries::<módulo>`. Fuera quedan `db` y `unit_of_work`, que son la
+//! conexión y las transacciones, no consultas.
+
+use chrono::{DateTime, NaiveDate, Utc};
+use sqlx::{postgres::PgRow, PgConnection, PgPool, Row};
+use std::{net::IpAddr, time::Duration};
+
+use crate::db::{self, DbError};
+use crate::html::MensajeRow;
+use crate::policy::MensajeMeta;
+
+pub mod accounts;
+pub mod admin_session;
+pub mod announcement;
+pub mod api_keys;
+pub mod archive;
+pub mod audit_log;
+pub mod author_cap;
+pub mod bans;
+pub mod db_stats;
+pub mod email_verification;
+pub mod image_review;
+pub mod index_advisor;
+pub mod login_lockout;
+pub mod oauth;
+pub mod outbox;
+pub mod overview;
+pub mod quarantine;
+pub mod quota;
+pub mod reimport;
+pub mod setup;
+pub mod spam_log;
+pub mod static_export;
+pub mod timezone;
+pub mod trash;
+pub mod users;
+pub mod verify_uploads;
+pub mod watchdog;
+
+/* ---------- MENSAJES ---------- */
+
+/// Lo que se guarda de un mensaje nuevo, publicado o retenido.
+pub struct NewMensaje<'a> {
+    pub nombre: &'a str,
+    pub mensaje: &'a str,
+    pub ip: Option<IpAddr>,
+    pub score: i32,
+    pub email: Option<&'a str>,
+    pub user_id: Option<i32>,
+    /// Hash del token de edición (ver `edit_token`); solo al publicarse sin cuenta.
+    pub edit_token_hash: Option<&'a str>,
+}
+
+pub async fn insert_mensaje(conn: &mut PgConnection, new: &NewMensaje<'_>) -> Result<i32, DbError> {
+    let insert = sqlx::query_scalar::<_, i32>(
+        "INSERT INTO mensajes (nombre, mensaje, author_ip, spam_score, author_email, user_id, edit_token_hash)
+         VALUES ($1,$2,$3,$4,$5,$6,$7) RETURNING id",
+    )
+    .bind(new.nombre)
+    .bind(new.mensaje)
+    .bind(new.ip.map(|ip| ip.to_string()))
+    .bind(new.score)
+    .bind(new.email)
+    .bind(new.user_id)
+    .bind(new.edit_token_hash)
+    .fetch_one(conn);
+    Ok(db::timed("mensajes.insert", || format!("len={}", new.mensaje.len()), insert).await?)
+}
+
+/// Devuelve `(nombre, mensaje)` de antes del cambio, para la auditoría;
+/// `None` si no existe.
+pub async fn update_mensaje(
+    pool: &PgPool,
+    id: i32,
+    nombre: &str,
+    mensaje: &str,
+    score: i32,
+) -> Result<Option<(String, String)>, DbError> {
+    let update = sqlx::query_as::<_, (String, String)>(
+        "WITH hot AS (
+             UPDATE mensajes m SET nombre=$1, mensaje=$2, spam_score=$3
+             FROM (SELECT nombre, mensaje FROM mensajes WHERE id=$4) old
+             WHERE m.id=$4 RETURNING old.nombre, old.mensaje
+         ), cold AS (
+             UPDATE mensajes_archive m SET nombre=$1, mensaje=$2, spam_score=$3
+             FROM (SELECT nombre, mensaje FROM mensajes_archive WHERE id=$4) old
+             WHERE m.id=$4 RETURNING old.nombre, old.mensaje
+         )
+         SELECT * FROM hot UNION ALL SELECT * FROM cold",
+    )
+    .bind(nombre)
+    .bind(mensaje)
+    .bind(score)
+    .bind(id)
+    .fetch_optional(pool);
+    Ok(db::timed("mensajes.update", || format!("id={id}"), update).await?)
+}
+
+/// Publicado o archivado. Devuelve `(nombre, mensaje)` del borrado; `None`
+/// si no existía.
+pub async fn delete_mensaje(conn: &mut PgConnection, id: i32) -> Result<Option<(String, String)>, DbError> {
+    let delete = sqlx::query_as::<_, (String, String)>(
+        "WITH hot AS (DELETE FROM mensajes WHERE id = $1 RETURNING nombre, mensaje),
+              cold AS (DELETE FROM mensajes_archive WHERE id = $1 RETURNING nombre, mensaje)
+         SELECT * FROM hot UNION ALL SELECT * FROM cold",
+    )
+    .bind(id)
+    .fetch_optional(conn);
+    Ok(db::timed("mensajes.delete", || format!("id={id}"), delete).await?)
+}
+
+pub async fn count_mensajes(pool: &PgPool) -> Result<i64, DbError> {
+    let count = sqlx::query_scalar::<_, i64>("SELECT count(*) FROM mensajes").fetch_one(pool);
+    Ok(db::timed("mensajes.count", String::new, count).await?)
+}
+
+/// Listado público, de más nuevo a más viejo. `columns` sale de
+/// `fields::Selection`, nunca del cliente tal cual. Con `cursor` se sigue por
+/// id y `offset` no cuenta.
+pub async fn list_mensajes(
+    pool: &PgPool,
+    columns: &str,
+    cursor: Option<i32>,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<PgRow>, DbError> {
+    let sql = format!(
+        "SELECT {columns} FROM mensajes
+         WHERE ($1::int IS NULL OR id < $1)
+         ORDER BY id DESC LIMIT $2 OFFSET $3"
+    );
+    let select = sqlx::query(&sql)
+        .bind(cursor)
+        .bind(limit)
+        .bind(if cursor.is_some() { 0 } else { offset })
+        .fetch_all(pool);
+    Ok(db::timed("mensajes.list", || format!("cursor={cursor:?}"), select).await?)
+}
+
+/// Búsqueda del panel: `q` vacío no filtra; si no, subcadena sin mayúsculas
+/// en nombre o mensaje.
+const ADMIN_FILTER: &str =
+    "($1 = '' OR strpos(lower(nombre), lower($1)) > 0 OR strpos(lower(mensaje), lower($1)) > 0)";
+
+pub async fn count_admin_mensajes(pool: &PgPool, q: &str) -> Result<i64, DbError> {
+    let sql = format!("SELECT count(*) FROM mensajes WHERE {ADMIN_FILTER}");
+    let count = sqlx::query_scalar::<_, i64>(&sql).bind(q).fetch_one(pool);
+    Ok(db::timed("mensajes.admin_count", || format!("q={q}"), count).await?)
+}
+
+/// `order_by` lo pone el handler a partir de un enum, no del cliente.
+pub async fn admin_mensajes(
+    pool: &PgPool,
+    q: &str,
+    order_by: &str,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<MensajeRow>, DbError> {
+    let sql = format!(
+        "SELECT id, nombre, mensaje, email_verified_at IS NOT NULL AS verified
+         FROM mensajes WHERE {ADMIN_FILTER} ORDER BY {order_by} LIMIT $2 OFFSET $3"
+    );
+    let select = sqlx::query(&sql).bind(q).bind(limit).bind(offset).fetch_all(pool);
+    let rows = db::timed("mensajes.admin_page", || format!("offset={offset}"), select).await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| MensajeRow {
+            id: r.get("id"),
+            nombre: r.get("nombre"),
+            mensaje: r.get("mensaje"),
+            verified: r.get("verified"),
+        })
+        .collect())
+}
+
+/// `id`, `nombre`, `mensaje` y `verified`. Los archivados se siguen sirviendo por id.
+pub async fn get_mensaje(pool: &PgPool, id: i32) -> Result<Option<PgRow>, DbError> {
+    let select = sqlx::query(
+        "SELECT id, nombre, mensaje, email_verified_at IS NOT NULL AS verified FROM mensajes WHERE id = $1
+         UNION ALL
+         SELECT id, nombre, mensaje, email_verified_at IS NOT NULL FROM mensajes_archive WHERE id = $1",
+    )
+    .bind(id)
+    .fetch_optional(pool);
+    Ok(db::timed("mensajes.get", || format!("id={id}"), select).await?)
+}
+
+/// Lo que enseña el enlace permanente.
+pub struct MensajeView {
+    pub nombre: String,
+    pub mensaje: String,
+    pub created_at: DateTime<Utc>,
+    pub verified: bool,
+}
+
+pub async fn view_mensaje(pool: &PgPool, id: i32) -> Result<Option<MensajeView>, DbError> {
+    let select = sqlx::query(
+        "SELECT nombre, mensaje, created_at, email_verified_at IS NOT NULL AS verified
+         FROM mensajes WHERE id = $1
+         UNION ALL
+         SELECT nombre, mensaje, created_at, email_verified_at IS NOT NULL FROM mensajes_archive WHERE id = $1",
+    )
+    .bind(id)
+    .fetch_optional(pool);
+    let row = db::timed("mensajes.view", || format!("id={id}"), select).await?;
+
+    Ok(row.map(|r| MensajeView {
+        nombre: r.get("nombre"),
+        mensaje: r.get("mensaje"),
+        created_at: r.get("created_at"),
+        verified: r.get("verified"),
+    }))
+}
+
+/// Solo publicados: los archivados no tienen relacionados.
+pub async fn mensaje_exists(pool: &PgPool, id: i32) -> Result<bool, DbError> {
+    let exists = sqlx::query_scalar::<_, bool>("SELECT EXISTS (SELECT 1 FROM mensajes WHERE id = $1)")
+        .bind(id)
+        .fetch_one(pool);
+    Ok(db::timed("mensajes.exists", || format!("id={id}"), exists).await?)
+}
+
+/// Mensajes que comparten palabras con `id`, por relevancia de texto completo
+/// (`ts_rank` sobre la columna `search`, en español). La consulta es la unión
+/// (`|`) de los lexemas del propio mensaje. Mismas columnas que `get_mensaje`.
+pub async fn related_mensajes(pool: &PgPool, id: i32, limit: i64) -> Result<Vec<PgRow>, DbError> {
+    let select = sqlx::query(
+        "SELECT m.id, m.nombre, m.mensaje, m.email_verified_at IS NOT NULL AS verified
+         FROM mensajes src
+         CROSS JOIN LATERAL to_tsquery('spanish', coalesce(
+             (SELECT string_agg(quote_literal(l), ' | ') FROM unnest(tsvector_to_array(src.search)) AS l), ''
+         )) AS q
+         JOIN mensajes m ON m.id <> src.id AND m.search @@ q
+         WHERE src.id = $1
+         ORDER BY ts_rank(m.search, q) DESC, m.id DESC
+         LIMIT $2",
+    )
+    .bind(id)
+    .bind(limit)
+    .fetch_all(pool);
+    Ok(db::timed("mensajes.related", || format!("id={id}"), select).await?)
+}
+
+/// Lo que necesita `policy` para decidir sobre un mensaje, publicado o
+/// archivado. Los archivados ya no guardan el token de edición.
+pub async fn mensaje_meta(pool: &PgPool, id: i32) -> Result<Option<MensajeMeta>, DbError> {
+    let select = sqlx::query(
+        "SELECT created_at, user_id, edit_token_hash FROM mensajes WHERE id = $1
+         UNION ALL
+         SELECT created_at, user_id, NULL FROM mensajes_archive WHERE id = $1",
+    )
+    .bind(id)
+    .fetch_optional(pool);
+    let row = db::timed("mensajes.meta", || format!("id={id}"), select).await?;
+
+    Ok(row.map(|r| MensajeMeta {
+        created_at: r.get("created_at"),
+        user_id: r.get("user_id"),
+        edit_token_hash: r.get("edit_token_hash"),
+    }))
+}
+
+/* ---------- IMÁGENES ---------- */
+
+/// Nueva; sin `approved`, pendiente de revisión (ver `image_review`).
+pub async fn insert_image(
+    conn: &mut PgConnection,
+    filename: &str,
+    size_bytes: i64,
+    original_name: &str,
+    approved: bool,
+) -> Result<i32, DbError> {
+    let insert = sqlx::query_scalar::<_, i32>(
+        "INSERT INTO images (filename, size_bytes, original_name, approved_at)
+         VALUES ($1, $2, $3, CASE WHEN $4 THEN now() END)
+         RETURNING id",
+    )
+    .bind(filename)
+    .bind(size_bytes)
+    .bind(original_name)
+    .bind(approved)
+    .fetch_one(conn);
+    Ok(db::timed("images.insert", || format!("filename={filename}"), insert).await?)
+}
+
+/// Fichero de una imagen visible: ni en la papelera ni pendiente de revisión.
+pub async fn published_image(pool: &PgPool, id: i32) -> Result<Option<String>, DbError> {
+    let select = sqlx::query_scalar::<_, String>(
+        "SELECT filename FROM images WHERE id = $1 AND deleted_at IS NULL AND approved_at IS NOT NULL",
+    )
+    .bind(id)
+    .fetch_optional(pool);
+    Ok(db::timed("images.published", || format!("id={id}"), select).await?)
+}
+
+/// Filtros de `GET /images`; `None` no filtra. Las fechas van ambas incluidas.
+#[derive(Default)]
+pub struct ImageFilter {
+    pub extension: Option<&'static str>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+/// Solo las visibles: ni en la papelera ni pendientes de revisión.
+const IMAGE_FILTER: &str = "deleted_at IS NULL AND approved_at IS NOT NULL
+       AND ($1::text IS NULL OR filename LIKE '%.' || $1)
+       AND ($2::date IS NULL OR created_at >= $2)
+       AND ($3::date IS NULL OR created_at < $3 + 1)";
+
+pub async fn count_images(pool: &PgPool, filter: &ImageFilter) -> Result<i64, DbError> {
+    let sql = format!("SELECT count(*) FROM images WHERE {IMAGE_FILTER}");
+    let count = sqlx::query_scalar::<_, i64>(&sql)
+        .bind(filter.extension)
+        .bind(filter.from)
+        .bind(filter.to)
+        .fetch_one(pool);
+    Ok(db::timed("images.count", String::new, count).await?)
+}
+
+/// `columns` y `order_by` los pone el handler (`fields::Selection`, `ImageSort`).
+pub async fn list_images(
+    pool: &PgPool,
+    filter: &ImageFilter,
+    columns: &str,
+    order_by: &str,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<PgRow>, DbError> {
+    let sql = format!(
+        "SELECT {columns} FROM images
+         WHERE {IMAGE_FILTER}
+         ORDER BY {order_by} LIMIT $4 OFFSET $5"
+    );
+    let select = sqlx::query(&sql)
+        .bind(filter.extension)
+        .bind(filter.from)
+        .bind(filter.to)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(pool);
+    Ok(db::timed("images.list", String::new, select).await?)
+}
+
+/// El id si `trash_image` la movería. Las pendientes no pasan por la papelera.
+pub async fn trashable_image(pool: &PgPool, id: i32) -> Result<Option<i32>, DbError> {
+    let select = sqlx::query_scalar::<_, i32>(
+        "SELECT id FROM images WHERE id = $1 AND deleted_at IS NULL AND approved_at IS NOT NULL",
+    )
+    .bind(id)
+    .fetch_optional(pool);
+    Ok(db::timed("images.trash_preview", || format!("id={id}"), select).await?)
+}
+
+/// Devuelve el fichero, que el handler mueve a la papelera.
+pub async fn trash_image(pool: &PgPool, id: i32) -> Result<Option<String>, DbError> {
+    let trash = sqlx::query_scalar::<_, String>(
+        "UPDATE images SET deleted_at = now()
+         WHERE id = $1 AND deleted_at IS NULL AND approved_at IS NOT NULL
+         RETURNING filename",
+    )
+    .bind(id)
+    .fetch_optional(pool);
+    Ok(db::timed("images.trash", || format!("id={id}"), trash).await?)
+}
+
+/// El id si sigue en la papelera dentro de `retention`.
+pub async fn restorable_image(pool: &PgPool, id: i32, retention: Duration) -> Result<Option<i32>, DbError> {
+    let select = sqlx::query_scalar::<_, i32>(
+        "SELECT id FROM images WHERE id = $1 AND deleted_at > now() - make_interval(secs => $2)",
+    )
+    .bind(id)
+    .bind(retention.as_secs_f64())
+    .fetch_optional(pool);
+    Ok(db::timed("images.restore_preview", || format!("id={id}"), select).await?)
+}
+
+pub async fn restore_image(pool: &PgPool, id: i32, retention: Duration) -> Result<Option<String>, DbError> {
+    let restore = sqlx::query_scalar::<_, String>(
+        "UPDATE images SET deleted_at = NULL
+         WHERE id = $1 AND deleted_at > now() - make_interval(secs => $2)
+         RETURNING filename",
+    )
+    .bind(id)
+    .bind(retention.as_secs_f64())
+    .fetch_optional(pool);
+    Ok(db::timed("images.restore", || format!("id={id}"), restore).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestDb;
+
+    fn new_mensaje<'a>(nombre: &'a str, mensaje: &'a str) -> NewMensaje<'a> {
+        NewMensaje {
+            nombre,
+            mensaje,
+            ip: Some("10.0.0.1".parse().unwrap()),
+            score: 5,
+            email: None,
+            user_id: None,
+            edit_token_hash: Some("hash"),
+        }
+    }
+
+    #[tokio::test]
+    async fn mensaje_queries_round_trip() {
+        let Some(db) = TestDb::new().await else { return };
+        let mut conn = db.pool.acquire().await.unwrap();
+        let ana = insert_mensaje(&mut conn, &new_mensaje("Ana", "Vendo moto clásica")).await.unwrap();
+        let luis = insert_mensaje(&mut conn, &new_mensaje("luis", "Compro moto antigua")).await.unwrap();
+        drop(conn);
+
+        assert_eq!(count_mensajes(&db.pool).await.unwrap(), 2);
+        let page = list_mensajes(&db.pool, "id", None, 10, 0).await.unwrap();
+        assert_eq!(page.iter().map(|r| r.get::<i32, _>("id")).collect::<Vec<_>>(), [luis, ana]);
+        let after = list_mensajes(&db.pool, "id", Some(luis), 10, 5).await.unwrap();
+        assert_eq!(after.len(), 1);
+
+        assert_eq!(count_admin_mensajes(&db.pool, "VENDO").await.unwrap(), 1);
+        let rows = admin_mensajes(&db.pool, "", "id ASC", 10, 0).await.unwrap();
+        assert_eq!(rows.iter().map(|r| r.id).collect::<Vec<_>>(), [ana, luis]);
+        assert!(!rows[0].verified);
+
+        let view = view_mensaje(&db.pool, ana).await.unwrap().unwrap();
+        assert_eq!((view.nombre.as_str(), view.mensaje.as_str()), ("Ana", "Vendo moto clásica"));
+        assert!(get_mensaje(&db.pool, ana).await.unwrap().is_some());
+
+        let meta = mensaje_meta(&db.pool, ana).await.unwrap().unwrap();
+        assert_eq!(meta.edit_token_hash.as_deref(), Some("hash"));
+        let related = related_mensajes(&db.pool, ana, 5).await.unwrap();
+        assert_eq!(related.iter().map(|r| r.get::<i32, _>("id")).collect::<Vec<_>>(), [luis]);
+
+        let old = update_mensaje(&db.pool, ana, "Ana", "Vendo moto roja", 0).await.unwrap();
+        assert_eq!(old, Some(("Ana".to_string(), "Vendo moto clásica".to_string())));
+        assert_eq!(update_mensaje(&db.pool, 0, "x", "y", 0).await.unwrap(), None);
+
+        let mut conn = db.pool.acquire().await.unwrap();
+        assert_eq!(delete_mensaje(&mut conn, ana).await.unwrap().map(|(_, m)| m).as_deref(), Some("Vendo moto roja"));
+        drop(conn);
+        assert!(!mensaje_exists(&db.pool, ana).await.unwrap());
+        assert!(view_mensaje(&db.pool, ana).await.unwrap().is_none());
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn image_queries_filter_and_trash() {
+        let Some(db) = TestDb::new().await else { return };
+        sqlx::query(
+            "INSERT INTO images (filename, size_bytes, original_name, approved_at) VALUES
+                ('a.png', 10, 'a.png', now()), ('b.jpg', 20, 'b.jpg', now()), ('c.png', 30, 'c.png', NULL)",
+        )
+        .execute(&db.pool)
+        .await
+        .unwrap();
+        let id: i32 = sqlx::query_scalar("SELECT id FROM images WHERE filename = 'a.png'")
+            .fetch_one(&db.pool)
+            .await
+            .unwrap();
+
+        let all = ImageFilter::default();
+        let png = ImageFilter { extension: Some("png"), ..Default::default() };
+        assert_eq!(count_images(&db.pool, &all).await.unwrap(), 2);
+        assert_eq!(count_images(&db.pool, &png).await.unwrap(), 1);
+        let rows = list_images(&db.pool, &all, "filename", "size_bytes DESC", 10, 0).await.unwrap();
+        assert_eq!(rows.iter().map(|r| r.get::<String, _>("filename")).collect::<Vec<_>>(), ["b.jpg", "a.png"]);
+
+        let retention = Duration::from_secs(60);
+        assert_eq!(trashable_image(&db.pool, id).await.unwrap(), Some(id));
+        assert_eq!(trash_image(&db.pool, id).await.unwrap().as_deref(), Some("a.png"));
+        assert_eq!(trash_image(&db.pool, id).await.unwrap(), None);
+        assert_eq!(count_images(&db.pool, &png).await.unwrap(), 0);
+
+        assert_eq!(restorable_image(&db.pool, id, retention).await.unwrap(), Some(id));
+        assert_eq!(restore_image(&db.pool, id, retention).await.unwrap().as_deref(), Some("a.png"));
+        assert_eq!(restorable_image(&db.pool, id, retention).await.unwrap(), None);
+
+        db.finish().await;
+    }
+}
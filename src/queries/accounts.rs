@@ -0,0 +1,69 @@
+//! `author_sessions`: sesiones de las cuentas de autores.
+
+use sqlx::PgPool;
+use std::time::Duration;
+
+use crate::db::{self, DbError};
+
+/// Cuenta de la sesión vigente con ese hash de token, si sigue activa.
+pub async fn session_user(pool: &PgPool, token_hash: &str) -> Result<Option<i32>, DbError> {
+    let select = sqlx::query_scalar::<_, i32>(
+        "SELECT s.user_id FROM author_sessions s
+         JOIN users u ON u.id = s.user_id
+         WHERE s.token_hash = $1 AND s.expires_at > now() AND u.disabled_at IS NULL",
+    )
+    .bind(token_hash)
+    .fetch_optional(pool);
+    Ok(db::timed("author_sessions.check", String::new, select).await?)
+}
+
+pub async fn insert_session(pool: &PgPool, token_hash: &str, user: i32, ttl: Duration) -> Result<(), DbError> {
+    let insert = sqlx::query(
+        "INSERT INTO author_sessions (token_hash, user_id, expires_at) VALUES ($1, $2, now() + make_interval(secs => $3))",
+    )
+    .bind(token_hash)
+    .bind(user)
+    .bind(ttl.as_secs_f64())
+    .execute(pool);
+    db::timed("author_sessions.insert", String::new, insert).await?;
+    Ok(())
+}
+
+pub async fn delete_session(pool: &PgPool, token_hash: &str) -> Result<(), DbError> {
+    let delete = sqlx::query("DELETE FROM author_sessions WHERE token_hash = $1").bind(token_hash).execute(pool);
+    db::timed("author_sessions.delete", String::new, delete).await?;
+    Ok(())
+}
+
+pub async fn purge_sessions(pool: &PgPool) -> Result<(), DbError> {
+    let purge = sqlx::query("DELETE FROM author_sessions WHERE expires_at <= now()").execute(pool);
+    db::timed("author_sessions.purge", String::new, purge).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::queries;
+    use crate::test_support::TestDb;
+    use crate::users::AUTHOR_ROLE;
+
+    #[tokio::test]
+    async fn author_session_queries_round_trip() {
+        let Some(db) = TestDb::new().await else { return };
+        let user = queries::users::insert(&db.pool, "autor", "h", AUTHOR_ROLE).await.unwrap().unwrap();
+        insert_session(&db.pool, "vigente", user, Duration::from_secs(60)).await.unwrap();
+        insert_session(&db.pool, "caducada", user, Duration::ZERO).await.unwrap();
+
+        assert_eq!(session_user(&db.pool, "vigente").await.unwrap(), Some(user));
+        assert_eq!(session_user(&db.pool, "caducada").await.unwrap(), None);
+        purge_sessions(&db.pool).await.unwrap();
+        let left: i64 = sqlx::query_scalar("SELECT count(*) FROM author_sessions").fetch_one(&db.pool).await.unwrap();
+        assert_eq!(left, 1);
+
+        delete_session(&db.pool, "vigente").await.unwrap();
+        assert_eq!(session_user(&db.pool, "vigente").await.unwrap(), None);
+
+        db.finish().await;
+    }
+}
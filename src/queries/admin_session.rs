@@ -0,0 +1,78 @@
+//! `admin_sessions`: sesiones del panel, con token o con usuario.
+
+use sqlx::PgPool;
+use std::time::Duration;
+
+use crate::db::{self, DbError};
+
+/// Sesión vigente con ese hash (ver `edit_token::hash`): `Some(None)` si se
+/// abrió con el token de administración, `Some(Some((id, role)))` si con un
+/// usuario que sigue activo.
+pub async fn session(pool: &PgPool, token_hash: &str) -> Result<Option<Option<(i32, String)>>, DbError> {
+    let select = sqlx::query_as::<_, (Option<i32>, Option<String>)>(
+        "SELECT s.user_id, u.role FROM admin_sessions s
+         LEFT JOIN users u ON u.id = s.user_id
+         WHERE s.token_hash = $1 AND s.expires_at > now()
+           AND (s.user_id IS NULL OR u.disabled_at IS NULL)",
+    )
+    .bind(token_hash)
+    .fetch_optional(pool);
+    let row = db::timed("admin_sessions.check", String::new, select).await?;
+    Ok(row.map(|(user, role)| user.zip(role)))
+}
+
+pub async fn insert_session(pool: &PgPool, token_hash: &str, user: Option<i32>, ttl: Duration) -> Result<(), DbError> {
+    let insert = sqlx::query(
+        "INSERT INTO admin_sessions (token_hash, user_id, expires_at) VALUES ($1, $2, now() + make_interval(secs => $3))",
+    )
+    .bind(token_hash)
+    .bind(user)
+    .bind(ttl.as_secs_f64())
+    .execute(pool);
+    db::timed("admin_sessions.insert", String::new, insert).await?;
+    Ok(())
+}
+
+pub async fn delete_session(pool: &PgPool, token_hash: &str) -> Result<(), DbError> {
+    let delete = sqlx::query("DELETE FROM admin_sessions WHERE token_hash = $1").bind(token_hash).execute(pool);
+    db::timed("admin_sessions.delete", String::new, delete).await?;
+    Ok(())
+}
+
+pub async fn purge_sessions(pool: &PgPool) -> Result<(), DbError> {
+    let purge = sqlx::query("DELETE FROM admin_sessions WHERE expires_at <= now()").execute(pool);
+    db::timed("admin_sessions.purge", String::new, purge).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::queries;
+    use crate::test_support::TestDb;
+
+    #[tokio::test]
+    async fn admin_session_queries_round_trip() {
+        let Some(db) = TestDb::new().await else { return };
+        let user = queries::users::insert(&db.pool, "mod", "h", "moderator").await.unwrap().unwrap();
+        let hour = Duration::from_secs(3600);
+        insert_session(&db.pool, "token", None, hour).await.unwrap();
+        insert_session(&db.pool, "usuario", Some(user), hour).await.unwrap();
+        insert_session(&db.pool, "caducada", None, Duration::ZERO).await.unwrap();
+
+        assert_eq!(session(&db.pool, "token").await.unwrap(), Some(None));
+        assert_eq!(session(&db.pool, "usuario").await.unwrap(), Some(Some((user, "moderator".to_string()))));
+        assert_eq!(session(&db.pool, "caducada").await.unwrap(), None);
+
+        // Desactivar al usuario corta su sesión.
+        queries::users::disable(&db.pool, user).await.unwrap();
+        assert_eq!(session(&db.pool, "usuario").await.unwrap(), None);
+
+        purge_sessions(&db.pool).await.unwrap();
+        delete_session(&db.pool, "token").await.unwrap();
+        let left: i64 = sqlx::query_scalar("SELECT count(*) FROM admin_sessions").fetch_one(&db.pool).await.unwrap();
+        assert_eq!(left, 1);
+
+        db.finish().await;
+    }
+}
@@ -0,0 +1,71 @@
+//! `announcement`: el aviso del sitio, una sola fila.
+
+use chrono::{DateTime, Utc};
+use sqlx::{postgres::PgRow, PgConnection, PgPool, Row};
+
+use crate::announcement::{Announcement, Stored};
+use crate::db::{self, DbError};
+
+fn announcement(r: &PgRow) -> Announcement {
+    Announcement { message: r.get("message"), expires_at: r.get("expires_at") }
+}
+
+/// El aviso guardado, caducado o no.
+pub async fn stored(pool: &PgPool) -> Result<Option<Announcement>, DbError> {
+    let select = sqlx::query("SELECT message, expires_at FROM announcement").fetch_optional(pool);
+    Ok(db::timed("announcement.stored", String::new, select).await?.as_ref().map(announcement))
+}
+
+/// Como `stored`, con la fecha de edición y si sigue vigente.
+pub async fn get(pool: &PgPool) -> Result<Option<Stored>, DbError> {
+    let select = sqlx::query(
+        "SELECT message, expires_at, updated_at, expires_at IS NULL OR expires_at > now() AS active
+         FROM announcement",
+    )
+    .fetch_optional(pool);
+    let row = db::timed("announcement.get", String::new, select).await?;
+    Ok(row.map(|r| Stored { announcement: announcement(&r), updated_at: r.get("updated_at"), active: r.get("active") }))
+}
+
+pub async fn put(conn: &mut PgConnection, message: &str, expires_at: Option<DateTime<Utc>>) -> Result<(), DbError> {
+    let upsert = sqlx::query(
+        "INSERT INTO announcement (message, expires_at) VALUES ($1, $2)
+         ON CONFLICT (id) DO UPDATE SET message = $1, expires_at = $2, updated_at = now()",
+    )
+    .bind(message)
+    .bind(expires_at)
+    .execute(conn);
+    db::timed("announcement.put", || format!("len={}", message.len()), upsert).await?;
+    Ok(())
+}
+
+pub async fn delete(conn: &mut PgConnection) -> Result<(), DbError> {
+    let delete = sqlx::query("DELETE FROM announcement").execute(conn);
+    db::timed("announcement.delete", String::new, delete).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestDb;
+
+    #[tokio::test]
+    async fn announcement_queries_round_trip() {
+        let Some(db) = TestDb::new().await else { return };
+        assert!(stored(&db.pool).await.unwrap().is_none());
+
+        let mut conn = db.pool.acquire().await.unwrap();
+        put(&mut conn, "Cerrado en agosto", None).await.unwrap();
+        put(&mut conn, "Abierto en agosto", Some(Utc::now() - chrono::Duration::hours(1))).await.unwrap();
+        assert_eq!(stored(&db.pool).await.unwrap().unwrap().message, "Abierto en agosto");
+        let got = get(&db.pool).await.unwrap().unwrap();
+        assert!(!got.active);
+
+        delete(&mut conn).await.unwrap();
+        drop(conn);
+        assert!(get(&db.pool).await.unwrap().is_none());
+
+        db.finish().await;
+    }
+}
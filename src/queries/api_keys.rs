@@ -0,0 +1,83 @@
+//! `api_keys`: claves de `X-Api-Key`, guardadas por su hash.
+
+use sqlx::{PgPool, Row};
+
+use crate::api_keys::KeyInfo;
+use crate::db::{self, DbError};
+
+/// `(id, scope)` de la clave vigente con ese hash; de paso apunta el uso.
+pub async fn touch(pool: &PgPool, key_hash: &str) -> Result<Option<(i32, String)>, DbError> {
+    let update = sqlx::query_as::<_, (i32, String)>(
+        "UPDATE api_keys SET last_used_at = now()
+         WHERE key_hash = $1 AND revoked_at IS NULL
+         RETURNING id, scope",
+    )
+    .bind(key_hash)
+    .fetch_optional(pool);
+    Ok(db::timed("api_keys.use", String::new, update).await?)
+}
+
+pub async fn insert(pool: &PgPool, name: &str, prefix: &str, key_hash: &str, scope: &str) -> Result<i32, DbError> {
+    let insert = sqlx::query_scalar::<_, i32>(
+        "INSERT INTO api_keys (name, prefix, key_hash, scope) VALUES ($1, $2, $3, $4) RETURNING id",
+    )
+    .bind(name)
+    .bind(prefix)
+    .bind(key_hash)
+    .bind(scope)
+    .fetch_one(pool);
+    Ok(db::timed("api_keys.insert", || format!("name={name}"), insert).await?)
+}
+
+pub async fn list(pool: &PgPool) -> Result<Vec<KeyInfo>, DbError> {
+    let select = sqlx::query(
+        "SELECT id, name, prefix, scope, created_at, last_used_at, revoked_at FROM api_keys ORDER BY id",
+    )
+    .fetch_all(pool);
+    let rows = db::timed("api_keys.list", String::new, select).await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| KeyInfo {
+            id: r.get("id"),
+            name: r.get("name"),
+            prefix: r.get("prefix"),
+            scope: r.get("scope"),
+            created_at: r.get("created_at"),
+            last_used_at: r.get("last_used_at"),
+            revoked_at: r.get("revoked_at"),
+        })
+        .collect())
+}
+
+/// `false` si no existe o ya estaba revocada.
+pub async fn revoke(pool: &PgPool, id: i32) -> Result<bool, DbError> {
+    let update = sqlx::query("UPDATE api_keys SET revoked_at = now() WHERE id = $1 AND revoked_at IS NULL")
+        .bind(id)
+        .execute(pool);
+    Ok(db::timed("api_keys.revoke", || format!("id={id}"), update).await?.rows_affected() > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestDb;
+
+    #[tokio::test]
+    async fn api_key_queries_round_trip() {
+        let Some(db) = TestDb::new().await else { return };
+        let id = insert(&db.pool, "ci", "hak_0123", "h1", "read").await.unwrap();
+
+        assert_eq!(touch(&db.pool, "h1").await.unwrap(), Some((id, "read".to_string())));
+        assert_eq!(touch(&db.pool, "otro").await.unwrap(), None);
+        let keys = list(&db.pool).await.unwrap();
+        assert_eq!((keys.len(), keys[0].prefix.as_str()), (1, "hak_0123"));
+        assert!(keys[0].last_used_at.is_some());
+
+        assert!(revoke(&db.pool, id).await.unwrap());
+        assert!(!revoke(&db.pool, id).await.unwrap());
+        assert_eq!(touch(&db.pool, "h1").await.unwrap(), None);
+
+        db.finish().await;
+    }
+}
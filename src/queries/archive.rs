@@ -0,0 +1,74 @@
+//! `mensajes_archive`: mensajes antiguos fuera de la tabla caliente.
+
+use sqlx::PgPool;
+use std::time::Duration;
+
+use crate::db::{self, DbError};
+
+/// Mueve hasta `limit` mensajes anteriores a `after` en una sola sentencia:
+/// un mensaje está en una tabla o en la otra, nunca en las dos ni en ninguna.
+pub async fn archive_batch(pool: &PgPool, after: Duration, limit: i64) -> Result<u64, DbError> {
+    let moved = sqlx::query(
+        "WITH moved AS (
+            DELETE FROM mensajes WHERE id IN (
+                SELECT id FROM mensajes WHERE created_at < now() - make_interval(secs => $1)
+                ORDER BY id LIMIT $2
+            )
+            RETURNING id, nombre, mensaje, created_at, author_ip, spam_score, author_email, email_verified_at, user_id
+         )
+         INSERT INTO mensajes_archive
+            (id, nombre, mensaje, created_at, author_ip, spam_score, author_email, email_verified_at, user_id)
+         SELECT * FROM moved",
+    )
+    .bind(after.as_secs_f64())
+    .bind(limit)
+    .execute(pool);
+    Ok(db::timed("mensajes.archive", || format!("limit={limit}"), moved).await?.rows_affected())
+}
+
+/// Cuántos mensajes son anteriores a `after` y los ids de los `limit` primeros.
+pub async fn archivable(pool: &PgPool, after: Duration, limit: i64) -> Result<(i64, Vec<i32>), DbError> {
+    let select = sqlx::query_as::<_, (i32, i64)>(
+        "SELECT id, count(*) OVER () FROM mensajes
+         WHERE created_at < now() - make_interval(secs => $1)
+         ORDER BY id LIMIT $2",
+    )
+    .bind(after.as_secs_f64())
+    .bind(limit)
+    .fetch_all(pool);
+
+    let rows = db::timed("mensajes.archive_preview", String::new, select).await?;
+    let count = rows.first().map_or(0, |(_, count)| *count);
+    Ok((count, rows.into_iter().map(|(id, _)| id).collect()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestDb;
+
+    #[tokio::test]
+    async fn archive_moves_only_old_mensajes() {
+        let Some(db) = TestDb::new().await else { return };
+        sqlx::query(
+            "INSERT INTO mensajes (nombre, mensaje, created_at) VALUES
+                ('a', 'viejo', now() - interval '3 days'), ('b', 'viejo', now() - interval '2 days'),
+                ('c', 'nuevo', now())",
+        )
+        .execute(&db.pool)
+        .await
+        .unwrap();
+        let day = Duration::from_secs(24 * 60 * 60);
+
+        let (count, ids) = archivable(&db.pool, day, 1).await.unwrap();
+        assert_eq!((count, ids.len()), (2, 1));
+        assert_eq!(archive_batch(&db.pool, day, 1).await.unwrap(), 1);
+        assert_eq!(archive_batch(&db.pool, day, 10).await.unwrap(), 1);
+        assert_eq!(archivable(&db.pool, day, 10).await.unwrap(), (0, vec![]));
+
+        let archived: i64 = sqlx::query_scalar("SELECT count(*) FROM mensajes_archive").fetch_one(&db.pool).await.unwrap();
+        assert_eq!(archived, 2);
+
+        db.finish().await;
+    }
+}
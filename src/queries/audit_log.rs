@@ -0,0 +1,189 @@
+//! `admin_audit` (peticiones del panel) y `audit_log` (cambios, con quién y
+//! los valores de antes y después).
+
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Row};
+
+use crate::audit_log::{Change, ChangeEntry, Entry};
+use crate::db::{self, DbError};
+
+/* ---------- admin_audit ---------- */
+
+/// Filtros de `GET /admin/audit`; vacíos (o `class` 0), sin filtro.
+pub struct RequestFilter<'a> {
+    pub method: &'a str,
+    /// Clase de respuesta: 4 para los `4xx`.
+    pub class: i16,
+    /// Texto contenido en la ruta.
+    pub q: &'a str,
+}
+
+impl RequestFilter<'_> {
+    const SQL: &'static str =
+        "($1 = '' OR method = $1) AND ($2 = 0 OR status / 100 = $2) AND ($3 = '' OR strpos(uri, $3) > 0)";
+
+    fn params(&self) -> String {
+        format!("method={} status={} q={}", self.method, self.class, self.q)
+    }
+}
+
+pub struct RequestEntry {
+    pub created_at: DateTime<Utc>,
+    pub method: String,
+    pub uri: String,
+    pub ip: Option<String>,
+    pub status: i16,
+}
+
+pub async fn insert_request(pool: &PgPool, entry: &Entry<'_>) -> Result<(), DbError> {
+    let insert = sqlx::query("INSERT INTO admin_audit (method, uri, ip, status) VALUES ($1, $2, $3, $4)")
+        .bind(entry.method)
+        .bind(entry.uri)
+        .bind(entry.ip.map(|ip| ip.to_string()))
+        .bind(entry.status as i16)
+        .execute(pool);
+    db::timed("admin_audit.insert", || format!("uri={}", entry.uri), insert).await?;
+    Ok(())
+}
+
+pub async fn count_requests(pool: &PgPool, filter: &RequestFilter<'_>) -> Result<i64, DbError> {
+    let sql = format!("SELECT count(*) FROM admin_audit WHERE {}", RequestFilter::SQL);
+    let count = sqlx::query_scalar::<_, i64>(&sql)
+        .bind(filter.method)
+        .bind(filter.class)
+        .bind(filter.q)
+        .fetch_one(pool);
+    Ok(db::timed("admin_audit.count", || filter.params(), count).await?)
+}
+
+/// Las más recientes primero.
+pub async fn requests(
+    pool: &PgPool,
+    filter: &RequestFilter<'_>,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<RequestEntry>, DbError> {
+    let sql = format!(
+        "SELECT created_at, method, uri, ip, status FROM admin_audit WHERE {}
+         ORDER BY id DESC LIMIT $4 OFFSET $5",
+        RequestFilter::SQL
+    );
+    let select = sqlx::query(&sql)
+        .bind(filter.method)
+        .bind(filter.class)
+        .bind(filter.q)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(pool);
+    let rows = db::timed("admin_audit.page", || filter.params(), select).await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| RequestEntry {
+            created_at: r.get("created_at"),
+            method: r.get("method"),
+            uri: r.get("uri"),
+            ip: r.get("ip"),
+            status: r.get("status"),
+        })
+        .collect())
+}
+
+/* ---------- audit_log ---------- */
+
+/// `action` exacta (`mensaje.delete`) o familia (`mensaje`); vacíos, sin filtro.
+const CHANGES_FILTER: &str = "($1 = '' OR action = $1 OR action LIKE $1 || '.%') AND ($2 = '' OR actor = $2)";
+
+pub async fn insert_change(pool: &PgPool, actor: &str, change: &Change) -> Result<(), DbError> {
+    let insert = sqlx::query(
+        "INSERT INTO audit_log (actor, action, target, old_value, new_value)
+         VALUES ($1, $2, $3, $4::jsonb, $5::jsonb)",
+    )
+    .bind(actor)
+    .bind(change.action)
+    .bind(&change.target)
+    .bind(change.old.as_ref().map(|v| v.to_string()))
+    .bind(change.new.as_ref().map(|v| v.to_string()))
+    .execute(pool);
+    db::timed("audit_log.insert", || change.action.to_string(), insert).await?;
+    Ok(())
+}
+
+pub async fn count_changes(pool: &PgPool, action: &str, actor: &str) -> Result<i64, DbError> {
+    let sql = format!("SELECT count(*) FROM audit_log WHERE {CHANGES_FILTER}");
+    let count = sqlx::query_scalar::<_, i64>(&sql).bind(action).bind(actor).fetch_one(pool);
+    Ok(db::timed("audit_log.count", || format!("action={action} actor={actor}"), count).await?)
+}
+
+/// Los más recientes primero.
+pub async fn changes(
+    pool: &PgPool,
+    action: &str,
+    actor: &str,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<ChangeEntry>, DbError> {
+    let sql = format!(
+        "SELECT id, created_at, actor, action, target, old_value::text AS old_value, new_value::text AS new_value
+         FROM audit_log WHERE {CHANGES_FILTER} ORDER BY id DESC LIMIT $3 OFFSET $4"
+    );
+    let select = sqlx::query(&sql).bind(action).bind(actor).bind(limit).bind(offset).fetch_all(pool);
+    let rows = db::timed("audit_log.page", || format!("action={action} actor={actor}"), select).await?;
+
+    let json = |text: Option<String>| text.and_then(|t| serde_json::from_str(&t).ok());
+    Ok(rows
+        .into_iter()
+        .map(|r| ChangeEntry {
+            id: r.get("id"),
+            created_at: r.get("created_at"),
+            actor: r.get("actor"),
+            action: r.get("action"),
+            target: r.get("target"),
+            old_value: json(r.get("old_value")),
+            new_value: json(r.get("new_value")),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestDb;
+
+    #[tokio::test]
+    async fn request_queries_filter() {
+        let Some(db) = TestDb::new().await else { return };
+        for (method, uri, status) in [("POST", "/api/admin/bans", 201), ("DELETE", "/mensajes/1", 404)] {
+            let entry = Entry { method, uri, ip: Some("10.0.0.1".parse().unwrap()), status };
+            insert_request(&db.pool, &entry).await.unwrap();
+        }
+
+        let all = RequestFilter { method: "", class: 0, q: "" };
+        assert_eq!(count_requests(&db.pool, &all).await.unwrap(), 2);
+        let missing = RequestFilter { class: 4, ..all };
+        assert_eq!(count_requests(&db.pool, &missing).await.unwrap(), 1);
+        let bans = RequestFilter { method: "POST", class: 0, q: "bans" };
+        let rows = requests(&db.pool, &bans, 10, 0).await.unwrap();
+        assert_eq!((rows.len(), rows[0].status, rows[0].ip.as_deref()), (1, 201, Some("10.0.0.1")));
+        assert!(requests(&db.pool, &all, 10, 2).await.unwrap().is_empty());
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn change_queries_filter_by_action_family() {
+        let Some(db) = TestDb::new().await else { return };
+        let delete = Change::new("mensaje.delete", "mensaje:1").old(serde_json::json!({ "nombre": "Ana" }));
+        insert_change(&db.pool, "admin", &delete).await.unwrap();
+        insert_change(&db.pool, "user:3", &Change::new("ban.create", "ban:1")).await.unwrap();
+
+        assert_eq!(count_changes(&db.pool, "", "").await.unwrap(), 2);
+        assert_eq!(count_changes(&db.pool, "mensaje", "").await.unwrap(), 1);
+        assert_eq!(count_changes(&db.pool, "mensaje.del", "").await.unwrap(), 0);
+        let rows = changes(&db.pool, "", "admin", 10, 0).await.unwrap();
+        assert_eq!(rows[0].old_value, Some(serde_json::json!({ "nombre": "Ana" })));
+        assert_eq!(rows[0].new_value, None);
+
+        db.finish().await;
+    }
+}
@@ -0,0 +1,64 @@
+//! Recuento de `author_cap`: lo publicado por un autor en la ventana.
+
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use std::net::IpAddr;
+
+use crate::db::{self, DbError};
+
+/// Mensajes de las últimas `hours` con ese nombre o desde esa IP, publicados o
+/// esperando confirmación, y el más antiguo.
+pub async fn recent(
+    pool: &PgPool,
+    hours: i32,
+    ip: Option<IpAddr>,
+    nombre: &str,
+) -> Result<(i64, Option<DateTime<Utc>>), DbError> {
+    let select = sqlx::query_as::<_, (i64, Option<DateTime<Utc>>)>(
+        "SELECT count(*), min(created_at) FROM (
+             SELECT created_at, author_ip, nombre FROM mensajes
+             UNION ALL
+             SELECT created_at, author_ip, nombre FROM mensajes_unconfirmed
+         ) AS m
+         WHERE created_at > now() - make_interval(hours => $1)
+           AND (author_ip = $2 OR lower(nombre) = lower($3))",
+    )
+    .bind(hours)
+    .bind(ip.map(|ip| ip.to_string()))
+    .bind(nombre)
+    .fetch_one(pool);
+    Ok(db::timed("mensajes.author_cap", String::new, select).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestDb;
+
+    #[tokio::test]
+    async fn counts_by_name_or_ip_inside_the_window() {
+        let Some(db) = TestDb::new().await else { return };
+        sqlx::query(
+            "INSERT INTO mensajes (nombre, mensaje, author_ip, created_at) VALUES
+                ('Ana', 'a', '10.0.0.1', now()), ('otra', 'b', '10.0.0.2', now()),
+                ('ana', 'c', '10.0.0.3', now() - interval '2 days')",
+        )
+        .execute(&db.pool)
+        .await
+        .unwrap();
+        sqlx::query(
+            "INSERT INTO mensajes_unconfirmed (token, expires_at, nombre, mensaje, author_ip, spam_score, author_email)
+             VALUES ('t', now() + interval '1 hour', 'Ana', 'd', '10.0.0.4', 0, 'ana@example.com')",
+        )
+        .execute(&db.pool)
+        .await
+        .unwrap();
+
+        let (count, oldest) = recent(&db.pool, 24, Some("10.0.0.2".parse().unwrap()), "ANA").await.unwrap();
+        assert_eq!(count, 3);
+        assert!(oldest.is_some());
+        assert_eq!(recent(&db.pool, 24, None, "nadie").await.unwrap(), (0, None));
+
+        db.finish().await;
+    }
+}
@@ -0,0 +1,77 @@
+//! `banned_ips`: direcciones y rangos bloqueados.
+
+use sqlx::{postgres::PgRow, PgPool, Row};
+use std::net::IpAddr;
+
+use crate::bans::Ban;
+use crate::db::{self, DbError};
+
+fn ban(r: PgRow) -> Ban {
+    Ban {
+        id: r.get("id"),
+        network: r.get("network"),
+        reason: r.get("reason"),
+        created_at: r.get("created_at"),
+    }
+}
+
+/// `true` si `ip` cae en algún rango bloqueado.
+pub async fn is_banned(pool: &PgPool, ip: IpAddr) -> Result<bool, DbError> {
+    let select = sqlx::query_scalar::<_, bool>("SELECT EXISTS (SELECT 1 FROM banned_ips WHERE $1::inet <<= network)")
+        .bind(ip.to_string())
+        .fetch_one(pool);
+    Ok(db::timed("banned_ips.check", || format!("ip={ip}"), select).await?)
+}
+
+/// `None` si el rango ya estaba. `network()` quita los bits de host:
+/// `10.1.2.3/8` se guarda como `10.0.0.0/8`.
+pub async fn insert(pool: &PgPool, network: &str, reason: &str) -> Result<Option<Ban>, DbError> {
+    let insert = sqlx::query(
+        "INSERT INTO banned_ips (network, reason) VALUES (network($1::inet), $2)
+         ON CONFLICT (network) DO NOTHING
+         RETURNING id, network::text AS network, reason, created_at",
+    )
+    .bind(network)
+    .bind(reason)
+    .fetch_optional(pool);
+    Ok(db::timed("banned_ips.insert", || format!("network={network}"), insert).await?.map(ban))
+}
+
+pub async fn list(pool: &PgPool) -> Result<Vec<Ban>, DbError> {
+    let select = sqlx::query("SELECT id, network::text AS network, reason, created_at FROM banned_ips ORDER BY id")
+        .fetch_all(pool);
+    Ok(db::timed("banned_ips.list", String::new, select).await?.into_iter().map(ban).collect())
+}
+
+/// `(network, reason)` del bloqueo borrado.
+pub async fn delete(pool: &PgPool, id: i32) -> Result<Option<(String, String)>, DbError> {
+    let delete = sqlx::query_as::<_, (String, String)>(
+        "DELETE FROM banned_ips WHERE id = $1 RETURNING network::text, reason",
+    )
+    .bind(id)
+    .fetch_optional(pool);
+    Ok(db::timed("banned_ips.delete", || format!("id={id}"), delete).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestDb;
+
+    #[tokio::test]
+    async fn ban_queries_round_trip() {
+        let Some(db) = TestDb::new().await else { return };
+        let ban = insert(&db.pool, "10.1.2.3/8", "spam").await.unwrap().unwrap();
+        assert_eq!(ban.network, "10.0.0.0/8");
+        assert!(insert(&db.pool, "10.0.0.0/8", "otra vez").await.unwrap().is_none());
+
+        assert!(is_banned(&db.pool, "10.9.9.9".parse().unwrap()).await.unwrap());
+        assert!(!is_banned(&db.pool, "11.0.0.1".parse().unwrap()).await.unwrap());
+        assert_eq!(list(&db.pool).await.unwrap().len(), 1);
+
+        assert_eq!(delete(&db.pool, ban.id).await.unwrap(), Some(("10.0.0.0/8".to_string(), "spam".to_string())));
+        assert_eq!(delete(&db.pool, ban.id).await.unwrap(), None);
+
+        db.finish().await;
+    }
+}
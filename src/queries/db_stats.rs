@@ -0,0 +1,57 @@
+//! Vistas `pg_stat_*` de `GET /admin/db`, solo del esquema actual: en tests
+//! cada uno tiene el suyo.
+
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use crate::db::{self, DbError};
+
+/// Nombre, filas vivas y muertas, bytes de tabla, índices y total, último VACUUM y ANALYZE.
+pub type TableRow = (String, i64, i64, i64, i64, i64, Option<DateTime<Utc>>, Option<DateTime<Utc>>);
+
+pub async fn database_size(pool: &PgPool) -> Result<i64, DbError> {
+    let size = sqlx::query_scalar::<_, i64>("SELECT pg_database_size(current_database())").fetch_one(pool);
+    Ok(db::timed("admin.db_size", String::new, size).await?)
+}
+
+/// Las más grandes primero.
+pub async fn tables(pool: &PgPool) -> Result<Vec<TableRow>, DbError> {
+    let tables = sqlx::query_as::<_, TableRow>(
+        "SELECT relname::text, n_live_tup, n_dead_tup,
+                pg_relation_size(relid), pg_indexes_size(relid), pg_total_relation_size(relid),
+                greatest(last_vacuum, last_autovacuum), greatest(last_analyze, last_autoanalyze)
+         FROM pg_stat_user_tables
+         WHERE schemaname = current_schema()
+         ORDER BY pg_total_relation_size(relid) DESC, relname",
+    )
+    .fetch_all(pool);
+    Ok(db::timed("admin.db_tables", String::new, tables).await?)
+}
+
+/// `(tabla, índice, bytes, lecturas)`.
+pub async fn indexes(pool: &PgPool) -> Result<Vec<(String, String, i64, i64)>, DbError> {
+    let indexes = sqlx::query_as::<_, (String, String, i64, i64)>(
+        "SELECT relname::text, indexrelname::text, pg_relation_size(indexrelid), idx_scan
+         FROM pg_stat_user_indexes
+         WHERE schemaname = current_schema()
+         ORDER BY relname, indexrelname",
+    )
+    .fetch_all(pool);
+    Ok(db::timed("admin.db_indexes", String::new, indexes).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestDb;
+
+    #[tokio::test]
+    async fn stats_cover_the_schema() {
+        let Some(db) = TestDb::new().await else { return };
+        assert!(database_size(&db.pool).await.unwrap() > 0);
+        assert!(tables(&db.pool).await.unwrap().iter().any(|t| t.0 == "mensajes"));
+        assert!(indexes(&db.pool).await.unwrap().iter().any(|(table, ..)| table == "mensajes"));
+
+        db.finish().await;
+    }
+}
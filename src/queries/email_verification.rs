@@ -0,0 +1,156 @@
+//! `email_verifications` (enlaces de `/verificar`) y `mensajes_unconfirmed`
+//! (mensajes que esperan a `/confirmar`).
+
+use sqlx::{PgConnection, PgPool};
+use std::time::Duration;
+
+use crate::db::{self, DbError};
+use crate::queries::NewMensaje;
+
+pub async fn insert_token(conn: &mut PgConnection, token: &str, mensaje_id: i32, ttl: Duration) -> Result<(), DbError> {
+    let insert = sqlx::query(
+        "INSERT INTO email_verifications (token, mensaje_id, expires_at)
+         VALUES ($1, $2, now() + make_interval(secs => $3))",
+    )
+    .bind(token)
+    .bind(mensaje_id)
+    .bind(ttl.as_secs_f64())
+    .execute(conn);
+    db::timed("email_verifications.insert", || format!("mensaje_id={mensaje_id}"), insert).await?;
+    Ok(())
+}
+
+/// Gasta el token y marca el mensaje como verificado; `None` si no vale.
+pub async fn use_token(pool: &PgPool, token: &str) -> Result<Option<i32>, DbError> {
+    let update = sqlx::query_scalar::<_, i32>(
+        "WITH used AS (
+             UPDATE email_verifications SET used_at = now()
+             WHERE token = $1 AND used_at IS NULL AND expires_at > now()
+             RETURNING mensaje_id
+         )
+         UPDATE mensajes SET email_verified_at = now()
+         FROM used WHERE mensajes.id = used.mensaje_id
+         RETURNING mensajes.id",
+    )
+    .bind(token)
+    .fetch_optional(pool);
+    Ok(db::timed("email_verifications.use", String::new, update).await?)
+}
+
+/* ---------- mensajes_unconfirmed ---------- */
+
+pub async fn purge_unconfirmed(conn: &mut PgConnection) -> Result<(), DbError> {
+    let purge = sqlx::query("DELETE FROM mensajes_unconfirmed WHERE expires_at <= now()").execute(conn);
+    db::timed("mensajes.unconfirmed_purge", String::new, purge).await?;
+    Ok(())
+}
+
+/// `false` si ese email ya tiene `max_pending` mensajes sin confirmar.
+pub async fn insert_unconfirmed(
+    conn: &mut PgConnection,
+    token: &str,
+    new: &NewMensaje<'_>,
+    ttl: Duration,
+    max_pending: i64,
+) -> Result<bool, DbError> {
+    let insert = sqlx::query(
+        "INSERT INTO mensajes_unconfirmed
+            (token, expires_at, nombre, mensaje, author_ip, spam_score, author_email, user_id)
+         SELECT $1, now() + make_interval(secs => $2), $3, $4, $5, $6, $7, $8
+         WHERE (SELECT count(*) FROM mensajes_unconfirmed WHERE author_email = $7 AND expires_at > now()) < $9",
+    )
+    .bind(token)
+    .bind(ttl.as_secs_f64())
+    .bind(new.nombre)
+    .bind(new.mensaje)
+    .bind(new.ip.map(|ip| ip.to_string()))
+    .bind(new.score)
+    .bind(new.email)
+    .bind(new.user_id)
+    .bind(max_pending)
+    .execute(conn);
+    let inserted = db::timed("mensajes.unconfirmed_insert", || format!("len={}", new.mensaje.len()), insert).await?;
+    Ok(inserted.rows_affected() == 1)
+}
+
+/// Nombre y texto del mensaje que espera con ese token, si no ha caducado.
+pub async fn unconfirmed(pool: &PgPool, token: &str) -> Result<Option<(String, String)>, DbError> {
+    let select = sqlx::query_as::<_, (String, String)>(
+        "SELECT nombre, mensaje FROM mensajes_unconfirmed WHERE token = $1 AND expires_at > now()",
+    )
+    .bind(token)
+    .fetch_optional(pool);
+    Ok(db::timed("mensajes.unconfirmed_get", String::new, select).await?)
+}
+
+/// Pasa el mensaje a `mensajes` con el mismo id y ya verificado; `None` si el
+/// token no vale.
+pub async fn confirm(conn: &mut PgConnection, token: &str) -> Result<Option<i32>, DbError> {
+    let publish = sqlx::query_scalar::<_, i32>(
+        "WITH moved AS (
+             DELETE FROM mensajes_unconfirmed WHERE token = $1 AND expires_at > now() RETURNING *
+         )
+         INSERT INTO mensajes (id, nombre, mensaje, created_at, author_ip, spam_score, author_email, email_verified_at, user_id)
+         SELECT id, nombre, mensaje, created_at, author_ip, spam_score, author_email, now(), user_id FROM moved
+         RETURNING id",
+    )
+    .bind(token)
+    .fetch_optional(conn);
+    Ok(db::timed("mensajes.confirm", String::new, publish).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::queries;
+    use crate::test_support::TestDb;
+
+    fn new_mensaje() -> NewMensaje<'static> {
+        NewMensaje {
+            nombre: "Ana",
+            mensaje: "Vendo moto",
+            ip: None,
+            score: 0,
+            email: Some("ana@example.com"),
+            user_id: None,
+            edit_token_hash: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn verification_tokens_are_single_use() {
+        let Some(db) = TestDb::new().await else { return };
+        let mut conn = db.pool.acquire().await.unwrap();
+        let id = queries::insert_mensaje(&mut conn, &new_mensaje()).await.unwrap();
+        insert_token(&mut conn, "t1", id, Duration::from_secs(60)).await.unwrap();
+        insert_token(&mut conn, "caducado", id, Duration::ZERO).await.unwrap();
+        drop(conn);
+
+        assert_eq!(use_token(&db.pool, "caducado").await.unwrap(), None);
+        assert_eq!(use_token(&db.pool, "t1").await.unwrap(), Some(id));
+        assert_eq!(use_token(&db.pool, "t1").await.unwrap(), None);
+
+        db.finish().await;
+    }
+
+    #[tokio::test]
+    async fn unconfirmed_mensajes_publish_once() {
+        let Some(db) = TestDb::new().await else { return };
+        let mut conn = db.pool.acquire().await.unwrap();
+        assert!(insert_unconfirmed(&mut conn, "caducado", &new_mensaje(), Duration::ZERO, 1).await.unwrap());
+        assert!(insert_unconfirmed(&mut conn, "c1", &new_mensaje(), Duration::from_secs(60), 1).await.unwrap());
+        // Los caducados no cuentan para el tope; los pendientes, sí.
+        assert!(!insert_unconfirmed(&mut conn, "c2", &new_mensaje(), Duration::from_secs(60), 1).await.unwrap());
+        purge_unconfirmed(&mut conn).await.unwrap();
+
+        assert_eq!(unconfirmed(&db.pool, "caducado").await.unwrap(), None);
+        assert_eq!(unconfirmed(&db.pool, "c1").await.unwrap(), Some(("Ana".to_string(), "Vendo moto".to_string())));
+        assert_eq!(confirm(&mut conn, "caducado").await.unwrap(), None);
+        let id = confirm(&mut conn, "c1").await.unwrap().unwrap();
+        assert_eq!(confirm(&mut conn, "c1").await.unwrap(), None);
+        drop(conn);
+        assert!(queries::mensaje_exists(&db.pool, id).await.unwrap());
+
+        db.finish().await;
+    }
+}
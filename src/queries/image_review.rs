@@ -0,0 +1,102 @@
+//! Cola de revisión de `images`: las pendientes tienen `approved_at` vacío.
+
+use sqlx::{PgConnection, PgPool, Row};
+
+use crate::db::{self, DbError};
+use crate::image_review::PendingImage;
+
+pub async fn count_pending(pool: &PgPool) -> Result<i64, DbError> {
+    let count = sqlx::query_scalar::<_, i64>(
+        "SELECT count(*) FROM images WHERE approved_at IS NULL AND deleted_at IS NULL",
+    )
+    .fetch_one(pool);
+    Ok(db::timed("images.pending_count", String::new, count).await?)
+}
+
+/// Las más antiguas primero.
+pub async fn pending(pool: &PgPool, limit: i64, offset: i64) -> Result<Vec<PendingImage>, DbError> {
+    let select = sqlx::query(
+        "SELECT id, filename, original_name, size_bytes, created_at FROM images
+         WHERE approved_at IS NULL AND deleted_at IS NULL
+         ORDER BY created_at, id LIMIT $1 OFFSET $2",
+    )
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool);
+    let rows = db::timed("images.pending", String::new, select).await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| PendingImage {
+            id: r.get("id"),
+            filename: r.get("filename"),
+            original_name: r.get("original_name"),
+            size_bytes: r.get("size_bytes"),
+            created_at: r.get("created_at"),
+        })
+        .collect())
+}
+
+pub async fn pending_filename(pool: &PgPool, id: i32) -> Result<Option<String>, DbError> {
+    let select = sqlx::query_scalar::<_, String>(
+        "SELECT filename FROM images WHERE id = $1 AND approved_at IS NULL AND deleted_at IS NULL",
+    )
+    .bind(id)
+    .fetch_optional(pool);
+    Ok(db::timed("images.pending_file", || format!("id={id}"), select).await?)
+}
+
+/// Fichero de la imagen aprobada; `None` si no estaba pendiente.
+pub async fn approve(conn: &mut PgConnection, id: i32) -> Result<Option<String>, DbError> {
+    let approve = sqlx::query_scalar::<_, String>(
+        "UPDATE images SET approved_at = now()
+         WHERE id = $1 AND approved_at IS NULL AND deleted_at IS NULL
+         RETURNING filename",
+    )
+    .bind(id)
+    .fetch_optional(conn);
+    Ok(db::timed("images.approve", || format!("id={id}"), approve).await?)
+}
+
+/// Borra el registro de la pendiente y devuelve su fichero.
+pub async fn reject(pool: &PgPool, id: i32) -> Result<Option<String>, DbError> {
+    let delete = sqlx::query_scalar::<_, String>(
+        "DELETE FROM images WHERE id = $1 AND approved_at IS NULL RETURNING filename",
+    )
+    .bind(id)
+    .fetch_optional(pool);
+    Ok(db::timed("images.reject", || format!("id={id}"), delete).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestDb;
+
+    #[tokio::test]
+    async fn review_queue_round_trip() {
+        let Some(db) = TestDb::new().await else { return };
+        let ids: Vec<i32> = sqlx::query_scalar(
+            "INSERT INTO images (filename, size_bytes, original_name, approved_at) VALUES
+                ('a.png', 10, 'a.png', NULL), ('b.png', 20, 'b.png', NULL), ('c.png', 30, 'c.png', now())
+             RETURNING id",
+        )
+        .fetch_all(&db.pool)
+        .await
+        .unwrap();
+
+        assert_eq!(count_pending(&db.pool).await.unwrap(), 2);
+        assert_eq!(pending(&db.pool, 10, 0).await.unwrap().len(), 2);
+        assert_eq!(pending_filename(&db.pool, ids[2]).await.unwrap(), None);
+
+        let mut conn = db.pool.acquire().await.unwrap();
+        assert_eq!(approve(&mut conn, ids[0]).await.unwrap().as_deref(), Some("a.png"));
+        assert_eq!(approve(&mut conn, ids[0]).await.unwrap(), None);
+        drop(conn);
+        assert_eq!(reject(&db.pool, ids[0]).await.unwrap(), None);
+        assert_eq!(reject(&db.pool, ids[1]).await.unwrap().as_deref(), Some("b.png"));
+        assert_eq!(count_pending(&db.pool).await.unwrap(), 0);
+
+        db.finish().await;
+    }
+}
@@ -0,0 +1,43 @@
+//! Catálogo de PostgreSQL para `index_advisor`, solo del esquema actual.
+
+use sqlx::PgPool;
+
+use crate::db::{self, DbError};
+
+pub async fn column_exists(pool: &PgPool, table: &str, column: &str) -> Result<bool, DbError> {
+    let column_exists = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS (SELECT 1 FROM information_schema.columns
+                        WHERE table_schema = current_schema() AND table_name = $1 AND column_name = $2)",
+    )
+    .bind(table)
+    .bind(column)
+    .fetch_one(pool);
+    Ok(db::timed("indexes.column", || column.to_string(), column_exists).await?)
+}
+
+/// `CREATE INDEX ...` de cada índice de la tabla.
+pub async fn index_defs(pool: &PgPool, table: &str) -> Result<Vec<String>, DbError> {
+    let defs = sqlx::query_scalar::<_, String>(
+        "SELECT indexdef FROM pg_indexes WHERE schemaname = current_schema() AND tablename = $1",
+    )
+    .bind(table)
+    .fetch_all(pool);
+    Ok(db::timed("indexes.list", || table.to_string(), defs).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestDb;
+
+    #[tokio::test]
+    async fn reads_columns_and_indexes() {
+        let Some(db) = TestDb::new().await else { return };
+        assert!(column_exists(&db.pool, "mensajes", "created_at").await.unwrap());
+        assert!(!column_exists(&db.pool, "mensajes", "no_existe").await.unwrap());
+        assert!(index_defs(&db.pool, "mensajes").await.unwrap().iter().any(|d| d.contains("(created_at)")));
+        assert!(index_defs(&db.pool, "no_existe").await.unwrap().is_empty());
+
+        db.finish().await;
+    }
+}
@@ -0,0 +1,79 @@
+//! `login_failures`: fallos seguidos y bloqueos por clave (`ip:...`, `user:...`).
+
+use sqlx::PgPool;
+use std::time::Duration;
+
+use crate::db::{self, DbError};
+
+/// Lo que falta del bloqueo más largo entre `keys`, si hay alguno.
+pub async fn remaining(pool: &PgPool, keys: &[String]) -> Result<Option<Duration>, DbError> {
+    let select = sqlx::query_scalar::<_, Option<f64>>(
+        "SELECT EXTRACT(EPOCH FROM max(locked_until) - now())::float8 FROM login_failures
+         WHERE key = ANY($1) AND locked_until > now()",
+    )
+    .bind(keys)
+    .fetch_one(pool);
+    let remaining = db::timed("login_failures.check", String::new, select).await?;
+    Ok(remaining.map(|secs| Duration::from_secs_f64(secs.max(0.0))))
+}
+
+/// Suma un fallo y devuelve los seguidos; los de hace más de `forget_after`
+/// ya no cuentan.
+pub async fn record_failure(pool: &PgPool, key: &str, forget_after: Duration) -> Result<i32, DbError> {
+    let upsert = sqlx::query_scalar::<_, i32>(
+        "INSERT INTO login_failures (key, failures) VALUES ($1, 1)
+         ON CONFLICT (key) DO UPDATE SET
+             failures = CASE WHEN login_failures.last_failure_at < now() - make_interval(secs => $2)
+                             THEN 1 ELSE login_failures.failures + 1 END,
+             last_failure_at = now()
+         RETURNING failures",
+    )
+    .bind(key)
+    .bind(forget_after.as_secs_f64())
+    .fetch_one(pool);
+    Ok(db::timed("login_failures.record", || key.to_string(), upsert).await?)
+}
+
+pub async fn lock(pool: &PgPool, key: &str, lock: Duration) -> Result<(), DbError> {
+    let update = sqlx::query(
+        "UPDATE login_failures SET locked_until = now() + make_interval(secs => $2) WHERE key = $1",
+    )
+    .bind(key)
+    .bind(lock.as_secs_f64())
+    .execute(pool);
+    db::timed("login_failures.lock", || key.to_string(), update).await?;
+    Ok(())
+}
+
+pub async fn clear(pool: &PgPool, keys: &[String]) -> Result<(), DbError> {
+    let delete = sqlx::query("DELETE FROM login_failures WHERE key = ANY($1)").bind(keys).execute(pool);
+    db::timed("login_failures.clear", String::new, delete).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestDb;
+
+    #[tokio::test]
+    async fn failures_count_lock_and_clear() {
+        let Some(db) = TestDb::new().await else { return };
+        let keys = ["ip:10.0.0.1".to_string(), "user:ana".to_string()];
+        let hour = Duration::from_secs(3600);
+
+        assert_eq!(record_failure(&db.pool, &keys[0], hour).await.unwrap(), 1);
+        assert_eq!(record_failure(&db.pool, &keys[0], hour).await.unwrap(), 2);
+        assert_eq!(remaining(&db.pool, &keys).await.unwrap(), None);
+
+        lock(&db.pool, &keys[0], Duration::from_secs(60)).await.unwrap();
+        let left = remaining(&db.pool, &keys).await.unwrap().unwrap();
+        assert!(left > Duration::from_secs(50) && left <= Duration::from_secs(60), "{left:?}");
+
+        clear(&db.pool, &keys).await.unwrap();
+        assert_eq!(remaining(&db.pool, &keys).await.unwrap(), None);
+        assert_eq!(record_failure(&db.pool, &keys[0], hour).await.unwrap(), 1);
+
+        db.finish().await;
+    }
+}
@@ -0,0 +1,80 @@
+//! `admin_accounts`: cuentas de Google o GitHub autorizadas para el panel.
+
+use sqlx::{PgPool, Row};
+
+use crate::db::{self, DbError};
+use crate::oauth::Account;
+
+/// Email de la cuenta si está autorizada; de paso apunta el acceso.
+pub async fn login(pool: &PgPool, provider: &str, email: &str) -> Result<Option<String>, DbError> {
+    let update = sqlx::query_scalar::<_, String>(
+        "UPDATE admin_accounts SET last_login_at = now()
+         WHERE provider = $1 AND lower(email) = lower($2)
+         RETURNING email",
+    )
+    .bind(provider)
+    .bind(email)
+    .fetch_optional(pool);
+    Ok(db::timed("admin_accounts.login", || format!("provider={provider}"), update).await?)
+}
+
+pub async fn list(pool: &PgPool) -> Result<Vec<Account>, DbError> {
+    let select = sqlx::query(
+        "SELECT id, provider, email, created_at, last_login_at FROM admin_accounts ORDER BY provider, email",
+    )
+    .fetch_all(pool);
+    let rows = db::timed("admin_accounts.list", String::new, select).await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| Account {
+            id: r.get("id"),
+            provider: r.get("provider"),
+            email: r.get("email"),
+            created_at: r.get("created_at"),
+            last_login_at: r.get("last_login_at"),
+        })
+        .collect())
+}
+
+/// `None` si ya estaba autorizada.
+pub async fn insert(pool: &PgPool, provider: &str, email: &str) -> Result<Option<i32>, DbError> {
+    let insert = sqlx::query_scalar::<_, i32>(
+        "INSERT INTO admin_accounts (provider, email) VALUES ($1, $2) ON CONFLICT DO NOTHING RETURNING id",
+    )
+    .bind(provider)
+    .bind(email)
+    .fetch_optional(pool);
+    Ok(db::timed("admin_accounts.insert", || format!("provider={provider}"), insert).await?)
+}
+
+/// `(provider, email)` de la cuenta retirada.
+pub async fn delete(pool: &PgPool, id: i32) -> Result<Option<(String, String)>, DbError> {
+    let delete = sqlx::query_as::<_, (String, String)>("DELETE FROM admin_accounts WHERE id = $1 RETURNING provider, email")
+        .bind(id)
+        .fetch_optional(pool);
+    Ok(db::timed("admin_accounts.delete", || format!("id={id}"), delete).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestDb;
+
+    #[tokio::test]
+    async fn account_queries_round_trip() {
+        let Some(db) = TestDb::new().await else { return };
+        let id = insert(&db.pool, "github", "Ana@example.com").await.unwrap().unwrap();
+        assert_eq!(insert(&db.pool, "github", "Ana@example.com").await.unwrap(), None);
+
+        assert_eq!(login(&db.pool, "github", "ana@EXAMPLE.com").await.unwrap().as_deref(), Some("Ana@example.com"));
+        assert_eq!(login(&db.pool, "google", "ana@example.com").await.unwrap(), None);
+        assert!(list(&db.pool).await.unwrap()[0].last_login_at.is_some());
+
+        let removed = delete(&db.pool, id).await.unwrap();
+        assert_eq!(removed, Some(("github".to_string(), "Ana@example.com".to_string())));
+        assert_eq!(delete(&db.pool, id).await.unwrap(), None);
+
+        db.finish().await;
+    }
+}
@@ -0,0 +1,144 @@
+//! `outbox`: eventos y correos pendientes de enviar, con su reserva y sus reintentos.
+
+use sqlx::{PgConnection, PgPool};
+use std::time::Duration;
+
+use crate::db::{self, DbError};
+use crate::events::Event;
+use crate::mailer::Email;
+
+/// Fila reservada, con el evento o el correo aún en JSON.
+pub struct Claimed {
+    pub id: i64,
+    pub payload: String,
+    pub is_email: bool,
+    pub attempts: i32,
+    pub webhook_sent: bool,
+    pub email_sent: bool,
+}
+
+pub async fn insert(conn: &mut PgConnection, event: &Event) -> Result<(), DbError> {
+    let insert = sqlx::query("INSERT INTO outbox (event) VALUES ($1::jsonb)")
+        .bind(serde_json::to_string(event).unwrap())
+        .execute(conn);
+    db::timed("outbox.enqueue", || event.name().to_string(), insert).await?;
+    Ok(())
+}
+
+pub async fn insert_email(conn: &mut PgConnection, email: &Email) -> Result<(), DbError> {
+    let insert = sqlx::query("INSERT INTO outbox (email) VALUES ($1::jsonb)")
+        .bind(serde_json::to_string(email).unwrap())
+        .execute(conn);
+    db::timed("outbox.enqueue", || "email".to_string(), insert).await?;
+    Ok(())
+}
+
+/// Reserva durante `lease` la fila pendiente más antigua con menos de
+/// `max_attempts` intentos; las reservadas por otra réplica se saltan.
+pub async fn claim(pool: &PgPool, lease: Duration, max_attempts: i32) -> Result<Option<Claimed>, DbError> {
+    let claim = sqlx::query_as::<_, (i64, String, bool, i32, bool, bool)>(
+        "UPDATE outbox SET next_attempt_at = now() + make_interval(secs => $1)
+         WHERE id = (
+             SELECT id FROM outbox
+             WHERE done_at IS NULL AND next_attempt_at <= now() AND attempts < $2
+             ORDER BY id LIMIT 1
+             FOR UPDATE SKIP LOCKED
+         )
+         RETURNING id, coalesce(event, email)::text, email IS NOT NULL, attempts,
+                   webhook_sent_at IS NOT NULL, email_sent_at IS NOT NULL",
+    )
+    .bind(lease.as_secs_f64())
+    .bind(max_attempts)
+    .fetch_optional(pool);
+    let row = db::timed("outbox.claim", String::new, claim).await?;
+    Ok(row.map(|(id, payload, is_email, attempts, webhook_sent, email_sent)| Claimed {
+        id,
+        payload,
+        is_email,
+        attempts,
+        webhook_sent,
+        email_sent,
+    }))
+}
+
+/// Deja la fila con `attempts` intentos y el error, sin reintento.
+pub async fn abandon(pool: &PgPool, id: i64, attempts: i32, error: &str) -> Result<(), DbError> {
+    let abandon = sqlx::query("UPDATE outbox SET attempts = $2, last_error = $3 WHERE id = $1")
+        .bind(id)
+        .bind(attempts)
+        .bind(error)
+        .execute(pool);
+    db::timed("outbox.abandon", || format!("id={id}"), abandon).await?;
+    Ok(())
+}
+
+/// Apunta el intento fallido y el siguiente dentro de `retry_in`.
+pub async fn failed(pool: &PgPool, id: i64, attempts: i32, error: &str, retry_in: Duration) -> Result<(), DbError> {
+    let update = sqlx::query(
+        "UPDATE outbox SET attempts = $2, last_error = $3, next_attempt_at = now() + make_interval(secs => $4)
+         WHERE id = $1",
+    )
+    .bind(id)
+    .bind(attempts)
+    .bind(error)
+    .bind(retry_in.as_secs_f64())
+    .execute(pool);
+    db::timed("outbox.failed", || format!("id={id}"), update).await?;
+    Ok(())
+}
+
+/// Pone `now()` en `column`, que es siempre uno de los nombres fijos de `outbox`.
+pub async fn mark(pool: &PgPool, id: i64, column: &'static str) -> Result<(), DbError> {
+    let sql = format!("UPDATE outbox SET {column} = now() WHERE id = $1");
+    let update = sqlx::query(&sql).bind(id).execute(pool);
+    db::timed("outbox.mark", || format!("id={id} {column}"), update).await?;
+    Ok(())
+}
+
+/// Borra lo terminado hace más de `days` días.
+pub async fn purge(pool: &PgPool, days: i32) -> Result<u64, DbError> {
+    let delete = sqlx::query("DELETE FROM outbox WHERE done_at < now() - make_interval(days => $1)")
+        .bind(days)
+        .execute(pool);
+    Ok(db::timed("outbox.purge", String::new, delete).await?.rows_affected())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestDb;
+
+    #[tokio::test]
+    async fn claimed_rows_wait_for_their_lease() {
+        let Some(db) = TestDb::new().await else { return };
+        let lease = Duration::from_secs(60);
+        let mut conn = db.pool.acquire().await.unwrap();
+        insert(&mut conn, &Event::MessageDeleted { id: 7 }).await.unwrap();
+        drop(conn);
+
+        let claimed = claim(&db.pool, lease, 3).await.unwrap().unwrap();
+        assert!(claimed.payload.contains("MessageDeleted") && !claimed.is_email);
+        assert!(!claimed.webhook_sent && !claimed.email_sent);
+        assert!(claim(&db.pool, lease, 3).await.unwrap().is_none());
+
+        failed(&db.pool, claimed.id, 1, "caído", Duration::ZERO).await.unwrap();
+        mark(&db.pool, claimed.id, "webhook_sent_at").await.unwrap();
+        let again = claim(&db.pool, lease, 3).await.unwrap().unwrap();
+        assert_eq!((again.attempts, again.webhook_sent), (1, true));
+
+        // Con los intentos agotados ya no se reserva.
+        abandon(&db.pool, again.id, 3, "ilegible").await.unwrap();
+        failed(&db.pool, again.id, 3, "ilegible", Duration::ZERO).await.unwrap();
+        assert!(claim(&db.pool, lease, 3).await.unwrap().is_none());
+
+        mark(&db.pool, again.id, "done_at").await.unwrap();
+        assert_eq!(purge(&db.pool, 0).await.unwrap(), 1);
+
+        let email = Email { to: "ana@example.com".into(), subject: "Hola".into(), text: "t".into(), html: "h".into() };
+        insert_email(&mut db.pool.acquire().await.unwrap(), &email).await.unwrap();
+        let claimed = claim(&db.pool, lease, 3).await.unwrap().unwrap();
+        assert!(claimed.is_email && claimed.payload.contains("ana@example.com"));
+
+        db.finish().await;
+    }
+}
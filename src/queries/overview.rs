@@ -0,0 +1,72 @@
+//! Recuentos y listas cortas de la portada del panel (`GET /admin/overview`).
+
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use crate::db::{self, DbError};
+
+pub async fn pending_images(pool: &PgPool) -> Result<i64, DbError> {
+    let count = sqlx::query_scalar::<_, i64>(
+        "SELECT count(*) FROM images WHERE approved_at IS NULL AND deleted_at IS NULL",
+    )
+    .fetch_one(pool);
+    Ok(db::timed("overview.pending", String::new, count).await?)
+}
+
+/// `(id, reason, ip, created_at)` de lo último de `spam_log`.
+pub async fn recent_spam(pool: &PgPool, limit: i64) -> Result<Vec<(i64, String, Option<String>, DateTime<Utc>)>, DbError> {
+    let select = sqlx::query_as("SELECT id, reason, ip, created_at FROM spam_log ORDER BY id DESC LIMIT $1")
+        .bind(limit)
+        .fetch_all(pool);
+    Ok(db::timed("overview.spam", String::new, select).await?)
+}
+
+/// `(id, nombre, mensaje, created_at)` de los últimos mensajes.
+pub async fn latest_mensajes(pool: &PgPool, limit: i64) -> Result<Vec<(i32, String, String, DateTime<Utc>)>, DbError> {
+    let select = sqlx::query_as("SELECT id, nombre, mensaje, created_at FROM mensajes ORDER BY id DESC LIMIT $1")
+        .bind(limit)
+        .fetch_all(pool);
+    Ok(db::timed("overview.mensajes", String::new, select).await?)
+}
+
+/// Número de imágenes, sus bytes y los que están en la papelera.
+pub async fn image_storage(pool: &PgPool) -> Result<(i64, i64, i64), DbError> {
+    let select = sqlx::query_as::<_, (i64, i64, i64)>(
+        "SELECT count(*),
+                coalesce(sum(size_bytes), 0)::bigint,
+                coalesce(sum(size_bytes) FILTER (WHERE deleted_at IS NOT NULL), 0)::bigint
+         FROM images",
+    )
+    .fetch_one(pool);
+    Ok(db::timed("overview.storage", String::new, select).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestDb;
+
+    #[tokio::test]
+    async fn overview_queries_count_and_limit() {
+        let Some(db) = TestDb::new().await else { return };
+        sqlx::query(
+            "INSERT INTO images (filename, size_bytes, original_name, approved_at, deleted_at) VALUES
+                ('a.png', 10, 'a.png', NULL, NULL), ('b.png', 20, 'b.png', now(), now()), ('c.png', 30, 'c.png', now(), NULL)",
+        )
+        .execute(&db.pool)
+        .await
+        .unwrap();
+        sqlx::query("INSERT INTO mensajes (nombre, mensaje) VALUES ('a', 'uno'), ('b', 'dos')")
+            .execute(&db.pool)
+            .await
+            .unwrap();
+
+        assert_eq!(pending_images(&db.pool).await.unwrap(), 1);
+        assert_eq!(image_storage(&db.pool).await.unwrap(), (3, 60, 20));
+        let latest = latest_mensajes(&db.pool, 1).await.unwrap();
+        assert_eq!((latest.len(), latest[0].2.as_str()), (1, "dos"));
+        assert!(recent_spam(&db.pool, 5).await.unwrap().is_empty());
+
+        db.finish().await;
+    }
+}
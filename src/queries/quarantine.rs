@@ -0,0 +1,169 @@
+//! `mensajes_quarantine`: mensajes retenidos por la puntuación o por Akismet.
+
+use sqlx::{PgConnection, PgPool, Row};
+use std::net::IpAddr;
+
+use crate::db::{self, DbError};
+use crate::quarantine::QuarantinedMensaje;
+use crate::queries::NewMensaje;
+
+/// Lo que se publica al soltar un mensaje, con lo que pide Akismet.
+pub struct Released {
+    pub nombre: String,
+    pub mensaje: String,
+    pub author_ip: Option<String>,
+    pub spam_score: i32,
+    pub author_email: Option<String>,
+    pub user_agent: Option<String>,
+    pub akismet_spam: bool,
+}
+
+/// Envíos de `ip` en los últimos `minutes`: publicados, retenidos o esperando confirmación.
+pub async fn recent_from_ip(pool: &PgPool, ip: IpAddr, minutes: i32) -> Result<i64, DbError> {
+    let select = sqlx::query_scalar::<_, i64>(
+        "SELECT (SELECT count(*) FROM mensajes WHERE author_ip = $1 AND created_at > now() - make_interval(mins => $2))
+              + (SELECT count(*) FROM mensajes_quarantine WHERE author_ip = $1 AND created_at > now() - make_interval(mins => $2))
+              + (SELECT count(*) FROM mensajes_unconfirmed WHERE author_ip = $1 AND created_at > now() - make_interval(mins => $2))",
+    )
+    .bind(ip.to_string())
+    .bind(minutes)
+    .fetch_one(pool);
+    Ok(db::timed("mensajes.velocity", String::new, select).await?)
+}
+
+pub async fn insert(
+    pool: &PgPool,
+    held: &NewMensaje<'_>,
+    user_agent: Option<&str>,
+    akismet_spam: bool,
+) -> Result<i32, DbError> {
+    let insert = sqlx::query_scalar::<_, i32>(
+        "INSERT INTO mensajes_quarantine
+            (nombre, mensaje, author_ip, spam_score, author_email, user_id, user_agent, akismet_spam)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8) RETURNING id",
+    )
+    .bind(held.nombre)
+    .bind(held.mensaje)
+    .bind(held.ip.map(|ip| ip.to_string()))
+    .bind(held.score)
+    .bind(held.email)
+    .bind(held.user_id)
+    .bind(user_agent)
+    .bind(akismet_spam)
+    .fetch_one(pool);
+    Ok(db::timed("mensajes.quarantine", || format!("score={}", held.score), insert).await?)
+}
+
+pub async fn count(pool: &PgPool) -> Result<i64, DbError> {
+    let count = sqlx::query_scalar::<_, i64>("SELECT count(*) FROM mensajes_quarantine").fetch_one(pool);
+    Ok(db::timed("mensajes.quarantine_count", String::new, count).await?)
+}
+
+/// La puntuación más alta primero.
+pub async fn page(pool: &PgPool, limit: i64, offset: i64) -> Result<Vec<QuarantinedMensaje>, DbError> {
+    let select = sqlx::query(
+        "SELECT id, nombre, mensaje, spam_score, akismet_spam, author_ip, created_at FROM mensajes_quarantine
+         ORDER BY spam_score DESC, id LIMIT $1 OFFSET $2",
+    )
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool);
+    let rows = db::timed("mensajes.quarantine_page", || format!("offset={offset}"), select).await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| QuarantinedMensaje {
+            id: r.get("id"),
+            nombre: r.get("nombre"),
+            mensaje: r.get("mensaje"),
+            spam_score: r.get("spam_score"),
+            akismet_spam: r.get("akismet_spam"),
+            author_ip: r.get("author_ip"),
+            created_at: r.get("created_at"),
+        })
+        .collect())
+}
+
+/// Pasa el mensaje a `mensajes` con el mismo id; `None` si no estaba retenido.
+pub async fn release(conn: &mut PgConnection, id: i32) -> Result<Option<Released>, DbError> {
+    let release = sqlx::query(
+        "WITH moved AS (
+            DELETE FROM mensajes_quarantine WHERE id = $1 RETURNING *
+         ), published AS (
+            INSERT INTO mensajes (id, nombre, mensaje, created_at, author_ip, spam_score, author_email, user_id)
+            SELECT id, nombre, mensaje, created_at, author_ip, spam_score, author_email, user_id FROM moved
+         )
+         SELECT nombre, mensaje, author_ip, spam_score, author_email, user_agent, akismet_spam FROM moved",
+    )
+    .bind(id)
+    .fetch_optional(conn);
+    let row = db::timed("mensajes.quarantine_release", || format!("id={id}"), release).await?;
+
+    Ok(row.map(|r| Released {
+        nombre: r.get("nombre"),
+        mensaje: r.get("mensaje"),
+        author_ip: r.get("author_ip"),
+        spam_score: r.get("spam_score"),
+        author_email: r.get("author_email"),
+        user_agent: r.get("user_agent"),
+        akismet_spam: r.get("akismet_spam"),
+    }))
+}
+
+/// `(nombre, mensaje, spam_score)` del mensaje descartado.
+pub async fn discard(pool: &PgPool, id: i32) -> Result<Option<(String, String, i32)>, DbError> {
+    let delete = sqlx::query_as("DELETE FROM mensajes_quarantine WHERE id = $1 RETURNING nombre, mensaje, spam_score")
+        .bind(id)
+        .fetch_optional(pool);
+    Ok(db::timed("mensajes.quarantine_discard", || format!("id={id}"), delete).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::queries;
+    use crate::test_support::TestDb;
+
+    fn held(mensaje: &str, score: i32) -> NewMensaje<'_> {
+        NewMensaje {
+            nombre: "spam",
+            mensaje,
+            ip: Some("10.0.0.5".parse().unwrap()),
+            score,
+            email: None,
+            user_id: None,
+            edit_token_hash: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn quarantine_queries_round_trip() {
+        let Some(db) = TestDb::new().await else { return };
+        let low = insert(&db.pool, &held("poco", 10), Some("curl"), false).await.unwrap();
+        let high = insert(&db.pool, &held("mucho", 90), None, true).await.unwrap();
+
+        assert_eq!(recent_from_ip(&db.pool, "10.0.0.5".parse().unwrap(), 10).await.unwrap(), 2);
+        sqlx::query(
+            "INSERT INTO mensajes_unconfirmed (token, expires_at, nombre, mensaje, author_ip, spam_score, author_email)
+             VALUES ('t', now() + interval '1 hour', 'x', 'y', '10.0.0.5', 0, 'a@example.com')",
+        )
+        .execute(&db.pool)
+        .await
+        .unwrap();
+        assert_eq!(recent_from_ip(&db.pool, "10.0.0.5".parse().unwrap(), 10).await.unwrap(), 3);
+        assert_eq!(count(&db.pool).await.unwrap(), 2);
+        assert_eq!(page(&db.pool, 10, 0).await.unwrap().iter().map(|m| m.id).collect::<Vec<_>>(), [high, low]);
+
+        let mut conn = db.pool.acquire().await.unwrap();
+        let released = release(&mut conn, high).await.unwrap().unwrap();
+        assert!(released.akismet_spam);
+        assert!(release(&mut conn, high).await.unwrap().is_none());
+        drop(conn);
+        assert!(queries::mensaje_exists(&db.pool, high).await.unwrap());
+
+        assert_eq!(discard(&db.pool, low).await.unwrap(), Some(("spam".to_string(), "poco".to_string(), 10)));
+        assert_eq!(discard(&db.pool, low).await.unwrap(), None);
+
+        db.finish().await;
+    }
+}
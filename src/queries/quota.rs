@@ -0,0 +1,67 @@
+//! `upload_quota`: subidas y bytes por identidad y día.
+
+use chrono::NaiveDate;
+use sqlx::{PgConnection, PgPool};
+
+use crate::db::{self, DbError};
+
+/// Suma una subida de `bytes` si con ella no se pasa de `max_uploads` ni de
+/// `max_bytes`; `false` si no cabe. Comprobar y sumar es una sola sentencia.
+pub async fn reserve(
+    conn: &mut PgConnection,
+    identity: &str,
+    day: NaiveDate,
+    bytes: i64,
+    max_uploads: i32,
+    max_bytes: i64,
+) -> Result<bool, DbError> {
+    let upsert = sqlx::query(
+        "INSERT INTO upload_quota (identity, day, uploads, bytes) VALUES ($1, $2, 1, $3)
+         ON CONFLICT (identity, day) DO UPDATE
+             SET uploads = upload_quota.uploads + 1, bytes = upload_quota.bytes + EXCLUDED.bytes
+             WHERE upload_quota.uploads < $4 AND upload_quota.bytes + EXCLUDED.bytes <= $5",
+    )
+    .bind(identity)
+    .bind(day)
+    .bind(bytes)
+    .bind(max_uploads)
+    .bind(max_bytes)
+    .execute(conn);
+    let result = db::timed("upload_quota.reserve", || format!("identity={identity}"), upsert).await?;
+    Ok(result.rows_affected() == 1)
+}
+
+/// `(subidas, bytes)` gastados ese día.
+pub async fn used(pool: &PgPool, identity: &str, day: NaiveDate) -> Result<Option<(i32, i64)>, DbError> {
+    let select = sqlx::query_as::<_, (i32, i64)>(
+        "SELECT uploads, bytes FROM upload_quota WHERE identity = $1 AND day = $2",
+    )
+    .bind(identity)
+    .bind(day)
+    .fetch_optional(pool);
+    Ok(db::timed("upload_quota.status", || format!("identity={identity}"), select).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestDb;
+
+    #[tokio::test]
+    async fn reserve_stops_at_either_limit() {
+        let Some(db) = TestDb::new().await else { return };
+        let day = NaiveDate::from_ymd_opt(2026, 3, 9).unwrap();
+        let mut conn = db.pool.acquire().await.unwrap();
+
+        assert!(reserve(&mut conn, "ip:10.0.0.1", day, 60, 2, 100).await.unwrap());
+        assert!(!reserve(&mut conn, "ip:10.0.0.1", day, 50, 2, 100).await.unwrap());
+        assert!(reserve(&mut conn, "ip:10.0.0.1", day, 40, 2, 100).await.unwrap());
+        assert!(!reserve(&mut conn, "ip:10.0.0.1", day, 0, 2, 100).await.unwrap());
+        drop(conn);
+
+        assert_eq!(used(&db.pool, "ip:10.0.0.1", day).await.unwrap(), Some((2, 100)));
+        assert_eq!(used(&db.pool, "ip:10.0.0.1", day.succ_opt().unwrap()).await.unwrap(), None);
+
+        db.finish().await;
+    }
+}
@@ -0,0 +1,49 @@
+//! Texto de los mensajes para `reimport-mensajes`, publicados o archivados.
+
+use sqlx::PgPool;
+
+use crate::db::{self, DbError};
+
+/// `(nombre, mensaje)` del mensaje, esté en `mensajes` o en `mensajes_archive`.
+pub async fn current(pool: &PgPool, id: i32) -> Result<Option<(String, String)>, DbError> {
+    let select = sqlx::query_as::<_, (String, String)>(
+        "SELECT nombre, mensaje FROM mensajes WHERE id = $1
+         UNION ALL
+         SELECT nombre, mensaje FROM mensajes_archive WHERE id = $1",
+    )
+    .bind(id)
+    .fetch_optional(pool);
+    Ok(db::timed("mensajes.reimport_current", || format!("id={id}"), select).await?)
+}
+
+/// En las dos tablas: el id solo está en una.
+pub async fn set_text(pool: &PgPool, id: i32, nombre: &str, mensaje: &str) -> Result<(), DbError> {
+    for (name, table) in [("mensajes.reimport", "mensajes"), ("mensajes.reimport_archive", "mensajes_archive")] {
+        let sql = format!("UPDATE {table} SET nombre = $2, mensaje = $3 WHERE id = $1");
+        let update = sqlx::query(&sql).bind(id).bind(nombre).bind(mensaje).execute(pool);
+        db::timed(name, || format!("id={id}"), update).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestDb;
+
+    #[tokio::test]
+    async fn reads_and_fixes_archived_mensajes_too() {
+        let Some(db) = TestDb::new().await else { return };
+        sqlx::query("INSERT INTO mensajes_archive (id, nombre, mensaje, created_at) VALUES (3, 'Eva', 'Precio 100 50', now())")
+            .execute(&db.pool)
+            .await
+            .unwrap();
+
+        assert_eq!(current(&db.pool, 3).await.unwrap(), Some(("Eva".to_string(), "Precio 100 50".to_string())));
+        set_text(&db.pool, 3, "Eva", "Precio 100 > 50").await.unwrap();
+        assert_eq!(current(&db.pool, 3).await.unwrap().unwrap().1, "Precio 100 > 50");
+        assert_eq!(current(&db.pool, 4).await.unwrap(), None);
+
+        db.finish().await;
+    }
+}
@@ -0,0 +1,42 @@
+//! `site_settings`: título e idioma del sitio, una sola fila.
+
+use sqlx::{PgConnection, PgPool};
+
+use crate::db::{self, DbError};
+use crate::setup::Site;
+
+pub async fn load_site(pool: &PgPool) -> Result<Option<Site>, DbError> {
+    let select = sqlx::query_as::<_, (String, String)>("SELECT title, language FROM site_settings").fetch_optional(pool);
+    let row = db::timed("site_settings.load", String::new, select).await?;
+    Ok(row.map(|(title, language)| Site { title, language }))
+}
+
+/// `false` si ya había ajustes guardados.
+pub async fn insert_site(conn: &mut PgConnection, site: &Site) -> Result<bool, DbError> {
+    let insert = sqlx::query("INSERT INTO site_settings (title, language) VALUES ($1, $2) ON CONFLICT DO NOTHING")
+        .bind(&site.title)
+        .bind(&site.language)
+        .execute(conn);
+    Ok(db::timed("site_settings.insert", String::new, insert).await?.rows_affected() > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestDb;
+
+    #[tokio::test]
+    async fn site_settings_are_saved_once() {
+        let Some(db) = TestDb::new().await else { return };
+        assert_eq!(load_site(&db.pool).await.unwrap(), None);
+
+        let site = Site { title: "Motos".to_string(), language: "en".to_string() };
+        let mut conn = db.pool.acquire().await.unwrap();
+        assert!(insert_site(&mut conn, &site).await.unwrap());
+        assert!(!insert_site(&mut conn, &Site::default()).await.unwrap());
+        drop(conn);
+        assert_eq!(load_site(&db.pool).await.unwrap(), Some(site));
+
+        db.finish().await;
+    }
+}
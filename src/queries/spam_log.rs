@@ -0,0 +1,72 @@
+//! `spam_log`: envíos descartados como spam de bots.
+
+use sqlx::{PgPool, Row};
+
+use crate::db::{self, DbError};
+use crate::spam_log::{Attempt, Entry};
+
+pub async fn insert(pool: &PgPool, attempt: &Attempt<'_>) -> Result<(), DbError> {
+    let insert = sqlx::query(
+        "INSERT INTO spam_log (reason, ip, user_agent, nombre, mensaje) VALUES ($1, $2, $3, $4, $5)",
+    )
+    .bind(attempt.reason)
+    .bind(attempt.ip.map(|ip| ip.to_string()))
+    .bind(attempt.user_agent)
+    .bind(attempt.nombre)
+    .bind(attempt.mensaje)
+    .execute(pool);
+    db::timed("spam_log.insert", || attempt.reason.to_string(), insert).await?;
+    Ok(())
+}
+
+pub async fn count(pool: &PgPool) -> Result<i64, DbError> {
+    let count = sqlx::query_scalar::<_, i64>("SELECT count(*) FROM spam_log").fetch_one(pool);
+    Ok(db::timed("spam_log.count", String::new, count).await?)
+}
+
+/// Los más recientes primero.
+pub async fn page(pool: &PgPool, limit: i64, offset: i64) -> Result<Vec<Entry>, DbError> {
+    let select = sqlx::query(
+        "SELECT id, reason, ip, user_agent, nombre, mensaje, created_at FROM spam_log
+         ORDER BY id DESC LIMIT $1 OFFSET $2",
+    )
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool);
+    let rows = db::timed("spam_log.page", || format!("offset={offset}"), select).await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| Entry {
+            id: r.get("id"),
+            reason: r.get("reason"),
+            ip: r.get("ip"),
+            user_agent: r.get("user_agent"),
+            nombre: r.get("nombre"),
+            mensaje: r.get("mensaje"),
+            created_at: r.get("created_at"),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestDb;
+
+    #[tokio::test]
+    async fn spam_log_round_trip() {
+        let Some(db) = TestDb::new().await else { return };
+        for reason in ["honeypot", "too_fast"] {
+            let attempt = Attempt { reason, ip: None, user_agent: Some("bot"), nombre: "x", mensaje: "compra" };
+            insert(&db.pool, &attempt).await.unwrap();
+        }
+
+        assert_eq!(count(&db.pool).await.unwrap(), 2);
+        let entries = page(&db.pool, 1, 0).await.unwrap();
+        assert_eq!((entries.len(), entries[0].reason.as_str()), (1, "too_fast"));
+        assert_eq!(page(&db.pool, 10, 1).await.unwrap()[0].user_agent.as_deref(), Some("bot"));
+
+        db.finish().await;
+    }
+}
@@ -0,0 +1,46 @@
+//! Mensajes de la exportación estática (`static_export`).
+
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use crate::db::{self, DbError};
+
+/// `(id, nombre, mensaje, created_at, verificado)` de todos, publicados y
+/// archivados, los más nuevos primero.
+pub async fn mensajes(pool: &PgPool) -> Result<Vec<(i32, String, String, DateTime<Utc>, bool)>, DbError> {
+    let select = sqlx::query_as(
+        "SELECT id, nombre, mensaje, created_at, email_verified_at IS NOT NULL AS verified FROM mensajes
+         UNION ALL
+         SELECT id, nombre, mensaje, created_at, email_verified_at IS NOT NULL FROM mensajes_archive
+         ORDER BY id DESC",
+    )
+    .fetch_all(pool);
+    Ok(db::timed("mensajes.export", String::new, select).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestDb;
+
+    #[tokio::test]
+    async fn exports_newest_first() {
+        let Some(db) = TestDb::new().await else { return };
+        sqlx::query("INSERT INTO mensajes (nombre, mensaje, email_verified_at) VALUES ('a', 'uno', now()), ('b', 'dos', NULL)")
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO mensajes_archive (id, nombre, mensaje, created_at) VALUES (-1, 'c', 'viejo', now())")
+            .execute(&db.pool)
+            .await
+            .unwrap();
+
+        let rows = mensajes(&db.pool).await.unwrap();
+        assert_eq!(
+            rows.iter().map(|r| (r.2.as_str(), r.4)).collect::<Vec<_>>(),
+            [("dos", false), ("uno", true), ("viejo", false)]
+        );
+
+        db.finish().await;
+    }
+}
@@ -0,0 +1,45 @@
+//! Zonas horarias de Postgres para `timezone`.
+
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use crate::db::{self, DbError};
+
+pub async fn exists(pool: &PgPool, tz: &str) -> Result<bool, DbError> {
+    let exists = sqlx::query_scalar::<_, bool>("SELECT EXISTS (SELECT 1 FROM pg_timezone_names WHERE name = $1)")
+        .bind(tz)
+        .fetch_one(pool);
+    Ok(db::timed("timezone.resolve", || format!("tz={tz}"), exists).await?)
+}
+
+/// Desfase en segundos de cada fecha en `tz`, en el mismo orden.
+pub async fn offsets(pool: &PgPool, tz: &str, times: &[DateTime<Utc>]) -> Result<Vec<i32>, DbError> {
+    let select = sqlx::query_scalar::<_, i32>(
+        "SELECT extract(epoch FROM (t AT TIME ZONE $2) - (t AT TIME ZONE 'UTC'))::int
+         FROM unnest($1::timestamptz[]) WITH ORDINALITY AS u(t, n) ORDER BY n",
+    )
+    .bind(times)
+    .bind(tz)
+    .fetch_all(pool);
+    Ok(db::timed("timezone.localize", || format!("tz={tz} n={}", times.len()), select).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestDb;
+    use chrono::TimeZone;
+
+    #[tokio::test]
+    async fn offsets_follow_daylight_saving() {
+        let Some(db) = TestDb::new().await else { return };
+        assert!(exists(&db.pool, "Europe/Madrid").await.unwrap());
+        assert!(!exists(&db.pool, "Marte/Olympus").await.unwrap());
+
+        let winter = Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap();
+        let summer = Utc.with_ymd_and_hms(2024, 7, 15, 12, 0, 0).unwrap();
+        assert_eq!(offsets(&db.pool, "Europe/Madrid", &[summer, winter]).await.unwrap(), [7200, 3600]);
+
+        db.finish().await;
+    }
+}
@@ -0,0 +1,60 @@
+//! Purga de la papelera de imágenes (`trash`).
+
+use sqlx::PgPool;
+use std::time::Duration;
+
+use crate::db::{self, DbError};
+
+/// Borra las que llevan más de `retention` en la papelera; devuelve sus ficheros.
+pub async fn purge(pool: &PgPool, retention: Duration) -> Result<Vec<String>, DbError> {
+    let delete = sqlx::query_scalar::<_, String>(
+        "DELETE FROM images WHERE deleted_at < now() - make_interval(secs => $1) RETURNING filename",
+    )
+    .bind(retention.as_secs_f64())
+    .fetch_all(pool);
+    Ok(db::timed("images.purge", String::new, delete).await?)
+}
+
+/// Cuántas borraría `purge` y los ids de las `limit` primeras.
+pub async fn expired(pool: &PgPool, retention: Duration, limit: i64) -> Result<(i64, Vec<i32>), DbError> {
+    let select = sqlx::query_as::<_, (i32, i64)>(
+        "SELECT id, count(*) OVER () FROM images
+         WHERE deleted_at < now() - make_interval(secs => $1)
+         ORDER BY id LIMIT $2",
+    )
+    .bind(retention.as_secs_f64())
+    .bind(limit)
+    .fetch_all(pool);
+
+    let rows = db::timed("images.purge_preview", String::new, select).await?;
+    let count = rows.first().map_or(0, |(_, count)| *count);
+    Ok((count, rows.into_iter().map(|(id, _)| id).collect()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestDb;
+
+    #[tokio::test]
+    async fn only_expired_images_are_purged() {
+        let Some(db) = TestDb::new().await else { return };
+        sqlx::query(
+            "INSERT INTO images (filename, size_bytes, original_name, deleted_at) VALUES
+             ('vieja.png', 1, 'a', now() - interval '2 days'),
+             ('reciente.png', 1, 'b', now()),
+             ('viva.png', 1, 'c', NULL)",
+        )
+        .execute(&db.pool)
+        .await
+        .unwrap();
+        let day = Duration::from_secs(24 * 60 * 60);
+
+        let (count, ids) = expired(&db.pool, day, 10).await.unwrap();
+        assert_eq!((count, ids.len()), (1, 1));
+        assert_eq!(purge(&db.pool, day).await.unwrap(), ["vieja.png"]);
+        assert_eq!(expired(&db.pool, day, 10).await.unwrap(), (0, vec![]));
+
+        db.finish().await;
+    }
+}
@@ -0,0 +1,111 @@
+//! `users`: cuentas del panel y de autores (ver `users::AUTHOR_ROLE`).
+
+use sqlx::{PgExecutor, PgPool, Row};
+
+use crate::db::{self, DbError};
+use crate::users::{User, AUTHOR_ROLE};
+
+/// `(id, password_hash)` del usuario activo; `author` elige entre autores y panel.
+pub async fn login(pool: &PgPool, username: &str, author: bool) -> Result<Option<(i32, String)>, DbError> {
+    let select = sqlx::query_as::<_, (i32, String)>(
+        "SELECT id, password_hash FROM users
+         WHERE lower(username) = lower($1) AND disabled_at IS NULL AND (role = $2) = $3",
+    )
+    .bind(username.trim())
+    .bind(AUTHOR_ROLE)
+    .bind(author)
+    .fetch_optional(pool);
+    Ok(db::timed("users.login", || format!("username={username}"), select).await?)
+}
+
+pub async fn exists(pool: &PgPool) -> Result<bool, DbError> {
+    let exists = sqlx::query_scalar::<_, bool>("SELECT EXISTS (SELECT 1 FROM users)").fetch_one(pool);
+    Ok(db::timed("users.exists", String::new, exists).await?)
+}
+
+pub async fn list(pool: &PgPool) -> Result<Vec<User>, DbError> {
+    let select = sqlx::query("SELECT id, username, role, created_at, disabled_at FROM users ORDER BY id")
+        .fetch_all(pool);
+    let rows = db::timed("users.list", String::new, select).await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| User {
+            id: r.get("id"),
+            username: r.get("username"),
+            role: r.get("role"),
+            created_at: r.get("created_at"),
+            disabled_at: r.get("disabled_at"),
+        })
+        .collect())
+}
+
+/// `None` si el nombre ya está cogido. `role` es `Role::as_str` o `AUTHOR_ROLE`.
+pub async fn insert<'e>(
+    db: impl PgExecutor<'e>,
+    username: &str,
+    hash: &str,
+    role: &str,
+) -> Result<Option<i32>, DbError> {
+    let insert = sqlx::query_scalar::<_, i32>(
+        "INSERT INTO users (username, password_hash, role) VALUES ($1, $2, $3)
+         ON CONFLICT DO NOTHING RETURNING id",
+    )
+    .bind(username)
+    .bind(hash)
+    .bind(role)
+    .fetch_optional(db);
+    Ok(db::timed("users.insert", || format!("username={username}"), insert).await?)
+}
+
+/// Rol anterior; `None` si el usuario no existe.
+pub async fn set_role(pool: &PgPool, id: i32, role: &str) -> Result<Option<String>, DbError> {
+    let update = sqlx::query_scalar::<_, String>(
+        "UPDATE users u SET role = $1 FROM (SELECT role FROM users WHERE id = $2) old
+         WHERE u.id = $2 RETURNING old.role",
+    )
+    .bind(role)
+    .bind(id)
+    .fetch_optional(pool);
+    Ok(db::timed("users.set_role", || format!("id={id}"), update).await?)
+}
+
+/// `false` si no existe o ya estaba desactivado.
+pub async fn disable(pool: &PgPool, id: i32) -> Result<bool, DbError> {
+    let update = sqlx::query("UPDATE users SET disabled_at = now() WHERE id = $1 AND disabled_at IS NULL")
+        .bind(id)
+        .execute(pool);
+    Ok(db::timed("users.disable", || format!("id={id}"), update).await?.rows_affected() > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestDb;
+
+    #[tokio::test]
+    async fn user_queries_round_trip() {
+        let Some(db) = TestDb::new().await else { return };
+        assert!(!exists(&db.pool).await.unwrap());
+        let ana = insert(&db.pool, "Ana", "h-ana", "viewer").await.unwrap().unwrap();
+        let autor = insert(&db.pool, "autor", "h-autor", AUTHOR_ROLE).await.unwrap().unwrap();
+        assert_eq!(insert(&db.pool, "Ana", "otro", "admin").await.unwrap(), None);
+        assert!(exists(&db.pool).await.unwrap());
+
+        // Los autores no entran al panel ni el panel por el login de autores.
+        assert_eq!(login(&db.pool, " ana ", false).await.unwrap(), Some((ana, "h-ana".to_string())));
+        assert_eq!(login(&db.pool, "autor", false).await.unwrap(), None);
+        assert_eq!(login(&db.pool, "autor", true).await.unwrap(), Some((autor, "h-autor".to_string())));
+
+        assert_eq!(set_role(&db.pool, ana, "admin").await.unwrap().as_deref(), Some("viewer"));
+        assert_eq!(set_role(&db.pool, 0, "admin").await.unwrap(), None);
+        assert!(disable(&db.pool, ana).await.unwrap());
+        assert!(!disable(&db.pool, ana).await.unwrap());
+        assert_eq!(login(&db.pool, "ana", false).await.unwrap(), None);
+
+        let users = list(&db.pool).await.unwrap();
+        assert_eq!((users[0].role.as_str(), users[0].disabled_at.is_some()), ("admin", true));
+
+        db.finish().await;
+    }
+}
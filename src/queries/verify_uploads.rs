@@ -0,0 +1,47 @@
+//! Registros de `images` para `verify_uploads`.
+
+use sqlx::PgPool;
+
+use crate::db::{self, DbError};
+
+/// `(id, filename, aprobada, en la papelera)` de todas.
+pub async fn images(pool: &PgPool) -> Result<Vec<(i32, String, bool, bool)>, DbError> {
+    let select = sqlx::query_as(
+        "SELECT id, filename, approved_at IS NOT NULL AS approved, deleted_at IS NOT NULL AS deleted FROM images",
+    )
+    .fetch_all(pool);
+    Ok(db::timed("images.verify", String::new, select).await?)
+}
+
+pub async fn delete(pool: &PgPool, ids: &[i32]) -> Result<(), DbError> {
+    let delete = sqlx::query("DELETE FROM images WHERE id = ANY($1)").bind(ids).execute(pool);
+    db::timed("images.verify_fix", || format!("rows={}", ids.len()), delete).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestDb;
+
+    #[tokio::test]
+    async fn lists_and_deletes_images() {
+        let Some(db) = TestDb::new().await else { return };
+        sqlx::query(
+            "INSERT INTO images (filename, size_bytes, original_name, approved_at, deleted_at) VALUES
+             ('a.png', 1, 'a', now(), NULL), ('b.png', 1, 'b', NULL, now())",
+        )
+        .execute(&db.pool)
+        .await
+        .unwrap();
+
+        let mut rows = images(&db.pool).await.unwrap();
+        rows.sort_by(|a, b| a.1.cmp(&b.1));
+        assert_eq!(rows.iter().map(|r| (r.1.as_str(), r.2, r.3)).collect::<Vec<_>>(), [("a.png", true, false), ("b.png", false, true)]);
+
+        delete(&db.pool, &[rows[0].0]).await.unwrap();
+        assert_eq!(images(&db.pool).await.unwrap().len(), 1);
+
+        db.finish().await;
+    }
+}
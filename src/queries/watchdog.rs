@@ -0,0 +1,24 @@
+//! Sonda de la base de datos para `watchdog`.
+
+use sqlx::PgPool;
+
+use crate::db::{self, DbError};
+
+pub async fn ping(pool: &PgPool) -> Result<(), DbError> {
+    let probe = sqlx::query("SELECT 1").execute(pool);
+    db::timed("db.ping", String::new, probe).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestDb;
+
+    #[tokio::test]
+    async fn ping_answers() {
+        let Some(db) = TestDb::new().await else { return };
+        ping(&db.pool).await.unwrap();
+        db.finish().await;
+    }
+}
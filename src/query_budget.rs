@@ -0,0 +1,75 @@
+//! Consultas por petición. `db::timed` apunta cada consulta en el contador de
+//! la petición en curso; al terminar, si se pasó de `DB_QUERY_BUDGET` se avisa
+//! en el log y en `db_query_budget_exceeded_total`, para pillar los N+1 antes
+//! de que lleguen a producción. Con `DEBUG_QUERY_COUNT=true` el total va además
+//! en la cabecera `X-Db-Queries`.
+
+use axum::{
+    extract::{Request, State},
+    http::HeaderValue,
+    middleware::Next,
+    response::Response,
+};
+use std::{cell::Cell, sync::Arc};
+
+use crate::metrics::{self, Metrics};
+
+pub const HEADER: &str = "x-db-queries";
+
+tokio::task_local! {
+    static QUERIES: Cell<u32>;
+}
+
+/// Suma una consulta a la petición en curso; fuera de una petición no hace nada.
+pub fn record() {
+    let _ = QUERIES.try_with(|count| count.set(count.get() + 1));
+}
+
+#[derive(Clone)]
+pub struct Budget {
+    pub max: u32,
+    pub header: bool,
+    pub metrics: Arc<Metrics>,
+}
+
+/* ---------- MIDDLEWARE ---------- */
+
+pub async fn count_queries(State(budget): State<Budget>, req: Request, next: Next) -> Response {
+    let method = req.method().clone();
+    let route = metrics::route_label(&req);
+
+    let (mut res, queries) = QUERIES
+        .scope(Cell::new(0), async {
+            let res = next.run(req).await;
+            (res, QUERIES.with(Cell::get))
+        })
+        .await;
+
+    if queries > budget.max {
+        tracing::warn!(%method, route, queries, budget = budget.max, "demasiadas consultas en una petición");
+        budget.metrics.incr_query_budget_exceeded(route);
+    }
+
+    if budget.header {
+        res.headers_mut().insert(HEADER, HeaderValue::from(queries));
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn counts_only_inside_the_request() {
+        record();
+        let counted = QUERIES
+            .scope(Cell::new(0), async {
+                record();
+                record();
+                QUERIES.with(Cell::get)
+            })
+            .await;
+        assert_eq!(counted, 2);
+    }
+}
@@ -0,0 +1,145 @@
+//! Cuota diaria de subidas por identidad: la IP del visitante mientras no haya cuentas.
+//! Los contadores viven en `upload_quota` y el día se cuenta en UTC.
+
+use axum::{
+    extract::State,
+    http::{header, StatusCode},
+    response::{Html, IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, NaiveDate, TimeDelta, Utc};
+use serde::Serialize;
+use sqlx::PgConnection;
+
+use crate::config::UploadsConfig;
+use crate::db::DbError;
+use crate::policy::{Principal, Role};
+use crate::queries;
+use crate::state::SharedState;
+
+/// Identidad a la que se carga la subida; `None` si no tiene cuota (administración).
+pub fn identity(principal: &Principal) -> Option<String> {
+    match principal {
+        Principal::Admin => None,
+        Principal::User { role: Role::Admin, .. } => None,
+        Principal::User { id, .. } => Some(format!("user:{id}")),
+        Principal::ApiKey { id, .. } => Some(format!("key:{id}")),
+        Principal::Author { id } => Some(format!("user:{id}")),
+        Principal::Visitor { ip, .. } => Some(format!("ip:{ip}")),
+        Principal::Anonymous => Some("anonymous".to_string()),
+    }
+}
+
+fn today(now: DateTime<Utc>) -> NaiveDate {
+    now.date_naive()
+}
+
+/// Próxima medianoche UTC, cuando se renuevan los contadores.
+fn resets_at(now: DateTime<Utc>) -> DateTime<Utc> {
+    (today(now) + TimeDelta::days(1)).and_hms_opt(0, 0, 0).unwrap().and_utc()
+}
+
+/// Cuenta una subida de `bytes` si cabe en la cuota; `false` si no cabe. La
+/// comprobación y el incremento son una sola sentencia, así que subidas
+/// simultáneas no la rebasan. Se ejecuta dentro de la transacción de la subida
+/// (`UnitOfWork`): si la subida falla, el contador vuelve atrás con ella.
+pub async fn reserve(
+    conn: &mut PgConnection,
+    config: &UploadsConfig,
+    identity: &str,
+    bytes: u64,
+) -> Result<bool, DbError> {
+    if config.quota_uploads == 0 || bytes > config.quota_bytes {
+        return Ok(false);
+    }
+
+    let (uploads, bytes_max) = (config.quota_uploads as i32, config.quota_bytes as i64);
+    queries::quota::reserve(conn, identity, today(Utc::now()), bytes as i64, uploads, bytes_max).await
+}
+
+/// Rechazo por cuota agotada.
+pub struct Exceeded {
+    pub resets_at: DateTime<Utc>,
+}
+
+impl Exceeded {
+    pub fn now() -> Self {
+        Exceeded {
+            resets_at: resets_at(Utc::now()),
+        }
+    }
+
+    pub fn message(&self) -> String {
+        format!(
+            "❌ Cuota diaria de subidas agotada; se renueva el {}",
+            self.resets_at.format("%Y-%m-%d a las %H:%M UTC")
+        )
+    }
+}
+
+impl IntoResponse for Exceeded {
+    fn into_response(self) -> Response {
+        let wait = (self.resets_at - Utc::now()).num_seconds().max(1);
+        (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(header::RETRY_AFTER, wait.to_string())],
+            Html(self.message()),
+        )
+            .into_response()
+    }
+}
+
+/* ---------- GET /me/quota ---------- */
+
+#[derive(Serialize)]
+pub struct QuotaStatus {
+    /// `false` para administración, que no tiene cuota.
+    limited: bool,
+    uploads_used: i64,
+    uploads_limit: u32,
+    bytes_used: i64,
+    bytes_limit: u64,
+    resets_at: DateTime<Utc>,
+}
+
+pub async fn me_quota(
+    State(app): State<SharedState>,
+    principal: Principal,
+) -> Result<Json<QuotaStatus>, DbError> {
+    let now = Utc::now();
+    let mut status = QuotaStatus {
+        limited: false,
+        uploads_used: 0,
+        uploads_limit: app.config.uploads.quota_uploads,
+        bytes_used: 0,
+        bytes_limit: app.config.uploads.quota_bytes,
+        resets_at: resets_at(now),
+    };
+
+    let Some(identity) = identity(&principal) else {
+        return Ok(Json(status));
+    };
+
+    let used = queries::quota::used(&app.db, &identity, today(now)).await?;
+
+    status.limited = true;
+    if let Some((uploads, bytes)) = used {
+        status.uploads_used = uploads.into();
+        status.bytes_used = bytes;
+    }
+    Ok(Json(status))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quota_resets_at_next_utc_midnight() {
+        let now = "2026-03-09T23:59:59Z".parse::<DateTime<Utc>>().unwrap();
+        assert_eq!(resets_at(now), "2026-03-10T00:00:00Z".parse::<DateTime<Utc>>().unwrap());
+
+        let midnight = "2026-03-10T00:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        assert_eq!(today(midnight), NaiveDate::from_ymd_opt(2026, 3, 10).unwrap());
+    }
+}
@@ -0,0 +1,118 @@
+use axum::{
+    extract::{Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::client_ip::client_ip;
+
+/// Por encima de este número de IPs se purgan los buckets que ya están llenos
+/// y, si no basta, los más quietos hasta quedar en la mitad.
+const MAX_TRACKED: usize = 10_000;
+
+/// Token bucket por IP: `burst` peticiones seguidas y `per_minute` de recarga.
+pub struct RateLimiter {
+    burst: f64,
+    refill_per_sec: f64,
+    buckets: Mutex<HashMap<IpAddr, Bucket>>,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(burst: u32, per_minute: u32) -> Arc<Self> {
+        Arc::new(RateLimiter {
+            burst: burst as f64,
+            refill_per_sec: per_minute as f64 / 60.0,
+            buckets: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Consume un token; si no quedan devuelve cuánto falta para el siguiente.
+    pub fn check(&self, ip: IpAddr) -> Result<(), Duration> {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+
+        if buckets.len() > MAX_TRACKED {
+            let (burst, rate) = (self.burst, self.refill_per_sec);
+            buckets.retain(|_, b| {
+                b.tokens + now.duration_since(b.updated).as_secs_f64() * rate < burst
+            });
+            // Un bucket olvidado vuelve lleno, pero así la purga no se repite en
+            // cada petición cuando hay muchas IPs a medio gastar.
+            if buckets.len() > MAX_TRACKED / 2 {
+                let mut updated: Vec<Instant> = buckets.values().map(|b| b.updated).collect();
+                let evict = buckets.len() - MAX_TRACKED / 2;
+                let oldest_kept = *updated.select_nth_unstable(evict).1;
+                buckets.retain(|_, b| b.updated >= oldest_kept);
+            }
+        }
+
+        let bucket = buckets.entry(ip).or_insert(Bucket {
+            tokens: self.burst,
+            updated: now,
+        });
+
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.refill_per_sec).min(self.burst);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            let wait = (1.0 - bucket.tokens) / self.refill_per_sec;
+            Err(Duration::from_secs_f64(wait))
+        }
+    }
+}
+
+/* ---------- MIDDLEWARE ---------- */
+
+pub async fn limit(State(limiter): State<Arc<RateLimiter>>, req: Request, next: Next) -> Response {
+    let Some(ip) = client_ip(&req) else {
+        return next.run(req).await;
+    };
+
+    match limiter.check(ip) {
+        Ok(()) => next.run(req).await,
+        Err(wait) => (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(header::RETRY_AFTER, (wait.as_secs_f64().ceil() as u64).max(1).to_string())],
+            "❌ Demasiadas peticiones, inténtalo más tarde",
+        )
+            .into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partly_used_buckets_do_not_pin_the_map_at_the_cap() {
+        let limiter = RateLimiter::new(5, 1);
+        for n in 0..=MAX_TRACKED as u32 {
+            limiter.check(IpAddr::from(n.to_be_bytes())).unwrap();
+        }
+        limiter.check("10.255.255.255".parse().unwrap()).unwrap();
+        assert!(limiter.buckets.lock().unwrap().len() <= MAX_TRACKED / 2 + 1);
+
+        // El ritmo sigue funcionando: a la sexta seguida, a esperar.
+        let ip = "192.0.2.1".parse().unwrap();
+        for _ in 0..5 {
+            limiter.check(ip).unwrap();
+        }
+        assert!(limiter.check(ip).unwrap_err() > Duration::from_secs(50));
+    }
+}
@@ -0,0 +1,94 @@
+//! Límite suave de lectura para listados públicos. Cada respuesta buena se
+//! guarda por consulta; a quien pasa su límite se le sirve esa copia, aunque
+//! esté algo desfasada, con una cabecera `Warning`, en vez de un 429. Así una
+//! ráfaga de scraping no llega a la base de datos y la página sigue legible.
+//! Sin copia reciente la consulta va a la base de datos como siempre.
+
+use axum::{
+    body::Bytes,
+    http::{header, HeaderValue},
+    response::{IntoResponse, Response},
+};
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::config::ReadsConfig;
+use crate::rate_limit::RateLimiter;
+
+/// RFC 7234: la respuesta puede no estar al día.
+const STALE_WARNING: &str = r#"110 - "Response is Stale""#;
+
+pub struct ReadCache {
+    limiter: Arc<RateLimiter>,
+    ttl: Duration,
+    max_entries: usize,
+    entries: Mutex<HashMap<String, Cached>>,
+}
+
+struct Cached {
+    body: Bytes,
+    stored: Instant,
+}
+
+impl ReadCache {
+    pub fn new(config: &ReadsConfig) -> Arc<Self> {
+        Arc::new(ReadCache {
+            limiter: RateLimiter::new(config.rate_burst, config.rate_per_minute),
+            ttl: config.cache_ttl,
+            max_entries: config.cache_entries,
+            entries: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Copia en caché para `key` si `ip` ha pasado su límite y hay una reciente.
+    /// Sin IP conocida no se limita.
+    pub fn throttled(&self, ip: Option<IpAddr>, key: &str) -> Option<Response> {
+        self.limiter.check(ip?).err()?;
+
+        let entries = self.entries.lock().unwrap();
+        let cached = entries.get(key).filter(|c| c.stored.elapsed() <= self.ttl)?;
+        let age = cached.stored.elapsed().as_secs().to_string();
+
+        let mut res = json(cached.body.clone());
+        res.headers_mut().insert(header::WARNING, HeaderValue::from_static(STALE_WARNING));
+        if let Ok(age) = HeaderValue::from_str(&age) {
+            res.headers_mut().insert(header::AGE, age);
+        }
+        Some(res)
+    }
+
+    /// Guarda la respuesta y la devuelve. Si se llena se descartan las caducadas
+    /// y, si no basta, la más antigua.
+    pub fn store(&self, key: String, body: Vec<u8>) -> Response {
+        let body = Bytes::from(body);
+        let mut entries = self.entries.lock().unwrap();
+
+        if entries.len() >= self.max_entries && !entries.contains_key(&key) {
+            let ttl = self.ttl;
+            entries.retain(|_, c| c.stored.elapsed() <= ttl);
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, c)| c.stored)
+                .map(|(k, _)| k.clone());
+            if let Some(oldest) = oldest.filter(|_| entries.len() >= self.max_entries) {
+                entries.remove(&oldest);
+            }
+        }
+
+        entries.insert(key, Cached { body: body.clone(), stored: Instant::now() });
+        json(body)
+    }
+
+    /// Los datos cambiaron: ninguna copia vale ya.
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+}
+
+fn json(body: Bytes) -> Response {
+    ([(header::CONTENT_TYPE, "application/json")], body).into_response()
+}
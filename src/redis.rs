@@ -0,0 +1,215 @@
+//! Cliente Redis mínimo (RESP2 sobre TCP) para el bus de eventos: `AUTH`,
+//! `PUBLISH` y `SUBSCRIBE`, nada más. `redis://[usuario:clave@]host[:puerto]`;
+//! sin TLS.
+
+use reqwest::Url;
+use std::{future::Future, io, pin::Pin};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufStream},
+    net::TcpStream,
+};
+
+const DEFAULT_PORT: u16 = 6379;
+
+/// Topes de lo que se acepta del servidor; los eventos son mucho más pequeños.
+const MAX_BULK: i64 = 16 * 1024 * 1024;
+const MAX_ITEMS: i64 = 1024;
+
+#[derive(Debug, PartialEq)]
+pub enum Value {
+    Simple(String),
+    Error(String),
+    Int(i64),
+    Bulk(Option<Vec<u8>>),
+    Array(Option<Vec<Value>>),
+}
+
+pub struct Connection {
+    stream: BufStream<TcpStream>,
+}
+
+fn invalid(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+/// Longitud de un bulk o un array: `None` para el -1 (nulo), error si es
+/// negativa o pasa de `max`.
+fn length(n: i64, max: i64) -> io::Result<Option<usize>> {
+    match n {
+        -1 => Ok(None),
+        n if (0..=max).contains(&n) => Ok(Some(n as usize)),
+        n => Err(invalid(format!("longitud inválida: {n}"))),
+    }
+}
+
+/// Usuario y clave vienen codificados en la URL (`%40` por `@`...).
+fn percent_decode(s: &str) -> Vec<u8> {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|h| std::str::from_utf8(h).ok());
+        match (bytes[i], hex.and_then(|h| u8::from_str_radix(h, 16).ok())) {
+            (b'%', Some(byte)) => {
+                out.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+impl Connection {
+    pub async fn open(url: &str) -> io::Result<Self> {
+        let url = Url::parse(url).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        if url.scheme() != "redis" {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "solo se admite redis://"));
+        }
+        let host = url.host_str().unwrap_or("localhost");
+        let stream = TcpStream::connect((host, url.port().unwrap_or(DEFAULT_PORT))).await?;
+        let mut conn = Connection { stream: BufStream::new(stream) };
+
+        if let Some(password) = url.password() {
+            let password = percent_decode(password);
+            let reply = match url.username() {
+                "" => conn.command(&[b"AUTH", &password]).await?,
+                user => conn.command(&[b"AUTH", &percent_decode(user), &password]).await?,
+            };
+            if let Value::Error(e) = reply {
+                return Err(io::Error::new(io::ErrorKind::PermissionDenied, e));
+            }
+        }
+        Ok(conn)
+    }
+
+    /// Envía un comando y espera su respuesta.
+    pub async fn command(&mut self, args: &[&[u8]]) -> io::Result<Value> {
+        self.send(args).await?;
+        self.read().await
+    }
+
+    pub async fn send(&mut self, args: &[&[u8]]) -> io::Result<()> {
+        let mut out = format!("*{}\r\n", args.len()).into_bytes();
+        for arg in args {
+            out.extend_from_slice(format!("${}\r\n", arg.len()).as_bytes());
+            out.extend_from_slice(arg);
+            out.extend_from_slice(b"\r\n");
+        }
+        self.stream.write_all(&out).await?;
+        self.stream.flush().await
+    }
+
+    /// Siguiente valor del servidor: la respuesta a un comando o, suscrito, un mensaje.
+    pub fn read(&mut self) -> Pin<Box<dyn Future<Output = io::Result<Value>> + Send + '_>> {
+        Box::pin(async move {
+            let line = self.line().await?;
+            let (kind, rest) = line.split_at(1);
+            let number = || rest.parse::<i64>().map_err(|_| invalid(format!("respuesta inválida: {line}")));
+
+            match kind {
+                "+" => Ok(Value::Simple(rest.to_string())),
+                "-" => Ok(Value::Error(rest.to_string())),
+                ":" => Ok(Value::Int(number()?)),
+                "$" => match length(number()?, MAX_BULK)? {
+                    None => Ok(Value::Bulk(None)),
+                    Some(len) => {
+                        let mut data = vec![0; len + 2];
+                        self.stream.read_exact(&mut data).await?;
+                        data.truncate(len);
+                        Ok(Value::Bulk(Some(data)))
+                    }
+                },
+                "*" => match length(number()?, MAX_ITEMS)? {
+                    None => Ok(Value::Array(None)),
+                    Some(len) => {
+                        let mut items = Vec::with_capacity(len);
+                        for _ in 0..len {
+                            items.push(self.read().await?);
+                        }
+                        Ok(Value::Array(Some(items)))
+                    }
+                },
+                _ => Err(invalid(format!("respuesta inválida: {line}"))),
+            }
+        })
+    }
+
+    async fn line(&mut self) -> io::Result<String> {
+        let mut line = String::new();
+        if self.stream.read_line(&mut line).await? == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Redis cerró la conexión"));
+        }
+        let line = line.trim_end_matches(['\r', '\n']);
+        if line.is_empty() {
+            return Err(invalid("línea vacía"));
+        }
+        Ok(line.to_string())
+    }
+}
+
+/// Carga de un `message` recibido en una suscripción; `None` para el resto
+/// (confirmaciones de `subscribe` y demás).
+pub fn message_payload(value: Value) -> Option<Vec<u8>> {
+    let Value::Array(Some(items)) = value else { return None };
+    let mut items = items.into_iter();
+    match (items.next(), items.next(), items.next()) {
+        (Some(Value::Bulk(Some(kind))), Some(_channel), Some(Value::Bulk(Some(payload)))) if kind == b"message" => {
+            Some(payload)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn speaks_resp_with_auth_and_subscriptions() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut received = Vec::new();
+            let mut buf = [0; 256];
+            // AUTH y SUBSCRIBE, cada uno con su respuesta.
+            for reply in [&b"+OK\r\n"[..], b"*3\r\n$9\r\nsubscribe\r\n$1\r\nc\r\n:1\r\n"] {
+                let n = socket.read(&mut buf).await.unwrap();
+                received.extend_from_slice(&buf[..n]);
+                socket.write_all(reply).await.unwrap();
+            }
+            socket.write_all(b"*3\r\n$7\r\nmessage\r\n$1\r\nc\r\n$5\r\nhola!\r\n").await.unwrap();
+            String::from_utf8(received).unwrap()
+        });
+
+        let mut conn = Connection::open(&format!("redis://:secreto@127.0.0.1:{port}")).await.unwrap();
+        let confirmation = conn.command(&[b"SUBSCRIBE", b"c"]).await.unwrap();
+        assert_eq!(message_payload(confirmation), None);
+        assert_eq!(message_payload(conn.read().await.unwrap()), Some(b"hola!".to_vec()));
+
+        let received = server.await.unwrap();
+        assert!(received.starts_with("*2\r\n$4\r\nAUTH\r\n$7\r\nsecreto\r\n"), "{received:?}");
+        assert!(received.ends_with("*2\r\n$9\r\nSUBSCRIBE\r\n$1\r\nc\r\n"), "{received:?}");
+    }
+
+    #[test]
+    fn credentials_are_percent_decoded() {
+        assert_eq!(percent_decode("p%40ss%3Aword%2F"), b"p@ss:word/");
+        assert_eq!(percent_decode("100%"), b"100%");
+        assert_eq!(percent_decode("%zz"), b"%zz");
+    }
+
+    #[test]
+    fn lengths_must_be_null_or_in_range() {
+        assert_eq!(length(-1, MAX_BULK).unwrap(), None);
+        assert_eq!(length(5, MAX_BULK).unwrap(), Some(5));
+        assert!(length(-2, MAX_BULK).is_err());
+        assert!(length(MAX_BULK + 1, MAX_BULK).is_err());
+    }
+}
@@ -0,0 +1,182 @@
+//! `hola_axum reimport-mensajes <fichero.jsonl> [--apply]`: devuelve a los
+//! mensajes el texto que les quitó `sanitize_text` antes de guardarse tal cual
+//! (`<`, `>`, comillas, `;`, `--`, `script`...). El fichero trae una línea
+//! JSON por mensaje, `{"id": 12, "mensaje": "...", "nombre": "..."}` (basta
+//! uno de los dos campos), sacada de los originales que se conserven.
+//!
+//! Sin `--apply` solo enseña lo que cambiaría. Con él, cada mensaje se
+//! actualiza (también los archivados) y el cambio queda en `audit_log` como
+//! `mensaje.reimport`, con el texto de antes y el de después, a nombre de
+//! `maintenance`. Los textos se validan como en `/enviar`. El código de salida
+//! es 0 si no queda nada por cambiar, 1 si hay cambios sin aplicar y 2 si el
+//! fichero o la base de datos fallan.
+
+use serde::Deserialize;
+use sqlx::PgPool;
+use std::path::Path;
+
+use crate::audit_log::{self, Change};
+use crate::db::DbError;
+use crate::queries;
+use crate::validation::{valid_mensaje, valid_nombre};
+
+pub const ACTOR: &str = "maintenance";
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Fix {
+    pub id: i32,
+    pub nombre: Option<String>,
+    pub mensaje: Option<String>,
+}
+
+/// Todas las líneas o ninguna: un fichero a medias no se aplica.
+pub fn parse(contents: &str) -> Result<Vec<Fix>, String> {
+    let mut fixes = Vec::new();
+    for (n, line) in contents.lines().enumerate().filter(|(_, l)| !l.trim().is_empty()) {
+        let line_no = n + 1;
+        let fix: Fix = serde_json::from_str(line).map_err(|e| format!("línea {line_no}: {e}"))?;
+        if fix.nombre.is_none() && fix.mensaje.is_none() {
+            return Err(format!("línea {line_no}: falta nombre o mensaje"));
+        }
+        if fix.nombre.as_deref().is_some_and(|n| !valid_nombre(n)) {
+            return Err(format!("línea {line_no}: nombre inválido"));
+        }
+        if fix.mensaje.as_deref().is_some_and(|m| !valid_mensaje(m)) {
+            return Err(format!("línea {line_no}: mensaje inválido"));
+        }
+        fixes.push(fix);
+    }
+    Ok(fixes)
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Outcome {
+    Missing,
+    Unchanged,
+    /// `(nombre, mensaje)` de antes y de después.
+    Changed { old: (String, String), new: (String, String) },
+}
+
+/// Qué cambiaría `fix`, sin tocar nada.
+pub async fn plan(pool: &PgPool, fix: &Fix) -> Result<Outcome, DbError> {
+    let Some(old) = queries::reimport::current(pool, fix.id).await? else { return Ok(Outcome::Missing) };
+    let new = (
+        fix.nombre.clone().unwrap_or_else(|| old.0.clone()),
+        fix.mensaje.clone().unwrap_or_else(|| old.1.clone()),
+    );
+    Ok(if new == old { Outcome::Unchanged } else { Outcome::Changed { old, new } })
+}
+
+/// Esté publicado o archivado.
+async fn apply(pool: &PgPool, id: i32, old: &(String, String), new: &(String, String)) -> Result<(), DbError> {
+    queries::reimport::set_text(pool, id, &new.0, &new.1).await?;
+
+    let change = Change::new("mensaje.reimport", format!("mensaje:{id}"))
+        .old(serde_json::json!({ "nombre": old.0, "mensaje": old.1 }))
+        .new_value(serde_json::json!({ "nombre": new.0, "mensaje": new.1 }));
+    audit_log::record_change_as(pool, ACTOR, change).await;
+    Ok(())
+}
+
+pub async fn run(pool: &PgPool, file: &Path, apply_changes: bool) -> i32 {
+    let fixes = match std::fs::read_to_string(file).map_err(|e| e.to_string()).and_then(|c| parse(&c)) {
+        Ok(fixes) => fixes,
+        Err(err) => {
+            eprintln!("{}: {err}", file.display());
+            return 2;
+        }
+    };
+
+    let (mut pending, mut missing) = (0, 0);
+    for fix in &fixes {
+        let outcome = match plan(pool, fix).await {
+            Ok(outcome) => outcome,
+            Err(err) => {
+                eprintln!("no se pudo leer el mensaje #{}: {err:?}", fix.id);
+                return 2;
+            }
+        };
+        match outcome {
+            Outcome::Missing => {
+                println!("no existe: #{}", fix.id);
+                missing += 1;
+            }
+            Outcome::Unchanged => {}
+            Outcome::Changed { old, new } => {
+                println!("#{}: {:?} → {:?}", fix.id, old.1, new.1);
+                if !apply_changes {
+                    pending += 1;
+                } else if let Err(err) = apply(pool, fix.id, &old, &new).await {
+                    eprintln!("no se pudo corregir el mensaje #{}: {err:?}", fix.id);
+                    return 2;
+                }
+            }
+        }
+    }
+
+    let verb = if apply_changes { "corregidos" } else { "por corregir" };
+    println!("-- {} líneas, {pending} {verb}, {missing} sin mensaje", fixes.len());
+    if pending > 0 { 1 } else { 0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestDb;
+
+    #[test]
+    fn the_whole_file_must_be_valid() {
+        let ok = "{\"id\": 1, \"mensaje\": \"Uso <b> y 'comillas'; sin problema\"}\n\n{\"id\": 2, \"nombre\": \"Ana\"}\n";
+        let fixes = parse(ok).unwrap();
+        assert_eq!(fixes.len(), 2);
+        assert_eq!(fixes[1], Fix { id: 2, nombre: Some("Ana".into()), mensaje: None });
+
+        assert_eq!(parse("{\"id\": 1}").unwrap_err(), "línea 1: falta nombre o mensaje");
+        assert_eq!(parse("{\"id\": 1, \"mensaje\": \"corto\"}").unwrap_err(), "línea 1: mensaje inválido");
+        assert!(parse("{\"id\": 1, \"texto\": \"x\"}").unwrap_err().starts_with("línea 1:"));
+    }
+
+    #[tokio::test]
+    async fn fixes_are_applied_and_audited() {
+        let Some(db) = TestDb::new().await else { return };
+        sqlx::query(
+            "INSERT INTO mensajes (id, nombre, mensaje) VALUES (1, 'Ana', 'La deion de la moto'), (2, 'Luis', 'Bien como está')",
+        )
+        .execute(&db.pool)
+        .await
+        .unwrap();
+        sqlx::query("INSERT INTO mensajes_archive (id, nombre, mensaje, created_at) VALUES (3, 'Eva', 'Precio 100 50', now())")
+            .execute(&db.pool)
+            .await
+            .unwrap();
+
+        let file = std::env::temp_dir().join(format!("reimport_{}.jsonl", uuid::Uuid::new_v4().simple()));
+        let lines = [
+            r#"{"id": 1, "mensaje": "La description de la moto"}"#,
+            r#"{"id": 2, "mensaje": "Bien como está"}"#,
+            r#"{"id": 3, "mensaje": "Precio 100 -- 50 <negociable>"}"#,
+            r#"{"id": 9, "mensaje": "No existe este mensaje"}"#,
+        ];
+        std::fs::write(&file, lines.join("\n")).unwrap();
+
+        assert_eq!(run(&db.pool, &file, false).await, 1);
+        assert_eq!(queries::reimport::current(&db.pool, 1).await.unwrap().unwrap().1, "La deion de la moto");
+
+        assert_eq!(run(&db.pool, &file, true).await, 0);
+        assert_eq!(queries::reimport::current(&db.pool, 1).await.unwrap().unwrap().1, "La description de la moto");
+        assert_eq!(queries::reimport::current(&db.pool, 3).await.unwrap().unwrap().1, "Precio 100 -- 50 <negociable>");
+        // Ya aplicado: no queda nada.
+        assert_eq!(run(&db.pool, &file, false).await, 0);
+
+        let audited: Vec<(String, String)> =
+            sqlx::query_as("SELECT actor, target FROM audit_log WHERE action = 'mensaje.reimport' ORDER BY id")
+                .fetch_all(&db.pool)
+                .await
+                .unwrap();
+        assert_eq!(audited, [(ACTOR.to_string(), "mensaje:1".to_string()), (ACTOR.to_string(), "mensaje:3".to_string())]);
+
+        let _ = std::fs::remove_file(&file);
+        db.finish().await;
+    }
+}
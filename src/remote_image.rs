@@ -0,0 +1,208 @@
+//! Descarga de imágenes para `POST /upload-image-url`. Solo http(s) hacia
+//! direcciones públicas: el host se resuelve aquí, se rechaza si alguna de sus
+//! IP es interna y la conexión se fija a la IP comprobada, para que una segunda
+//! resolución no pueda llevarla a otro sitio. Las redirecciones se siguen a mano
+//! y cada salto pasa la misma comprobación.
+
+use reqwest::{header, redirect, Url};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::time::Duration;
+
+const MAX_REDIRECTS: usize = 3;
+
+const INVALID: &str = "❌ URL no válida";
+const BLOCKED: &str = "❌ No se permiten direcciones internas";
+const FAILED: &str = "❌ No se pudo descargar la imagen";
+const TIMEOUT: &str = "❌ La descarga tardó demasiado";
+
+pub struct Fetched {
+    pub bytes: Vec<u8>,
+    /// `Content-Type` sin parámetros.
+    pub mime: String,
+    /// Último segmento de la ruta, como nombre original.
+    pub name: String,
+}
+
+/// Límites de la descarga; los mensajes de error van tal cual al cliente.
+pub struct Limits<'a> {
+    pub allowed_mime: &'a [&'a str],
+    pub max_bytes: usize,
+    pub too_large: &'static str,
+    pub not_allowed: &'static str,
+    /// Para toda la descarga, redirecciones incluidas.
+    pub timeout: Duration,
+}
+
+pub async fn fetch(raw: &str, limits: &Limits<'_>) -> Result<Fetched, &'static str> {
+    tokio::time::timeout(limits.timeout, follow(raw, limits))
+        .await
+        .unwrap_or(Err(TIMEOUT))
+}
+
+async fn follow(raw: &str, limits: &Limits<'_>) -> Result<Fetched, &'static str> {
+    let mut url = Url::parse(raw.trim()).map_err(|_| INVALID)?;
+
+    for _ in 0..=MAX_REDIRECTS {
+        let addr = public_addr(&url).await?;
+        // Sin proxy: uno de `HTTP(S)_PROXY` resolvería el host por su cuenta y
+        // la dirección ya comprobada no serviría de nada.
+        let mut client = reqwest::Client::builder()
+            .no_proxy()
+            .redirect(redirect::Policy::none())
+            .user_agent(concat!("hola_axum/", env!("CARGO_PKG_VERSION")));
+        if let Some(domain) = url.host_str().filter(|_| literal_ip(&url).is_none()) {
+            client = client.resolve(domain, addr);
+        }
+        let client = client.build().map_err(|_| FAILED)?;
+
+        let mut res = client.get(url.clone()).send().await.map_err(|_| FAILED)?;
+
+        if res.status().is_redirection() {
+            let location = res
+                .headers()
+                .get(header::LOCATION)
+                .and_then(|v| v.to_str().ok())
+                .ok_or(FAILED)?;
+            url = url.join(location).map_err(|_| INVALID)?;
+            continue;
+        }
+        if !res.status().is_success() {
+            return Err(FAILED);
+        }
+
+        let mime = res
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.split(';').next())
+            .map(|v| v.trim().to_ascii_lowercase())
+            .unwrap_or_default();
+        if !limits.allowed_mime.contains(&mime.as_str()) {
+            return Err(limits.not_allowed);
+        }
+        if res.content_length().is_some_and(|len| len > limits.max_bytes as u64) {
+            return Err(limits.too_large);
+        }
+
+        let mut bytes = Vec::new();
+        while let Some(chunk) = res.chunk().await.map_err(|_| FAILED)? {
+            bytes.extend_from_slice(&chunk);
+            if bytes.len() > limits.max_bytes {
+                return Err(limits.too_large);
+            }
+        }
+
+        let name = url
+            .path_segments()
+            .and_then(|mut segments| segments.next_back())
+            .unwrap_or_default()
+            .to_string();
+
+        return Ok(Fetched { bytes, mime, name });
+    }
+
+    Err(FAILED)
+}
+
+/// Dirección a la que conectar, si todas las del host son públicas.
+async fn public_addr(url: &Url) -> Result<SocketAddr, &'static str> {
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(INVALID);
+    }
+    let port = url.port_or_known_default().ok_or(INVALID)?;
+    let host = url.host_str().ok_or(INVALID)?;
+
+    let addrs: Vec<SocketAddr> = match literal_ip(url) {
+        Some(ip) => vec![SocketAddr::new(ip, port)],
+        None => tokio::net::lookup_host((host, port))
+            .await
+            .map_err(|_| FAILED)?
+            .collect(),
+    };
+
+    match addrs.first() {
+        Some(first) if addrs.iter().all(|a| is_public(a.ip())) => Ok(*first),
+        Some(_) => Err(BLOCKED),
+        None => Err(FAILED),
+    }
+}
+
+/// Host escrito como IP (`http://10.0.0.1/`, `http://[::1]/`): no hay nada que resolver.
+fn literal_ip(url: &Url) -> Option<IpAddr> {
+    let host = url.host_str()?;
+    host.trim_start_matches('[').trim_end_matches(']').parse().ok()
+}
+
+/// Fuera quedan loopback, redes privadas, enlace local (metadatos de la nube
+/// incluidos), CGNAT, multicast, documentación y rangos reservados. Las IPv6
+/// que llevan una IPv4 dentro (mapeada, compatible, NAT64, 6to4) se juzgan por
+/// la de dentro; Teredo, que la lleva ofuscada, no pasa.
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || ip.is_documentation()
+                || a == 0
+                || a >= 240
+                || (a == 100 && (64..128).contains(&b))
+                || (a == 198 && (b == 18 || b == 19)))
+        }
+        IpAddr::V6(ip) => {
+            let s = ip.segments();
+            let embedded = |hi: u16, lo: u16| IpAddr::V4(Ipv4Addr::from((u32::from(hi) << 16) | u32::from(lo)));
+            // `::ffff:a.b.c.d` y `::a.b.c.d` (también `::1` y `::`, que caen como 0.0.0.x).
+            if let Some(v4) = ip.to_ipv4() {
+                return is_public(v4.into());
+            }
+            if s[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+                return is_public(embedded(s[6], s[7]));
+            }
+            if s[0] == 0x2002 {
+                return is_public(embedded(s[1], s[2]));
+            }
+            !(ip.is_multicast()
+                || s[0] & 0xfe00 == 0xfc00
+                || s[0] & 0xffc0 == 0xfe80
+                || (s[0] == 0x64 && s[1] == 0xff9b)
+                || (s[0] == 0x2001 && (s[1] == 0 || s[1] == 0x0db8)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_public_addresses_pass() {
+        for internal in [
+            "127.0.0.1", "10.1.2.3", "172.16.0.1", "192.168.1.1", "169.254.169.254",
+            "100.64.0.1", "0.0.0.0", "255.255.255.255", "::1", "::", "fd00::1",
+            "fe80::1", "::ffff:127.0.0.1", "::ffff:10.0.0.1",
+            // IPv4 dentro de IPv6: compatible, NAT64, 6to4; Teredo y documentación.
+            "::127.0.0.1", "::a9fe:a9fe", "64:ff9b::10.0.0.1", "64:ff9b::7f00:1", "64:ff9b:1::1",
+            "2002:a00:1::1", "2002:7f00:1::", "2001:0:4136:e378::1", "2001:db8::1",
+        ] {
+            assert!(!is_public(internal.parse().unwrap()), "{internal}");
+        }
+        for public in ["93.184.216.34", "8.8.8.8", "2606:2800:220:1::1", "64:ff9b::808:808", "2002:808:808::1"] {
+            assert!(is_public(public.parse().unwrap()), "{public}");
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_non_http_and_internal_hosts() {
+        let url = |s: &str| Url::parse(s).unwrap();
+        assert_eq!(public_addr(&url("file:///etc/passwd")).await, Err(INVALID));
+        assert_eq!(public_addr(&url("ftp://example.com/a.png")).await, Err(INVALID));
+        assert_eq!(public_addr(&url("http://127.0.0.1:3000/a.png")).await, Err(BLOCKED));
+        assert_eq!(public_addr(&url("http://[::1]/a.png")).await, Err(BLOCKED));
+        assert_eq!(public_addr(&url("http://localhost/a.png")).await, Err(BLOCKED));
+    }
+}
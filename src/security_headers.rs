@@ -0,0 +1,59 @@
+//! Cabeceras de seguridad en todas las respuestas. Cada una se sustituye con
+//! `HEADER_<NOMBRE>` (`HEADER_CONTENT_SECURITY_POLICY`, …); vacía, no se envía.
+//! Un handler que ya ponga la suya la conserva.
+
+use axum::{
+    http::{HeaderName, HeaderValue},
+    Router,
+};
+use tower_http::set_header::SetResponseHeaderLayer;
+
+/// La CSP admite los scripts en línea de las páginas, htmx (unpkg), los
+/// widgets de captcha y las imágenes externas de las páginas de ejemplo. Con
+/// `CSP_NONCE` las páginas HTML cambian `'unsafe-inline'` por un nonce (ver `csp_nonce`).
+pub const DEFAULTS: &[(&str, &str)] = &[
+    (
+        "content-security-policy",
+        "default-src 'self'; \
+         script-src 'self' 'unsafe-inline' https://unpkg.com https://www.google.com https://www.gstatic.com \
+         https://challenges.cloudflare.com https://js.hcaptcha.com https://*.hcaptcha.com; \
+         style-src 'self' 'unsafe-inline' https://*.hcaptcha.com; \
+         img-src 'self' data: https:; \
+         frame-src https://www.google.com https://challenges.cloudflare.com https://*.hcaptcha.com; \
+         connect-src 'self' ws: wss: https://*.hcaptcha.com; \
+         frame-ancestors 'none'; base-uri 'self'; form-action 'self'",
+    ),
+    ("x-frame-options", "DENY"),
+    ("x-content-type-options", "nosniff"),
+    ("referrer-policy", "strict-origin-when-cross-origin"),
+    ("strict-transport-security", "max-age=31536000"),
+];
+
+/// Variable de entorno que sustituye a `header`.
+pub fn env_var(header: &str) -> String {
+    format!("HEADER_{}", header.to_uppercase().replace('-', "_"))
+}
+
+pub fn apply<S: Clone + Send + Sync + 'static>(
+    mut router: Router<S>,
+    headers: &[(HeaderName, HeaderValue)],
+) -> Router<S> {
+    for (name, value) in headers {
+        router = router.layer(SetResponseHeaderLayer::if_not_present(name.clone(), value.clone()));
+    }
+    router
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_are_valid_headers() {
+        for (name, value) in DEFAULTS {
+            assert!(HeaderName::try_from(*name).is_ok(), "{name}");
+            assert!(HeaderValue::try_from(*value).is_ok(), "{name}");
+        }
+        assert_eq!(env_var("x-frame-options"), "HEADER_X_FRAME_OPTIONS");
+    }
+}
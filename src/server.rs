@@ -0,0 +1,288 @@
+use axum::{extract::ConnectInfo, Router};
+use hyper_util::{
+    rt::{TokioExecutor, TokioIo},
+    server::{conn::auto, graceful::GracefulShutdown},
+    service::TowerToHyperService,
+};
+use std::{
+    net::SocketAddr,
+    os::unix::{
+        fs::{FileTypeExt, PermissionsExt},
+        io::{FromRawFd, IntoRawFd, RawFd},
+    },
+    path::{Path, PathBuf},
+    pin::pin,
+};
+use tokio::net::{TcpListener, TcpSocket, UnixListener};
+use tokio_rustls::TlsAcceptor;
+use tower::ServiceExt;
+
+use crate::client_ip::UnixPeer;
+use crate::config::ServerConfig;
+use crate::tls;
+
+/// Primer descriptor que pasa systemd en la activación por socket.
+const SD_LISTEN_FDS_START: RawFd = 3;
+
+/// Dirección de escucha: `0.0.0.0:3000`, `unix:/run/app.sock` o `fd:3` (heredado).
+#[derive(Clone, Debug)]
+pub enum Listen {
+    Tcp(SocketAddr),
+    Unix(PathBuf),
+    Fd(RawFd),
+}
+
+impl std::str::FromStr for Listen {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(path) = s.strip_prefix("unix:") {
+            Ok(Listen::Unix(PathBuf::from(path)))
+        } else if let Some(fd) = s.strip_prefix("fd:") {
+            fd.parse().map(Listen::Fd).map_err(|_| format!("descriptor inválido: {s}"))
+        } else {
+            s.parse().map(Listen::Tcp).map_err(|_| format!("dirección inválida: {s}"))
+        }
+    }
+}
+
+impl std::fmt::Display for Listen {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Listen::Tcp(addr) => write!(f, "{addr}"),
+            Listen::Unix(path) => write!(f, "unix:{}", path.display()),
+            Listen::Fd(fd) => write!(f, "fd:{fd}"),
+        }
+    }
+}
+
+/// Descriptores pasados por systemd (`LISTEN_FDS`/`LISTEN_PID`), si van dirigidos a este proceso.
+pub fn systemd_listeners() -> Vec<Listen> {
+    let for_us = std::env::var("LISTEN_PID")
+        .ok()
+        .and_then(|pid| pid.parse::<u32>().ok())
+        .is_some_and(|pid| pid == std::process::id());
+    let count: RawFd = std::env::var("LISTEN_FDS")
+        .ok()
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(0);
+
+    if !for_us {
+        return Vec::new();
+    }
+    (SD_LISTEN_FDS_START..SD_LISTEN_FDS_START + count)
+        .map(Listen::Fd)
+        .collect()
+}
+
+enum Bound {
+    Tcp(TcpListener),
+    /// Listener Unix y, si lo creamos nosotros, la ruta a borrar al apagar.
+    Unix(UnixListener, Option<PathBuf>),
+}
+
+/// Arranca todos los listeners a la vez y espera a que terminen (tras la señal de apagado).
+/// En el apagado se deja de aceptar y se drenan las conexiones en curso, de modo que
+/// un reemplazo (socket heredado o `SO_REUSEPORT`) no pierde peticiones.
+///
+/// El `bool` de cada listener marca los públicos: con `TLS_CERT`/`TLS_KEY` sus
+/// sockets TCP sirven HTTPS; los Unix y los internos siguen en HTTP.
+pub async fn run(listeners: Vec<(Listen, Router, bool)>, config: &ServerConfig) {
+    let mut tasks = tokio::task::JoinSet::new();
+    let acceptor = config.tls.as_ref().map(tls::acceptor);
+    let mut https_port = None;
+
+    for (listen, app, public) in listeners {
+        let bound = bind(&listen, config);
+        let acceptor = acceptor.clone().filter(|_| public);
+        match (&bound, &acceptor) {
+            (Bound::Tcp(listener), Some(_)) => {
+                https_port = https_port.or(listener.local_addr().ok().map(|a| a.port()));
+                tracing::info!("escuchando en {listen} (https)");
+            }
+            _ => tracing::info!("escuchando en {listen}"),
+        }
+
+        tasks.spawn(async move {
+            match (bound, acceptor) {
+                (Bound::Tcp(listener), Some(acceptor)) => serve_tls(app, listener, acceptor).await,
+                (Bound::Tcp(listener), None) => serve_tcp(app, listener).await,
+                (Bound::Unix(listener, path), _) => serve_unix(app, listener, path).await,
+            }
+        });
+    }
+
+    if let Some(addr) = config.tls.as_ref().and_then(|tls| tls.redirect_listen) {
+        let listener = bind_tcp(addr, config.reuse_port);
+        let app = tls::redirect_router(config.public_url.clone(), https_port.unwrap_or(443));
+        tracing::info!("escuchando en {addr} (redirección a https)");
+        tasks.spawn(serve_tcp(app, listener));
+    }
+
+    while let Some(result) = tasks.join_next().await {
+        if let Err(err) = result {
+            panic!("un listener terminó con error: {err}");
+        }
+    }
+}
+
+fn bind(listen: &Listen, config: &ServerConfig) -> Bound {
+    match listen {
+        Listen::Tcp(addr) => Bound::Tcp(bind_tcp(*addr, config.reuse_port)),
+        Listen::Unix(path) => {
+            remove_stale_socket(path);
+            let listener = UnixListener::bind(path).expect("no se pudo crear el socket Unix");
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(config.socket_mode))
+                .expect("no se pudieron fijar los permisos del socket");
+            Bound::Unix(listener, Some(path.clone()))
+        }
+        Listen::Fd(fd) => from_fd(*fd),
+    }
+}
+
+/// Con `SO_REUSEPORT` una instancia nueva puede escuchar en el mismo puerto mientras
+/// la antigua drena sus conexiones.
+fn bind_tcp(addr: SocketAddr, reuse_port: bool) -> TcpListener {
+    let socket = if addr.is_ipv4() {
+        TcpSocket::new_v4()
+    } else {
+        TcpSocket::new_v6()
+    }
+    .unwrap();
+
+    socket.set_reuseaddr(true).unwrap();
+    if reuse_port {
+        socket.set_reuseport(true).expect("SO_REUSEPORT no soportado");
+    }
+    socket.bind(addr).expect("no se pudo escuchar en la dirección");
+    socket.listen(1024).unwrap()
+}
+
+fn from_fd(fd: RawFd) -> Bound {
+    // SAFETY: el descriptor lo hereda este proceso (activación por socket) y nadie más lo usa.
+    let tcp = unsafe { std::net::TcpListener::from_raw_fd(fd) };
+    if tcp.local_addr().is_ok() {
+        tcp.set_nonblocking(true).unwrap();
+        return Bound::Tcp(TcpListener::from_std(tcp).unwrap());
+    }
+
+    // SAFETY: mismo descriptor; solo cambia el tipo con el que lo envolvemos.
+    let unix = unsafe { std::os::unix::net::UnixListener::from_raw_fd(tcp.into_raw_fd()) };
+    unix.local_addr()
+        .unwrap_or_else(|_| panic!("fd:{fd} no es un socket TCP ni Unix"));
+    unix.set_nonblocking(true).unwrap();
+    Bound::Unix(UnixListener::from_std(unix).unwrap(), None)
+}
+
+async fn serve_tcp(app: Router, listener: TcpListener) {
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+        .with_graceful_shutdown(shutdown_signal())
+        .await
+        .unwrap();
+}
+
+/// Sirve sobre un socket Unix. La IP del cliente llega por `X-Forwarded-For`
+/// desde el proxy, que es de confianza (`UnixPeer`); al apagar se drenan las conexiones y se borra el socket.
+async fn serve_unix(app: Router, listener: UnixListener, path: Option<PathBuf>) {
+    let builder = auto::Builder::new(TokioExecutor::new());
+    let graceful = GracefulShutdown::new();
+    let mut shutdown = pin!(shutdown_signal());
+
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => {
+                let Ok((stream, _)) = accepted else { continue };
+                let app = app.clone().map_request(|mut req: axum::extract::Request<hyper::body::Incoming>| {
+                    req.extensions_mut().insert(UnixPeer);
+                    req
+                });
+                let service = TowerToHyperService::new(app);
+                let conn = builder
+                    .serve_connection_with_upgrades(TokioIo::new(stream), service)
+                    .into_owned();
+                let conn = graceful.watch(conn);
+                tokio::spawn(async move {
+                    if let Err(err) = conn.await {
+                        tracing::debug!(error = %err, "conexión unix cerrada con error");
+                    }
+                });
+            }
+            _ = &mut shutdown => break,
+        }
+    }
+
+    drop(listener);
+    graceful.shutdown().await;
+    if let Some(path) = path {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+/// Como `serve_unix`, pero con el handshake TLS de cada conexión en su propia
+/// tarea (uno lento no frena el `accept`) y la dirección del cliente en
+/// `ConnectInfo`, igual que `serve_tcp`.
+async fn serve_tls(app: Router, listener: TcpListener, acceptor: TlsAcceptor) {
+    let builder = auto::Builder::new(TokioExecutor::new());
+    let graceful = GracefulShutdown::new();
+    let mut shutdown = pin!(shutdown_signal());
+
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => {
+                let Ok((stream, addr)) = accepted else { continue };
+                let (acceptor, builder, app) = (acceptor.clone(), builder.clone(), app.clone());
+                let watcher = graceful.watcher();
+                tokio::spawn(async move {
+                    let stream = match acceptor.accept(stream).await {
+                        Ok(stream) => stream,
+                        Err(err) => {
+                            tracing::debug!(error = %err, %addr, "handshake TLS fallido");
+                            return;
+                        }
+                    };
+                    let app = app.map_request(move |mut req: axum::extract::Request<hyper::body::Incoming>| {
+                        req.extensions_mut().insert(ConnectInfo(addr));
+                        req
+                    });
+                    let conn = builder.serve_connection_with_upgrades(TokioIo::new(stream), TowerToHyperService::new(app));
+                    if let Err(err) = watcher.watch(conn).await {
+                        tracing::debug!(error = %err, "conexión tls cerrada con error");
+                    }
+                });
+            }
+            _ = &mut shutdown => break,
+        }
+    }
+
+    drop(listener);
+    graceful.shutdown().await;
+}
+
+/// Un socket que sobrevivió a un cierre abrupto impediría el `bind`; cualquier
+/// otro tipo de fichero en esa ruta se deja intacto y aborta el arranque.
+fn remove_stale_socket(path: &Path) {
+    if let Ok(meta) = std::fs::symlink_metadata(path) {
+        if !meta.file_type().is_socket() {
+            panic!("{} existe y no es un socket", path.display());
+        }
+        std::fs::remove_file(path).expect("no se pudo borrar el socket antiguo");
+    }
+}
+
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        let _ = tokio::signal::ctrl_c().await;
+    };
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("no se pudo instalar el manejador de SIGTERM")
+            .recv()
+            .await;
+    };
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+    tracing::info!("apagando servidor");
+}
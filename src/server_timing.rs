@@ -0,0 +1,122 @@
+//! Cabecera `Server-Timing` (con `SERVER_TIMING=true`), que las devtools del
+//! navegador enseñan junto a cada petición: `db` es lo que midió `db::timed`,
+//! `render` lo envuelto en `render` (HTML y JSON de los listados), `app` el
+//! handler entero, `mw` el middleware alrededor y `total` todo junto. Se
+//! acumula como `query_budget`: `track` abre el contador por fuera de todo el
+//! middleware y `handler` mide cada ruta por dentro.
+
+use axum::{
+    extract::{Request, State},
+    http::HeaderValue,
+    middleware::Next,
+    response::Response,
+};
+use std::{
+    cell::Cell,
+    time::{Duration, Instant},
+};
+
+pub const HEADER: &str = "server-timing";
+
+tokio::task_local! {
+    static TIMINGS: Cell<Timings>;
+}
+
+#[derive(Default, Clone, Copy)]
+struct Timings {
+    db: Duration,
+    queries: u32,
+    render: Duration,
+    handler: Duration,
+}
+
+impl Timings {
+    fn header(&self, total: Duration) -> String {
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        format!(
+            r#"db;dur={:.1};desc="{} consultas", render;dur={:.1}, app;dur={:.1}, mw;dur={:.1}, total;dur={:.1}"#,
+            ms(self.db),
+            self.queries,
+            ms(self.render),
+            ms(self.handler),
+            ms(total.saturating_sub(self.handler)),
+            ms(total)
+        )
+    }
+}
+
+fn add(f: impl FnOnce(&mut Timings)) {
+    let _ = TIMINGS.try_with(|cell| {
+        let mut timings = cell.get();
+        f(&mut timings);
+        cell.set(timings);
+    });
+}
+
+/// Una consulta de la petición en curso; fuera de una petición no hace nada.
+pub fn record_db(elapsed: Duration) {
+    add(|t| {
+        t.db += elapsed;
+        t.queries += 1;
+    });
+}
+
+/// Mide la generación de una respuesta.
+pub fn render<T>(f: impl FnOnce() -> T) -> T {
+    let start = Instant::now();
+    let out = f();
+    add(|t| t.render += start.elapsed());
+    out
+}
+
+/* ---------- MIDDLEWARE ---------- */
+
+/// Por fuera de todo el middleware; sin `SERVER_TIMING` no mide nada.
+pub async fn track(State(enabled): State<bool>, req: Request, next: Next) -> Response {
+    if !enabled {
+        return next.run(req).await;
+    }
+    let start = Instant::now();
+    let (mut res, timings) = TIMINGS
+        .scope(Cell::new(Timings::default()), async {
+            let res = next.run(req).await;
+            (res, TIMINGS.with(Cell::get))
+        })
+        .await;
+
+    if let Ok(value) = HeaderValue::from_str(&timings.header(start.elapsed())) {
+        res.headers_mut().insert(HEADER, value);
+    }
+    res
+}
+
+/// En cada ruta (`route_layer`): el tiempo del handler.
+pub async fn handler(req: Request, next: Next) -> Response {
+    let start = Instant::now();
+    let res = next.run(req).await;
+    add(|t| t.handler += start.elapsed());
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn middleware_is_what_the_handler_did_not_take() {
+        let timings = TIMINGS
+            .scope(Cell::new(Timings::default()), async {
+                record_db(Duration::from_millis(3));
+                record_db(Duration::from_millis(2));
+                add(|t| t.handler += Duration::from_millis(8));
+                TIMINGS.with(Cell::get)
+            })
+            .await;
+        assert_eq!(
+            timings.header(Duration::from_millis(10)),
+            r#"db;dur=5.0;desc="2 consultas", render;dur=0.0, app;dur=8.0, mw;dur=2.0, total;dur=10.0"#
+        );
+        // Fuera de una petición no cuenta.
+        record_db(Duration::from_millis(1));
+    }
+}
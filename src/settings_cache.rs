@@ -0,0 +1,66 @@
+//! Ajustes que se leen en casi cada petición, servidos desde memoria: el aviso
+//! del sitio lo pide cada página (`GET /announcement`) y el enlace permanente.
+//! La copia dura `SETTINGS_CACHE_TTL_SECS` y se descarta antes en cuanto llega
+//! `SettingsChanged` por el bus de eventos, de esta réplica o de otra. Los
+//! aciertos y fallos salen en `/metrics` (`cache_requests_total`).
+
+use chrono::Utc;
+use sqlx::PgPool;
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::announcement::Announcement;
+use crate::db::DbError;
+use crate::metrics::Metrics;
+use crate::queries;
+
+const CACHE: &str = "settings";
+
+pub struct SettingsCache {
+    ttl: Duration,
+    metrics: Arc<Metrics>,
+    announcement: Mutex<Option<Cached>>,
+}
+
+struct Cached {
+    /// El guardado aunque caduque dentro del TTL: la caducidad se mira al leer.
+    announcement: Option<Announcement>,
+    loaded: Instant,
+}
+
+impl SettingsCache {
+    pub fn new(ttl: Duration, metrics: Arc<Metrics>) -> Arc<Self> {
+        Arc::new(SettingsCache { ttl, metrics, announcement: Mutex::new(None) })
+    }
+
+    /// El aviso vigente, como `announcement::active`.
+    pub async fn announcement(&self, pool: &PgPool) -> Result<Option<Announcement>, DbError> {
+        let cached = self
+            .announcement
+            .lock()
+            .unwrap()
+            .as_ref()
+            .filter(|c| c.loaded.elapsed() < self.ttl)
+            .map(|c| c.announcement.clone());
+
+        let stored = match cached {
+            Some(stored) => {
+                self.metrics.incr_cache(CACHE, true);
+                stored
+            }
+            None => {
+                self.metrics.incr_cache(CACHE, false);
+                let stored = queries::announcement::stored(pool).await?;
+                *self.announcement.lock().unwrap() = Some(Cached { announcement: stored.clone(), loaded: Instant::now() });
+                stored
+            }
+        };
+        Ok(stored.filter(|a| a.expires_at.is_none_or(|at| at > Utc::now())))
+    }
+
+    pub fn clear(&self) {
+        *self.announcement.lock().unwrap() = None;
+    }
+}
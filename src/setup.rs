@@ -0,0 +1,184 @@
+//! Configuración inicial. Con la base de datos vacía (sin ajustes ni usuarios)
+//! `/setup` crea la primera cuenta de administración y guarda el título del
+//! sitio y el idioma, sin tener que tocar SQL a mano. Para que no la complete
+//! el primero que llegue, al arrancar se genera un código de un solo uso que
+//! sale en el log y que el formulario pide. Completada, la ruta da 404.
+
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{Html, IntoResponse, Response},
+    routing::get,
+    Form, Router,
+};
+use serde::Deserialize;
+use sqlx::PgPool;
+use std::sync::{Arc, Mutex, RwLock};
+use uuid::Uuid;
+
+use crate::admin::{self, constant_time_eq};
+use crate::admin_session;
+use crate::db::DbError;
+use crate::events::{self, Event};
+use crate::flash::{self, Flash};
+use crate::html;
+use crate::outbox;
+use crate::policy::Role;
+use crate::queries;
+use crate::state::SharedState;
+use crate::unit_of_work::UnitOfWork;
+use crate::users;
+
+/// Idiomas que se pueden elegir: código (`lang` de las páginas) y nombre.
+pub const LANGUAGES: &[(&str, &str)] = &[("es", "Español"), ("en", "English")];
+
+const MAX_TITLE_CHARS: usize = 80;
+
+/// Ajustes del sitio que usan las páginas generadas en el servidor.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Site {
+    pub title: String,
+    pub language: String,
+}
+
+impl Default for Site {
+    fn default() -> Self {
+        Site { title: "Axum Motors".to_string(), language: "es".to_string() }
+    }
+}
+
+/// Ajustes en memoria y, mientras falte la configuración inicial, su código.
+#[derive(Default)]
+pub struct Setup {
+    site: RwLock<Site>,
+    code: Mutex<Option<String>>,
+}
+
+impl Setup {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Setup::default())
+    }
+
+    pub fn site(&self) -> Site {
+        self.site.read().unwrap().clone()
+    }
+
+    /// Código de `/setup`; `None` si no hay configuración pendiente.
+    pub fn code(&self) -> Option<String> {
+        self.code.lock().unwrap().clone()
+    }
+
+    /// Lee los ajustes guardados. Sin ellos y sin usuarios genera el código
+    /// de `/setup` y lo deja en el log.
+    pub async fn load(&self, pool: &PgPool) -> Result<(), DbError> {
+        if let Some(site) = queries::setup::load_site(pool).await? {
+            *self.site.write().unwrap() = site;
+            return Ok(());
+        }
+
+        if !queries::users::exists(pool).await? {
+            let code = Uuid::new_v4().simple().to_string()[..12].to_string();
+            tracing::warn!(code, "sin configurar: completa /setup con este código");
+            *self.code.lock().unwrap() = Some(code);
+        }
+        Ok(())
+    }
+
+    fn complete(&self, site: Option<Site>) {
+        if let Some(site) = site {
+            *self.site.write().unwrap() = site;
+        }
+        *self.code.lock().unwrap() = None;
+    }
+}
+
+/* ---------- /setup ---------- */
+
+/// Con rate limit, como el resto de accesos: el código no se puede adivinar a fuerza bruta.
+pub fn routes(state: &SharedState) -> Router<SharedState> {
+    admin::throttle(Router::new().route("/setup", get(setup_page).post(complete)), state)
+}
+
+async fn setup_page(State(app): State<SharedState>) -> Response {
+    if app.setup.code().is_none() {
+        return not_found();
+    }
+    Html(html::setup_page(&app.setup.site(), LANGUAGES)).into_response()
+}
+
+#[derive(Deserialize)]
+struct SetupForm {
+    code: String,
+    username: String,
+    password: String,
+    title: String,
+    language: String,
+}
+
+/// Guarda los ajustes, crea la cuenta y entra con ella en el panel.
+async fn complete(State(app): State<SharedState>, headers: HeaderMap, Form(form): Form<SetupForm>) -> Response {
+    let wants_html = flash::wants_html(&headers);
+    let Some(code) = app.setup.code() else {
+        return not_found();
+    };
+
+    let title = form.title.trim();
+    let rejected = if !constant_time_eq(&code, form.code.trim()) {
+        Some((StatusCode::UNAUTHORIZED, "❌ Código incorrecto"))
+    } else if title.is_empty() || title.chars().count() > MAX_TITLE_CHARS {
+        Some((StatusCode::BAD_REQUEST, "❌ Título inválido"))
+    } else if !LANGUAGES.iter().any(|(lang, _)| *lang == form.language) {
+        Some((StatusCode::BAD_REQUEST, "❌ Idioma no disponible"))
+    } else {
+        users::invalid(&form.username, &form.password).map(|reason| (StatusCode::BAD_REQUEST, reason))
+    };
+    if let Some((status, reason)) = rejected {
+        return if wants_html {
+            flash::redirect("/setup", Flash::error(reason))
+        } else {
+            (status, Html(reason)).into_response()
+        };
+    }
+
+    let Some(hash) = users::hash(form.password).await else {
+        return (StatusCode::INTERNAL_SERVER_ERROR, Html("❌ No se pudo guardar la configuración")).into_response();
+    };
+    let site = Site { title: title.to_string(), language: form.language };
+    let username = form.username.trim();
+
+    match save(&app.db, &site, username, &hash).await {
+        Ok(Some(user)) => {
+            tracing::info!(target: "audit", user, username, "configuración inicial completada");
+            app.setup.complete(Some(site));
+            events::publish(&app, Event::SettingsChanged);
+            admin_session::sign_in(&app, Some(user), wants_html).await
+        }
+        // Otra instancia se adelantó: sus ajustes llegan con su `SettingsChanged`
+        // (con `EVENTS_REDIS_URL`) o al reiniciar.
+        Ok(None) => {
+            app.setup.complete(None);
+            not_found()
+        }
+        Err(e) => e.into_response(),
+    }
+}
+
+/// Ajustes y cuenta en una transacción; `None` si ya estaba configurado.
+async fn save(pool: &PgPool, site: &Site, username: &str, hash: &str) -> Result<Option<i32>, DbError> {
+    let mut uow = UnitOfWork::begin(pool).await?;
+
+    if !queries::setup::insert_site(uow.conn(), site).await? {
+        return Ok(None);
+    }
+    let Some(user) = queries::users::insert(uow.conn(), username, hash, Role::Admin.as_str()).await? else {
+        return Ok(None);
+    };
+    outbox::enqueue(uow.conn(), &Event::SettingsChanged).await?;
+
+    uow.commit().await?;
+    Ok(Some(user))
+}
+
+fn not_found() -> Response {
+    (StatusCode::NOT_FOUND, Html("❌ El sitio ya está configurado")).into_response()
+}
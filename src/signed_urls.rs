@@ -0,0 +1,156 @@
+//! Subidas privadas: con `UPLOADS_PRIVATE=true` el directorio de subidas deja
+//! de servirse tal cual. `GET /images/:id/url` da una URL firmada con HMAC
+//! (`UPLOAD_URL_SECRET`) que caduca a los `UPLOAD_URL_TTL_SECS`, y
+//! `GET /uploads/:filename` y `GET /images/:id/thumb` solo entregan la imagen
+//! si firma y caducidad valen. Sin el modo privado `/images/:id/url` devuelve
+//! las rutas públicas de siempre.
+//!
+//! «Privado» quiere decir enlaces que caducan, no control de acceso: la
+//! galería es pública y cualquiera puede pedir una URL nueva para una imagen
+//! publicada. Lo que se evita es que un enlace copiado valga para siempre y
+//! que se descarguen las subidas por nombre sin pasar por aquí.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{Html, IntoResponse, Response},
+    Json,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::config::SignedUrlConfig;
+use crate::queries;
+use crate::state::SharedState;
+use crate::thumbs;
+
+fn mac(secret: &str, filename: &str, expires: i64) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC admite claves de cualquier longitud");
+    mac.update(format!("{filename}:{expires}").as_bytes());
+    mac
+}
+
+/// `expires=<unix>&sig=<hmac>` para `filename`: vale para el original y su miniatura.
+fn signature(config: &SignedUrlConfig, filename: &str, now: i64) -> (String, i64) {
+    let expires = now + config.ttl.as_secs() as i64;
+    let sig = URL_SAFE_NO_PAD.encode(mac(&config.secret, filename, expires).finalize().into_bytes());
+    (format!("expires={expires}&sig={sig}"), expires)
+}
+
+/// Firma correcta (en tiempo constante) y sin caducar.
+pub fn verify(secret: &str, filename: &str, expires: i64, sig: &str, now: i64) -> bool {
+    let Ok(sig) = URL_SAFE_NO_PAD.decode(sig) else { return false };
+    mac(secret, filename, expires).verify_slice(&sig).is_ok() && expires > now
+}
+
+/* ---------- GET /images/:id/url ---------- */
+
+#[derive(Serialize)]
+struct SignedUrl {
+    url: String,
+    thumb_url: String,
+    /// `null` si las subidas son públicas: las URL no caducan.
+    expires_at: Option<DateTime<Utc>>,
+}
+
+pub async fn image_url(State(app): State<SharedState>, Path(id): Path<i32>) -> Response {
+    let filename = match queries::published_image(&app.db, id).await {
+        Ok(Some(filename)) => filename,
+        Ok(None) => return (StatusCode::NOT_FOUND, Html("❌ Imagen no encontrada")).into_response(),
+        Err(e) => return e.into_response(),
+    };
+
+    let body = match &app.config.uploads.private {
+        Some(config) => {
+            let (query, expires) = signature(config, &filename, Utc::now().timestamp());
+            SignedUrl {
+                url: format!("/uploads/{filename}?{query}"),
+                thumb_url: format!("/images/{id}/thumb?{query}"),
+                expires_at: DateTime::from_timestamp(expires, 0),
+            }
+        }
+        None => SignedUrl {
+            url: format!("/uploads/{filename}"),
+            thumb_url: format!("/images/{id}/thumb"),
+            expires_at: None,
+        },
+    };
+    // Cada respuesta lleva otra caducidad: que nadie guarde una ya vencida.
+    ([(header::CACHE_CONTROL, "no-store")], Json(body)).into_response()
+}
+
+/* ---------- GET /uploads/:filename ---------- */
+
+#[derive(Deserialize)]
+pub struct SignatureQuery {
+    #[serde(default)]
+    expires: i64,
+    #[serde(default)]
+    sig: String,
+}
+
+impl SignatureQuery {
+    /// En modo privado, firma de `filename` válida y sin caducar; si no, siempre.
+    pub fn allows(&self, private: Option<&SignedUrlConfig>, filename: &str) -> bool {
+        private.is_none_or(|config| verify(&config.secret, filename, self.expires, &self.sig, Utc::now().timestamp()))
+    }
+}
+
+pub fn forbidden() -> Response {
+    (StatusCode::FORBIDDEN, Html("❌ Enlace no válido o caducado")).into_response()
+}
+
+/// Sustituye a `ServeDir` en modo privado.
+pub async fn serve_signed(
+    State(app): State<SharedState>,
+    Path(filename): Path<String>,
+    Query(query): Query<SignatureQuery>,
+) -> Response {
+    let Some(config) = &app.config.uploads.private else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    if !query.allows(Some(config), &filename) {
+        return forbidden();
+    }
+
+    let Ok(path) = app.uploads.file(&filename) else { return StatusCode::NOT_FOUND.into_response() };
+    match tokio::fs::read(&path).await {
+        Ok(bytes) => (
+            [(header::CONTENT_TYPE, thumbs::content_type(&filename)), (header::CACHE_CONTROL, "private, no-store")],
+            bytes,
+        )
+            .into_response(),
+        Err(_) => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    const SECRET: &str = "0123456789abcdef0123456789abcdef";
+
+    fn params(query: &str) -> (i64, String) {
+        let (expires, sig) = query.split_once('&').unwrap();
+        (expires.strip_prefix("expires=").unwrap().parse().unwrap(), sig.strip_prefix("sig=").unwrap().to_string())
+    }
+
+    #[test]
+    fn signature_covers_file_and_expiry() {
+        let config = SignedUrlConfig { secret: SECRET.to_string(), ttl: Duration::from_secs(60) };
+        let (query, expires) = signature(&config, "a.png", 1_000);
+        let (expires_param, sig) = params(&query);
+        assert_eq!(expires_param, expires);
+
+        assert!(verify(SECRET, "a.png", expires, &sig, 1_059));
+        assert!(!verify(SECRET, "a.png", expires, &sig, 1_060));
+        assert!(!verify(SECRET, "b.png", expires, &sig, 1_000));
+        assert!(!verify(SECRET, "a.png", expires + 3600, &sig, 1_000));
+        assert!(!verify(&SECRET.replace('0', "1"), "a.png", expires, &sig, 1_000));
+        assert!(!verify(SECRET, "a.png", expires, "no base64!", 1_000));
+    }
+}
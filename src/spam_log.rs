@@ -0,0 +1,56 @@
+//! Registro de envíos descartados como spam de bots en `spam_log`, para que
+//! administración vea la actividad en `GET /api/admin/spam`. El bot recibe la
+//! misma respuesta que un envío correcto y no sabe que se ha descartado.
+
+use std::net::IpAddr;
+
+use axum::{extract::State, Json};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+
+use crate::db::DbError;
+use crate::pagination::{PageQuery, Paginated};
+use crate::queries;
+use crate::state::SharedState;
+
+/// Lo que se guarda del texto, de sobra para reconocer al bot.
+const MAX_STORED_CHARS: usize = 500;
+
+pub struct Attempt<'a> {
+    pub reason: &'static str,
+    pub ip: Option<IpAddr>,
+    pub user_agent: Option<&'a str>,
+    pub nombre: &'a str,
+    pub mensaje: &'a str,
+}
+
+/// Un fallo al registrar no cambia la respuesta: solo se avisa en el log.
+pub async fn record(pool: &PgPool, attempt: &Attempt<'_>) {
+    let truncate = |s: &str| s.chars().take(MAX_STORED_CHARS).collect::<String>();
+    let (user_agent, nombre, mensaje) = (attempt.user_agent.map(truncate), truncate(attempt.nombre), truncate(attempt.mensaje));
+    let stored = Attempt { user_agent: user_agent.as_deref(), nombre: &nombre, mensaje: &mensaje, ..*attempt };
+
+    tracing::info!(reason = attempt.reason, ip = ?attempt.ip, "envío descartado como spam");
+    if let Err(e) = queries::spam_log::insert(pool, &stored).await {
+        tracing::warn!(error = ?e, "no se pudo registrar el intento de spam");
+    }
+}
+
+#[derive(Serialize)]
+pub struct Entry {
+    pub id: i64,
+    pub reason: String,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub nombre: String,
+    pub mensaje: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Los más recientes primero.
+pub async fn list(State(app): State<SharedState>, page: PageQuery) -> Result<Json<Paginated<Entry>>, DbError> {
+    let total = queries::spam_log::count(&app.db).await?;
+    let entries = queries::spam_log::page(&app.db, page.per_page(), page.offset()).await?;
+    Ok(Json(Paginated::new(entries, total, &page)))
+}
@@ -0,0 +1,89 @@
+//! Estado compartido de la aplicación. Los handlers reciben `State<SharedState>`
+//! y toman de aquí lo que necesitan, así que un subsistema nuevo es un campo más
+//! sin tocar las firmas que ya existen.
+
+use sqlx::PgPool;
+use std::sync::Arc;
+use tokio::sync::Notify;
+
+use crate::akismet::Akismet;
+use crate::captcha::{self, CaptchaVerifier};
+use crate::config::Config;
+use crate::empty_listing::EmptyListing;
+use crate::events::EventBus;
+use crate::mailer::Mailer;
+use crate::metrics::Metrics;
+use crate::read_cache::ReadCache;
+use crate::settings_cache::SettingsCache;
+use crate::setup::Setup;
+use crate::thumbs::Thumbnails;
+use crate::upload_pipeline::Pipeline;
+use crate::upload_progress::UploadProgress;
+use crate::uploads::UploadsRoot;
+
+pub type SharedState = Arc<AppState>;
+
+pub struct AppState {
+    pub db: PgPool,
+    /// Para exportaciones y trabajos lanzados a mano (ver `db::connect_jobs`).
+    pub jobs_db: PgPool,
+    pub config: Arc<Config>,
+    pub uploads: Arc<UploadsRoot>,
+    pub metrics: Arc<Metrics>,
+    /// Subidas en curso, para el progreso por WebSocket.
+    pub progress: Arc<UploadProgress>,
+    /// Etapas de cada subida, montadas según la configuración.
+    pub upload_pipeline: Pipeline,
+    pub thumbs: Arc<Thumbnails>,
+    /// Copias de `GET /mensajes` para quien pasa el límite de lectura.
+    pub mensajes_cache: Arc<ReadCache>,
+    /// `GET /mensajes` sin consultas mientras no haya mensajes.
+    pub mensajes_empty: Arc<EmptyListing>,
+    pub mailer: Arc<Mailer>,
+    pub captcha: Arc<dyn CaptchaVerifier>,
+    /// Solo con `AKISMET_API_KEY`.
+    pub akismet: Option<Akismet>,
+    pub events: Arc<EventBus>,
+    /// Despierta a `outbox::dispatch` (ver `outbox::wake`).
+    pub outbox: Arc<Notify>,
+    /// Título e idioma del sitio, y la configuración inicial pendiente.
+    pub setup: Arc<Setup>,
+    /// El aviso del sitio sin ir a la base de datos en cada página.
+    pub settings: Arc<SettingsCache>,
+}
+
+impl AppState {
+    pub fn new(
+        db: PgPool,
+        jobs_db: PgPool,
+        config: Arc<Config>,
+        uploads: Arc<UploadsRoot>,
+        metrics: Arc<Metrics>,
+    ) -> SharedState {
+        let mensajes_cache = ReadCache::new(&config.reads);
+        let mailer = Mailer::new(&config.mail);
+        let captcha = captcha::verifier(&config.captcha);
+        let akismet = config.akismet.as_ref().map(Akismet::new);
+        let settings = SettingsCache::new(config.reads.settings_ttl, metrics.clone());
+        let upload_pipeline = Pipeline::new(&config.uploads);
+        Arc::new(AppState {
+            db,
+            jobs_db,
+            config,
+            uploads,
+            metrics,
+            progress: UploadProgress::new(),
+            upload_pipeline,
+            thumbs: Thumbnails::new(),
+            mensajes_cache,
+            mensajes_empty: EmptyListing::new(),
+            mailer,
+            captcha,
+            akismet,
+            events: EventBus::new(),
+            outbox: Arc::new(Notify::new()),
+            setup: Setup::new(),
+            settings,
+        })
+    }
+}
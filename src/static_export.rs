@@ -0,0 +1,157 @@
+//! `GET /admin/export/static`: el libro de visitas completo como web estática
+//! dentro de un ZIP, para archivarlo o publicarlo en un hosting estático, junto
+//! con las páginas públicas y sus recursos. Se conservan las rutas de la
+//! aplicación (`/mensajes/<id>/view` queda en `mensajes/<id>/view/index.html`),
+//! así que enlaces y URL canónicas siguen valiendo tal cual.
+
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, StatusCode},
+    response::{Html, IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+
+use crate::db::DbError;
+use crate::html;
+use crate::queries;
+use crate::state::SharedState;
+use crate::timezone::{LocalTime, Zone};
+use crate::zip::ZipWriter;
+use crate::STATIC_DIR;
+
+/// Mensajes por página del listado exportado.
+const PER_PAGE: usize = 50;
+
+/// Ficheros de `STATIC_DIR` que no se publican.
+const PRIVATE: [&str; 1] = ["admin.html"];
+
+/// Imagen para las vistas previas al compartir, dentro de las subidas.
+const SHARE_IMAGE: &str = "Logo.png";
+
+enum ExportError {
+    Db(DbError),
+    Io(std::io::Error),
+}
+
+impl From<std::io::Error> for ExportError {
+    fn from(e: std::io::Error) -> Self {
+        ExportError::Io(e)
+    }
+}
+
+/// Con `?tz=` las fechas de las páginas salen en esa zona.
+pub async fn static_export(State(app): State<SharedState>, zone: Zone, headers: HeaderMap) -> Response {
+    let base_url = html::base_url(app.config.server.public_url.as_deref(), &headers);
+
+    match build(&app, &base_url, &zone).await {
+        Ok(zip) => {
+            let filename = format!("libro-de-visitas-{}.zip", Utc::now().format("%Y%m%d-%H%M"));
+            (
+                [
+                    (header::CONTENT_TYPE, "application/zip".to_string()),
+                    (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{filename}\"")),
+                ],
+                zip,
+            )
+                .into_response()
+        }
+        Err(ExportError::Db(e)) => e.into_response(),
+        Err(ExportError::Io(e)) => {
+            tracing::error!(error = %e, "no se pudo generar la exportación estática");
+            (StatusCode::INTERNAL_SERVER_ERROR, Html("❌ No se pudo generar la exportación")).into_response()
+        }
+    }
+}
+
+struct Exported {
+    id: i32,
+    nombre: String,
+    mensaje: String,
+    created_at: LocalTime,
+    verified: bool,
+}
+
+async fn build(app: &SharedState, base_url: &str, zone: &Zone) -> Result<Vec<u8>, ExportError> {
+    let rows = queries::static_export::mensajes(&app.jobs_db).await.map_err(ExportError::Db)?;
+    let times: Vec<DateTime<Utc>> = rows.iter().map(|r| r.3).collect();
+    let times = zone.localize(&app.jobs_db, &times).await.map_err(ExportError::Db)?;
+
+    let mensajes: Vec<Exported> = rows
+        .into_iter()
+        .zip(times)
+        .map(|((id, nombre, mensaje, _, verified), created_at)| Exported { id, nombre, mensaje, created_at, verified })
+        .collect();
+
+    let mut zip = ZipWriter::new();
+    let site = app.setup.site();
+
+    // Listado paginado en `mensajes/`, el resto de páginas en `mensajes/pagina/<n>/`.
+    let pages = mensajes.len().div_ceil(PER_PAGE).max(1);
+    for page in 1..=pages {
+        let start = ((page - 1) * PER_PAGE).min(mensajes.len());
+        let end = (page * PER_PAGE).min(mensajes.len());
+        let entries: Vec<html::GuestbookEntry> = mensajes[start..end]
+            .iter()
+            .map(|m| html::GuestbookEntry {
+                id: m.id,
+                nombre: &m.nombre,
+                mensaje: &m.mensaje,
+                created_at: m.created_at.clone(),
+                verified: m.verified,
+            })
+            .collect();
+        let body = html::guestbook_page(&html::GuestbookPage { entries: &entries, page, pages, site: &site });
+        zip.add(&html::guestbook_path(page), body.as_bytes())?;
+    }
+
+    for m in &mensajes {
+        let page = html::MensajePage {
+            id: m.id,
+            nombre: &m.nombre,
+            mensaje: &m.mensaje,
+            created_at: m.created_at.clone(),
+            base_url,
+            verified: m.verified,
+            related: &[],
+            site: &site,
+            // La exportación es una foto fija: un aviso con caducidad no pinta nada.
+            announcement: None,
+        };
+        zip.add(&format!("mensajes/{}/view/index.html", m.id), html::mensaje_page(&page).as_bytes())?;
+    }
+
+    add_static(&mut zip).await?;
+
+    // La imagen para compartir es opcional: si no está, las vistas previas salen sin ella.
+    if let Ok(path) = app.uploads.file(SHARE_IMAGE)
+        && let Ok(bytes) = tokio::fs::read(path).await
+    {
+        zip.add(&format!("uploads/{SHARE_IMAGE}"), &bytes)?;
+    }
+
+    Ok(zip.finish())
+}
+
+/// Todo `STATIC_DIR` (páginas, CSS, JS) salvo lo privado, con las mismas rutas.
+async fn add_static(zip: &mut ZipWriter) -> Result<(), ExportError> {
+    let root = std::path::Path::new(STATIC_DIR);
+    let mut pending = vec![root.to_path_buf()];
+
+    while let Some(dir) = pending.pop() {
+        let mut entries = tokio::fs::read_dir(&dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if entry.file_type().await?.is_dir() {
+                pending.push(path);
+                continue;
+            }
+            let Ok(relative) = path.strip_prefix(root) else { continue };
+            let name = relative.to_string_lossy().replace('\\', "/");
+            if PRIVATE.contains(&name.as_str()) {
+                continue;
+            }
+            zip.add(&name, &tokio::fs::read(&path).await?)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,231 @@
+//! Piezas comunes para tests de endpoints a nivel de `tower::Service`:
+//! base de datos aislada, app lista para `oneshot`, constructores de
+//! peticiones (formularios, multipart) y credenciales de administración.
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::ConnectInfo,
+    http::{header, Method, Request, StatusCode},
+    Router,
+};
+use sqlx::PgPool;
+use std::{collections::HashMap, net::SocketAddr, sync::Arc};
+use tower::ServiceExt;
+use uuid::Uuid;
+
+use crate::config::{Config, Vars};
+use crate::metrics::Metrics;
+use crate::state::AppState;
+use crate::uploads::UploadsRoot;
+use crate::{build_routers, db};
+
+pub const ADMIN_TOKEN: &str = "token-de-test";
+
+/// Configuración de test: lo mínimo para arrancar más los valores indicados.
+pub fn test_config(db_url: &str, overrides: &[(&str, &str)]) -> Arc<Config> {
+    let mut vars: HashMap<&str, &str> = HashMap::from([
+        ("DATABASE_URL", db_url),
+        ("ADMIN_TOKEN", ADMIN_TOKEN),
+        ("DB_MAX_CONNECTIONS", "2"),
+        ("DB_MIN_CONNECTIONS", "0"),
+        ("TRUSTED_PROXIES", "127.0.0.1"),
+        ("CAPTCHA_UNVERIFIED", "true"),
+    ]);
+    vars.extend(overrides.iter().copied());
+
+    Arc::new(Config::from_vars(&Vars(&|key| vars.get(key).map(|v| v.to_string()))))
+}
+
+/// Base de datos de test. Cada instancia trabaja en un esquema propio con las
+/// migraciones aplicadas, y `finish` lo descarta entero: todo lo que escriba el
+/// test se deshace como en un rollback, sin interferir con tests en paralelo.
+/// Las subidas van a un directorio temporal propio que también se borra.
+pub struct TestDb {
+    pub pool: PgPool,
+    pub jobs: PgPool,
+    pub config: Arc<Config>,
+    pub uploads: Arc<UploadsRoot>,
+    admin: PgPool,
+    schema: String,
+}
+
+impl TestDb {
+    /// `None` si no hay `TEST_DATABASE_URL`; los tests que la necesitan se saltan.
+    pub async fn new() -> Option<Self> {
+        Self::with_config(&[]).await
+    }
+
+    pub async fn with_config(overrides: &[(&str, &str)]) -> Option<Self> {
+        let Ok(base_url) = std::env::var("TEST_DATABASE_URL") else {
+            eprintln!("TEST_DATABASE_URL no definida; se omite el test");
+            return None;
+        };
+
+        let schema = format!("test_{}", Uuid::new_v4().simple());
+        let admin = PgPool::connect(&base_url).await.expect("TEST_DATABASE_URL inaccesible");
+        sqlx::query(&format!("CREATE SCHEMA {schema}"))
+            .execute(&admin)
+            .await
+            .unwrap();
+
+        let sep = if base_url.contains('?') { '&' } else { '?' };
+        let url = format!("{base_url}{sep}options[search_path]={schema}");
+        let uploads_dir = std::env::temp_dir().join(format!("hola_axum_{schema}"));
+        let uploads_dir = uploads_dir.to_string_lossy().into_owned();
+        let mut overrides = overrides.to_vec();
+        overrides.insert(0, ("UPLOADS_DIR", &uploads_dir));
+        let config = test_config(&url, &overrides);
+        let uploads = Arc::new(UploadsRoot::open(&config.uploads.dir));
+
+        let pool = db::connect(&config.db).await;
+        db::migrate(&pool).await;
+        let jobs = db::connect_jobs(&config.db);
+
+        Some(TestDb {
+            pool,
+            jobs,
+            config,
+            uploads,
+            admin,
+            schema,
+        })
+    }
+
+    /// La app pública completa, tal y como la monta `main`. Como en el
+    /// servidor, toda petición llega desde un socket: sin `from_ip`, el de un
+    /// cliente directo.
+    pub fn app(&self) -> Router {
+        let metrics = Arc::new(Metrics::new(&self.config.metrics));
+        let state = AppState::new(self.pool.clone(), self.jobs.clone(), self.config.clone(), self.uploads.clone(), metrics);
+        build_routers(&state, &None).0.layer(axum::middleware::map_request(direct_peer))
+    }
+
+    /// Borra el esquema y los ficheros que el test haya subido.
+    pub async fn finish(self) {
+        let _ = tokio::fs::remove_dir_all(self.uploads.dir()).await;
+
+        self.pool.close().await;
+        self.jobs.close().await;
+        sqlx::query(&format!("DROP SCHEMA {} CASCADE", self.schema))
+            .execute(&self.admin)
+            .await
+            .unwrap();
+    }
+}
+
+async fn direct_peer(mut req: Request<Body>) -> Request<Body> {
+    if req.extensions().get::<ConnectInfo<SocketAddr>>().is_none() {
+        req.extensions_mut().insert(ConnectInfo(SocketAddr::from(([192, 0, 2, 1], 40000))));
+    }
+    req
+}
+
+/* ---------- PETICIONES ---------- */
+
+/// Ejecuta la petición contra el router y devuelve estado y cuerpo como texto.
+pub async fn send(app: &Router, req: Request<Body>) -> (StatusCode, String) {
+    let res = app.clone().oneshot(req).await.unwrap();
+    let status = res.status();
+    let body = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+    (status, String::from_utf8_lossy(&body).into_owned())
+}
+
+pub fn get(uri: &str) -> Request<Body> {
+    Request::get(uri).body(Body::empty()).unwrap()
+}
+
+/// Petición `application/x-www-form-urlencoded` con los pares dados.
+pub fn form(method: Method, uri: &str, fields: &[(&str, &str)]) -> Request<Body> {
+    let body = serde_urlencoded::to_string(fields).unwrap();
+    Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .body(Body::from(body))
+        .unwrap()
+}
+
+/// Añade el token de administración.
+pub fn as_admin(mut req: Request<Body>) -> Request<Body> {
+    req.headers_mut().insert(
+        header::AUTHORIZATION,
+        format!("Bearer {ADMIN_TOKEN}").parse().unwrap(),
+    );
+    req
+}
+
+/// Simula la IP del cliente: llega por `X-Forwarded-For` desde un proxy de
+/// confianza (`TRUSTED_PROXIES` de `test_config`), porque en `oneshot` no hay socket.
+pub fn from_ip(mut req: Request<Body>, ip: &str) -> Request<Body> {
+    req.headers_mut()
+        .insert("x-forwarded-for", ip.parse().unwrap());
+    req.extensions_mut().insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 40000))));
+    req
+}
+
+/// Imagen de 1×1 en formato `ext` rellenada con ceros hasta `len` bytes: pasa
+/// la detección de tipo y la lectura de dimensiones, que solo miran la cabecera.
+pub fn image_bytes(ext: &str, len: usize) -> Vec<u8> {
+    let format = match ext {
+        "png" => image::ImageFormat::Png,
+        "jpg" => image::ImageFormat::Jpeg,
+        "webp" => image::ImageFormat::WebP,
+        other => panic!("formato de test desconocido: {other}"),
+    };
+    let mut bytes = std::io::Cursor::new(Vec::new());
+    image::RgbImage::new(1, 1).write_to(&mut bytes, format).unwrap();
+    let mut bytes = bytes.into_inner();
+    bytes.resize(len.max(bytes.len()), 0);
+    bytes
+}
+
+/// Constructor de cuerpos `multipart/form-data`.
+pub struct MultipartBuilder {
+    boundary: String,
+    body: Vec<u8>,
+}
+
+impl MultipartBuilder {
+    pub fn new() -> Self {
+        MultipartBuilder {
+            boundary: format!("test-{}", Uuid::new_v4().simple()),
+            body: Vec::new(),
+        }
+    }
+
+    pub fn text(mut self, name: &str, value: &str) -> Self {
+        self.body.extend_from_slice(
+            format!(
+                "--{}\r\nContent-Disposition: form-data; name=\"{name}\"\r\n\r\n{value}\r\n",
+                self.boundary
+            )
+            .as_bytes(),
+        );
+        self
+    }
+
+    pub fn file(mut self, name: &str, filename: &str, content_type: &str, bytes: &[u8]) -> Self {
+        self.body.extend_from_slice(
+            format!(
+                "--{}\r\nContent-Disposition: form-data; name=\"{name}\"; filename=\"{filename}\"\r\nContent-Type: {content_type}\r\n\r\n",
+                self.boundary
+            )
+            .as_bytes(),
+        );
+        self.body.extend_from_slice(bytes);
+        self.body.extend_from_slice(b"\r\n");
+        self
+    }
+
+    pub fn into_request(mut self, uri: &str) -> Request<Body> {
+        self.body
+            .extend_from_slice(format!("--{}--\r\n", self.boundary).as_bytes());
+        Request::post(uri)
+            .header(
+                header::CONTENT_TYPE,
+                format!("multipart/form-data; boundary={}", self.boundary),
+            )
+            .body(Body::from(self.body))
+            .unwrap()
+    }
+}
@@ -0,0 +1,173 @@
+//! Miniaturas de la galería: se generan en la primera petición, se guardan en
+//! `.thumbs/` dentro del directorio de subidas y a partir de ahí se sirven desde disco.
+//! Con subidas privadas piden la misma firma que el original (ver `signed_urls`).
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{Html, IntoResponse, Response},
+};
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use crate::conditional;
+use crate::queries;
+use crate::signed_urls::{self, SignatureQuery};
+use crate::state::SharedState;
+use crate::uploads::UploadsRoot;
+
+/// Lado mayor de la miniatura, en píxeles.
+const THUMB_SIZE: u32 = 320;
+
+/// Bloqueo por imagen: si llegan varias peticiones a la vez por una miniatura
+/// que no existe, solo la primera la genera y el resto espera y lee el fichero.
+#[derive(Default)]
+pub struct Thumbnails {
+    locks: Mutex<HashMap<i32, Arc<tokio::sync::Mutex<()>>>>,
+}
+
+impl Thumbnails {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Thumbnails::default())
+    }
+
+    fn lock_for(&self, id: i32) -> Arc<tokio::sync::Mutex<()>> {
+        self.locks.lock().unwrap().entry(id).or_default().clone()
+    }
+
+    fn release(&self, id: i32) {
+        let mut locks = self.locks.lock().unwrap();
+        // Solo queda la referencia del mapa: nadie más espera por esta imagen.
+        if locks.get(&id).is_some_and(|l| Arc::strong_count(l) == 1) {
+            locks.remove(&id);
+        }
+    }
+
+    /// Ruta de la miniatura, generándola si aún no existe.
+    async fn ensure(&self, root: &UploadsRoot, id: i32, filename: &str) -> Result<PathBuf, ThumbError> {
+        let (Ok(source), Ok(thumb)) = (root.file(filename), root.thumb(filename)) else {
+            return Err(ThumbError::Missing);
+        };
+        if tokio::fs::try_exists(&thumb).await.unwrap_or(false) {
+            return Ok(thumb);
+        }
+
+        let lock = self.lock_for(id);
+        let result = {
+            let _guard = lock.lock().await;
+            if tokio::fs::try_exists(&thumb).await.unwrap_or(false) {
+                Ok(thumb.clone())
+            } else {
+                let target = thumb.clone();
+                tokio::task::spawn_blocking(move || generate(&source, &target))
+                    .await
+                    .unwrap_or(Err(ThumbError::Generate))
+                    .map(|()| thumb)
+            }
+        };
+        drop(lock);
+        self.release(id);
+        result
+    }
+}
+
+/// Escribe en un temporal y renombra, para que nadie lea una miniatura a medias.
+fn generate(source: &std::path::Path, target: &std::path::Path) -> Result<(), ThumbError> {
+    if !source.exists() {
+        return Err(ThumbError::Missing);
+    }
+    let format = image::ImageFormat::from_path(source).map_err(|_| ThumbError::Generate)?;
+    let img = image::open(source).map_err(|_| ThumbError::Generate)?;
+    let thumb = img.thumbnail(THUMB_SIZE, THUMB_SIZE);
+
+    if let Some(dir) = target.parent() {
+        std::fs::create_dir_all(dir).map_err(|_| ThumbError::Generate)?;
+    }
+    let tmp = target.with_extension(format!("tmp-{}", uuid::Uuid::new_v4().simple()));
+    thumb
+        .save_with_format(&tmp, format)
+        .and_then(|()| std::fs::rename(&tmp, target).map_err(Into::into))
+        .map_err(|err| {
+            let _ = std::fs::remove_file(&tmp);
+            tracing::warn!(error = %err, path = %target.display(), "no se pudo guardar la miniatura");
+            ThumbError::Generate
+        })
+}
+
+enum ThumbError {
+    /// La imagen original no está en disco.
+    Missing,
+    Generate,
+}
+
+pub fn content_type(filename: &str) -> &'static str {
+    match filename.rsplit('.').next() {
+        Some("png") => "image/png",
+        Some("webp") => "image/webp",
+        _ => "image/jpeg",
+    }
+}
+
+/* ---------- GET /images/:id/thumb ---------- */
+
+pub async fn thumbnail(
+    State(app): State<SharedState>,
+    Path(id): Path<i32>,
+    Query(signature): Query<SignatureQuery>,
+) -> Response {
+    let filename = match queries::published_image(&app.db, id).await {
+        Ok(Some(filename)) => filename,
+        Ok(None) => return (StatusCode::NOT_FOUND, Html("❌ Imagen no encontrada")).into_response(),
+        Err(e) => return e.into_response(),
+    };
+    let private = app.config.uploads.private.as_ref();
+    if !signature.allows(private, &filename) {
+        return signed_urls::forbidden();
+    }
+
+    // Formatos sin el paso `thumb` en `UPLOAD_TYPES`: la galería recibe el original.
+    let ensured = if app.config.uploads.types.thumbnail(&filename) {
+        app.thumbs.ensure(&app.uploads, id, &filename).await
+    } else {
+        app.uploads.file(&filename).map_err(|_| ThumbError::Missing)
+    };
+
+    let path = match ensured {
+        Ok(path) => path,
+        Err(ThumbError::Missing) => {
+            return (StatusCode::NOT_FOUND, Html("❌ Imagen no encontrada")).into_response();
+        }
+        Err(ThumbError::Generate) => {
+            return (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Html("❌ No se pudo generar la miniatura"),
+            )
+                .into_response();
+        }
+    };
+
+    let modified = tokio::fs::metadata(&path).await.ok().and_then(|m| m.modified().ok());
+    match tokio::fs::read(&path).await {
+        // El contenido de una imagen no cambia nunca para un mismo id; en modo
+        // privado, como el original, no se guarda en ninguna caché.
+        Ok(bytes) => {
+            let cache = match private {
+                Some(_) => "private, no-store",
+                None => "public, max-age=31536000, immutable",
+            };
+            let mut res = (
+                [(header::CONTENT_TYPE, content_type(&filename)), (header::CACHE_CONTROL, cache)],
+                bytes,
+            )
+                .into_response();
+            if let Some(modified) = modified {
+                res.headers_mut().insert(header::LAST_MODIFIED, conditional::http_date(modified));
+            }
+            res
+        }
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    }
+}
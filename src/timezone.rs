@@ -0,0 +1,115 @@
+//! `?tz=Europe/Madrid` en las vistas HTML y en la exportación. Las fechas se
+//! guardan en UTC (`timestamptz`) y solo al pintarlas se pasan a la zona
+//! pedida. Las zonas y sus cambios de horario los pone Postgres
+//! (`pg_timezone_names`, `AT TIME ZONE`), así no hay otra copia de la base tz.
+
+use std::sync::Arc;
+
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Query},
+    http::{request::Parts, StatusCode},
+    response::{Html, IntoResponse, Response},
+};
+use chrono::{DateTime, FixedOffset, Offset, Utc};
+use serde::Deserialize;
+use sqlx::PgPool;
+
+use crate::db::DbError;
+use crate::queries;
+use crate::state::SharedState;
+
+#[derive(Deserialize, Default)]
+struct TzQuery {
+    #[serde(default)]
+    tz: String,
+}
+
+/// Zona de `?tz=`. Sin el parámetro (o vacío) las fechas salen en UTC, como
+/// siempre; una zona que Postgres no conoce es un 400.
+#[derive(Clone, Default)]
+pub struct Zone(Option<Arc<str>>);
+
+impl Zone {
+    /// Valor para conservar `tz` en enlaces y formularios; vacío en UTC.
+    pub fn query_value(&self) -> &str {
+        self.0.as_deref().unwrap_or("")
+    }
+
+    /// `None` si la zona no existe.
+    pub async fn resolve(pool: &PgPool, tz: &str) -> Result<Option<Zone>, DbError> {
+        let tz = tz.trim();
+        if tz.is_empty() || tz == "UTC" {
+            return Ok(Some(Zone::default()));
+        }
+        let exists = queries::timezone::exists(pool, tz).await?;
+        Ok(exists.then(|| Zone(Some(tz.into()))))
+    }
+
+    /// Las fechas en esta zona, en el mismo orden. En UTC no consulta nada;
+    /// si no, una sola consulta para todas con el desfase de cada una.
+    pub async fn localize(&self, pool: &PgPool, times: &[DateTime<Utc>]) -> Result<Vec<LocalTime>, DbError> {
+        let Some(tz) = &self.0 else {
+            return Ok(times.iter().map(|t| LocalTime::utc(*t)).collect());
+        };
+        let offsets = queries::timezone::offsets(pool, tz, times).await?;
+
+        Ok(times
+            .iter()
+            .zip(offsets)
+            .map(|(t, secs)| LocalTime {
+                time: t.with_timezone(&FixedOffset::east_opt(secs).unwrap_or(Utc.fix())),
+                zone: Some(tz.clone()),
+            })
+            .collect())
+    }
+
+    pub async fn localize_one(&self, pool: &PgPool, time: DateTime<Utc>) -> Result<LocalTime, DbError> {
+        Ok(self.localize(pool, &[time]).await?.remove(0))
+    }
+}
+
+#[async_trait]
+impl FromRequestParts<SharedState> for Zone {
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &SharedState) -> Result<Self, Self::Rejection> {
+        let Query(query) = Query::<TzQuery>::from_request_parts(parts, state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+        match Zone::resolve(&state.db, &query.tz).await {
+            Ok(Some(zone)) => Ok(zone),
+            Ok(None) => Err((StatusCode::BAD_REQUEST, Html("❌ Zona horaria desconocida")).into_response()),
+            Err(e) => Err(e.into_response()),
+        }
+    }
+}
+
+/// Fecha ya pasada a la zona de la petición, para las plantillas.
+#[derive(Clone)]
+pub struct LocalTime {
+    time: DateTime<FixedOffset>,
+    zone: Option<Arc<str>>,
+}
+
+impl LocalTime {
+    pub fn utc(time: DateTime<Utc>) -> Self {
+        LocalTime { time: time.fixed_offset(), zone: None }
+    }
+
+    /// Con el formato de chrono seguido de la zona: `15/10/2026 13:05 Europe/Madrid`.
+    pub fn format(&self, fmt: &str) -> String {
+        format!("{} {}", self.time.format(fmt), self.zone.as_deref().unwrap_or("UTC"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn utc_keeps_the_previous_format() {
+        let time = DateTime::parse_from_rfc3339("2026-07-01T10:30:00Z").unwrap().to_utc();
+        assert_eq!(LocalTime::utc(time).format("%d/%m/%Y %H:%M"), "01/07/2026 10:30 UTC");
+    }
+}
@@ -0,0 +1,110 @@
+//! HTTPS sin proxy: con `TLS_CERT` y `TLS_KEY` los listeners TCP de `LISTEN`
+//! hablan TLS (rustls, HTTP/2 o HTTP/1.1 según ALPN). Con
+//! `TLS_REDIRECT_LISTEN` se escucha además en HTTP solo para redirigir a HTTPS.
+
+use std::{fs, io, path::Path, sync::Arc};
+
+use axum::{
+    http::{header, HeaderMap, StatusCode, Uri},
+    response::{IntoResponse, Response},
+    Router,
+};
+use tokio_rustls::{
+    rustls::{
+        crypto::ring,
+        pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer},
+        ServerConfig,
+    },
+    TlsAcceptor,
+};
+
+use crate::config::TlsConfig;
+
+/// Certificado y clave de la configuración. Se llama al arrancar: un fichero
+/// que falta o no vale es un error de despliegue y aborta.
+pub fn acceptor(config: &TlsConfig) -> TlsAcceptor {
+    let certs = load_certs(&config.cert).unwrap_or_else(|e| panic!("TLS_CERT ({}): {e}", config.cert.display()));
+    let key = load_key(&config.key).unwrap_or_else(|e| panic!("TLS_KEY ({}): {e}", config.key.display()));
+
+    let mut server = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .and_then(|builder| builder.with_no_client_auth().with_single_cert(certs, key))
+        .unwrap_or_else(|e| panic!("certificado TLS inválido: {e}"));
+    server.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    TlsAcceptor::from(Arc::new(server))
+}
+
+fn load_certs(path: &Path) -> io::Result<Vec<CertificateDer<'static>>> {
+    let pem = fs::read(path)?;
+    let certs = CertificateDer::pem_slice_iter(&pem)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+    if certs.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "sin certificados"));
+    }
+    Ok(certs)
+}
+
+fn load_key(path: &Path) -> io::Result<PrivateKeyDer<'static>> {
+    let pem = fs::read(path)?;
+    PrivateKeyDer::from_pem_slice(&pem).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))
+}
+
+/// Router del listener HTTP: todo a la misma ruta en HTTPS. `https_port` es el
+/// del listener TLS, por si no es el 443.
+pub fn redirect_router(public_url: Option<String>, https_port: u16) -> Router {
+    Router::new().fallback(move |headers: HeaderMap, uri: Uri| {
+        let public_url = public_url.clone();
+        async move { redirect(public_url.as_deref(), https_port, &headers, &uri) }
+    })
+}
+
+fn redirect(public_url: Option<&str>, https_port: u16, headers: &HeaderMap, uri: &Uri) -> Response {
+    let path = uri.path_and_query().map_or("/", |p| p.as_str());
+    let base = match public_url {
+        Some(url) => url.trim_end_matches('/').to_string(),
+        None => {
+            let Some(host) = headers.get(header::HOST).and_then(|h| h.to_str().ok()) else {
+                return (StatusCode::BAD_REQUEST, "❌ Falta la cabecera Host").into_response();
+            };
+            let host = without_port(host);
+            if https_port == 443 { format!("https://{host}") } else { format!("https://{host}:{https_port}") }
+        }
+    };
+    (StatusCode::PERMANENT_REDIRECT, [(header::LOCATION, format!("{base}{path}"))]).into_response()
+}
+
+/// `Host` puede traer el puerto HTTP; el de HTTPS es otro.
+fn without_port(host: &str) -> &str {
+    if let Some(rest) = host.strip_prefix('[') {
+        return rest.split_once(']').map_or(host, |(addr, _)| &host[..addr.len() + 2]);
+    }
+    host.split_once(':').map_or(host, |(name, _)| name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn location(public_url: Option<&str>, port: u16, host: &str, uri: &str) -> String {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::HOST, host.parse().unwrap());
+        let res = redirect(public_url, port, &headers, &uri.parse().unwrap());
+        assert_eq!(res.status(), StatusCode::PERMANENT_REDIRECT);
+        res.headers()[header::LOCATION].to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn redirects_to_the_same_path_over_https() {
+        assert_eq!(location(None, 443, "motos.example:80", "/mensajes?page=2"), "https://motos.example/mensajes?page=2");
+        assert_eq!(location(None, 8443, "motos.example", "/"), "https://motos.example:8443/");
+        assert_eq!(location(None, 443, "[::1]:8080", "/a"), "https://[::1]/a");
+        assert_eq!(location(Some("https://motos.example/"), 8443, "otro:80", "/a"), "https://motos.example/a");
+    }
+
+    #[test]
+    fn missing_files_are_reported() {
+        assert!(load_certs(Path::new("/nonexistent/cert.pem")).is_err());
+        assert!(load_key(Path::new("/nonexistent/key.pem")).is_err());
+    }
+}
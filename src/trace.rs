@@ -0,0 +1,207 @@
+//! Trazas por petición: a las peticiones muestreadas se les abre un span
+//! `request` con su `trace_id` (formato W3C `traceparent`), de modo que todos
+//! los eventos que generan quedan agrupados y al cerrarse se registra su
+//! duración. Las no muestreadas no crean span, así que apenas cuestan nada.
+//!
+//! Con `X-Debug-Trace: <TRACE_DEBUG_TOKEN>` se fuerza la traza de una petición
+//! concreta y además se bajan sus eventos a nivel DEBUG (consultas SQL incluidas).
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::{HeaderMap, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use std::{sync::Arc, time::Instant};
+use tracing::Instrument;
+
+use crate::config::TraceConfig;
+
+const TRACEPARENT: &str = "traceparent";
+const DEBUG_HEADER: &str = "x-debug-trace";
+
+/// Directiva de `EnvFilter` que activa DEBUG dentro de las trazas forzadas.
+pub const FORCED_DIRECTIVE: &str = "[request{forced=true}]=debug";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Sampler {
+    Always,
+    Never,
+    /// Una fracción (`TRACE_SAMPLE_RATIO`) de las peticiones, decidida por el `trace_id`.
+    Ratio,
+    /// Respeta la decisión de quien llama (`traceparent`); sin ella, como `Ratio`.
+    ParentBased,
+}
+
+impl std::str::FromStr for Sampler {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "always" => Ok(Sampler::Always),
+            "never" => Ok(Sampler::Never),
+            "ratio" => Ok(Sampler::Ratio),
+            "parent" => Ok(Sampler::ParentBased),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Contexto W3C de quien llama: `00-<trace_id>-<parent_id>-<flags>`.
+struct Parent {
+    trace_id: String,
+    sampled: bool,
+}
+
+fn parse_traceparent(value: &str) -> Option<Parent> {
+    let mut parts = value.trim().split('-');
+    let (version, trace_id, parent_id, flags) = (parts.next()?, parts.next()?, parts.next()?, parts.next()?);
+    let hex = |s: &str, len: usize| s.len() == len && s.bytes().all(|b| b.is_ascii_hexdigit());
+    if version != "00" || !hex(trace_id, 32) || !hex(parent_id, 16) || !hex(flags, 2) {
+        return None;
+    }
+    if trace_id.bytes().all(|b| b == b'0') || parent_id.bytes().all(|b| b == b'0') {
+        return None;
+    }
+    let flags = u8::from_str_radix(flags, 16).ok()?;
+    Some(Parent {
+        trace_id: trace_id.to_ascii_lowercase(),
+        sampled: flags & 1 == 1,
+    })
+}
+
+/// Decisión por `trace_id`: la misma traza da lo mismo en todos los servicios.
+fn ratio_sampled(trace_id: &str, ratio: f64) -> bool {
+    let Ok(head) = u64::from_str_radix(&trace_id[16..], 16) else {
+        return false;
+    };
+    (head as f64) < ratio.clamp(0.0, 1.0) * u64::MAX as f64
+}
+
+fn decide(config: &TraceConfig, parent: Option<&Parent>, trace_id: &str) -> bool {
+    match config.sampler {
+        Sampler::Always => true,
+        Sampler::Never => false,
+        Sampler::Ratio => ratio_sampled(trace_id, config.ratio),
+        Sampler::ParentBased => match parent {
+            Some(parent) => parent.sampled,
+            None => ratio_sampled(trace_id, config.ratio),
+        },
+    }
+}
+
+fn forced(config: &TraceConfig, headers: &HeaderMap) -> bool {
+    let given = headers.get(DEBUG_HEADER).and_then(|v| v.to_str().ok());
+    matches!((config.debug_token.as_deref(), given), (Some(token), Some(given)) if token == given)
+}
+
+fn new_id(len: usize) -> String {
+    uuid::Uuid::new_v4().simple().to_string()[..len].to_string()
+}
+
+/* ---------- MIDDLEWARE ---------- */
+
+pub async fn trace(State(config): State<Arc<TraceConfig>>, req: Request, next: Next) -> Response {
+    let parent = req
+        .headers()
+        .get(TRACEPARENT)
+        .and_then(|v| v.to_str().ok())
+        .and_then(parse_traceparent);
+    let trace_id = parent.as_ref().map_or_else(|| new_id(32), |p| p.trace_id.clone());
+    let forced = forced(&config, req.headers());
+    let sampled = forced || decide(&config, parent.as_ref(), &trace_id);
+
+    let span_id = new_id(16);
+    let traceparent = format!("00-{trace_id}-{span_id}-{}", if sampled { "01" } else { "00" });
+
+    let mut res = if sampled {
+        let route = req
+            .extensions()
+            .get::<MatchedPath>()
+            .map_or_else(|| req.uri().path().to_string(), |p| p.as_str().to_string());
+        let span = tracing::info_span!(
+            "request",
+            trace_id = %trace_id,
+            span_id = %span_id,
+            method = %req.method(),
+            route = %route,
+            forced,
+        );
+        let start = Instant::now();
+        async move {
+            let res = next.run(req).await;
+            tracing::info!(
+                status = res.status().as_u16(),
+                elapsed_ms = start.elapsed().as_secs_f64() * 1000.0,
+                "petición trazada"
+            );
+            res
+        }
+        .instrument(span)
+        .await
+    } else {
+        next.run(req).await
+    };
+
+    if let Ok(value) = HeaderValue::from_str(&traceparent) {
+        res.headers_mut().insert(TRACEPARENT, value);
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(sampler: Sampler, ratio: f64) -> TraceConfig {
+        TraceConfig {
+            sampler,
+            ratio,
+            debug_token: Some("secreto".to_string()),
+        }
+    }
+
+    #[test]
+    fn samplers_follow_their_rule() {
+        let sampled = parse_traceparent("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01").unwrap();
+        let unsampled = parse_traceparent("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-00").unwrap();
+        let id = "4bf92f3577b34da6a3ce929d0e0e4736";
+
+        assert!(decide(&config(Sampler::Always, 0.0), None, id));
+        assert!(!decide(&config(Sampler::Never, 1.0), Some(&sampled), id));
+        assert!(decide(&config(Sampler::Ratio, 1.0), None, id));
+        assert!(!decide(&config(Sampler::Ratio, 0.0), None, id));
+        assert!(decide(&config(Sampler::ParentBased, 0.0), Some(&sampled), id));
+        assert!(!decide(&config(Sampler::ParentBased, 1.0), Some(&unsampled), id));
+        assert!(decide(&config(Sampler::ParentBased, 1.0), None, id));
+    }
+
+    #[test]
+    fn rejects_malformed_traceparent() {
+        for bad in [
+            "",
+            "01-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e473-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+            "00-zzf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+        ] {
+            assert!(parse_traceparent(bad).is_none(), "{bad:?}");
+        }
+    }
+
+    #[test]
+    fn debug_header_needs_the_token() {
+        let mut headers = HeaderMap::new();
+        assert!(!forced(&config(Sampler::Never, 0.0), &headers));
+
+        headers.insert(DEBUG_HEADER, "otro".parse().unwrap());
+        assert!(!forced(&config(Sampler::Never, 0.0), &headers));
+
+        headers.insert(DEBUG_HEADER, "secreto".parse().unwrap());
+        assert!(forced(&config(Sampler::Never, 0.0), &headers));
+
+        let no_token = TraceConfig { debug_token: None, ..config(Sampler::Never, 0.0) };
+        assert!(!forced(&no_token, &headers));
+    }
+}
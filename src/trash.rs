@@ -0,0 +1,111 @@
+//! Papelera de imágenes: el registro queda marcado con `deleted_at` y el fichero
+//! pasa a `.trash/` dentro del directorio de subidas hasta que se restaura o
+//! vence la retención.
+
+use axum::{
+    extract::{Query, State},
+    response::{Html, IntoResponse, Response},
+};
+use sqlx::PgPool;
+use std::{io, path::PathBuf, sync::Arc, time::Duration};
+
+use crate::audit_log::{self, Change};
+use crate::db::DbError;
+use crate::dry_run::{self, DryRunQuery, Preview};
+use crate::policy::{self, Action, Principal, Resource};
+use crate::queries;
+use crate::state::SharedState;
+use crate::uploads::UploadsRoot;
+
+/// Cada cuánto se buscan imágenes con la retención vencida.
+const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+fn outside_root() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, "ruta fuera del directorio de subidas")
+}
+
+/// Mueve el fichero a la papelera. Si no existe (ya se perdió) no es un error.
+pub async fn move_to_trash(root: &UploadsRoot, filename: &str) -> io::Result<()> {
+    let from = root.file(filename).map_err(|_| outside_root())?;
+    let to = root.trash(filename).map_err(|_| outside_root())?;
+    if let Some(dir) = to.parent() {
+        tokio::fs::create_dir_all(dir).await?;
+    }
+    rename_if_exists(from, to).await
+}
+
+pub async fn move_from_trash(root: &UploadsRoot, filename: &str) -> io::Result<()> {
+    let from = root.trash(filename).map_err(|_| outside_root())?;
+    let to = root.file(filename).map_err(|_| outside_root())?;
+    rename_if_exists(from, to).await
+}
+
+async fn rename_if_exists(from: PathBuf, to: PathBuf) -> io::Result<()> {
+    match tokio::fs::rename(&from, &to).await {
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+        other => other,
+    }
+}
+
+/// Borra definitivamente las imágenes que llevan más de `retention` en la papelera.
+pub async fn purge_loop(pool: PgPool, root: Arc<UploadsRoot>, retention: Duration) {
+    let mut interval = tokio::time::interval(PURGE_INTERVAL);
+    loop {
+        interval.tick().await;
+        match purge(&pool, &root, retention).await {
+            Ok(0) => {}
+            Ok(n) => tracing::info!(purged = n, "papelera de imágenes purgada"),
+            Err(err) => tracing::warn!(error = ?err, "no se pudo purgar la papelera"),
+        }
+    }
+}
+
+async fn purge(pool: &PgPool, root: &UploadsRoot, retention: Duration) -> Result<usize, DbError> {
+    let purged = queries::trash::purge(pool, retention).await?;
+
+    for filename in &purged {
+        let (Ok(trashed), Ok(thumb)) = (root.trash(filename), root.thumb(filename)) else {
+            continue;
+        };
+        if let Err(err) = tokio::fs::remove_file(trashed).await
+            && err.kind() != io::ErrorKind::NotFound
+        {
+            tracing::warn!(error = %err, filename, "no se pudo borrar la imagen purgada");
+        }
+        let _ = tokio::fs::remove_file(thumb).await;
+    }
+    Ok(purged.len())
+}
+
+/// `POST /api/admin/images/trash/purge`: la misma purga que el bucle, sin esperar a la próxima vuelta.
+pub async fn purge_now(
+    State(app): State<SharedState>,
+    principal: Principal,
+    Query(dry): Query<DryRunQuery>,
+) -> Response {
+    if let Err(e) = policy::authorize(&principal, Action::Delete, &Resource::Image) {
+        return e.into_response();
+    }
+    let retention = app.config.uploads.trash_retention;
+
+    if dry.dry_run {
+        return match expired(&app.jobs_db, retention).await {
+            Ok((count, ids)) => Preview::new(count, ids).into_response(),
+            Err(e) => e.into_response(),
+        };
+    }
+
+    match purge(&app.jobs_db, &app.uploads, retention).await {
+        Ok(n) => {
+            let change = Change::new("image.purge", "trash").new_value(serde_json::json!({ "purged": n }));
+            audit_log::record_change(&app.db, &principal, change).await;
+            Html(format!("✅ {n} imágenes purgadas")).into_response()
+        }
+        Err(e) => e.into_response(),
+    }
+}
+
+/// Cuántas imágenes borraría `purge` y los ids de las primeras.
+async fn expired(pool: &PgPool, retention: Duration) -> Result<(i64, Vec<i32>), DbError> {
+    queries::trash::expired(pool, retention, dry_run::SAMPLE as i64).await
+}
@@ -0,0 +1,57 @@
+//! Unidad de trabajo: una transacción que el handler abre y pasa a las funciones
+//! de acceso a datos (`&mut PgConnection`) para combinar varias escrituras de forma
+//! atómica. Si no se llama a `commit`, al soltarla se deshace todo.
+
+use sqlx::{PgConnection, PgPool, Postgres, Transaction};
+
+use crate::db::{self, DbError};
+
+pub struct UnitOfWork {
+    tx: Transaction<'static, Postgres>,
+}
+
+impl UnitOfWork {
+    pub async fn begin(pool: &PgPool) -> Result<Self, DbError> {
+        let tx = db::timed("tx.begin", String::new, pool.begin()).await?;
+        Ok(UnitOfWork { tx })
+    }
+
+    /// Conexión de la transacción, para las funciones de acceso a datos.
+    pub fn conn(&mut self) -> &mut PgConnection {
+        &mut self.tx
+    }
+
+    pub async fn commit(self) -> Result<(), DbError> {
+        db::timed("tx.commit", String::new, self.tx.commit()).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestDb;
+
+    async fn count(pool: &PgPool) -> i64 {
+        sqlx::query_scalar("SELECT count(*) FROM mensajes").fetch_one(pool).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn only_committed_work_is_kept() {
+        let Some(db) = TestDb::new().await else { return };
+        let insert = "INSERT INTO mensajes (nombre, mensaje) VALUES ('Ana', 'Mensaje de prueba')";
+
+        let mut uow = UnitOfWork::begin(&db.pool).await.unwrap();
+        sqlx::query(insert).execute(uow.conn()).await.unwrap();
+        sqlx::query(insert).execute(uow.conn()).await.unwrap();
+        drop(uow);
+        assert_eq!(count(&db.pool).await, 0);
+
+        let mut uow = UnitOfWork::begin(&db.pool).await.unwrap();
+        sqlx::query(insert).execute(uow.conn()).await.unwrap();
+        uow.commit().await.unwrap();
+        assert_eq!(count(&db.pool).await, 1);
+
+        db.finish().await;
+    }
+}
@@ -0,0 +1,292 @@
+//! Camino de una imagen subida, por etapas: recibir (el handler: multipart o
+//! descarga por URL) → detectar el formato → política (tipo, bytes, píxeles)
+//! → análisis → transformar → guardar y registrar → eventos. Las etapas
+//! intermedias son `Stage` y se montan al arrancar (`Pipeline::new`), así que
+//! un antivirus o una conversión nueva es una etapa más y no otro handler.
+//! Guardar y registrar van juntos: cuota, fichero y fila se confirman en la
+//! misma transacción.
+
+use axum::async_trait;
+use sqlx::PgPool;
+use tokio::io::AsyncWriteExt;
+use uuid::Uuid;
+
+use crate::config::UploadsConfig;
+use crate::events::{self, Event};
+use crate::file_types::{self, FileTypePolicy};
+use crate::outbox;
+use crate::policy::{self, Action, Principal, Resource};
+use crate::queries;
+use crate::quota::{self, Exceeded};
+use crate::state::AppState;
+use crate::unit_of_work::UnitOfWork;
+use crate::uploads::{self, DimensionLimits, UploadsRoot};
+
+pub enum UploadError {
+    Invalid(&'static str),
+    /// Pasa del máximo de su tipo, en bytes.
+    TooLarge(usize),
+    /// Pasa del máximo en píxeles (`UploadsConfig::dimensions`).
+    TooManyPixels(DimensionLimits),
+    Quota(Exceeded),
+}
+
+impl UploadError {
+    pub fn too_large(max_bytes: usize) -> String {
+        format!("❌ Imagen demasiado grande (máx {})", file_types::human_size(max_bytes))
+    }
+
+    pub fn too_many_pixels(limits: DimensionLimits) -> String {
+        format!(
+            "❌ Imagen demasiado grande (máx {}×{} píxeles, {} megapíxeles)",
+            limits.max_width,
+            limits.max_height,
+            limits.max_pixels as f64 / 1_000_000.0
+        )
+    }
+}
+
+/// Lo que pasa de una etapa a la siguiente.
+pub struct Upload {
+    /// Tipo que declara el cliente.
+    pub mime: String,
+    /// El nombre que manda el cliente; tras `Sniff`, ya normalizado.
+    pub name: String,
+    pub bytes: Vec<u8>,
+    /// Extensión según el contenido; la pone `Sniff`.
+    pub extension: &'static str,
+}
+
+impl Upload {
+    pub fn new(mime: String, name: String, bytes: Vec<u8>) -> Self {
+        Upload { mime, name, bytes, extension: "" }
+    }
+}
+
+#[async_trait]
+pub trait Stage: Send + Sync {
+    /// Comprueba o transforma la subida; un error la descarta.
+    async fn run(&self, upload: &mut Upload) -> Result<(), UploadError>;
+}
+
+pub struct Pipeline {
+    stages: Vec<Box<dyn Stage>>,
+    config: UploadsConfig,
+}
+
+impl Pipeline {
+    /// Las etapas de siempre, según `UPLOAD_TYPES` y los límites de tamaño.
+    pub fn new(config: &UploadsConfig) -> Self {
+        Pipeline { stages: Vec::new(), config: config.clone() }
+            .with_stage(Sniff)
+            .with_stage(Policy { types: config.types.clone(), dimensions: config.dimensions })
+            .with_stage(Transcode { types: config.types.clone() })
+    }
+
+    /// Añade una etapa tras las que ya hay, antes de guardar.
+    pub fn with_stage(mut self, stage: impl Stage + 'static) -> Self {
+        self.stages.push(Box::new(stage));
+        self
+    }
+
+    /// Común a las subidas directas y por URL. Devuelve el id.
+    pub async fn store(
+        &self,
+        pool: &PgPool,
+        uploads: &UploadsRoot,
+        uploader: &Uploader,
+        mut upload: Upload,
+    ) -> Result<i32, UploadError> {
+        self.check(&mut upload).await?;
+        self.persist(pool, uploads, uploader, &upload).await
+    }
+
+    /// Todas las etapas, en orden, hasta la primera que falle.
+    async fn check(&self, upload: &mut Upload) -> Result<(), UploadError> {
+        for stage in &self.stages {
+            stage.run(upload).await?;
+        }
+        Ok(())
+    }
+
+    async fn persist(
+        &self,
+        pool: &PgPool,
+        uploads: &UploadsRoot,
+        uploader: &Uploader,
+        upload: &Upload,
+    ) -> Result<i32, UploadError> {
+        let Ok(mut uow) = UnitOfWork::begin(pool).await else {
+            return Err(UploadError::Invalid("❌ No se pudo guardar la imagen"));
+        };
+
+        if let Some(identity) = &uploader.identity {
+            match quota::reserve(uow.conn(), &self.config, identity, upload.bytes.len() as u64).await {
+                Ok(true) => {}
+                Ok(false) => return Err(UploadError::Quota(Exceeded::now())),
+                Err(_) => return Err(UploadError::Invalid("❌ No se pudo guardar la imagen")),
+            }
+        }
+
+        let filename = format!("{}.{}", Uuid::new_v4(), upload.extension);
+        let path = if uploader.approved { uploads.file(&filename) } else { uploads.pending(&filename) };
+        let Ok(path) = path else {
+            return Err(UploadError::Invalid("❌ No se pudo guardar la imagen"));
+        };
+
+        // Cuota, registro y aviso (ver `outbox`) se confirman juntos y solo con
+        // el fichero ya escrito; si algo falla, la transacción se deshace al soltarla.
+        if let Some(dir) = path.parent()
+            && tokio::fs::create_dir_all(dir).await.is_ok()
+            && let Ok(mut file) = tokio::fs::File::create(&path).await
+            && file.write_all(&upload.bytes).await.is_ok()
+            && let Ok(id) =
+                queries::insert_image(uow.conn(), &filename, upload.bytes.len() as i64, &upload.name, uploader.approved).await
+            && (!uploader.approved || outbox::enqueue(uow.conn(), &Event::ImageUploaded { id }).await.is_ok())
+            && uow.commit().await.is_ok()
+        {
+            return Ok(id);
+        }
+
+        let _ = tokio::fs::remove_file(&path).await;
+        Err(UploadError::Invalid("❌ No se pudo guardar la imagen"))
+    }
+}
+
+/* ---------- Etapas ---------- */
+
+/// La extensión guardada sale del contenido, no de lo que diga el cliente.
+struct Sniff;
+
+#[async_trait]
+impl Stage for Sniff {
+    async fn run(&self, upload: &mut Upload) -> Result<(), UploadError> {
+        let Some(extension) = uploads::sniff(&upload.bytes) else {
+            return Err(UploadError::Invalid("❌ El contenido no es una imagen válida"));
+        };
+        if uploads::extension_for_mime(&upload.mime) != Some(extension) {
+            return Err(UploadError::Invalid("❌ El tipo declarado no coincide con el contenido"));
+        }
+        let Ok(name) = uploads::original_name(&upload.name, extension) else {
+            return Err(UploadError::Invalid("❌ La extensión del archivo no coincide con su contenido"));
+        };
+        upload.extension = extension;
+        upload.name = name;
+        Ok(())
+    }
+}
+
+/// Tipo admitido, bytes de su tipo y píxeles. Los píxeles salen de la cabecera,
+/// antes de decodificar nada (conversión, miniatura).
+struct Policy {
+    types: FileTypePolicy,
+    dimensions: DimensionLimits,
+}
+
+#[async_trait]
+impl Stage for Policy {
+    async fn run(&self, upload: &mut Upload) -> Result<(), UploadError> {
+        let Some(file_type) = self.types.for_ext(upload.extension) else {
+            return Err(UploadError::Invalid("❌ Tipo de archivo no permitido"));
+        };
+        if upload.bytes.len() > file_type.max_bytes {
+            return Err(UploadError::TooLarge(file_type.max_bytes));
+        }
+        match uploads::dimensions(&upload.bytes) {
+            None => Err(UploadError::Invalid("❌ El contenido no es una imagen válida")),
+            Some(size) if !self.dimensions.allows(size) => Err(UploadError::TooManyPixels(self.dimensions)),
+            Some(_) => Ok(()),
+        }
+    }
+}
+
+/// Paso `to-<formato>` de la política: se guarda ya convertida.
+struct Transcode {
+    types: FileTypePolicy,
+}
+
+#[async_trait]
+impl Stage for Transcode {
+    async fn run(&self, upload: &mut Upload) -> Result<(), UploadError> {
+        let to = self.types.for_ext(upload.extension).and_then(|t| t.transcode);
+        let Some(to) = to.filter(|to| *to != upload.extension) else { return Ok(()) };
+
+        let source = std::mem::take(&mut upload.bytes);
+        let Ok(Some(converted)) = tokio::task::spawn_blocking(move || file_types::transcode(&source, to)).await else {
+            return Err(UploadError::Invalid("❌ No se pudo convertir la imagen"));
+        };
+        upload.bytes = converted;
+        upload.name = file_types::with_extension(&upload.name, to);
+        upload.extension = to;
+        Ok(())
+    }
+}
+
+/* ---------- Quién sube y eventos ---------- */
+
+/// Quién sube: identidad para la cuota y si se publica sin pasar por revisión
+/// (solo administración; ver `image_review`).
+pub struct Uploader {
+    identity: Option<String>,
+    approved: bool,
+}
+
+impl Uploader {
+    pub fn new(principal: &Principal) -> Self {
+        Uploader {
+            identity: quota::identity(principal),
+            approved: policy::can(principal, Action::Publish, &Resource::Image),
+        }
+    }
+
+    /// Lo publicado sin revisión ya es visible: se avisa como `ImageUploaded`.
+    pub fn published(&self, app: &AppState, ids: impl IntoIterator<Item = i32>) {
+        if self.approved {
+            for id in ids {
+                events::publish(app, Event::ImageUploaded { id });
+            }
+        }
+    }
+
+    pub fn uploaded_message(&self) -> &'static str {
+        if self.approved {
+            "✅ Imagen subida correctamente"
+        } else {
+            "✅ Imagen subida; se publicará cuando se revise"
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{image_bytes, test_config};
+
+    /// Un análisis enchufado al final, como lo haría un antivirus.
+    struct RejectAll;
+
+    #[async_trait]
+    impl Stage for RejectAll {
+        async fn run(&self, _: &mut Upload) -> Result<(), UploadError> {
+            Err(UploadError::Invalid("❌ Rechazada por el análisis"))
+        }
+    }
+
+    #[tokio::test]
+    async fn stages_run_in_order_and_can_be_added() {
+        let config = test_config("", &[("UPLOAD_TYPES", "png:1M:to-jpg,jpg:1M")]);
+        let pipeline = Pipeline::new(&config.uploads);
+
+        let mut upload = Upload::new("image/png".into(), "Moto Roja.png".into(), image_bytes("png", 100));
+        assert!(pipeline.check(&mut upload).await.is_ok());
+        assert_eq!((upload.extension, upload.name.as_str()), ("jpg", "Moto_Roja.jpg"));
+        assert_eq!(uploads::sniff(&upload.bytes), Some("jpg"));
+
+        let mut upload = Upload::new("image/jpeg".into(), "a.png".into(), image_bytes("png", 100));
+        assert!(matches!(pipeline.check(&mut upload).await, Err(UploadError::Invalid(msg)) if msg.contains("no coincide")));
+
+        let pipeline = Pipeline::new(&config.uploads).with_stage(RejectAll);
+        let mut upload = Upload::new("image/jpeg".into(), "a.jpg".into(), image_bytes("jpg", 100));
+        assert!(matches!(pipeline.check(&mut upload).await, Err(UploadError::Invalid(msg)) if msg.contains("análisis")));
+    }
+}
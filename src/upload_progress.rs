@@ -0,0 +1,200 @@
+//! Progreso de subidas en el servidor (recepción y procesado), publicado por
+//! WebSocket en `/ws/uploads/:id` para que la UI pueda seguirlo más allá de la
+//! fase de red.
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocketUpgrade},
+        Path, State,
+    },
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::sync::watch;
+use uuid::Uuid;
+
+use crate::state::SharedState;
+
+/// Vida de un id de subida; pasado este tiempo se descarta aunque no haya terminado.
+const TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Ids pendientes como máximo; evita que emitir ids sin subir nada llene la memoria.
+const MAX_PENDING: usize = 10_000;
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "stage", rename_all = "snake_case")]
+pub enum Progress {
+    /// Id emitido, la subida aún no ha empezado.
+    Pending,
+    /// `total` es el tamaño declarado de la petición, si se conoce.
+    Receiving { received: u64, total: Option<u64> },
+    /// Fichero recibido; se está guardando.
+    Processing,
+    Done,
+    Failed { error: String },
+}
+
+impl Progress {
+    fn is_finished(&self) -> bool {
+        matches!(self, Progress::Done | Progress::Failed { .. })
+    }
+}
+
+pub struct UploadProgress {
+    channels: Mutex<HashMap<Uuid, Channel>>,
+}
+
+struct Channel {
+    tx: Arc<watch::Sender<Progress>>,
+    issued: Instant,
+}
+
+impl UploadProgress {
+    pub fn new() -> Arc<Self> {
+        Arc::new(UploadProgress {
+            channels: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Reserva un id nuevo; `None` si hay demasiadas subidas pendientes.
+    pub fn issue(&self) -> Option<Uuid> {
+        let mut channels = self.channels.lock().unwrap();
+        channels.retain(|_, c| c.issued.elapsed() < TTL);
+
+        if channels.len() >= MAX_PENDING {
+            return None;
+        }
+
+        let id = Uuid::new_v4();
+        let (tx, _) = watch::channel(Progress::Pending);
+        channels.insert(
+            id,
+            Channel {
+                tx: Arc::new(tx),
+                issued: Instant::now(),
+            },
+        );
+        Some(id)
+    }
+
+    /// Publicador para la subida `id`; sin id (o desconocido) no publica nada.
+    pub fn reporter(&self, id: Option<Uuid>) -> Reporter {
+        let channels = self.channels.lock().unwrap();
+        Reporter(id.and_then(|id| channels.get(&id)).map(|c| c.tx.clone()))
+    }
+
+    pub fn subscribe(&self, id: Uuid) -> Option<watch::Receiver<Progress>> {
+        let channels = self.channels.lock().unwrap();
+        channels.get(&id).map(|c| c.tx.subscribe())
+    }
+}
+
+/// Lado del handler de subida.
+pub struct Reporter(Option<Arc<watch::Sender<Progress>>>);
+
+impl Reporter {
+    pub fn receiving(&self, received: u64, total: Option<u64>) {
+        self.set(Progress::Receiving { received, total });
+    }
+
+    pub fn processing(&self) {
+        self.set(Progress::Processing);
+    }
+
+    pub fn done(&self) {
+        self.set(Progress::Done);
+    }
+
+    pub fn failed(&self, error: &str) {
+        self.set(Progress::Failed {
+            error: error.trim_start_matches("❌ ").to_string(),
+        });
+    }
+
+    fn set(&self, progress: Progress) {
+        if let Some(tx) = &self.0 {
+            tx.send_replace(progress);
+        }
+    }
+}
+
+/* ---------- HANDLERS ---------- */
+
+#[derive(Serialize)]
+pub struct Issued {
+    upload_id: Uuid,
+}
+
+pub async fn issue(State(app): State<SharedState>) -> Response {
+    match app.progress.issue() {
+        Some(upload_id) => Json(Issued { upload_id }).into_response(),
+        None => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Html("❌ Demasiadas subidas en curso, inténtalo más tarde"),
+        )
+            .into_response(),
+    }
+}
+
+/// Envía el estado actual y cada cambio posterior; cierra al terminar la subida.
+pub async fn progress_ws(
+    State(app): State<SharedState>,
+    Path(id): Path<Uuid>,
+    ws: WebSocketUpgrade,
+) -> Response {
+    let Some(mut rx) = app.progress.subscribe(id) else {
+        return (StatusCode::NOT_FOUND, Html("❌ Subida no encontrada")).into_response();
+    };
+
+    ws.on_upgrade(move |mut socket| async move {
+        loop {
+            let current = rx.borrow_and_update().clone();
+            let event = serde_json::to_string(&current).unwrap();
+
+            if socket.send(Message::Text(event)).await.is_err() {
+                return;
+            }
+            if current.is_finished() || rx.changed().await.is_err() {
+                break;
+            }
+        }
+        let _ = socket.send(Message::Close(None)).await;
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subscriber_sees_latest_stage() {
+        let progress = UploadProgress::new();
+        let id = progress.issue().unwrap();
+        let rx = progress.subscribe(id).unwrap();
+        assert_eq!(*rx.borrow(), Progress::Pending);
+
+        let reporter = progress.reporter(Some(id));
+        reporter.receiving(10, Some(100));
+        reporter.failed("❌ Imagen demasiado grande");
+
+        assert_eq!(
+            *rx.borrow(),
+            Progress::Failed { error: "Imagen demasiado grande".to_string() }
+        );
+    }
+
+    #[test]
+    fn unknown_upload_has_no_channel() {
+        let progress = UploadProgress::new();
+        assert!(progress.subscribe(Uuid::new_v4()).is_none());
+        // Sin canal el reporter no hace nada.
+        progress.reporter(Some(Uuid::new_v4())).done();
+    }
+}
@@ -0,0 +1,240 @@
+//! Ficheros subidos: directorio raíz, tipo real según el contenido, tamaño en
+//! píxeles y nombre original saneado.
+
+use std::{
+    io::Cursor,
+    path::{Component, Path, PathBuf},
+};
+
+/// Longitud máxima (en caracteres) del nombre original guardado, sin extensión.
+const MAX_STEM_CHARS: usize = 100;
+
+const TRASH: &str = ".trash";
+const THUMBS: &str = ".thumbs";
+const PENDING: &str = ".pending";
+
+/// Directorio de subidas, ya canonicalizado. Toda ruta a un fichero subido se
+/// construye aquí y se comprueba que no sale de él.
+#[derive(Debug)]
+pub struct UploadsRoot {
+    dir: PathBuf,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct OutsideRoot;
+
+impl UploadsRoot {
+    /// Crea el directorio si no existe y lo canonicaliza; un fallo aborta el arranque.
+    pub fn open(dir: &Path) -> Self {
+        std::fs::create_dir_all(dir).expect("no se pudo crear el directorio de subidas");
+        let dir = dir
+            .canonicalize()
+            .expect("no se pudo resolver el directorio de subidas");
+        UploadsRoot { dir }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// La raíz y los subdirectorios donde acaban ficheros subidos.
+    pub fn dirs(&self) -> [PathBuf; 4] {
+        [self.dir.clone(), self.dir.join(PENDING), self.dir.join(TRASH), self.dir.join(THUMBS)]
+    }
+
+    pub fn file(&self, name: &str) -> Result<PathBuf, OutsideRoot> {
+        resolve(&self.dir, name)
+    }
+
+    /// Imágenes borradas pendientes de purga.
+    pub fn trash(&self, name: &str) -> Result<PathBuf, OutsideRoot> {
+        resolve(&self.dir.join(TRASH), name)
+    }
+
+    pub fn thumb(&self, name: &str) -> Result<PathBuf, OutsideRoot> {
+        resolve(&self.dir.join(THUMBS), name)
+    }
+
+    /// Imágenes subidas pendientes de revisión; no se sirven en `/uploads`.
+    pub fn pending(&self, name: &str) -> Result<PathBuf, OutsideRoot> {
+        resolve(&self.dir.join(PENDING), name)
+    }
+}
+
+/// `name` tiene que ser un único componente normal: nada de `..`, rutas
+/// absolutas ni subcarpetas.
+fn resolve(base: &Path, name: &str) -> Result<PathBuf, OutsideRoot> {
+    if name.contains(['/', '\\']) {
+        return Err(OutsideRoot);
+    }
+
+    let mut components = Path::new(name).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(_)), None) => {}
+        _ => return Err(OutsideRoot),
+    }
+
+    let path = base.join(name);
+    if path.parent() == Some(base) {
+        Ok(path)
+    } else {
+        Err(OutsideRoot)
+    }
+}
+
+/// Extensión que corresponde al contenido, si es uno de los formatos admitidos.
+pub fn sniff(bytes: &[u8]) -> Option<&'static str> {
+    match image::guess_format(bytes).ok()? {
+        image::ImageFormat::Jpeg => Some("jpg"),
+        image::ImageFormat::Png => Some("png"),
+        image::ImageFormat::WebP => Some("webp"),
+        _ => None,
+    }
+}
+
+/// Tamaño máximo de una imagen en píxeles (`MAX_IMAGE_WIDTH`,
+/// `MAX_IMAGE_HEIGHT`, `MAX_IMAGE_MEGAPIXELS`). Un PNG de pocos KB puede
+/// declarar 30000×30000 y pedir gigas al decodificarlo para la miniatura.
+#[derive(Clone, Copy, Debug)]
+pub struct DimensionLimits {
+    pub max_width: u32,
+    pub max_height: u32,
+    pub max_pixels: u64,
+}
+
+impl DimensionLimits {
+    pub fn allows(&self, (width, height): (u32, u32)) -> bool {
+        width <= self.max_width && height <= self.max_height && u64::from(width) * u64::from(height) <= self.max_pixels
+    }
+}
+
+/// Ancho y alto que declara la cabecera, sin decodificar los píxeles.
+pub fn dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    image::ImageReader::new(Cursor::new(bytes)).with_guessed_format().ok()?.into_dimensions().ok()
+}
+
+/// Extensión asociada a un tipo MIME declarado.
+pub fn extension_for_mime(mime: &str) -> Option<&'static str> {
+    match mime {
+        "image/jpeg" | "image/jpg" => Some("jpg"),
+        "image/png" => Some("png"),
+        "image/webp" => Some("webp"),
+        _ => None,
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ExtensionMismatch;
+
+/// Nombre original normalizado con la extensión real (`ext`). Se descartan las
+/// carpetas, y los puntos intermedios se sustituyen para que `photo.php.jpg`
+/// quede como `photo_php.jpg`. Si el nombre trae una extensión distinta de la
+/// real, se rechaza.
+pub fn original_name(raw: &str, ext: &str) -> Result<String, ExtensionMismatch> {
+    let base = raw.rsplit(['/', '\\']).next().unwrap_or_default();
+    let (stem, declared) = match base.rsplit_once('.') {
+        Some((stem, declared)) if !stem.is_empty() => (stem, Some(declared)),
+        _ => (base, None),
+    };
+
+    if let Some(declared) = declared {
+        let declared = declared.to_ascii_lowercase();
+        let declared = if declared == "jpeg" { "jpg" } else { declared.as_str() };
+        if declared != ext {
+            return Err(ExtensionMismatch);
+        }
+    }
+
+    let mut clean = String::new();
+    for c in stem.chars().take(MAX_STEM_CHARS) {
+        let c = if c.is_alphanumeric() || c == '-' { c } else { '_' };
+        if !(c == '_' && clean.ends_with('_')) {
+            clean.push(c);
+        }
+    }
+    let clean = clean.trim_matches('_');
+    let clean = if clean.is_empty() { "imagen" } else { clean };
+
+    Ok(format!("{clean}.{ext}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_original_names() {
+        let cases = [
+            ("moto.jpg", "jpg", Ok("moto.jpg")),
+            ("Moto Roja.JPEG", "jpg", Ok("Moto_Roja.jpg")),
+            ("photo.php.jpg", "jpg", Ok("photo_php.jpg")),
+            ("../../etc/passwd.png", "png", Ok("passwd.png")),
+            ("C:\\fotos\\moto.png", "png", Ok("moto.png")),
+            ("sin_extension", "webp", Ok("sin_extension.webp")),
+            (".png", "png", Ok("png.png")),
+            ("", "png", Ok("imagen.png")),
+            ("shell.php", "jpg", Err(ExtensionMismatch)),
+            ("foto.png", "jpg", Err(ExtensionMismatch)),
+        ];
+
+        for (raw, ext, expected) in cases {
+            assert_eq!(original_name(raw, ext), expected.map(String::from), "{raw}");
+        }
+    }
+
+    #[test]
+    fn paths_stay_inside_root() {
+        let root = UploadsRoot {
+            dir: PathBuf::from("/srv/uploads"),
+        };
+
+        assert_eq!(root.file("a.png"), Ok(PathBuf::from("/srv/uploads/a.png")));
+        assert_eq!(root.trash("a.png"), Ok(PathBuf::from("/srv/uploads/.trash/a.png")));
+
+        let hostile = [
+            "", ".", "..", "../a.png", "../../etc/passwd", "/etc/passwd", "sub/a.png", "a.png/",
+            "..\\a.png",
+        ];
+        for name in hostile {
+            assert_eq!(root.file(name), Err(OutsideRoot), "{name:?}");
+            assert_eq!(root.thumb(name), Err(OutsideRoot), "{name:?}");
+        }
+    }
+
+    #[test]
+    fn sniffs_content_not_declared_type() {
+        assert_eq!(sniff(b"\x89PNG\r\n\x1a\n\0\0\0\0"), Some("png"));
+        assert_eq!(sniff(b"\xff\xd8\xff\xe0\0\0"), Some("jpg"));
+        assert_eq!(sniff(b"<?php echo 1; ?>"), None);
+    }
+
+    #[test]
+    fn dimensions_come_from_the_header() {
+        // Cabecera de un PNG de 30000×30000 sin datos de imagen: basta para rechazarlo.
+        let mut bomb = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        bomb.extend(30000u32.to_be_bytes());
+        bomb.extend(30000u32.to_be_bytes());
+        bomb.extend([8, 2, 0, 0, 0]);
+        bomb.extend(crc32(&bomb[12..]).to_be_bytes());
+        bomb.extend(b"\0\0\0\0IDAT");
+        bomb.extend(crc32(b"IDAT").to_be_bytes());
+        assert_eq!(dimensions(&bomb), Some((30000, 30000)));
+
+        let limits = DimensionLimits { max_width: 10000, max_height: 10000, max_pixels: 40_000_000 };
+        assert!(!limits.allows((30000, 30000)));
+        assert!(!limits.allows((8000, 8000)));
+        assert!(limits.allows((6000, 4000)));
+        assert_eq!(dimensions(b"<?php echo 1; ?>"), None);
+    }
+
+    fn crc32(bytes: &[u8]) -> u32 {
+        let mut crc = !0u32;
+        for &b in bytes {
+            crc ^= u32::from(b);
+            for _ in 0..8 {
+                crc = if crc & 1 == 1 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+            }
+        }
+        !crc
+    }
+}
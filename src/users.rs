@@ -0,0 +1,181 @@
+//! Usuarios del panel con rol (`admin`, `moderator`, `viewer`; ver
+//! `policy::Role`). Entran por `/admin/login` con usuario y contraseña y se
+//! gestionan en `/api/admin/users`. En la misma tabla, con rol `author`, están
+//! las cuentas de autores (ver `accounts`), que no entran al panel. Las
+//! contraseñas se guardan con Argon2id. Desactivar un usuario corta sus sesiones al momento: el
+//! rol y el estado se leen en cada petición.
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+    Form, Json,
+};
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+use crate::audit_log::{self, Change};
+use crate::db::DbError;
+use crate::policy::{Principal, Role};
+use crate::queries;
+use crate::state::SharedState;
+
+/// Rol de las cuentas de autores en `users.role`; no es un `Role` del panel.
+pub const AUTHOR_ROLE: &str = "author";
+
+const MIN_PASSWORD_CHARS: usize = 10;
+
+/* ---------- CONTRASEÑAS ---------- */
+
+/// Cadena PHC de Argon2id (`$argon2id$v=19$...`) con los parámetros por defecto.
+fn hash_password(password: &str) -> Option<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default().hash_password(password.as_bytes(), &salt).ok().map(|h| h.to_string())
+}
+
+/// Solo cadenas PHC de Argon2id.
+fn verify_password(password: &str, stored: &str) -> bool {
+    stored.starts_with("$argon2id$")
+        && PasswordHash::new(stored)
+            .is_ok_and(|hash| Argon2::default().verify_password(password.as_bytes(), &hash).is_ok())
+}
+
+/// Id del usuario del panel activo si la contraseña es suya.
+pub async fn check_password(pool: &PgPool, username: &str, password: &str) -> Result<Option<i32>, DbError> {
+    check(pool, username, password, false).await
+}
+
+/// Igual que `check_password`, para las cuentas de autores.
+pub async fn check_author(pool: &PgPool, username: &str, password: &str) -> Result<Option<i32>, DbError> {
+    check(pool, username, password, true).await
+}
+
+async fn check(pool: &PgPool, username: &str, password: &str, author: bool) -> Result<Option<i32>, DbError> {
+    let Some((id, stored)) = queries::users::login(pool, username, author).await? else {
+        return Ok(None);
+    };
+    // Argon2: fuera del hilo del runtime.
+    let password = password.to_string();
+    let ok = tokio::task::spawn_blocking(move || verify_password(&password, &stored)).await.unwrap_or(false);
+    Ok(ok.then_some(id))
+}
+
+/* ---------- /api/admin/users ---------- */
+
+#[derive(Serialize)]
+pub struct User {
+    pub id: i32,
+    pub username: String,
+    pub role: String,
+    pub created_at: DateTime<Utc>,
+    pub disabled_at: Option<DateTime<Utc>>,
+}
+
+pub async fn list_users(State(app): State<SharedState>) -> Result<Json<Vec<User>>, DbError> {
+    Ok(Json(queries::users::list(&app.db).await?))
+}
+
+#[derive(Deserialize)]
+pub struct NewUser {
+    username: String,
+    password: String,
+    role: Role,
+}
+
+pub async fn create_user(State(app): State<SharedState>, principal: Principal, Form(new): Form<NewUser>) -> Response {
+    let username = new.username.trim();
+    if let Some(reason) = invalid(username, &new.password) {
+        return (StatusCode::BAD_REQUEST, Html(reason)).into_response();
+    }
+    let Some(hash) = hash(new.password).await else {
+        return (StatusCode::INTERNAL_SERVER_ERROR, Html("❌ No se pudo guardar el usuario")).into_response();
+    };
+
+    match queries::users::insert(&app.db, username, &hash, new.role.as_str()).await {
+        Ok(Some(id)) => {
+            let audit = Change::new("user.create", format!("user:{id}"))
+                .new_value(serde_json::json!({ "username": username, "role": new.role.as_str() }));
+            audit_log::record_change(&app.db, &principal, audit).await;
+            (StatusCode::CREATED, Json(serde_json::json!({ "id": id }))).into_response()
+        }
+        Ok(None) => (StatusCode::CONFLICT, Html("❌ Ese usuario ya existe")).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+/// Motivo para rechazar el usuario, listo para enseñar; `None` si vale.
+pub fn invalid(username: &str, password: &str) -> Option<&'static str> {
+    let username = username.trim();
+    if username.is_empty() || username.chars().count() > 50 {
+        return Some("❌ Nombre de usuario inválido");
+    }
+    if password.chars().count() < MIN_PASSWORD_CHARS {
+        return Some("❌ La contraseña debe tener al menos 10 caracteres");
+    }
+    None
+}
+
+/// `hash_password` fuera del hilo del runtime.
+pub async fn hash(password: String) -> Option<String> {
+    tokio::task::spawn_blocking(move || hash_password(&password)).await.ok().flatten()
+}
+
+#[derive(Deserialize)]
+pub struct RoleChange {
+    role: Role,
+}
+
+/// `POST /api/admin/users/:id/role`: vale desde la siguiente petición del usuario.
+pub async fn set_role(
+    State(app): State<SharedState>,
+    principal: Principal,
+    Path(id): Path<i32>,
+    Form(change): Form<RoleChange>,
+) -> Response {
+    match queries::users::set_role(&app.db, id, change.role.as_str()).await {
+        Ok(None) => not_found(),
+        Ok(Some(old)) => {
+            let audit = Change::new("user.role", format!("user:{id}"))
+                .old(serde_json::json!({ "role": old }))
+                .new_value(serde_json::json!({ "role": change.role.as_str() }));
+            audit_log::record_change(&app.db, &principal, audit).await;
+            Html("✅ Rol actualizado").into_response()
+        }
+        Err(e) => e.into_response(),
+    }
+}
+
+/// `DELETE /api/admin/users/:id`: se desactiva, no se borra, para no perder el historial.
+pub async fn disable_user(State(app): State<SharedState>, principal: Principal, Path(id): Path<i32>) -> Response {
+    match queries::users::disable(&app.db, id).await {
+        Ok(false) => not_found(),
+        Ok(true) => {
+            audit_log::record_change(&app.db, &principal, Change::new("user.disable", format!("user:{id}"))).await;
+            Html("✅ Usuario desactivado").into_response()
+        }
+        Err(e) => e.into_response(),
+    }
+}
+
+fn not_found() -> Response {
+    (StatusCode::NOT_FOUND, Html("❌ Usuario no encontrado")).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verifies_only_the_right_password() {
+        let stored = hash_password("una contraseña larga").unwrap();
+        assert!(stored.starts_with("$argon2id$"), "{stored}");
+        assert!(verify_password("una contraseña larga", &stored));
+        assert!(!verify_password("otra contraseña larga", &stored));
+        assert!(!verify_password("una contraseña larga", "texto-plano"));
+    }
+}
@@ -0,0 +1,80 @@
+//! Validación de lo que llega en los formularios. El texto se guarda tal cual
+//! lo escribió el autor; al pintarlo en HTML se escapa (`html::escape`).
+
+use regex::Regex;
+use std::sync::LazyLock;
+
+pub const MENSAJE_MIN_CHARS: usize = 10;
+pub const MENSAJE_MAX_CHARS: usize = 500;
+
+static NAME_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[a-zA-ZáéíóúÁÉÍÓÚñÑ\s]{3,50}$").unwrap());
+
+static EMAIL_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(\.[A-Za-z0-9-]+)*\.[A-Za-z]{2,}$").unwrap());
+
+/// Longitud máxima de una dirección según la RFC 5321.
+const EMAIL_MAX_CHARS: usize = 254;
+
+pub fn valid_nombre(nombre: &str) -> bool {
+    NAME_RE.is_match(nombre)
+}
+
+/// Forma básica `usuario@dominio.tld`; que exista lo confirma el enlace de verificación.
+pub fn valid_email(email: &str) -> bool {
+    email.len() <= EMAIL_MAX_CHARS && EMAIL_RE.is_match(email)
+}
+
+/// La longitud se mide en caracteres, no en bytes: "ñ" cuenta como uno.
+pub fn valid_mensaje(mensaje: &str) -> bool {
+    (MENSAJE_MIN_CHARS..=MENSAJE_MAX_CHARS).contains(&mensaje.chars().count())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn nombre_never_panics_and_respects_bounds(input in ".*") {
+            if valid_nombre(&input) {
+                let chars = input.chars().count();
+                prop_assert!((3..=50).contains(&chars));
+            }
+        }
+
+        #[test]
+        fn nombre_accepts_spanish_letters(input in "[a-zA-ZáéíóúÁÉÍÓÚñÑ ]{3,50}") {
+            prop_assert!(valid_nombre(&input));
+        }
+
+        #[test]
+        fn mensaje_length_counts_chars(c in prop::char::any(), n in 0usize..600) {
+            let mensaje: String = std::iter::repeat_n(c, n).collect();
+            prop_assert_eq!(
+                valid_mensaje(&mensaje),
+                (MENSAJE_MIN_CHARS..=MENSAJE_MAX_CHARS).contains(&n)
+            );
+        }
+    }
+
+    #[test]
+    fn multibyte_message_within_char_limit_is_valid() {
+        // 300 caracteres, 600 bytes.
+        let mensaje = "ñ".repeat(300);
+        assert!(mensaje.len() > MENSAJE_MAX_CHARS);
+        assert!(valid_mensaje(&mensaje));
+    }
+
+    #[test]
+    fn email_needs_user_domain_and_tld() {
+        for ok in ["ana@example.com", "ana.garcia+motos@correo.example.es"] {
+            assert!(valid_email(ok), "{ok}");
+        }
+        for bad in ["", "ana", "ana@", "@example.com", "ana@example", "ana @example.com", "<a>@example.com"] {
+            assert!(!valid_email(bad), "{bad}");
+        }
+        assert!(!valid_email(&format!("{}@example.com", "a".repeat(250))));
+    }
+}
@@ -0,0 +1,141 @@
+//! `hola_axum verify-uploads [--fix]`: cruza la tabla `images` con los ficheros
+//! del directorio de subidas. Informa de registros sin fichero (en la raíz,
+//! `.pending/` o `.trash/` según su estado) y de ficheros huérfanos; con
+//! `--fix` borra unos y otros. Pensado para cron: el código de salida es 0 si
+//! todo cuadra (o se arregló), 1 si hay diferencias sin arreglar y 2 si falla.
+//!
+//! Solo cuentan como huérfanos los ficheros con nombre de subida
+//! (`<uuid>.<ext>`): el directorio también guarda imágenes del sitio.
+
+use sqlx::PgPool;
+use std::{collections::HashSet, io, path::PathBuf};
+use uuid::Uuid;
+
+use crate::db::DbError;
+use crate::queries;
+use crate::uploads::UploadsRoot;
+
+#[derive(Debug, PartialEq)]
+pub struct Missing {
+    pub id: i32,
+    pub filename: String,
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Default)]
+pub struct Report {
+    pub missing: Vec<Missing>,
+    pub orphans: Vec<PathBuf>,
+}
+
+impl Report {
+    pub fn is_clean(&self) -> bool {
+        self.missing.is_empty() && self.orphans.is_empty()
+    }
+}
+
+/// Nombre generado al subir: `<uuid>.<ext>`.
+fn is_upload_name(name: &str) -> bool {
+    name.split_once('.')
+        .is_some_and(|(stem, ext)| Uuid::parse_str(stem).is_ok() && !ext.is_empty() && !ext.contains('.'))
+}
+
+pub async fn reconcile(pool: &PgPool, root: &UploadsRoot) -> Result<Report, DbError> {
+    let rows = queries::verify_uploads::images(pool).await?;
+
+    let mut report = Report::default();
+    let mut known = HashSet::new();
+    for (id, filename, approved, deleted) in rows {
+        let path = match (deleted, approved) {
+            (true, _) => root.trash(&filename),
+            (false, true) => root.file(&filename),
+            (false, false) => root.pending(&filename),
+        };
+        // Un nombre que se sale del directorio nunca tuvo fichero válido.
+        let path = path.unwrap_or_else(|_| root.dir().join(&filename));
+        if !tokio::fs::try_exists(&path).await.unwrap_or(false) {
+            report.missing.push(Missing { id, filename: filename.clone(), path });
+        }
+        known.insert(filename);
+    }
+
+    for dir in root.dirs() {
+        let Ok(mut entries) = tokio::fs::read_dir(&dir).await else { continue };
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let is_file = entry.file_type().await.is_ok_and(|t| t.is_file());
+            if is_file && is_upload_name(&name) && !known.contains(&name) {
+                report.orphans.push(entry.path());
+            }
+        }
+    }
+    report.orphans.sort();
+    Ok(report)
+}
+
+/// Borra los registros sin fichero y los ficheros huérfanos.
+pub async fn fix(pool: &PgPool, report: &Report) -> Result<(), DbError> {
+    let ids: Vec<i32> = report.missing.iter().map(|m| m.id).collect();
+    if !ids.is_empty() {
+        queries::verify_uploads::delete(pool, &ids).await?;
+    }
+
+    for path in &report.orphans {
+        match tokio::fs::remove_file(path).await {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                eprintln!("no se pudo borrar {}: {e}", path.display());
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Punto de entrada del subcomando; devuelve el código de salida.
+pub async fn run(pool: &PgPool, root: &UploadsRoot, apply: bool) -> i32 {
+    let report = match reconcile(pool, root).await {
+        Ok(report) => report,
+        Err(err) => {
+            eprintln!("no se pudieron revisar las subidas: {err:?}");
+            return 2;
+        }
+    };
+
+    for m in &report.missing {
+        println!("sin fichero: #{} {} ({})", m.id, m.filename, m.path.display());
+    }
+    for path in &report.orphans {
+        println!("huérfano: {}", path.display());
+    }
+    println!("-- {} registros sin fichero, {} ficheros huérfanos", report.missing.len(), report.orphans.len());
+
+    if report.is_clean() {
+        return 0;
+    }
+    if !apply {
+        return 1;
+    }
+    match fix(pool, &report).await {
+        Ok(()) => {
+            println!("-- arreglado");
+            0
+        }
+        Err(err) => {
+            eprintln!("no se pudieron borrar los registros: {err:?}");
+            2
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_generated_names_can_be_orphans() {
+        assert!(is_upload_name("67e55044-10b1-426f-9247-bb680e5fe0c8.png"));
+        assert!(!is_upload_name("Logo.png"));
+        assert!(!is_upload_name("67e55044-10b1-426f-9247-bb680e5fe0c8"));
+        assert!(!is_upload_name("67e55044-10b1-426f-9247-bb680e5fe0c8.png.bak"));
+    }
+}
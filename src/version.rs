@@ -0,0 +1,30 @@
+use axum::Json;
+use serde::Serialize;
+use std::{sync::LazyLock, time::Instant};
+
+/// Momento de arranque del proceso; `main` lo fuerza al inicio.
+pub static STARTED_AT: LazyLock<Instant> = LazyLock::new(Instant::now);
+
+#[derive(Serialize)]
+pub struct VersionInfo {
+    version: &'static str,
+    git_sha: &'static str,
+    build_timestamp: String,
+    uptime_seconds: u64,
+}
+
+pub async fn version() -> Json<VersionInfo> {
+    let build_timestamp = env!("BUILD_TIMESTAMP")
+        .parse::<i64>()
+        .ok()
+        .and_then(|secs| chrono::DateTime::from_timestamp(secs, 0))
+        .map(|t| t.to_rfc3339())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    Json(VersionInfo {
+        version: env!("CARGO_PKG_VERSION"),
+        git_sha: env!("GIT_SHA"),
+        build_timestamp,
+        uptime_seconds: STARTED_AT.elapsed().as_secs(),
+    })
+}
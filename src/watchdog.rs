@@ -0,0 +1,218 @@
+//! Vigilante interno para despliegues sin sistema de monitorización: cada
+//! intervalo mira la tasa de errores, la latencia de la base de datos y el disco
+//! de subidas, y si algo pasa de su umbral lo avisa por webhook (`POST` JSON
+//! con `text`, compatible con Slack y similares; para correo, un relé de webhooks).
+
+use serde::Serialize;
+use sqlx::PgPool;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use crate::config::AlertConfig;
+use crate::metrics::Metrics;
+use crate::queries;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Kind {
+    ErrorRate,
+    DbLatency,
+    DbDown,
+    DiskUsage,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Alert {
+    alert: Kind,
+    text: String,
+    value: f64,
+    threshold: f64,
+}
+
+/// Medidas de un intervalo.
+#[derive(Debug, Default)]
+struct Sample {
+    requests: u64,
+    errors: u64,
+    /// `None` si la base de datos no respondió.
+    db_latency: Option<Duration>,
+    disk_usage_pct: Option<f64>,
+}
+
+/// Arranca el vigilante si hay webhook configurado.
+pub fn spawn(config: &AlertConfig, metrics: Arc<Metrics>, pool: PgPool, uploads_dir: &Path) {
+    let Some(webhook) = config.webhook.clone() else {
+        return;
+    };
+    tracing::info!(interval_secs = config.interval.as_secs(), "vigilante de alertas activo");
+    tokio::spawn(run(config.clone(), webhook, metrics, pool, uploads_dir.to_path_buf()));
+}
+
+async fn run(config: AlertConfig, webhook: String, metrics: Arc<Metrics>, pool: PgPool, dir: PathBuf) {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()
+        .expect("no se pudo crear el cliente HTTP de alertas");
+    let mut last_sent: HashMap<Kind, Instant> = HashMap::new();
+    let mut previous = metrics.request_totals();
+    let mut interval = tokio::time::interval(config.interval);
+    interval.tick().await;
+
+    loop {
+        interval.tick().await;
+
+        let totals = metrics.request_totals();
+        let sample = Sample {
+            requests: totals.0 - previous.0,
+            errors: totals.1 - previous.1,
+            db_latency: probe_db(&pool, config.db_latency * 4).await,
+            disk_usage_pct: disk_usage_pct(&dir),
+        };
+        previous = totals;
+
+        for alert in evaluate(&config, &sample) {
+            if last_sent.get(&alert.alert).is_some_and(|t| t.elapsed() < config.cooldown) {
+                continue;
+            }
+            tracing::warn!(alert = ?alert.alert, value = alert.value, "{}", alert.text);
+            match client.post(&webhook).json(&alert).send().await {
+                Ok(res) if res.status().is_success() => {
+                    last_sent.insert(alert.alert, Instant::now());
+                }
+                Ok(res) => tracing::warn!(status = %res.status(), "el webhook de alertas rechazó el aviso"),
+                Err(err) => tracing::warn!(error = %err, "no se pudo enviar la alerta"),
+            }
+        }
+    }
+}
+
+fn evaluate(config: &AlertConfig, sample: &Sample) -> Vec<Alert> {
+    let mut alerts = Vec::new();
+
+    if sample.requests >= config.min_requests {
+        let rate = sample.errors as f64 / sample.requests as f64;
+        if rate > config.error_rate {
+            alerts.push(Alert {
+                alert: Kind::ErrorRate,
+                text: format!(
+                    "⚠️ {:.1}% de respuestas 5xx ({} de {}) en el último intervalo",
+                    rate * 100.0,
+                    sample.errors,
+                    sample.requests
+                ),
+                value: rate,
+                threshold: config.error_rate,
+            });
+        }
+    }
+
+    match sample.db_latency {
+        None => alerts.push(Alert {
+            alert: Kind::DbDown,
+            text: "⚠️ La base de datos no responde".to_string(),
+            value: 0.0,
+            threshold: 0.0,
+        }),
+        Some(latency) if latency > config.db_latency => alerts.push(Alert {
+            alert: Kind::DbLatency,
+            text: format!("⚠️ La base de datos tarda {} ms en responder", latency.as_millis()),
+            value: latency.as_secs_f64() * 1000.0,
+            threshold: config.db_latency.as_secs_f64() * 1000.0,
+        }),
+        Some(_) => {}
+    }
+
+    if let Some(pct) = sample.disk_usage_pct
+        && pct > config.disk_usage_pct
+    {
+        alerts.push(Alert {
+            alert: Kind::DiskUsage,
+            text: format!("⚠️ El disco de subidas está al {pct:.0}%"),
+            value: pct,
+            threshold: config.disk_usage_pct,
+        });
+    }
+
+    alerts
+}
+
+/// Duración de un `SELECT 1`; `None` si falla o no termina en `timeout`.
+pub async fn probe_db(pool: &PgPool, timeout: Duration) -> Option<Duration> {
+    let start = Instant::now();
+    match tokio::time::timeout(timeout, queries::watchdog::ping(pool)).await {
+        Ok(Ok(_)) => Some(start.elapsed()),
+        _ => None,
+    }
+}
+
+/// Ocupación del sistema de ficheros que contiene `dir`, con `statvfs`.
+pub fn disk_usage_pct(dir: &Path) -> Option<f64> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = std::ffi::CString::new(dir.as_os_str().as_bytes()).ok()?;
+    // SAFETY: `statvfs` es una estructura C de enteros, válida a ceros; la llamada
+    // solo lee la ruta terminada en NUL y escribe en `stat`.
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 || stat.f_blocks == 0 {
+        return None;
+    }
+    let used = stat.f_blocks.saturating_sub(stat.f_bfree) as f64;
+    let available = used + stat.f_bavail as f64;
+    Some(used / available * 100.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> AlertConfig {
+        AlertConfig {
+            webhook: Some("http://localhost/hook".to_string()),
+            interval: Duration::from_secs(60),
+            cooldown: Duration::from_secs(900),
+            error_rate: 0.05,
+            min_requests: 20,
+            db_latency: Duration::from_millis(500),
+            disk_usage_pct: 90.0,
+        }
+    }
+
+    fn kinds(sample: &Sample) -> Vec<Kind> {
+        evaluate(&config(), sample).into_iter().map(|a| a.alert).collect()
+    }
+
+    #[test]
+    fn fires_only_past_thresholds() {
+        let healthy = Sample {
+            requests: 100,
+            errors: 2,
+            db_latency: Some(Duration::from_millis(3)),
+            disk_usage_pct: Some(40.0),
+        };
+        assert!(kinds(&healthy).is_empty());
+
+        let few_requests = Sample { requests: 5, errors: 5, ..healthy };
+        assert!(kinds(&few_requests).is_empty());
+
+        let bad = Sample {
+            requests: 100,
+            errors: 10,
+            db_latency: Some(Duration::from_secs(2)),
+            disk_usage_pct: Some(95.0),
+        };
+        assert_eq!(kinds(&bad), [Kind::ErrorRate, Kind::DbLatency, Kind::DiskUsage]);
+
+        let db_down = Sample { db_latency: None, ..Sample::default() };
+        assert_eq!(kinds(&db_down), [Kind::DbDown]);
+    }
+
+    #[test]
+    fn measures_a_real_filesystem() {
+        let pct = disk_usage_pct(&std::env::temp_dir()).unwrap();
+        assert!((0.0..=100.0).contains(&pct));
+    }
+}
@@ -0,0 +1,279 @@
+//! Escritor mínimo de ficheros ZIP en memoria: entradas comprimidas con deflate
+//! y nombres en UTF-8. Pasa a ZIP64 solo cuando hace falta: con 65535 entradas
+//! o más, o con tamaños o posiciones de 4 GiB o más.
+
+use flate2::{write::DeflateEncoder, Compression};
+use std::io::Write;
+
+const LOCAL_HEADER: u32 = 0x0403_4b50;
+const CENTRAL_HEADER: u32 = 0x0201_4b50;
+const END_OF_CENTRAL: u32 = 0x0605_4b50;
+const END_OF_CENTRAL64: u32 = 0x0606_4b50;
+const END_OF_CENTRAL64_LOCATOR: u32 = 0x0706_4b50;
+
+/// Versión 2.0: deflate.
+const VERSION: u16 = 20;
+/// Versión 4.5: ZIP64.
+const VERSION_ZIP64: u16 = 45;
+/// Bit 11: nombres en UTF-8.
+const UTF8_NAMES: u16 = 1 << 11;
+const DEFLATE: u16 = 8;
+/// Campo extra con los valores de 64 bits.
+const ZIP64_EXTRA: u16 = 0x0001;
+
+/// 1980-01-01 00:00 en formato MS-DOS; la fecha real no aporta nada a la exportación.
+const DOS_TIME: u16 = 0;
+const DOS_DATE: u16 = (1 << 5) | 1;
+
+#[derive(Default)]
+pub struct ZipWriter {
+    out: Vec<u8>,
+    central: Vec<u8>,
+    entries: u64,
+}
+
+impl ZipWriter {
+    pub fn new() -> Self {
+        ZipWriter::default()
+    }
+
+    /// Solo falla si el nombre no cabe en la cabecera (64 KiB).
+    pub fn add(&mut self, name: &str, data: &[u8]) -> std::io::Result<()> {
+        let name_len = u16::try_from(name.len())
+            .map_err(|_| std::io::Error::other(format!("nombre demasiado largo para el ZIP: {} bytes", name.len())))?;
+
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data)?;
+        let compressed = encoder.finish()?;
+
+        let crc = crc32fast::hash(data);
+        let (size, compressed_len) = (data.len() as u64, compressed.len() as u64);
+        let offset = self.out.len() as u64;
+
+        // Cabecera local: firma, versión, flags, método, hora, fecha, crc, tamaños,
+        // nombre y extra. Con ZIP64 el extra lleva los dos tamaños.
+        let extra = if fits32(size) && fits32(compressed_len) {
+            Vec::new()
+        } else {
+            zip64_extra(&[size, compressed_len])
+        };
+        put32(&mut self.out, LOCAL_HEADER);
+        for v in [version(&extra), UTF8_NAMES, DEFLATE, DOS_TIME, DOS_DATE] {
+            put16(&mut self.out, v);
+        }
+        for v in [crc, short32(compressed_len), short32(size)] {
+            put32(&mut self.out, v);
+        }
+        put16(&mut self.out, name_len);
+        put16(&mut self.out, extra.len() as u16);
+        self.out.extend_from_slice(name.as_bytes());
+        self.out.extend_from_slice(&extra);
+        self.out.extend_from_slice(&compressed);
+
+        self.add_central(name, name_len, crc, size, compressed_len, offset);
+        Ok(())
+    }
+
+    /// Entrada del directorio central: lo mismo más la posición de la cabecera
+    /// local. En el extra ZIP64 van solo los valores que no caben, en este orden.
+    fn add_central(&mut self, name: &str, name_len: u16, crc: u32, size: u64, compressed_len: u64, offset: u64) {
+        let large: Vec<u64> = [size, compressed_len, offset].into_iter().filter(|v| !fits32(*v)).collect();
+        let extra = if large.is_empty() { Vec::new() } else { zip64_extra(&large) };
+
+        put32(&mut self.central, CENTRAL_HEADER);
+        let version = version(&extra);
+        for v in [version, version, UTF8_NAMES, DEFLATE, DOS_TIME, DOS_DATE] {
+            put16(&mut self.central, v);
+        }
+        for v in [crc, short32(compressed_len), short32(size)] {
+            put32(&mut self.central, v);
+        }
+        // Nombre, extra, comentario, disco, atributos internos.
+        for v in [name_len, extra.len() as u16, 0, 0, 0] {
+            put16(&mut self.central, v);
+        }
+        put32(&mut self.central, 0);
+        put32(&mut self.central, short32(offset));
+        self.central.extend_from_slice(name.as_bytes());
+        self.central.extend_from_slice(&extra);
+
+        self.entries += 1;
+    }
+
+    pub fn finish(mut self) -> Vec<u8> {
+        let central_offset = self.out.len() as u64;
+        let central_len = self.central.len() as u64;
+        self.out.append(&mut self.central);
+
+        // Registro final ZIP64 y su localizador, delante del final de siempre,
+        // que queda con los campos que no caben a 0xFFFF.. para que lo busquen.
+        if self.entries >= u64::from(u16::MAX) || !fits32(central_offset) || !fits32(central_len) {
+            let record_offset = self.out.len() as u64;
+            put32(&mut self.out, END_OF_CENTRAL64);
+            put64(&mut self.out, 44);
+            put16(&mut self.out, VERSION_ZIP64);
+            put16(&mut self.out, VERSION_ZIP64);
+            put32(&mut self.out, 0);
+            put32(&mut self.out, 0);
+            for v in [self.entries, self.entries, central_len, central_offset] {
+                put64(&mut self.out, v);
+            }
+
+            put32(&mut self.out, END_OF_CENTRAL64_LOCATOR);
+            put32(&mut self.out, 0);
+            put64(&mut self.out, record_offset);
+            put32(&mut self.out, 1);
+        }
+
+        let entries = u16::try_from(self.entries).unwrap_or(u16::MAX);
+        put32(&mut self.out, END_OF_CENTRAL);
+        for v in [0, 0, entries, entries] {
+            put16(&mut self.out, v);
+        }
+        put32(&mut self.out, short32(central_len));
+        put32(&mut self.out, short32(central_offset));
+        put16(&mut self.out, 0);
+        self.out
+    }
+}
+
+/// 0xFFFFFFFF ya significa "mira en ZIP64", así que tampoco cabe.
+fn fits32(v: u64) -> bool {
+    v < u64::from(u32::MAX)
+}
+
+fn short32(v: u64) -> u32 {
+    u32::try_from(v).unwrap_or(u32::MAX)
+}
+
+fn version(extra: &[u8]) -> u16 {
+    if extra.is_empty() { VERSION } else { VERSION_ZIP64 }
+}
+
+fn zip64_extra(values: &[u64]) -> Vec<u8> {
+    let mut extra = Vec::new();
+    put16(&mut extra, ZIP64_EXTRA);
+    put16(&mut extra, (values.len() * 8) as u16);
+    for v in values {
+        put64(&mut extra, *v);
+    }
+    extra
+}
+
+fn put16(out: &mut Vec<u8>, v: u16) {
+    out.extend_from_slice(&v.to_le_bytes());
+}
+
+fn put32(out: &mut Vec<u8>, v: u32) {
+    out.extend_from_slice(&v.to_le_bytes());
+}
+
+fn put64(out: &mut Vec<u8>, v: u64) {
+    out.extend_from_slice(&v.to_le_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::DeflateDecoder;
+    use std::io::Read;
+
+    fn u16_at(b: &[u8], at: usize) -> u16 {
+        u16::from_le_bytes([b[at], b[at + 1]])
+    }
+
+    fn u32_at(b: &[u8], at: usize) -> u32 {
+        u32::from_le_bytes(b[at..at + 4].try_into().unwrap())
+    }
+
+    fn u64_at(b: &[u8], at: usize) -> u64 {
+        u64::from_le_bytes(b[at..at + 8].try_into().unwrap())
+    }
+
+    /// Recorre el directorio central como lo haría un lector y descomprime cada entrada.
+    #[test]
+    fn entries_can_be_read_back() {
+        let mut zip = ZipWriter::new();
+        zip.add("index.html", b"<h1>Hola</h1>").unwrap();
+        zip.add("mensajes/1/view/index.html", "ñandú".repeat(100).as_bytes()).unwrap();
+        let bytes = zip.finish();
+
+        let end = bytes.len() - 22;
+        assert_eq!(u32_at(&bytes, end), END_OF_CENTRAL);
+        assert_eq!(u16_at(&bytes, end + 10), 2);
+
+        let mut at = u32_at(&bytes, end + 16) as usize;
+        let mut found = Vec::new();
+        for _ in 0..2 {
+            assert_eq!(u32_at(&bytes, at), CENTRAL_HEADER);
+            let crc = u32_at(&bytes, at + 16);
+            let size = u32_at(&bytes, at + 20) as usize;
+            let name_len = u16_at(&bytes, at + 28) as usize;
+            let local = u32_at(&bytes, at + 42) as usize;
+            let name = String::from_utf8(bytes[at + 46..at + 46 + name_len].to_vec()).unwrap();
+
+            assert_eq!(u32_at(&bytes, local), LOCAL_HEADER);
+            let data_at = local + 30 + u16_at(&bytes, local + 26) as usize;
+            let mut data = Vec::new();
+            DeflateDecoder::new(&bytes[data_at..data_at + size]).read_to_end(&mut data).unwrap();
+            assert_eq!(crc32fast::hash(&data), crc);
+
+            found.push((name, data));
+            at += 46 + name_len;
+        }
+
+        assert_eq!(found[0], ("index.html".to_string(), b"<h1>Hola</h1>".to_vec()));
+        assert_eq!(found[1].1, "ñandú".repeat(100).as_bytes());
+    }
+
+    #[test]
+    fn many_entries_use_the_zip64_end_record() {
+        let mut zip = ZipWriter::new();
+        let count = u64::from(u16::MAX) + 10;
+        // Solo el directorio central: comprimir 65545 entradas vacías no aporta nada.
+        for _ in 0..count {
+            zip.add_central("a", 1, 0, 0, 0, 0);
+        }
+        let bytes = zip.finish();
+
+        let end = bytes.len() - 22;
+        assert_eq!(u32_at(&bytes, end), END_OF_CENTRAL);
+        assert_eq!(u16_at(&bytes, end + 10), u16::MAX);
+
+        let locator = end - 20;
+        assert_eq!(u32_at(&bytes, locator), END_OF_CENTRAL64_LOCATOR);
+        let record = u64_at(&bytes, locator + 8) as usize;
+        assert_eq!(u32_at(&bytes, record), END_OF_CENTRAL64);
+        assert_eq!(u64_at(&bytes, record + 32), count);
+
+        // La última entrada del directorio central acaba justo en el registro ZIP64.
+        let central = u64_at(&bytes, record + 48) as usize;
+        assert_eq!(u64_at(&bytes, record + 40) as usize, record - central);
+        assert_eq!(u32_at(&bytes, central), CENTRAL_HEADER);
+    }
+
+    /// Sin escribir 4 GiB: la entrada central con valores grandes los lleva en el extra.
+    #[test]
+    fn large_sizes_and_offsets_go_in_the_zip64_extra() {
+        let mut zip = ZipWriter::new();
+        let (size, compressed, offset) = (5 << 30, u64::from(u32::MAX), 6 << 30);
+        zip.add_central("grande", 6, 0, size, compressed, offset);
+        let entry = &zip.central;
+
+        assert_eq!(u16_at(entry, 6), VERSION_ZIP64);
+        for at in [20, 24, 42] {
+            assert_eq!(u32_at(entry, at), u32::MAX);
+        }
+        assert_eq!(u16_at(entry, 30), 28);
+        let extra = 46 + 6;
+        assert_eq!(u16_at(entry, extra), ZIP64_EXTRA);
+        assert_eq!(u16_at(entry, extra + 2), 24);
+        assert_eq!([u64_at(entry, extra + 4), u64_at(entry, extra + 12), u64_at(entry, extra + 20)], [size, compressed, offset]);
+
+        // Con valores pequeños no hay extra.
+        let mut zip = ZipWriter::new();
+        zip.add_central("a", 1, 0, 10, 5, 0);
+        assert_eq!(u16_at(&zip.central, 4), VERSION);
+        assert_eq!(u16_at(&zip.central, 30), 0);
+    }
+}
@@ -0,0 +1,16 @@
+// Muestra el aviso del sitio (GET /announcement) encima del contenido
+(function () {
+    fetch("/announcement")
+        .then(res => res.status === 200 ? res.json() : null)
+        .then(aviso => {
+            if (!aviso) return;
+            const banner = document.createElement("div");
+            banner.className = "announcement";
+            banner.setAttribute("role", "note");
+            banner.textContent = aviso.message;
+
+            const destino = document.querySelector(".main-content") || document.body;
+            destino.prepend(banner);
+        })
+        .catch(() => {});
+})();
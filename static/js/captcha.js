@@ -0,0 +1,31 @@
+// Pinta el widget del captcha configurado en el servidor (GET /captcha)
+(function () {
+    const destino = document.querySelector(".captcha-wrapper");
+    if (!destino) return;
+
+    const widgets = {
+        recaptcha: { clase: "g-recaptcha", script: "https://www.google.com/recaptcha/api.js" },
+        turnstile: { clase: "cf-turnstile", script: "https://challenges.cloudflare.com/turnstile/v0/api.js" },
+        hcaptcha: { clase: "h-captcha", script: "https://js.hcaptcha.com/1/api.js" },
+    };
+
+    fetch("/captcha")
+        .then(res => res.json())
+        .then(config => {
+            const widget = widgets[config.provider];
+            if (!widget || !config.site_key) return;
+
+            const div = document.createElement("div");
+            div.className = widget.clase;
+            div.dataset.sitekey = config.site_key;
+            destino.append(div);
+
+            // El script del proveedor busca el div al cargar.
+            const script = document.createElement("script");
+            script.src = widget.script;
+            script.async = true;
+            script.defer = true;
+            document.head.append(script);
+        })
+        .catch(() => {});
+})();
@@ -0,0 +1,37 @@
+// Repite el token CSRF de la página en formularios, fetch y htmx (ver src/csrf.rs)
+(function () {
+    const meta = document.querySelector('meta[name="csrf-token"]');
+    if (!meta) return;
+    const token = meta.content;
+    const mismoOrigen = url => new URL(url, location.href).origin === location.origin;
+
+    // Formularios POST: campo oculto justo antes de enviarse
+    document.addEventListener("submit", (e) => {
+        const form = e.target;
+        if (form.method.toLowerCase() !== "post" || !mismoOrigen(form.action)) return;
+        if (form.querySelector('input[name="csrf_token"]')) return;
+        const campo = document.createElement("input");
+        campo.type = "hidden";
+        campo.name = "csrf_token";
+        campo.value = token;
+        form.append(campo);
+    }, true);
+
+    // fetch: cabecera en todo lo que no sea GET hacia el propio sitio
+    const fetchOriginal = window.fetch;
+    window.fetch = (recurso, opciones = {}) => {
+        const url = recurso instanceof Request ? recurso.url : String(recurso);
+        const metodo = (opciones.method || (recurso instanceof Request ? recurso.method : "GET")).toUpperCase();
+        if (metodo !== "GET" && metodo !== "HEAD" && mismoOrigen(url)) {
+            const headers = new Headers(opciones.headers || (recurso instanceof Request ? recurso.headers : undefined));
+            headers.set("X-CSRF-Token", token);
+            opciones = { ...opciones, headers };
+        }
+        return fetchOriginal(recurso, opciones);
+    };
+
+    // htmx
+    document.addEventListener("htmx:configRequest", (e) => {
+        e.detail.headers["X-CSRF-Token"] = token;
+    });
+})();
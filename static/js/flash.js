@@ -0,0 +1,20 @@
+// Muestra el aviso flash que deja el servidor tras un formulario y lo borra
+(function () {
+    const cookie = document.cookie.split("; ").find(c => c.startsWith("flash="));
+    if (!cookie) return;
+    document.cookie = "flash=; Path=/; Max-Age=0";
+
+    const valor = cookie.slice("flash=".length);
+    const sep = valor.indexOf("|");
+    const tipo = valor.slice(0, sep) === "error" ? "error" : "success";
+    const mensaje = decodeURIComponent(valor.slice(sep + 1));
+
+    const aviso = document.createElement("div");
+    aviso.className = `flash flash-${tipo}`;
+    aviso.setAttribute("role", "status");
+    aviso.textContent = mensaje;
+    aviso.onclick = () => aviso.remove();
+
+    const destino = document.querySelector(".main-content") || document.body;
+    destino.prepend(aviso);
+})();